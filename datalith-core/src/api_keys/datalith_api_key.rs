@@ -0,0 +1,152 @@
+use std::fmt::{self, Display, Formatter};
+
+use chrono::{DateTime, Local, TimeZone};
+use educe::Educe;
+use uuid::Uuid;
+
+/// The permissions granted to an API key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct DatalithApiKeyScopes {
+    /// Allow fetching and listing files.
+    pub read:   bool,
+    /// Allow uploading files.
+    pub write:  bool,
+    /// Allow deleting files.
+    pub delete: bool,
+}
+
+impl DatalithApiKeyScopes {
+    /// All the scopes.
+    pub const ALL: Self = Self {
+        read: true, write: true, delete: true
+    };
+    /// The delete scope.
+    pub const DELETE: Self = Self {
+        read: false, write: false, delete: true
+    };
+    /// The read scope.
+    pub const READ: Self = Self {
+        read: true, write: false, delete: false
+    };
+    /// The write scope.
+    pub const WRITE: Self = Self {
+        read: false, write: true, delete: false
+    };
+
+    /// Check whether these scopes cover all the `required` scopes.
+    #[inline]
+    pub const fn contains(&self, required: Self) -> bool {
+        (self.read || !required.read)
+            && (self.write || !required.write)
+            && (self.delete || !required.delete)
+    }
+
+    #[inline]
+    pub(crate) const fn to_bits(self) -> i64 {
+        (self.read as i64) | ((self.write as i64) << 1) | ((self.delete as i64) << 2)
+    }
+
+    #[inline]
+    pub(crate) const fn from_bits(bits: i64) -> Self {
+        Self {
+            read: bits & 1 != 0, write: bits & 2 != 0, delete: bits & 4 != 0
+        }
+    }
+}
+
+impl Display for DatalithApiKeyScopes {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut scopes = Vec::with_capacity(3);
+
+        if self.read {
+            scopes.push("read");
+        }
+
+        if self.write {
+            scopes.push("write");
+        }
+
+        if self.delete {
+            scopes.push("delete");
+        }
+
+        f.write_str(&scopes.join(","))
+    }
+}
+
+/// A struct that represents an API key. The key itself is not stored, only its hash.
+#[derive(Debug, Clone, Educe)]
+#[educe(PartialEq, Eq, Hash)]
+pub struct DatalithApiKey {
+    id:         Uuid,
+    #[educe(Eq(ignore), Hash(ignore))]
+    created_at: DateTime<Local>,
+    #[educe(Eq(ignore), Hash(ignore))]
+    name:       String,
+    #[educe(Eq(ignore), Hash(ignore))]
+    scopes:     DatalithApiKeyScopes,
+    #[educe(Eq(ignore), Hash(ignore))]
+    namespaces: Option<Vec<String>>,
+}
+
+impl DatalithApiKey {
+    /// Create an API key instance.
+    #[inline]
+    pub(crate) fn new<Tz: TimeZone>(
+        id: impl Into<Uuid>,
+        created_at: DateTime<Tz>,
+        name: impl Into<String>,
+        scopes: DatalithApiKeyScopes,
+        namespaces: Option<Vec<String>>,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            created_at: created_at.with_timezone(&Local),
+            name: name.into(),
+            scopes,
+            namespaces,
+        }
+    }
+}
+
+impl DatalithApiKey {
+    /// Retrieve the API key ID (UUID).
+    #[inline]
+    pub const fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// Retrieve the creation time.
+    #[inline]
+    pub const fn created_at(&self) -> DateTime<Local> {
+        self.created_at
+    }
+
+    /// Retrieve the name.
+    #[inline]
+    pub const fn name(&self) -> &String {
+        &self.name
+    }
+
+    /// Retrieve the scopes.
+    #[inline]
+    pub const fn scopes(&self) -> DatalithApiKeyScopes {
+        self.scopes
+    }
+
+    /// Retrieve the namespaces which this key can access. `None` means all namespaces.
+    #[inline]
+    pub fn namespaces(&self) -> Option<&[String]> {
+        self.namespaces.as_deref()
+    }
+
+    /// Check whether this key can access a namespace.
+    #[inline]
+    pub fn allows_namespace(&self, namespace: &str) -> bool {
+        match self.namespaces.as_ref() {
+            Some(namespaces) => namespaces.iter().any(|e| e == namespace),
+            None => true,
+        }
+    }
+}
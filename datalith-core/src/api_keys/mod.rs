@@ -0,0 +1,187 @@
+mod datalith_api_key;
+
+use std::io;
+
+use chrono::prelude::*;
+pub use datalith_api_key::*;
+use uuid::Uuid;
+
+use crate::{
+    Datalith, DatalithReadError, DatalithWriteError,
+    functions::{encode_hex, get_hash_by_buffer, get_random_hash},
+    is_valid_namespace,
+};
+
+/// The prefix of every API key.
+const API_KEY_PREFIX: &str = "dlk_";
+/// The separator of the namespaces in the `namespaces` column, which cannot appear in a namespace.
+const NAMESPACE_SEPARATOR: char = ',';
+
+#[inline]
+fn join_namespaces(namespaces: &[String]) -> String {
+    namespaces.join(&NAMESPACE_SEPARATOR.to_string())
+}
+
+#[inline]
+fn split_namespaces(namespaces: Option<String>) -> Option<Vec<String>> {
+    namespaces.map(|e| e.split(NAMESPACE_SEPARATOR).map(String::from).collect())
+}
+
+// Create
+impl Datalith {
+    /// Create an API key. The returned string is the key itself, which cannot be retrieved again because only its hash is stored.
+    ///
+    /// If `namespaces` is `Some`, the key can only access those namespaces. The default namespace is an empty string.
+    pub async fn create_api_key(
+        &self,
+        name: impl Into<String>,
+        scopes: DatalithApiKeyScopes,
+        namespaces: Option<Vec<String>>,
+    ) -> Result<(DatalithApiKey, String), DatalithWriteError> {
+        let id = Uuid::new_v4();
+        let created_at = Local::now();
+        let name = name.into();
+
+        let namespaces = match namespaces {
+            Some(mut namespaces) => {
+                if namespaces.is_empty() || !namespaces.iter().all(|e| is_valid_namespace(e)) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "the namespaces of the API key are invalid",
+                    )
+                    .into());
+                }
+
+                namespaces.sort_unstable();
+                namespaces.dedup();
+
+                Some(namespaces)
+            },
+            None => None,
+        };
+
+        let key = format!("{API_KEY_PREFIX}{}", encode_hex(get_random_hash()));
+
+        #[rustfmt::skip]
+        let result = sqlx::query(
+            "
+                INSERT INTO `api_keys` (`id`, `created_at`, `name`, `key_hash`, `scopes`, `namespaces`)
+                    VALUES (?, ?, ?, ?, ?, ?)
+            ",
+        )
+        .bind(id)
+        .bind(created_at.timestamp_millis())
+        .bind(name.as_str())
+        .bind(get_hash_by_buffer(&key).to_vec())
+        .bind(scopes.to_bits())
+        .bind(namespaces.as_deref().map(join_namespaces))
+        .execute(&self.0.db)
+        .await?;
+
+        debug_assert!(result.rows_affected() > 0);
+
+        Ok((DatalithApiKey::new(id, created_at, name, scopes, namespaces), key))
+    }
+}
+
+// Read
+impl Datalith {
+    /// Find the API key which matches the given key. Return `None` if the key is invalid.
+    pub async fn verify_api_key(
+        &self,
+        key: impl AsRef<str>,
+    ) -> Result<Option<DatalithApiKey>, DatalithReadError> {
+        let key = key.as_ref();
+
+        if !key.starts_with(API_KEY_PREFIX) {
+            return Ok(None);
+        }
+
+        #[rustfmt::skip]
+        let row: Option<(Uuid, i64, String, i64, Option<String>)> = sqlx::query_as(
+            "
+                SELECT
+                    `id`,
+                    `created_at`,
+                    `name`,
+                    `scopes`,
+                    `namespaces`
+                FROM
+                    `api_keys`
+                WHERE
+                    `key_hash` = ?
+            ",
+        )
+        .bind(get_hash_by_buffer(key).to_vec())
+        .fetch_optional(&self.0.db)
+        .await?;
+
+        Ok(row.map(|(id, created_at, name, scopes, namespaces)| {
+            DatalithApiKey::new(
+                id,
+                DateTime::from_timestamp_millis(created_at).unwrap(),
+                name,
+                DatalithApiKeyScopes::from_bits(scopes),
+                split_namespaces(namespaces),
+            )
+        }))
+    }
+
+    /// List all API keys.
+    pub async fn list_api_keys(&self) -> Result<Vec<DatalithApiKey>, DatalithReadError> {
+        #[rustfmt::skip]
+        let rows: Vec<(Uuid, i64, String, i64, Option<String>)> = sqlx::query_as(
+            "
+                SELECT
+                    `id`,
+                    `created_at`,
+                    `name`,
+                    `scopes`,
+                    `namespaces`
+                FROM
+                    `api_keys`
+                ORDER BY
+                    `created_at` ASC
+            ",
+        )
+        .fetch_all(&self.0.db)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(id, created_at, name, scopes, namespaces)| {
+                DatalithApiKey::new(
+                    id,
+                    DateTime::from_timestamp_millis(created_at).unwrap(),
+                    name,
+                    DatalithApiKeyScopes::from_bits(scopes),
+                    split_namespaces(namespaces),
+                )
+            })
+            .collect())
+    }
+}
+
+// Delete
+impl Datalith {
+    /// Remove an API key using an ID.
+    pub async fn delete_api_key_by_id(
+        &self,
+        id: impl Into<Uuid>,
+    ) -> Result<bool, DatalithReadError> {
+        #[rustfmt::skip]
+        let result = sqlx::query(
+            "
+                DELETE FROM
+                    `api_keys`
+                WHERE
+                    `id` = ?
+            ",
+        )
+        .bind(id.into())
+        .execute(&self.0.db)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,194 @@
+use std::{collections::HashSet, io, io::ErrorKind, path::Path, time::Duration};
+
+use sqlx::{Connection, SqliteConnection, sqlite::SqliteConnectOptions};
+use tokio::{fs, time};
+use uuid::Uuid;
+
+use crate::{
+    DEFAULT_FILE_DIRECTORY_DEPTH, Datalith, DatalithReadError, LocalStorageBackend, PATH_DB_FILE,
+    PATH_FILE_DIRECTORY, functions::allow_not_found_error,
+};
+
+/// The number of extra snapshots taken by a read-only instance for the files which seem to be deleted by another process.
+const MAX_SNAPSHOT_RETRIES: usize = 3;
+
+// Backup
+impl Datalith {
+    /// Back up the whole environment into the directory `path` while this Datalith is in use. Return the number of the backed up files.
+    ///
+    /// The metadata is snapshotted by SQLite (`VACUUM INTO`), and then the stored data of every file in the snapshot is hard-linked or copied. The result is self-consistent and can be opened by `Datalith::new` directly, with the same encryption key if the files are encrypted. Upload sessions are not backed up.
+    ///
+    /// To back up an environment which is used by another process, open it with [`DatalithBuilder::read_only`](crate::DatalithBuilder::read_only).
+    ///
+    /// The directory must not exist or must be empty.
+    pub async fn backup_to(&self, path: impl AsRef<Path>) -> Result<usize, DatalithReadError> {
+        let path = path.as_ref();
+
+        let created = match fs::read_dir(path).await {
+            Ok(mut read_dir) => {
+                if read_dir.next_entry().await?.is_some() {
+                    return Err(io::Error::new(
+                        ErrorKind::AlreadyExists,
+                        "the backup directory is not empty",
+                    )
+                    .into());
+                }
+
+                false
+            },
+            Err(error) if error.kind() == ErrorKind::NotFound => {
+                fs::create_dir_all(path).await?;
+
+                true
+            },
+            Err(error) => return Err(error.into()),
+        };
+
+        let result = self.backup_to_inner(path).await;
+
+        if result.is_err() {
+            // remove the incomplete backup, and the directory if it has been created here
+            if created {
+                allow_not_found_error(fs::remove_dir_all(path).await)?;
+            } else {
+                let mut read_dir = fs::read_dir(path).await?;
+
+                while let Some(entry) = read_dir.next_entry().await? {
+                    if entry.file_type().await?.is_dir() {
+                        allow_not_found_error(fs::remove_dir_all(entry.path()).await)?;
+                    } else {
+                        allow_not_found_error(fs::remove_file(entry.path()).await)?;
+                    }
+                }
+            }
+        }
+
+        result
+    }
+
+    async fn backup_to_inner(&self, path: &Path) -> Result<usize, DatalithReadError> {
+        let db_path = path.join(PATH_DB_FILE);
+        let db_path_str = db_path.to_str().ok_or_else(|| {
+            io::Error::new(ErrorKind::InvalidInput, "the backup path is not valid UTF-8")
+        })?;
+
+        let mut exported_file_ids = HashSet::new();
+        let mut retries = 0;
+
+        loop {
+            allow_not_found_error(fs::remove_file(db_path.as_path()).await)?;
+
+            sqlx::query("VACUUM INTO ?").bind(db_path_str).execute(&self.0.db).await?;
+
+            let (file_ids, storage) = {
+                let mut conn = SqliteConnection::connect_with(
+                    &SqliteConnectOptions::new().filename(db_path.as_path()),
+                )
+                .await?;
+
+                let result = async {
+                    // the partial data of upload sessions is not backed up
+                    sqlx::query("DELETE FROM `upload_sessions`").execute(&mut conn).await?;
+
+                    let file_ids = sqlx::query_as::<_, (Uuid,)>("SELECT `id` FROM `files`")
+                        .fetch_all(&mut conn)
+                        .await?;
+
+                    let file_directory_depth = sqlx::query_as::<_, (String,)>(
+                        "SELECT `value` FROM `sys_db_information` WHERE `key` = \
+                         'file_directory_depth'",
+                    )
+                    .fetch_optional(&mut conn)
+                    .await?;
+
+                    Ok::<_, sqlx::Error>((file_ids, file_directory_depth))
+                }
+                .await;
+
+                conn.close().await?;
+
+                let (file_ids, file_directory_depth) = result?;
+
+                // the backup uses the same layout as this environment
+                let storage = LocalStorageBackend::with_depth(
+                    path.join(PATH_FILE_DIRECTORY),
+                    file_directory_depth
+                        .and_then(|(depth,)| depth.parse().ok())
+                        .unwrap_or(DEFAULT_FILE_DIRECTORY_DEPTH),
+                );
+
+                (file_ids.into_iter().map(|(id,)| id).collect::<HashSet<Uuid>>(), storage)
+            };
+
+            let mut deleted_concurrently = false;
+
+            for &file_id in file_ids.iter() {
+                if exported_file_ids.contains(&file_id) {
+                    continue;
+                }
+
+                let file_path = storage.get_file_path(file_id);
+
+                fs::create_dir_all(file_path.parent().unwrap()).await?;
+
+                match self.0.storage.export_to_file(file_id, file_path.as_path()).await {
+                    Ok(()) => {
+                        exported_file_ids.insert(file_id);
+                    },
+                    Err(error) if error.kind() == ErrorKind::NotFound => {
+                        let is_deleting = self.0._deleting_files.lock().unwrap().contains(&file_id);
+
+                        // another process may have removed the data without committing the deletion yet
+                        if is_deleting
+                            || !self.check_file_row_exist(file_id).await?
+                            || self.is_read_only() && retries < MAX_SNAPSHOT_RETRIES
+                        {
+                            deleted_concurrently = true;
+                        } else {
+                            return Err(io::Error::new(
+                                ErrorKind::NotFound,
+                                format!("the data of the file {file_id} is missing"),
+                            )
+                            .into());
+                        }
+                    },
+                    Err(error) => return Err(error.into()),
+                }
+            }
+
+            // the snapshot contains files which have been deleted since then, so take another snapshot
+            if deleted_concurrently {
+                retries += 1;
+
+                time::sleep(Duration::from_millis(100)).await;
+
+                continue;
+            }
+
+            for file_id in exported_file_ids.difference(&file_ids) {
+                allow_not_found_error(fs::remove_file(storage.get_file_path(*file_id)).await)?;
+            }
+
+            return Ok(file_ids.len());
+        }
+    }
+
+    async fn check_file_row_exist(&self, id: Uuid) -> Result<bool, DatalithReadError> {
+        #[rustfmt::skip]
+        let row = sqlx::query(
+            "
+                SELECT
+                    1
+                FROM
+                    `files`
+                WHERE
+                    `id` = ?
+            ",
+        )
+        .bind(id)
+        .fetch_optional(&self.0.db)
+        .await?;
+
+        Ok(row.is_some())
+    }
+}
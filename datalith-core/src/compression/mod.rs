@@ -0,0 +1,259 @@
+#[cfg(feature = "compression")]
+mod zstd_decoding_reader;
+#[cfg(feature = "compression")]
+mod zstd_seek_table;
+
+#[cfg(feature = "compression")]
+use std::path::PathBuf;
+use std::{io, path::Path};
+
+use mime::Mime;
+#[cfg(feature = "compression")]
+use tokio::{fs, task};
+use uuid::Uuid;
+#[cfg(feature = "compression")]
+pub(crate) use zstd_decoding_reader::*;
+#[cfg(feature = "compression")]
+pub(crate) use zstd_seek_table::*;
+
+#[cfg(feature = "compression")]
+use crate::guard::TemporaryFileGuard;
+use crate::{Datalith, StorageReader};
+
+/// The compression algorithm of a stored file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum DatalithCompression {
+    /// The file is stored as it is.
+    #[default]
+    None,
+    /// The file is compressed by Zstandard.
+    Zstd,
+}
+
+impl DatalithCompression {
+    #[inline]
+    pub(crate) const fn to_i64(self) -> i64 {
+        match self {
+            Self::None => 0,
+            Self::Zstd => 1,
+        }
+    }
+
+    #[inline]
+    pub(crate) const fn from_i64(value: i64) -> Self {
+        match value {
+            1 => Self::Zstd,
+            _ => Self::None,
+        }
+    }
+
+    /// Retrieve the token used in the `Content-Encoding` HTTP header. Return `None` if the file is not compressed.
+    #[inline]
+    pub const fn content_encoding(self) -> Option<&'static str> {
+        match self {
+            Self::None => None,
+            Self::Zstd => Some("zstd"),
+        }
+    }
+}
+
+/// The policy deciding which files are compressed when they are put. The default policy never compresses files, and `text` is the policy for the common text-based file types.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatalithCompressionPolicy {
+    /// The compressible file types. A pattern can be an exact MIME type (`application/json`), a top-level type (`text/*`) or a structured syntax suffix (`*/*+xml`).
+    pub file_types:    Vec<String>,
+    /// Files smaller than this size (in bytes) are not compressed.
+    pub min_file_size: u64,
+    /// The compression level of Zstandard (1 to 22).
+    pub zstd_level:    i32,
+}
+
+impl Default for DatalithCompressionPolicy {
+    #[inline]
+    fn default() -> Self {
+        Self::disabled()
+    }
+}
+
+impl DatalithCompressionPolicy {
+    /// A policy which never compresses files.
+    #[inline]
+    pub const fn disabled() -> Self {
+        Self {
+            file_types: Vec::new(), min_file_size: 0, zstd_level: 3
+        }
+    }
+
+    /// A policy which compresses the files of the common text-based types (as in `text/*`, JSON, XML and SVG) which are at least 512 bytes, at level 3.
+    pub fn text() -> Self {
+        Self {
+            file_types:    [
+                "text/*",
+                "application/json",
+                "application/x-ndjson",
+                "application/xml",
+                "application/javascript",
+                "application/x-javascript",
+                "application/yaml",
+                "application/x-yaml",
+                "application/toml",
+                "application/sql",
+                "application/x-tar",
+                "image/svg+xml",
+                "image/bmp",
+                "*/*+json",
+                "*/*+xml",
+            ]
+            .into_iter()
+            .map(String::from)
+            .collect(),
+            min_file_size: 512,
+            zstd_level:    3,
+        }
+    }
+
+    /// Decide the compression for a file.
+    pub fn choose(&self, file_type: &Mime, file_size: u64) -> DatalithCompression {
+        if file_size < self.min_file_size {
+            return DatalithCompression::None;
+        }
+
+        if self.file_types.iter().any(|pattern| match_file_type(pattern, file_type)) {
+            DatalithCompression::Zstd
+        } else {
+            DatalithCompression::None
+        }
+    }
+}
+
+fn match_file_type(pattern: &str, file_type: &Mime) -> bool {
+    let Some((type_pattern, subtype_pattern)) = pattern.split_once('/') else {
+        return false;
+    };
+
+    if type_pattern != "*" && !type_pattern.eq_ignore_ascii_case(file_type.type_().as_str()) {
+        return false;
+    }
+
+    if subtype_pattern == "*" {
+        true
+    } else if let Some(suffix_pattern) = subtype_pattern.strip_prefix("*+") {
+        file_type
+            .suffix()
+            .is_some_and(|suffix| suffix_pattern.eq_ignore_ascii_case(suffix.as_str()))
+    } else {
+        pattern.eq_ignore_ascii_case(file_type.essence_str())
+    }
+}
+
+#[cfg(feature = "compression")]
+impl Datalith {
+    /// Retrieve the compression policy.
+    #[inline]
+    pub fn get_compression_policy(&self) -> DatalithCompressionPolicy {
+        self.0._compression_policy.lock().unwrap().clone()
+    }
+
+    /// Set the compression policy. It only affects the files put afterwards. Files are not compressed until a policy is set.
+    #[inline]
+    pub fn set_compression_policy(&self, mut compression_policy: DatalithCompressionPolicy) {
+        compression_policy.zstd_level = compression_policy.zstd_level.clamp(1, 22);
+
+        *self.0._compression_policy.lock().unwrap() = compression_policy;
+    }
+
+    /// Compress a temporary file in place if the compression policy says so and the compressed data is smaller.
+    pub(crate) async fn compress_temporary_file(
+        &self,
+        file_type: &Mime,
+        file_size: u64,
+        temporary_file_path: &Path,
+    ) -> io::Result<DatalithCompression> {
+        let (compression, zstd_level) = {
+            let compression_policy = self.0._compression_policy.lock().unwrap();
+
+            (compression_policy.choose(file_type, file_size), compression_policy.zstd_level)
+        };
+
+        match compression {
+            DatalithCompression::None => Ok(DatalithCompression::None),
+            DatalithCompression::Zstd => {
+                let compressed_file_path = {
+                    let mut path = PathBuf::from(temporary_file_path);
+
+                    path.as_mut_os_string().push(".zst");
+
+                    path
+                };
+
+                let _file_guard = TemporaryFileGuard::new(compressed_file_path.as_path());
+
+                let compressed_file_size = {
+                    let from = temporary_file_path.to_path_buf();
+                    let to = compressed_file_path.clone();
+
+                    task::spawn_blocking(move || {
+                        let from = std::fs::File::open(from)?;
+                        let mut to = std::fs::File::create(to)?;
+
+                        copy_encode_seekable(
+                            io::BufReader::new(from),
+                            io::BufWriter::new(&mut to),
+                            zstd_level,
+                        )?;
+
+                        to.metadata().map(|metadata| metadata.len())
+                    })
+                    .await
+                    .unwrap()?
+                };
+
+                if compressed_file_size < file_size {
+                    fs::rename(compressed_file_path.as_path(), temporary_file_path).await?;
+
+                    Ok(DatalithCompression::Zstd)
+                } else {
+                    Ok(DatalithCompression::None)
+                }
+            },
+        }
+    }
+}
+
+#[cfg(not(feature = "compression"))]
+impl Datalith {
+    #[inline]
+    pub(crate) async fn compress_temporary_file(
+        &self,
+        _file_type: &Mime,
+        _file_size: u64,
+        _temporary_file_path: &Path,
+    ) -> io::Result<DatalithCompression> {
+        Ok(DatalithCompression::None)
+    }
+}
+
+impl Datalith {
+    /// Open a reader which reads the decompressed data of a stored file.
+    pub(crate) async fn open_decoding_reader(
+        &self,
+        id: Uuid,
+        compression: DatalithCompression,
+        #[allow(unused_variables)] file_size: u64,
+    ) -> io::Result<Box<dyn StorageReader>> {
+        let file = self.0.storage.open_reader(id).await?;
+
+        match compression {
+            DatalithCompression::None => Ok(file),
+            #[cfg(feature = "compression")]
+            DatalithCompression::Zstd => {
+                Ok(Box::new(ZstdDecodingReader::open(file, file_size).await?))
+            },
+            #[cfg(not(feature = "compression"))]
+            DatalithCompression::Zstd => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the file is compressed by Zstandard, but the `compression` feature is disabled",
+            )),
+        }
+    }
+}
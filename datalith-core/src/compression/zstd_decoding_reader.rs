@@ -0,0 +1,244 @@
+use std::{
+    fmt::{self, Debug, Formatter},
+    io,
+    io::SeekFrom,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use tokio::io::{AsyncRead, AsyncSeek, ReadBuf};
+use zstd::stream::raw::{Decoder, Operation};
+
+use super::{SeekPoint, read_seek_table};
+use crate::StorageReader;
+
+const BUFFER_SIZE: usize = 64 * 1024;
+
+#[derive(Debug)]
+enum SeekState {
+    Idle,
+    /// The inner reader is seeking to the start of the frame at `position`.
+    Restarting {
+        position: u64,
+        target:   u64,
+    },
+    Skipping(u64),
+}
+
+/// A reader which decompresses a stored Zstandard stream. If the stream has a seek table, seeking restarts the decompression from the frame containing the target, otherwise seeking forward is done by decompressing and discarding the data, and seeking backward restarts the decompression from the beginning.
+pub(crate) struct ZstdDecodingReader {
+    inner:        Box<dyn StorageReader>,
+    decoder:      Decoder<'static>,
+    input:        Box<[u8]>,
+    input_start:  usize,
+    input_end:    usize,
+    output:       Box<[u8]>,
+    output_start: usize,
+    output_end:   usize,
+    inner_eof:    bool,
+    /// The number of bytes expected by the decoder to finish the current frame. `0` means the frame is finished.
+    remaining:    usize,
+    file_size:    u64,
+    position:     u64,
+    seek:         SeekState,
+    seek_points:  Option<Vec<SeekPoint>>,
+}
+
+impl Debug for ZstdDecodingReader {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ZstdDecodingReader")
+            .field("inner", &self.inner)
+            .field("file_size", &self.file_size)
+            .field("position", &self.position)
+            .field("seekable", &self.seek_points.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl ZstdDecodingReader {
+    /// Create a reader. `file_size` is the size of the decompressed data.
+    pub(crate) async fn open(
+        mut inner: Box<dyn StorageReader>,
+        file_size: u64,
+    ) -> io::Result<Self> {
+        let seek_points = read_seek_table(&mut inner, file_size).await?;
+
+        Ok(Self {
+            inner,
+            decoder: Decoder::new()?,
+            input: vec![0; BUFFER_SIZE].into_boxed_slice(),
+            input_start: 0,
+            input_end: 0,
+            output: vec![0; BUFFER_SIZE].into_boxed_slice(),
+            output_start: 0,
+            output_end: 0,
+            inner_eof: false,
+            remaining: 0,
+            file_size,
+            position: 0,
+            seek: SeekState::Idle,
+            seek_points,
+        })
+    }
+
+    /// Make sure there is decompressed data in the output buffer. `false` means the end of the data.
+    fn poll_fill(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<bool>> {
+        loop {
+            if self.output_start < self.output_end {
+                return Poll::Ready(Ok(true));
+            }
+
+            if self.input_start == self.input_end && !self.inner_eof {
+                let mut buf = ReadBuf::new(&mut self.input);
+
+                ready!(Pin::new(&mut self.inner).poll_read(cx, &mut buf))?;
+
+                self.input_start = 0;
+                self.input_end = buf.filled().len();
+
+                if self.input_end == 0 {
+                    self.inner_eof = true;
+                }
+            }
+
+            let status = self
+                .decoder
+                .run_on_buffers(&self.input[self.input_start..self.input_end], &mut self.output)?;
+
+            self.input_start += status.bytes_read;
+            self.output_start = 0;
+            self.output_end = status.bytes_written;
+
+            if status.bytes_read > 0 || status.bytes_written > 0 {
+                self.remaining = status.remaining;
+            } else if self.inner_eof && self.input_start == self.input_end {
+                if self.remaining > 0 {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "the compressed data is truncated",
+                    )));
+                }
+
+                return Poll::Ready(Ok(false));
+            }
+        }
+    }
+
+    fn poll_seek(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        loop {
+            match self.seek {
+                SeekState::Idle => return Poll::Ready(Ok(())),
+                SeekState::Restarting {
+                    position,
+                    target,
+                } => {
+                    ready!(Pin::new(&mut self.inner).poll_complete(cx))?;
+
+                    self.decoder.reinit()?;
+                    self.input_start = 0;
+                    self.input_end = 0;
+                    self.output_start = 0;
+                    self.output_end = 0;
+                    self.inner_eof = false;
+                    self.remaining = 0;
+                    self.position = position;
+
+                    self.seek = SeekState::Skipping(target);
+                },
+                SeekState::Skipping(target) => {
+                    while self.position < target {
+                        if !ready!(self.poll_fill(cx))? {
+                            // seeking beyond the end is allowed, and reading from there returns nothing
+                            self.position = target;
+
+                            break;
+                        }
+
+                        let skip = ((self.output_end - self.output_start) as u64)
+                            .min(target - self.position)
+                            as usize;
+
+                        self.output_start += skip;
+                        self.position += skip as u64;
+                    }
+
+                    self.seek = SeekState::Idle;
+                },
+            }
+        }
+    }
+}
+
+impl AsyncRead for ZstdDecodingReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+
+        ready!(this.poll_seek(cx))?;
+
+        if buf.remaining() == 0 || !ready!(this.poll_fill(cx))? {
+            return Poll::Ready(Ok(()));
+        }
+
+        let c = (this.output_end - this.output_start).min(buf.remaining());
+
+        buf.put_slice(&this.output[this.output_start..this.output_start + c]);
+
+        this.output_start += c;
+        this.position += c as u64;
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncSeek for ZstdDecodingReader {
+    fn start_seek(mut self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        let target = match position {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.file_size.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+
+        let Some(target) = target else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            ));
+        };
+
+        // the frame containing the target, or the beginning if there is no seek table
+        let seek_point = self
+            .seek_points
+            .as_deref()
+            .and_then(|seek_points| {
+                let index = seek_points.partition_point(|e| e.decompressed_offset <= target);
+
+                index.checked_sub(1).map(|index| seek_points[index])
+            })
+            .unwrap_or_default();
+
+        if target >= self.position && seek_point.decompressed_offset <= self.position {
+            self.seek = SeekState::Skipping(target);
+        } else {
+            Pin::new(&mut self.inner).start_seek(SeekFrom::Start(seek_point.compressed_offset))?;
+
+            self.seek = SeekState::Restarting {
+                position: seek_point.decompressed_offset,
+                target,
+            };
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn poll_complete(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        ready!(self.poll_seek(cx))?;
+
+        Poll::Ready(Ok(self.position))
+    }
+}
@@ -0,0 +1,164 @@
+use std::{
+    io,
+    io::{Read, SeekFrom, Write},
+};
+
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+use crate::StorageReader;
+
+/// The size of the decompressed data of each frame, except the last one. Seeking decompresses at most one frame.
+pub(crate) const SEEKABLE_FRAME_SIZE: usize = 1024 * 1024;
+
+const SKIPPABLE_FRAME_MAGIC_NUMBER: u32 = 0x184D2A5E;
+const SEEKABLE_MAGIC_NUMBER: u32 = 0x8F92EAB1;
+const SKIPPABLE_FRAME_HEADER_SIZE: u64 = 8;
+const SEEK_TABLE_FOOTER_SIZE: u64 = 9;
+
+/// The start of a frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct SeekPoint {
+    /// The offset of the frame in the compressed data.
+    pub(crate) compressed_offset:   u64,
+    /// The offset of the frame in the decompressed data.
+    pub(crate) decompressed_offset: u64,
+}
+
+/// Compress data into independent frames of `SEEKABLE_FRAME_SIZE`, followed by a seek table in the Zstandard seekable format. The table is a skippable frame, so the output is still an ordinary Zstandard stream.
+pub(crate) fn copy_encode_seekable(
+    mut from: impl Read,
+    mut to: impl Write,
+    level: i32,
+) -> io::Result<()> {
+    let mut compressor = zstd::bulk::Compressor::new(level)?;
+    let mut buffer = Vec::with_capacity(SEEKABLE_FRAME_SIZE);
+
+    // (compressed size, decompressed size)
+    let mut frames: Vec<(u32, u32)> = Vec::new();
+
+    loop {
+        buffer.clear();
+
+        (&mut from).take(SEEKABLE_FRAME_SIZE as u64).read_to_end(&mut buffer)?;
+
+        if buffer.is_empty() {
+            break;
+        }
+
+        let compressed = compressor.compress(&buffer)?;
+
+        to.write_all(&compressed)?;
+
+        frames.push((compressed.len() as u32, buffer.len() as u32));
+
+        if buffer.len() < SEEKABLE_FRAME_SIZE {
+            break;
+        }
+    }
+
+    let frame_count = u32::try_from(frames.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "the data is too large"))?;
+
+    let mut seek_table = Vec::with_capacity(
+        (SKIPPABLE_FRAME_HEADER_SIZE + SEEK_TABLE_FOOTER_SIZE) as usize + frames.len() * 8,
+    );
+
+    seek_table.extend_from_slice(&SKIPPABLE_FRAME_MAGIC_NUMBER.to_le_bytes());
+    seek_table.extend_from_slice(&(frame_count * 8 + SEEK_TABLE_FOOTER_SIZE as u32).to_le_bytes());
+
+    for (compressed_size, decompressed_size) in frames {
+        seek_table.extend_from_slice(&compressed_size.to_le_bytes());
+        seek_table.extend_from_slice(&decompressed_size.to_le_bytes());
+    }
+
+    seek_table.extend_from_slice(&frame_count.to_le_bytes());
+    // no checksums
+    seek_table.push(0);
+    seek_table.extend_from_slice(&SEEKABLE_MAGIC_NUMBER.to_le_bytes());
+
+    to.write_all(&seek_table)?;
+
+    to.flush()
+}
+
+/// Read the seek table at the end of a stored Zstandard stream, and seek back to the beginning. Return `None` if there is no valid seek table, as in the files compressed as a single frame.
+pub(crate) async fn read_seek_table(
+    inner: &mut Box<dyn StorageReader>,
+    file_size: u64,
+) -> io::Result<Option<Vec<SeekPoint>>> {
+    let seek_points = read_seek_table_inner(inner, file_size).await;
+
+    inner.seek(SeekFrom::Start(0)).await?;
+
+    seek_points
+}
+
+async fn read_seek_table_inner(
+    inner: &mut Box<dyn StorageReader>,
+    file_size: u64,
+) -> io::Result<Option<Vec<SeekPoint>>> {
+    let stored_size = inner.seek(SeekFrom::End(0)).await?;
+
+    if stored_size < SKIPPABLE_FRAME_HEADER_SIZE + SEEK_TABLE_FOOTER_SIZE {
+        return Ok(None);
+    }
+
+    let mut footer = [0u8; SEEK_TABLE_FOOTER_SIZE as usize];
+
+    inner.seek(SeekFrom::Start(stored_size - SEEK_TABLE_FOOTER_SIZE)).await?;
+    inner.read_exact(&mut footer).await?;
+
+    let frame_count = u32::from_le_bytes(footer[0..4].try_into().unwrap()) as u64;
+    let descriptor = footer[4];
+
+    // the reserved bits must be zero
+    if u32::from_le_bytes(footer[5..9].try_into().unwrap()) != SEEKABLE_MAGIC_NUMBER
+        || descriptor & 0x7C != 0
+    {
+        return Ok(None);
+    }
+
+    let entry_size = if descriptor & 0x80 == 0 { 8 } else { 12 };
+    let seek_table_size = frame_count * entry_size + SEEK_TABLE_FOOTER_SIZE;
+
+    let Some(seek_table_start) =
+        stored_size.checked_sub(SKIPPABLE_FRAME_HEADER_SIZE + seek_table_size)
+    else {
+        return Ok(None);
+    };
+
+    let mut seek_table = vec![0u8; (SKIPPABLE_FRAME_HEADER_SIZE + seek_table_size) as usize];
+
+    inner.seek(SeekFrom::Start(seek_table_start)).await?;
+    inner.read_exact(&mut seek_table).await?;
+
+    if u32::from_le_bytes(seek_table[0..4].try_into().unwrap()) != SKIPPABLE_FRAME_MAGIC_NUMBER
+        || u32::from_le_bytes(seek_table[4..8].try_into().unwrap()) as u64 != seek_table_size
+    {
+        return Ok(None);
+    }
+
+    let mut seek_points = Vec::with_capacity(frame_count as usize);
+    let mut compressed_offset = 0u64;
+    let mut decompressed_offset = 0u64;
+
+    for entry in seek_table[SKIPPABLE_FRAME_HEADER_SIZE as usize..]
+        .chunks_exact(entry_size as usize)
+        .take(frame_count as usize)
+    {
+        seek_points.push(SeekPoint {
+            compressed_offset,
+            decompressed_offset,
+        });
+
+        compressed_offset += u32::from_le_bytes(entry[0..4].try_into().unwrap()) as u64;
+        decompressed_offset += u32::from_le_bytes(entry[4..8].try_into().unwrap()) as u64;
+    }
+
+    // the table must describe exactly the stored data
+    if compressed_offset != seek_table_start || decompressed_offset != file_size {
+        return Ok(None);
+    }
+
+    Ok(Some(seek_points))
+}
@@ -0,0 +1,299 @@
+use std::collections::{HashMap, HashSet};
+
+use uuid::Uuid;
+
+use crate::{
+    Datalith, DatalithReadError,
+    metadata::delete_metadata,
+    quotas::{calculate_usage, read_recorded_usage, recalculate_usage},
+    search::delete_search_document,
+};
+
+/// The result of checking the consistency between the metadata and the stored data.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DatalithConsistencyReport {
+    /// The files whose stored data does not exist.
+    pub missing_file_data:  Vec<Uuid>,
+    /// The resources pointing at nonexistent files.
+    pub dangling_resources: Vec<Uuid>,
+    /// The images whose original file or thumbnails point at nonexistent files, including the images which only exist in the thumbnail table.
+    pub dangling_images:    Vec<Uuid>,
+    /// The files whose reference counts are less than the numbers of the resources and images referencing them, as `(file ID, count, expected count)`. Deleting the references of such a file would delete its data while it is still referenced.
+    pub count_mismatches:   Vec<(Uuid, u64, u64)>,
+    /// The namespaces whose recorded usage does not match their resources and images.
+    pub usage_mismatches:   Vec<String>,
+    /// Whether the problems have been repaired.
+    pub repaired:           bool,
+}
+
+impl DatalithConsistencyReport {
+    /// Check if there is no problem.
+    #[inline]
+    pub fn is_consistent(&self) -> bool {
+        self.missing_file_data.is_empty()
+            && self.dangling_resources.is_empty()
+            && self.dangling_images.is_empty()
+            && self.count_mismatches.is_empty()
+            && self.usage_mismatches.is_empty()
+    }
+}
+
+// Consistency
+impl Datalith {
+    /// Check the consistency between the metadata and the stored data. If `repair` is `true`, the problems are fixed as follows.
+    ///
+    /// * The files whose stored data is missing are removed.
+    /// * The resources pointing at nonexistent files are removed.
+    /// * The images whose original file or any thumbnail points at a nonexistent file are removed, with all their thumbnails.
+    /// * The reference counts of files which are too small are raised to the numbers of the remaining resources and images referencing them.
+    /// * The usage of every namespace is counted again.
+    ///
+    /// A reference count also counts the times the file is put directly, which are not recorded anywhere, so a count greater than the number of references is not a problem and is never lowered. For the same reason, the files which were only referenced by the removed records are kept.
+    ///
+    /// This should be run when no file is being put or deleted, for example, before starting a service.
+    pub async fn check_consistency(
+        &self,
+        repair: bool,
+    ) -> Result<DatalithConsistencyReport, DatalithReadError> {
+        let mut report = DatalithConsistencyReport::default();
+
+        #[rustfmt::skip]
+        let files: HashMap<Uuid, u64> = sqlx::query_as(
+            "
+                SELECT
+                    `id`,
+                    `count`
+                FROM
+                    `files`
+            ",
+        )
+        .fetch_all(&self.0.db)
+        .await?
+        .into_iter()
+        .collect();
+
+        for &file_id in files.keys() {
+            if self.0.storage.stat(file_id).await?.is_none() {
+                report.missing_file_data.push(file_id);
+            }
+        }
+
+        let missing_file_data = report.missing_file_data.iter().copied().collect::<HashSet<_>>();
+
+        let is_valid_file =
+            |file_id: &Uuid| files.contains_key(file_id) && !missing_file_data.contains(file_id);
+
+        // the numbers of the valid references
+        let mut references: HashMap<Uuid, u64> = HashMap::new();
+
+        #[rustfmt::skip]
+        let resources: Vec<(Uuid, Uuid)> = sqlx::query_as(
+            "
+                SELECT
+                    `id`,
+                    `file_id`
+                FROM
+                    `resources`
+            ",
+        )
+        .fetch_all(&self.0.db)
+        .await?;
+
+        for (resource_id, file_id) in resources {
+            if is_valid_file(&file_id) {
+                *references.entry(file_id).or_default() += 1;
+            } else {
+                report.dangling_resources.push(resource_id);
+            }
+        }
+
+        // image ID -> file IDs
+        let mut images: HashMap<Uuid, (bool, Vec<Uuid>)> = HashMap::new();
+
+        #[rustfmt::skip]
+        let image_rows: Vec<(Uuid, Option<Uuid>)> = sqlx::query_as(
+            "
+                SELECT
+                    `id`,
+                    `original_file_id`
+                FROM
+                    `images`
+            ",
+        )
+        .fetch_all(&self.0.db)
+        .await?;
+
+        for (image_id, original_file_id) in image_rows {
+            images.insert(image_id, (true, original_file_id.into_iter().collect()));
+        }
+
+        #[rustfmt::skip]
+        let thumbnail_rows: Vec<(Uuid, Uuid)> = sqlx::query_as(
+            "
+                SELECT
+                    `image_id`,
+                    `file_id`
+                FROM
+                    `image_thumbnails`
+            ",
+        )
+        .fetch_all(&self.0.db)
+        .await?;
+
+        for (image_id, file_id) in thumbnail_rows {
+            images.entry(image_id).or_insert_with(|| (false, Vec::new())).1.push(file_id);
+        }
+
+        for (image_id, (exists, file_ids)) in images {
+            let dangling = !exists || !file_ids.iter().all(is_valid_file);
+
+            if dangling {
+                report.dangling_images.push(image_id);
+            } else {
+                for file_id in file_ids {
+                    *references.entry(file_id).or_default() += 1;
+                }
+            }
+        }
+
+        for (&file_id, &count) in files.iter() {
+            if missing_file_data.contains(&file_id) {
+                continue;
+            }
+
+            if let Some(&references) = references.get(&file_id)
+                && references > count
+            {
+                report.count_mismatches.push((file_id, count, references));
+            }
+        }
+
+        {
+            let mut conn = self.0.db.acquire().await?;
+
+            let recorded_usage = read_recorded_usage(&mut conn).await?;
+            let usage = calculate_usage(&mut conn).await?;
+
+            for (namespace, recorded_usage) in recorded_usage.iter() {
+                if usage.get(namespace).copied().unwrap_or_default() != *recorded_usage {
+                    report.usage_mismatches.push(namespace.clone());
+                }
+            }
+
+            for namespace in usage.keys() {
+                if !recorded_usage.contains_key(namespace) {
+                    report.usage_mismatches.push(namespace.clone());
+                }
+            }
+        }
+
+        report.missing_file_data.sort_unstable();
+        report.dangling_resources.sort_unstable();
+        report.dangling_images.sort_unstable();
+        report.count_mismatches.sort_unstable();
+        report.usage_mismatches.sort_unstable();
+
+        if repair && !report.is_consistent() {
+            self.repair_consistency(&report).await?;
+
+            report.repaired = true;
+        }
+
+        Ok(report)
+    }
+
+    async fn repair_consistency(
+        &self,
+        report: &DatalithConsistencyReport,
+    ) -> Result<(), DatalithReadError> {
+        let mut tx = self.0.db.begin().await?;
+
+        for resource_id in report.dangling_resources.iter() {
+            #[rustfmt::skip]
+            sqlx::query(
+                "
+                    DELETE FROM
+                        `resources`
+                    WHERE
+                        `id` = ?
+                ",
+            )
+            .bind(resource_id)
+            .execute(&mut *tx)
+            .await?;
+
+            delete_metadata(&mut tx, *resource_id).await?;
+            delete_search_document(&mut tx, *resource_id).await?;
+        }
+
+        for image_id in report.dangling_images.iter() {
+            #[rustfmt::skip]
+            sqlx::query(
+                "
+                    DELETE FROM
+                        `image_thumbnails`
+                    WHERE
+                        `image_id` = ?
+                ",
+            )
+            .bind(image_id)
+            .execute(&mut *tx)
+            .await?;
+
+            #[rustfmt::skip]
+            sqlx::query(
+                "
+                    DELETE FROM
+                        `images`
+                    WHERE
+                        `id` = ?
+                ",
+            )
+            .bind(image_id)
+            .execute(&mut *tx)
+            .await?;
+
+            delete_metadata(&mut tx, *image_id).await?;
+            delete_search_document(&mut tx, *image_id).await?;
+        }
+
+        for &(file_id, _, expected_count) in report.count_mismatches.iter() {
+            #[rustfmt::skip]
+            sqlx::query(
+                "
+                    UPDATE
+                        `files`
+                    SET
+                        `count` = MAX(`count`, ?)
+                    WHERE
+                        `id` = ?
+                ",
+            )
+            .bind(expected_count as i64)
+            .bind(file_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        for file_id in report.missing_file_data.iter() {
+            #[rustfmt::skip]
+            sqlx::query(
+                "
+                    DELETE FROM
+                        `files`
+                    WHERE
+                        `id` = ?
+                ",
+            )
+            .bind(file_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        recalculate_usage(&mut tx).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
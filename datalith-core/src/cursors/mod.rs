@@ -0,0 +1,263 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    str,
+};
+
+use sqlx::{QueryBuilder, Row, Sqlite, sqlite::SqliteRow};
+use uuid::Uuid;
+
+use crate::functions::{decode_hex, encode_hex};
+
+/// The number of IDs in a page of a cursor listing if it is not specified.
+pub const DEFAULT_CURSOR_LIMIT: usize = 20;
+
+/// A key which a cursor listing can be sorted by. The IDs break ties, so that the order is total.
+pub trait DatalithSortKey: Copy + Eq {
+    /// The name of this key, as in `created_at`.
+    fn as_str(self) -> &'static str;
+
+    /// Find the key by its name.
+    fn from_name(name: &str) -> Option<Self>;
+}
+
+/// The column of a sort key.
+pub(crate) struct SortColumn {
+    /// The qualified column name, as in `` `resources`.`created_at` ``.
+    pub(crate) column: &'static str,
+    /// Whether the column is text rather than an integer.
+    pub(crate) text:   bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum CursorValue {
+    Integer(i64),
+    Text(String),
+}
+
+/// The position in a cursor listing where the next page starts. It is encoded into an opaque string by `Display` and decoded by `DatalithCursor::decode`.
+///
+/// A cursor remembers the key and the order of the listing it comes from, so that the iteration stays the same when it is continued.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatalithCursor<K> {
+    sort_key:   K,
+    descending: bool,
+    value:      CursorValue,
+    id:         Uuid,
+}
+
+impl<K: DatalithSortKey> DatalithCursor<K> {
+    /// Retrieve the key which the listing is sorted by.
+    #[inline]
+    pub const fn sort_key(&self) -> K {
+        self.sort_key
+    }
+
+    /// Check whether the listing is sorted in descending order.
+    #[inline]
+    pub const fn is_descending(&self) -> bool {
+        self.descending
+    }
+
+    /// Decode a cursor from its string form. Return `None` if it is malformed or it is for a different kind of listing.
+    pub fn decode(s: &str) -> Option<Self> {
+        let bytes = decode_hex(s)?;
+
+        // layout: the ID (16 bytes), the order (`a` or `d`), the key name, NUL, the value type (`i` or `t`), the value
+        if bytes.len() < 19 {
+            return None;
+        }
+
+        let id = Uuid::from_slice(&bytes[..16]).ok()?;
+
+        let descending = match bytes[16] {
+            b'a' => false,
+            b'd' => true,
+            _ => return None,
+        };
+
+        let rest = &bytes[17..];
+        let nul = rest.iter().position(|b| *b == 0)?;
+
+        let sort_key = K::from_name(str::from_utf8(&rest[..nul]).ok()?)?;
+
+        let value = str::from_utf8(rest.get(nul + 2..)?).ok()?;
+
+        let value = match rest[nul + 1] {
+            b'i' => CursorValue::Integer(value.parse().ok()?),
+            b't' => CursorValue::Text(value.to_string()),
+            _ => return None,
+        };
+
+        Some(Self {
+            sort_key,
+            descending,
+            value,
+            id,
+        })
+    }
+}
+
+impl<K: DatalithSortKey> Display for DatalithCursor<K> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut bytes = Vec::with_capacity(32);
+
+        bytes.extend_from_slice(self.id.as_bytes());
+        bytes.push(if self.descending { b'd' } else { b'a' });
+        bytes.extend_from_slice(self.sort_key.as_str().as_bytes());
+        bytes.push(0);
+
+        match &self.value {
+            CursorValue::Integer(value) => {
+                bytes.push(b'i');
+                bytes.extend_from_slice(value.to_string().as_bytes());
+            },
+            CursorValue::Text(value) => {
+                bytes.push(b't');
+                bytes.extend_from_slice(value.as_bytes());
+            },
+        }
+
+        f.write_str(&encode_hex(bytes))
+    }
+}
+
+/// The options of a cursor listing.
+#[derive(Debug, Clone)]
+pub struct DatalithCursorOptions<K> {
+    /// The key to sort by. It is ignored when `cursor` is set, because the cursor keeps the key of its listing.
+    pub sort_key:    K,
+    /// Whether to sort in descending order. It is ignored when `cursor` is set.
+    pub descending:  bool,
+    /// The maximum number of IDs in the page. At least one ID is returned if there is any.
+    pub limit:       usize,
+    /// Continue from this cursor. `None` starts from the beginning.
+    pub cursor:      Option<DatalithCursor<K>>,
+    /// Whether to count the total number of the items which match. Counting has to visit every item, so it is off by default.
+    pub count_total: bool,
+}
+
+impl<K: Default> Default for DatalithCursorOptions<K> {
+    /// Sort by the default key in descending order, which is the newest first.
+    #[inline]
+    fn default() -> Self {
+        Self {
+            sort_key:    K::default(),
+            descending:  true,
+            limit:       DEFAULT_CURSOR_LIMIT,
+            cursor:      None,
+            count_total: false,
+        }
+    }
+}
+
+/// A page of a cursor listing.
+#[derive(Debug, Clone)]
+pub struct DatalithCursorPage<K> {
+    /// The IDs in this page.
+    pub ids:         Vec<Uuid>,
+    /// The cursor of the next page, or `None` if this is the last page.
+    pub next_cursor: Option<DatalithCursor<K>>,
+    /// The total number of the items which match, if it is counted.
+    pub total_items: Option<usize>,
+}
+
+impl<K: DatalithSortKey> DatalithCursorOptions<K> {
+    /// The key and the order which are in effect.
+    #[inline]
+    pub(crate) fn sort(&self) -> (K, bool) {
+        match self.cursor.as_ref() {
+            Some(cursor) => (cursor.sort_key, cursor.descending),
+            None => (self.sort_key, self.descending),
+        }
+    }
+
+    #[inline]
+    fn limit(&self) -> usize {
+        self.limit.max(1)
+    }
+
+    /// Push the SQL conditions which skip the items up to the cursor.
+    pub(crate) fn push_seek_conditions(
+        &self,
+        builder: &mut QueryBuilder<'_, Sqlite>,
+        sort_column: &SortColumn,
+        id_column: &str,
+    ) {
+        let Some(cursor) = self.cursor.as_ref() else {
+            return;
+        };
+
+        let column = sort_column.column;
+        let operator = if cursor.descending { "<" } else { ">" };
+
+        let push_value = |builder: &mut QueryBuilder<'_, Sqlite>| match &cursor.value {
+            CursorValue::Integer(value) => {
+                builder.push_bind(*value);
+            },
+            CursorValue::Text(value) => {
+                builder.push_bind(value.clone());
+            },
+        };
+
+        builder.push(format_args!(" AND ({column} {operator} "));
+        push_value(builder);
+        builder.push(format_args!(" OR ({column} = "));
+        push_value(builder);
+        builder.push(format_args!(" AND {id_column} {operator} "));
+        builder.push_bind(cursor.id);
+        builder.push("))");
+    }
+
+    /// Push the SQL `ORDER BY` and `LIMIT` clauses. One more row than the limit is fetched in order to know whether there is a next page.
+    pub(crate) fn push_order_by_limit(
+        &self,
+        builder: &mut QueryBuilder<'_, Sqlite>,
+        sort_column: &SortColumn,
+        id_column: &str,
+    ) {
+        let column = sort_column.column;
+        let order = if self.sort().1 { "DESC" } else { "ASC" };
+
+        builder.push(format_args!(" ORDER BY {column} {order}, {id_column} {order} LIMIT "));
+        builder.push_bind(self.limit() as i64 + 1);
+    }
+
+    /// Make a page from the rows of the sort value and the ID.
+    pub(crate) fn to_page(
+        &self,
+        mut rows: Vec<SqliteRow>,
+        sort_column: &SortColumn,
+        total_items: Option<usize>,
+    ) -> Result<DatalithCursorPage<K>, sqlx::Error> {
+        let (sort_key, descending) = self.sort();
+
+        let next_cursor = if rows.len() > self.limit() {
+            rows.truncate(self.limit());
+
+            let last = rows.last().unwrap();
+
+            let value = if sort_column.text {
+                CursorValue::Text(last.try_get(0)?)
+            } else {
+                CursorValue::Integer(last.try_get(0)?)
+            };
+
+            Some(DatalithCursor {
+                sort_key,
+                descending,
+                value,
+                id: last.try_get(1)?,
+            })
+        } else {
+            None
+        };
+
+        let ids = rows.iter().map(|row| row.try_get(1)).collect::<Result<Vec<Uuid>, _>>()?;
+
+        Ok(DatalithCursorPage {
+            ids,
+            next_cursor,
+            total_items,
+        })
+    }
+}
@@ -22,8 +22,8 @@ use mime::Mime;
 use rdb_pagination::{Pagination, PaginationOptions, SqlJoin, SqlOrderByComponent, prelude::*};
 use sha2::{Digest, Sha256};
 use sqlx::{
-    Acquire, Pool, Row, Sqlite,
-    sqlite::{SqliteConnectOptions, SqlitePoolOptions, SqliteQueryResult},
+    Acquire, Pool, QueryBuilder, Row, Sqlite, Transaction,
+    sqlite::{SqliteConnectOptions, SqliteConnection, SqlitePoolOptions, SqliteQueryResult},
 };
 use tokio::{
     fs,
@@ -34,15 +34,29 @@ use tokio::{
 };
 pub use uuid::Uuid;
 
+#[cfg(feature = "compression")]
+use crate::DatalithCompressionPolicy;
 use crate::{
-    DEFAULT_MIME_TYPE, DatalithCreateError, DatalithFile, DatalithReadError, DatalithWriteError,
+    DEFAULT_FILE_DIRECTORY_DEPTH, DEFAULT_MIME_TYPE, DEFAULT_NAMESPACE, DatalithBuilder,
+    DatalithCompression, DatalithCreateError, DatalithCursorOptions, DatalithCursorPage,
+    DatalithFile, DatalithLowWaterMark, DatalithQuota, DatalithReadError, DatalithSortKey,
+    DatalithVerifyOptions, DatalithWriteError, LocalStorageBackend, MAX_FILE_DIRECTORY_DEPTH,
+    StorageBackend,
+    cursors::SortColumn,
+    disk_space::FreeSpaceChecker,
     functions::{
-        BUFFER_SIZE, allow_not_found_error, calculate_buffer_size, detect_file_type_by_buffer,
-        detect_file_type_by_path, get_current_timestamp, get_file_name, get_hash_by_buffer,
-        get_hash_by_path, get_random_hash,
+        BUFFER_SIZE, allow_not_found_error, calculate_buffer_size, decode_hex,
+        detect_file_type_by_buffer, detect_file_type_by_path, encode_hex, get_current_timestamp,
+        get_file_name, get_hash_by_buffer, get_hash_by_path, get_random_hash,
     },
     guard::{DeleteGuard, OpenGuard, PutGuard, TemporaryFileGuard},
+    metadata::delete_metadata,
+    migrations::{DATABASE_VERSION, MIGRATIONS, MigrationContext, decide_file_directory_depth},
+    quotas::add_usage,
+    search::delete_search_document,
 };
+#[cfg(feature = "encryption")]
+use crate::{DatalithEncryptionKey, EncryptedStorageBackend};
 
 /// The path to the SQLite DB file.
 pub const PATH_DB_FILE: &str = "datalith.sqlite";
@@ -51,11 +65,13 @@ pub const PATH_TEMPORARY_FILE_DIRECTORY: &str = "datalith.temp";
 /// The path to the directory where all stored files are located.
 pub const PATH_FILE_DIRECTORY: &str = "datalith.files";
 
-const DATABASE_VERSION: u32 = 1;
-const TABLE_DB_INFORMATION: &str = "sys_db_information";
+pub(crate) const TABLE_DB_INFORMATION: &str = "sys_db_information";
 
 const FILE_READ_BUFFER_SIZE: usize = 64 * 1024;
 const TEMPORARY_FILE_LIFESPAN: Duration = Duration::from_secs(60);
+const UPLOAD_SESSION_LIFESPAN: Duration = Duration::from_secs(24 * 60 * 60);
+/// The name of the directory inside the temporary directory where the partial data of upload sessions is located.
+const UPLOAD_SESSION_DIRECTORY_NAME: &str = "uploads";
 
 #[cfg(feature = "image-convert")]
 const MAX_IMAGE_RESOLUTION: u32 = 50_000_000; // 50MP
@@ -83,19 +99,83 @@ pub struct DatalithFileOrderBy {
     pub file_name:  OrderMethod,
 }
 
+/// The keys which a cursor listing of files can be sorted by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DatalithFileSortKey {
+    #[default]
+    CreatedAt,
+    FileSize,
+    FileType,
+    FileName,
+}
+
+impl DatalithSortKey for DatalithFileSortKey {
+    #[inline]
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::CreatedAt => "created_at",
+            Self::FileSize => "file_size",
+            Self::FileType => "file_type",
+            Self::FileName => "file_name",
+        }
+    }
+
+    #[inline]
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "created_at" => Some(Self::CreatedAt),
+            "file_size" => Some(Self::FileSize),
+            "file_type" => Some(Self::FileType),
+            "file_name" => Some(Self::FileName),
+            _ => None,
+        }
+    }
+}
+
+impl DatalithFileSortKey {
+    #[inline]
+    const fn sort_column(self) -> SortColumn {
+        match self {
+            Self::CreatedAt => SortColumn {
+                column: "`files`.`created_at`", text: false
+            },
+            Self::FileSize => SortColumn {
+                column: "`files`.`file_size`", text: false
+            },
+            Self::FileType => SortColumn {
+                column: "`files`.`file_type`", text: true
+            },
+            Self::FileName => SortColumn {
+                column: "`files`.`file_name`", text: true
+            },
+        }
+    }
+}
+
 #[derive(Educe)]
 #[educe(Debug(name(Datalith)))]
 pub(crate) struct DatalithInner {
     pub(crate) db:                               Pool<Sqlite>,
     environment:                                 PathBuf,
+    pub(crate) storage:                          Box<dyn StorageBackend>,
+    #[cfg(feature = "encryption")]
+    encrypted_storage:                           Option<Arc<EncryptedStorageBackend>>,
     _create_time:                                DateTime<Local>,
     _version:                                    u32,
     pub(crate) _uploading_files:                 Mutex<HashSet<[u8; 32]>>,
     pub(crate) _opening_files:                   Mutex<HashMap<Uuid, usize>>,
     pub(crate) _deleting_files:                  Mutex<HashSet<Uuid>>,
-    _sql_file:                                   File,
+    pub(crate) _appending_upload_sessions:       Mutex<HashSet<Uuid>>,
+    _sql_file:                                   Option<File>,
     pub(crate) _file_read_buffer_size:           AtomicUsize,
     pub(crate) _temporary_file_lifespan:         AtomicU64,
+    pub(crate) _upload_session_lifespan:         AtomicU64,
+    pub(crate) _url_signing_key:                 [u8; 32],
+    pub(crate) _background_verify_options:       Mutex<DatalithVerifyOptions>,
+    pub(crate) _global_quota:                    Mutex<DatalithQuota>,
+    pub(crate) _low_water_mark:                  Mutex<Option<DatalithLowWaterMark>>,
+    #[cfg(feature = "compression")]
+    pub(crate) _compression_policy:              Mutex<DatalithCompressionPolicy>,
     #[cfg(feature = "image-convert")]
     pub(crate) _max_image_resolution:            AtomicU32,
     #[cfg(feature = "image-convert")]
@@ -103,8 +183,10 @@ pub(crate) struct DatalithInner {
 }
 
 /// The Datalith file storage center.
+///
+/// Resources and images are scoped by the namespace of the handle, which is the default namespace unless the handle is created by `with_namespace`. Files are shared by all namespaces.
 #[derive(Clone)]
-pub struct Datalith(pub(crate) Arc<DatalithInner>);
+pub struct Datalith(pub(crate) Arc<DatalithInner>, pub(crate) Arc<str>);
 
 impl Debug for Datalith {
     #[inline]
@@ -120,6 +202,24 @@ impl Datalith {
         self.0.environment.as_path()
     }
 
+    /// Retrieve the version of the database schema.
+    #[inline]
+    pub fn get_database_version(&self) -> u32 {
+        self.0._version
+    }
+
+    /// Check whether this Datalith is opened read-only. See [`DatalithBuilder::read_only`].
+    #[inline]
+    pub fn is_read_only(&self) -> bool {
+        self.0._sql_file.is_none()
+    }
+
+    /// Retrieve the storage backend of this Datalith.
+    #[inline]
+    pub fn get_storage_backend(&self) -> &dyn StorageBackend {
+        self.0.storage.as_ref()
+    }
+
     /// Retrieve the size of the file read buffer.
     #[inline]
     pub fn get_file_read_buffer_size(&self) -> usize {
@@ -162,6 +262,30 @@ impl Datalith {
             ._temporary_file_lifespan
             .swap(temporary_file_lifespan.as_millis() as u64, Ordering::Relaxed);
     }
+
+    /// Retrieve the lifespan for each of the upload sessions.
+    #[inline]
+    pub fn get_upload_session_lifespan(&self) -> Duration {
+        let milli_secs = self.0._upload_session_lifespan.load(Ordering::Relaxed);
+
+        Duration::from_millis(milli_secs)
+    }
+
+    /// Set the lifespan for each of the upload sessions. An upload session expires if no data is appended to it within its lifespan.
+    ///
+    /// The minimum lifespan is **100 milliseconds**. The maximum lifespan is **10000 hours**.
+    #[inline]
+    pub fn set_upload_session_lifespan(&self, mut upload_session_lifespan: Duration) {
+        const ONE_TENTH_SECOND: Duration = Duration::from_millis(100);
+        const TEN_THOUSANDS_HOUR: Duration = Duration::from_secs(10000 * 60 * 60);
+
+        upload_session_lifespan =
+            upload_session_lifespan.clamp(ONE_TENTH_SECOND, TEN_THOUSANDS_HOUR);
+
+        self.0
+            ._upload_session_lifespan
+            .swap(upload_session_lifespan.as_millis() as u64, Ordering::Relaxed);
+    }
 }
 
 impl Datalith {
@@ -187,23 +311,26 @@ impl Datalith {
     }
 
     #[inline]
-    async fn get_file_directory(&self) -> io::Result<PathBuf> {
-        self.get_directory(PATH_FILE_DIRECTORY).await
+    async fn get_temporary_directory(&self) -> io::Result<PathBuf> {
+        self.get_directory(PATH_TEMPORARY_FILE_DIRECTORY).await
     }
 
     #[inline]
-    pub(crate) async fn get_file_path(&self, id: Uuid) -> io::Result<PathBuf> {
-        Ok(self.get_file_directory().await?.join(format!("{:x}", id.as_u128())))
+    pub(crate) async fn get_temporary_file_path(&self, temporary_id: Uuid) -> io::Result<PathBuf> {
+        Ok(self.get_temporary_directory().await?.join(format!("{:x}", temporary_id.as_u128())))
     }
 
     #[inline]
-    async fn get_temporary_directory(&self) -> io::Result<PathBuf> {
-        self.get_directory(PATH_TEMPORARY_FILE_DIRECTORY).await
+    async fn get_upload_session_directory(&self) -> io::Result<PathBuf> {
+        self.get_directory(format!(
+            "{PATH_TEMPORARY_FILE_DIRECTORY}/{UPLOAD_SESSION_DIRECTORY_NAME}"
+        ))
+        .await
     }
 
     #[inline]
-    pub(crate) async fn get_temporary_file_path(&self, temporary_id: Uuid) -> io::Result<PathBuf> {
-        Ok(self.get_temporary_directory().await?.join(format!("{:x}", temporary_id.as_u128())))
+    pub(crate) async fn get_upload_session_file_path(&self, id: Uuid) -> io::Result<PathBuf> {
+        Ok(self.get_upload_session_directory().await?.join(format!("{:x}", id.as_u128())))
     }
 
     #[inline]
@@ -214,9 +341,40 @@ impl Datalith {
 
 // UP / Down
 impl Datalith {
-    /// Create a Datalith file storage center.
+    /// Create a Datalith file storage center. The files are stored in the `datalith.files` directory of the environment.
+    ///
+    /// Use [`DatalithBuilder`] to choose another storage backend.
+    #[inline]
     pub async fn new(environment_path: impl AsRef<Path>) -> Result<Self, DatalithCreateError> {
-        let environment_path_ref = environment_path.as_ref();
+        DatalithBuilder::new(environment_path.as_ref()).build().await
+    }
+
+    /// Create a [`DatalithBuilder`].
+    #[inline]
+    pub fn builder(environment_path: impl Into<PathBuf>) -> DatalithBuilder {
+        DatalithBuilder::new(environment_path)
+    }
+
+    pub(crate) async fn new_with_storage_backend(
+        environment_path_ref: &Path,
+        storage: Option<Box<dyn StorageBackend>>,
+        file_directory_depth: Option<u8>,
+        read_only: bool,
+        #[cfg(feature = "encryption")] encryption_key: Option<DatalithEncryptionKey>,
+    ) -> Result<Self, DatalithCreateError> {
+        let file_directory_depth =
+            file_directory_depth.map(|depth| depth.min(MAX_FILE_DIRECTORY_DEPTH));
+
+        if read_only {
+            return Self::open_read_only(
+                environment_path_ref,
+                storage,
+                file_directory_depth,
+                #[cfg(feature = "encryption")]
+                encryption_key,
+            )
+            .await;
+        }
 
         let environment_path = match fs::canonicalize(environment_path_ref).await {
             Ok(environment_path_canonical) => {
@@ -274,60 +432,210 @@ impl Datalith {
                 Err(error) => return Err(error.into()),
             }
 
-            sql_file
+            Some(sql_file)
         };
 
-        let (version, create_time) = Self::initial_with_migration(&pool).await?;
+        let migration_context = MigrationContext {
+            file_directory:       environment_path.join(PATH_FILE_DIRECTORY),
+            file_directory_depth: file_directory_depth.unwrap_or(DEFAULT_FILE_DIRECTORY_DEPTH),
+        };
 
-        let uploading_files = Mutex::new(HashSet::new());
-        let opening_files = Mutex::new(HashMap::new());
-        let deleting_files = Mutex::new(HashSet::new());
+        let (version, create_time) =
+            Self::initial_with_migration(&pool, &migration_context).await?;
 
-        let datalith = Self(Arc::new(DatalithInner {
-            db:                                                                 pool,
-            environment:                                                        environment_path,
-            _create_time:                                                       create_time,
-            _version:                                                           version,
-            _uploading_files:                                                   uploading_files,
-            _opening_files:                                                     opening_files,
-            _deleting_files:                                                    deleting_files,
-            _sql_file:                                                          sql_file,
-            _file_read_buffer_size:                                             AtomicUsize::new(
-                FILE_READ_BUFFER_SIZE,
-            ),
-            _temporary_file_lifespan:                                           AtomicU64::new(
-                TEMPORARY_FILE_LIFESPAN.as_millis() as u64,
-            ),
-            #[cfg(feature = "image-convert")]
-            _max_image_resolution:                                              AtomicU32::new(
-                MAX_IMAGE_RESOLUTION,
-            ),
-            #[cfg(feature = "image-convert")]
-            _max_image_resolution_multiplier:                                   AtomicU8::new(
-                MAX_IMAGE_RESOLUTION_MULTIPLIER,
-            ),
-        }));
+        let url_signing_key = Self::initial_url_signing_key(&pool).await?;
 
-        // clear temp
+        let depth =
+            Self::initial_file_directory_depth(&pool, &migration_context, file_directory_depth)
+                .await?;
+
+        let storage = match storage {
+            Some(storage) => storage,
+            None => {
+                Box::new(LocalStorageBackend::with_depth(migration_context.file_directory, depth))
+            },
+        };
+
+        let datalith = Self::from_parts(
+            pool,
+            environment_path,
+            storage,
+            create_time,
+            version,
+            sql_file,
+            url_signing_key,
+            #[cfg(feature = "encryption")]
+            encryption_key,
+        );
+
+        // clear temp, but keep the partial data of upload sessions
         {
             let temporary_directory = datalith.get_temporary_directory().await?;
 
             let mut read_dir = fs::read_dir(temporary_directory.as_path()).await?;
 
-            if read_dir.next_entry().await?.is_some() {
-                fs::remove_dir_all(temporary_directory.as_path()).await?;
+            while let Some(entry) = read_dir.next_entry().await? {
+                let path = entry.path();
 
-                datalith.get_temporary_directory().await?;
+                if entry.file_type().await?.is_dir() {
+                    if entry.file_name() == UPLOAD_SESSION_DIRECTORY_NAME {
+                        continue;
+                    }
+
+                    allow_not_found_error(fs::remove_dir_all(path).await)?;
+                } else {
+                    allow_not_found_error(fs::remove_file(path).await)?;
+                }
             }
         }
 
         Ok(datalith)
     }
 
+    /// Open an existing environment without locking it, migrating its database or clearing its temporary files. The database is opened read-only.
+    async fn open_read_only(
+        environment_path_ref: &Path,
+        storage: Option<Box<dyn StorageBackend>>,
+        file_directory_depth: Option<u8>,
+        #[cfg(feature = "encryption")] encryption_key: Option<DatalithEncryptionKey>,
+    ) -> Result<Self, DatalithCreateError> {
+        let environment_path = fs::canonicalize(environment_path_ref).await?;
+
+        let sql_options = SqliteConnectOptions::new()
+            .filename(environment_path.join(PATH_DB_FILE))
+            .read_only(true);
+
+        let pool = SqlitePoolOptions::new()
+            .min_connections(1)
+            .max_connections(num_cpus::get() as u32)
+            .connect_with(sql_options)
+            .await?;
+
+        let (version, create_time) = {
+            let mut conn = pool.acquire().await?;
+
+            Self::fetch_information(&mut conn).await?
+        };
+
+        // the database is not migrated by a read-only instance
+        if DATABASE_VERSION < version {
+            return Err(DatalithCreateError::DatabaseTooNewError {
+                app_db_version:     DATABASE_VERSION,
+                current_db_version: version,
+            });
+        }
+
+        if DATABASE_VERSION > version {
+            return Err(DatalithCreateError::DatabaseTooOldError {
+                app_db_version:     DATABASE_VERSION,
+                current_db_version: version,
+            });
+        }
+
+        let url_signing_key = Self::fetch_url_signing_key(&pool).await?;
+
+        let depth = Self::fetch_file_directory_depth(&pool).await?;
+
+        if let Some(depth) = depth {
+            Self::check_file_directory_depth(depth, file_directory_depth)?;
+        }
+
+        let storage = match storage {
+            Some(storage) => storage,
+            None => {
+                let Some(depth) = depth else {
+                    return Err(DatalithCreateError::IOError(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "the depth of the file directory has not been decided",
+                    )));
+                };
+
+                Box::new(LocalStorageBackend::with_depth(
+                    environment_path.join(PATH_FILE_DIRECTORY),
+                    depth,
+                ))
+            },
+        };
+
+        Ok(Self::from_parts(
+            pool,
+            environment_path,
+            storage,
+            create_time,
+            version,
+            None,
+            url_signing_key,
+            #[cfg(feature = "encryption")]
+            encryption_key,
+        ))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn from_parts(
+        pool: Pool<Sqlite>,
+        environment_path: PathBuf,
+        storage: Box<dyn StorageBackend>,
+        create_time: DateTime<Local>,
+        version: u32,
+        sql_file: Option<File>,
+        url_signing_key: [u8; 32],
+        #[cfg(feature = "encryption")] encryption_key: Option<DatalithEncryptionKey>,
+    ) -> Self {
+        #[cfg(feature = "encryption")]
+        let (storage, encrypted_storage) = match encryption_key {
+            Some(encryption_key) => {
+                let encrypted_storage =
+                    Arc::new(EncryptedStorageBackend::from_boxed(storage, &encryption_key));
+
+                (
+                    Box::new(encrypted_storage.clone()) as Box<dyn StorageBackend>,
+                    Some(encrypted_storage),
+                )
+            },
+            None => (storage, None),
+        };
+
+        let uploading_files = Mutex::new(HashSet::new());
+        let opening_files = Mutex::new(HashMap::new());
+        let deleting_files = Mutex::new(HashSet::new());
+        let appending_upload_sessions = Mutex::new(HashSet::new());
+
+        let inner = Arc::new(DatalithInner {
+            db: pool,
+            environment: environment_path,
+            storage,
+            #[cfg(feature = "encryption")]
+            encrypted_storage,
+            _create_time: create_time,
+            _version: version,
+            _uploading_files: uploading_files,
+            _opening_files: opening_files,
+            _deleting_files: deleting_files,
+            _appending_upload_sessions: appending_upload_sessions,
+            _sql_file: sql_file,
+            _file_read_buffer_size: AtomicUsize::new(FILE_READ_BUFFER_SIZE),
+            _temporary_file_lifespan: AtomicU64::new(TEMPORARY_FILE_LIFESPAN.as_millis() as u64),
+            _upload_session_lifespan: AtomicU64::new(UPLOAD_SESSION_LIFESPAN.as_millis() as u64),
+            _url_signing_key: url_signing_key,
+            _background_verify_options: Mutex::new(DatalithVerifyOptions::background()),
+            _global_quota: Mutex::new(DatalithQuota::default()),
+            _low_water_mark: Mutex::new(None),
+            #[cfg(feature = "compression")]
+            _compression_policy: Mutex::new(DatalithCompressionPolicy::default()),
+            #[cfg(feature = "image-convert")]
+            _max_image_resolution: AtomicU32::new(MAX_IMAGE_RESOLUTION),
+            #[cfg(feature = "image-convert")]
+            _max_image_resolution_multiplier: AtomicU8::new(MAX_IMAGE_RESOLUTION_MULTIPLIER),
+        });
+
+        Self(inner, Arc::from(DEFAULT_NAMESPACE))
+    }
+
     async fn initial_with_migration(
         pool: &Pool<Sqlite>,
+        context: &MigrationContext,
     ) -> Result<(u32, DateTime<Local>), DatalithCreateError> {
-        let (version, create_time) = Self::initial_db_or_fetch_information(pool).await?;
+        let (version, create_time) = Self::initial_db_or_fetch_information(pool, context).await?;
 
         if DATABASE_VERSION < version {
             return Err(DatalithCreateError::DatabaseTooNewError {
@@ -337,27 +645,78 @@ impl Datalith {
         }
 
         if DATABASE_VERSION > version {
+            let mut tx = pool.begin().await?;
+
             for upgrade_version in (version + 1)..=DATABASE_VERSION {
-                #[allow(clippy::match_single_binding)]
-                match upgrade_version {
-                    2 => {
-                        // TODO
-                    },
-                    _ => {
-                        return Err(DatalithCreateError::DatabaseTooOldError {
-                            app_db_version:     DATABASE_VERSION,
-                            current_db_version: version,
-                        });
-                    },
+                let Some(migration) = MIGRATIONS.iter().find(|m| m.version == upgrade_version)
+                else {
+                    return Err(DatalithCreateError::DatabaseTooOldError {
+                        app_db_version:     DATABASE_VERSION,
+                        current_db_version: version,
+                    });
+                };
+
+                let result = async {
+                    Self::execute_sql_script(&mut tx, migration.sql).await?;
+
+                    if let Some(step) = migration.step {
+                        step(&mut tx, context).await?;
+                    }
+
+                    Ok(()) as Result<(), sqlx::Error>
+                }
+                .await;
+
+                if let Err(error) = result {
+                    return Err(DatalithCreateError::MigrationError {
+                        version: upgrade_version,
+                        error,
+                    });
                 }
+
+                tracing::info!("the database has been migrated to version {upgrade_version}");
             }
+
+            #[rustfmt::skip]
+            sqlx::query(&format!(
+                "
+                    UPDATE
+                        `{TABLE_DB_INFORMATION}`
+                    SET
+                        `value` = '{DATABASE_VERSION}'
+                    WHERE
+                        `key` = 'version'
+                "
+            ))
+            .execute(&mut *tx)
+            .await?;
+
+            tx.commit().await?;
         }
 
-        Ok((version, create_time))
+        Ok((DATABASE_VERSION, create_time))
+    }
+
+    async fn execute_sql_script(
+        tx: &mut Transaction<'_, Sqlite>,
+        sql_script: &str,
+    ) -> Result<(), sqlx::Error> {
+        for sql in sql_script.split(";\n") {
+            let sql = sql.trim();
+
+            if sql.is_empty() {
+                continue;
+            }
+
+            sqlx::query(sql).execute(&mut **tx).await?;
+        }
+
+        Ok(())
     }
 
     async fn initial_db_or_fetch_information(
         pool: &Pool<Sqlite>,
+        context: &MigrationContext,
     ) -> Result<(u32, DateTime<Local>), DatalithCreateError> {
         let mut conn = pool.acquire().await?;
 
@@ -386,23 +745,15 @@ impl Datalith {
                     INSERT INTO `{TABLE_DB_INFORMATION}`
                         VALUES
                             ('version', '{DATABASE_VERSION}'),
-                            ('create_time', '{create_time_rfc}')
+                            ('create_time', '{create_time_rfc}'),
+                            ('file_directory_depth', ?)
                 "
             ))
+            .bind(context.file_directory_depth.to_string())
             .execute(&mut *tx)
             .await?;
 
-            let schema_sql = include_str!("sql/schema.sql");
-
-            for sql in schema_sql.split(";\n") {
-                let sql = sql.trim();
-
-                if sql.is_empty() {
-                    continue;
-                }
-
-                sqlx::query(sql).execute(&mut *tx).await?;
-            }
+            Self::execute_sql_script(&mut tx, include_str!("sql/schema.sql")).await?;
 
             tx.commit().await?;
 
@@ -410,50 +761,161 @@ impl Datalith {
         } else {
             tx.commit().await?;
 
-            let version = {
-                #[rustfmt::skip]
-                let row = sqlx::query(&format!(
-                    "
-                        SELECT
-                            `value`
-                        FROM
-                            `{TABLE_DB_INFORMATION}`
-                        WHERE
-                            `key` = 'version'
-                    "
-                ))
-                .fetch_one(&mut *conn)
-                .await?;
+            Self::fetch_information(&mut conn).await?
+        };
 
-                row.get::<String, _>(0).parse().unwrap()
-            };
+        Ok((version, create_time))
+    }
 
-            let create_time = {
-                #[rustfmt::skip]
-                let row = sqlx::query(&format!(
-                    "
-                        SELECT
-                            `value`
-                        FROM
-                            `{TABLE_DB_INFORMATION}`
-                        WHERE
-                            `key` = 'create_time'
-                    "
-                ))
-                .fetch_one(&mut *conn)
-                .await?;
+    async fn fetch_information(
+        conn: &mut SqliteConnection,
+    ) -> Result<(u32, DateTime<Local>), DatalithCreateError> {
+        let version = {
+            #[rustfmt::skip]
+            let row = sqlx::query(&format!(
+                "
+                    SELECT
+                        `value`
+                    FROM
+                        `{TABLE_DB_INFORMATION}`
+                    WHERE
+                        `key` = 'version'
+                "
+            ))
+            .fetch_one(&mut *conn)
+            .await?;
 
-                let create_time_rfc = row.get::<String, _>(0);
+            row.get::<String, _>(0).parse().unwrap()
+        };
 
-                DateTime::parse_from_rfc3339(&create_time_rfc).unwrap().into()
-            };
+        let create_time = {
+            #[rustfmt::skip]
+            let row = sqlx::query(&format!(
+                "
+                    SELECT
+                        `value`
+                    FROM
+                        `{TABLE_DB_INFORMATION}`
+                    WHERE
+                        `key` = 'create_time'
+                "
+            ))
+            .fetch_one(&mut *conn)
+            .await?;
+
+            let create_time_rfc = row.get::<String, _>(0);
 
-            (version, create_time)
+            DateTime::parse_from_rfc3339(&create_time_rfc).unwrap().into()
         };
 
         Ok((version, create_time))
     }
 
+    /// Load the key used for signing URLs. The key is generated when it does not exist.
+    async fn initial_url_signing_key(pool: &Pool<Sqlite>) -> Result<[u8; 32], sqlx::Error> {
+        #[rustfmt::skip]
+        sqlx::query(&format!(
+            "
+                INSERT OR IGNORE INTO `{TABLE_DB_INFORMATION}`
+                    VALUES
+                        ('url_signing_key', ?)
+            "
+        ))
+        .bind(encode_hex(get_random_hash()))
+        .execute(pool)
+        .await?;
+
+        Self::fetch_url_signing_key(pool).await
+    }
+
+    async fn fetch_url_signing_key(pool: &Pool<Sqlite>) -> Result<[u8; 32], sqlx::Error> {
+        #[rustfmt::skip]
+        let row = sqlx::query(&format!(
+            "
+                SELECT
+                    `value`
+                FROM
+                    `{TABLE_DB_INFORMATION}`
+                WHERE
+                    `key` = 'url_signing_key'
+            "
+        ))
+        .fetch_one(pool)
+        .await?;
+
+        let url_signing_key = row.get::<String, _>(0);
+
+        match decode_hex(&url_signing_key).and_then(|key| <[u8; 32]>::try_from(key).ok()) {
+            Some(key) => Ok(key),
+            None => Err(sqlx::Error::Decode("the URL signing key is broken".into())),
+        }
+    }
+
+    /// Load the depth of nested directories of the file directory, which is decided when the environment is created or migrated. An environment which has no decided depth gets one here.
+    async fn initial_file_directory_depth(
+        pool: &Pool<Sqlite>,
+        context: &MigrationContext,
+        file_directory_depth: Option<u8>,
+    ) -> Result<u8, DatalithCreateError> {
+        let depth = match Self::fetch_file_directory_depth(pool).await? {
+            Some(depth) => depth,
+            None => {
+                let mut conn = pool.acquire().await?;
+
+                decide_file_directory_depth(&mut conn, context).await?;
+
+                context.file_directory_depth
+            },
+        };
+
+        Self::check_file_directory_depth(depth, file_directory_depth)?;
+
+        Ok(depth)
+    }
+
+    /// The depth cannot be changed after the environment is created, so a different requested depth is refused instead of being ignored.
+    #[inline]
+    fn check_file_directory_depth(
+        depth: u8,
+        file_directory_depth: Option<u8>,
+    ) -> Result<(), DatalithCreateError> {
+        match file_directory_depth {
+            Some(requested_depth) if requested_depth != depth => {
+                Err(DatalithCreateError::FileDirectoryDepthMismatch {
+                    requested_depth,
+                    current_depth: depth,
+                })
+            },
+            _ => Ok(()),
+        }
+    }
+
+    async fn fetch_file_directory_depth(pool: &Pool<Sqlite>) -> Result<Option<u8>, sqlx::Error> {
+        #[rustfmt::skip]
+        let row = sqlx::query(&format!(
+            "
+                SELECT
+                    `value`
+                FROM
+                    `{TABLE_DB_INFORMATION}`
+                WHERE
+                    `key` = 'file_directory_depth'
+            "
+        ))
+        .fetch_optional(pool)
+        .await?;
+
+        match row {
+            Some(row) => match row.get::<String, _>(0).parse::<u8>() {
+                Ok(depth) => Ok(Some(depth)),
+                Err(_) => {
+                    Err(sqlx::Error::Decode("the depth of the file directory is broken".into()))
+                },
+            },
+            None => Ok(None),
+        }
+    }
+
     #[inline]
     fn check_create_table_already_exist(
         result: Result<SqliteQueryResult, sqlx::Error>,
@@ -478,11 +940,24 @@ impl Datalith {
         self.0.db.close().await;
     }
 
-    /// Close the Datalith file storage center and remove the entire database and associated files.
+    /// Close the Datalith file storage center and remove the entire database and associated files. A read-only Datalith is only closed, and an error is returned.
     #[inline]
     pub async fn drop_datalith(self) -> Result<(), io::Error> {
         self.0.db.close().await;
 
+        if self.is_read_only() {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "a read-only Datalith cannot be dropped",
+            ));
+        }
+
+        for id in self.0.storage.list().await? {
+            self.0.storage.delete(id).await?;
+        }
+
+        self.0.storage.clear_unrecognized().await?;
+
         // remove associated files
         allow_not_found_error(fs::remove_file(self.0.environment.join(PATH_DB_FILE)).await)?;
         allow_not_found_error(
@@ -520,16 +995,19 @@ pub enum FileTypeLevel {
 
 // Permanent Upload
 impl Datalith {
-    /// Input a file into Datalith using a buffer.
+    /// Input a file into Datalith using a buffer. If `expected_hash` is set, the SHA-256 hash of the data has to match it.
     pub async fn put_file_by_buffer(
         &self,
         buffer: impl AsRef<[u8]>,
         file_name: Option<impl Into<String>>,
         file_type: Option<(Mime, FileTypeLevel)>,
+        expected_hash: Option<&[u8; 32]>,
     ) -> Result<DatalithFile, DatalithWriteError> {
         let file_data = buffer.as_ref();
         let hash = get_hash_by_buffer(file_data);
 
+        check_expected_hash(&hash, expected_hash)?;
+
         let _put_guard = PutGuard::new(self.clone(), hash).await;
 
         if let Some(file) = self.get_file_by_hash(&hash).await? {
@@ -574,13 +1052,24 @@ impl Datalith {
         let expired_at =
             if temporary { Some(self.get_expired_timestamp(created_at)) } else { None };
 
+        let temporary_file_path = self.get_temporary_file_path(Uuid::new_v4()).await?;
+        let _file_guard = TemporaryFileGuard::new(temporary_file_path.as_path());
+
+        self.free_space_checker().check(file_size as u64)?;
+
+        fs::write(temporary_file_path.as_path(), file_data).await?;
+
+        let compression = self
+            .compress_temporary_file(&file_type, file_size as u64, temporary_file_path.as_path())
+            .await?;
+
         let mut tx = self.0.db.begin().await?;
 
         #[rustfmt::skip]
         let result = sqlx::query(
             "
-                INSERT INTO `files` (`id`, `hash`, `created_at`, `file_size`, `file_type`, `file_name`, `expired_at`)
-                    VALUES (?, ?, ?, ?, ?, ?, ?)
+                INSERT INTO `files` (`id`, `hash`, `created_at`, `file_size`, `file_type`, `file_name`, `compression`, `expired_at`)
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?)
             ",
         )
         .bind(id)
@@ -589,18 +1078,17 @@ impl Datalith {
         .bind(file_size)
         .bind(file_type.essence_str())
         .bind(file_name.as_str())
+        .bind(compression.to_i64())
         .bind(expired_at)
         .execute(&mut *tx)
         .await?;
 
         debug_assert!(result.rows_affected() > 0);
 
-        let file_path = self.get_file_path(id).await?;
-
         // protect this id before actually store in the DB
         let open_guard = OpenGuard::new(self.clone(), id).await;
 
-        fs::write(file_path, file_data).await?;
+        self.0.storage.put_from_temporary_file(id, temporary_file_path.as_path()).await?;
 
         tx.commit().await?;
 
@@ -608,10 +1096,14 @@ impl Datalith {
             self.clone(),
             open_guard,
             id,
+            hash,
             created_at,
             file_size as u64,
             file_type,
             file_name,
+            compression,
+            None,
+            false,
             expired_at.is_some(),
             true,
         );
@@ -619,17 +1111,20 @@ impl Datalith {
         Ok(file)
     }
 
-    /// Input a file into Datalith using a file path.
+    /// Input a file into Datalith using a file path. If `expected_hash` is set, the SHA-256 hash of the file has to match it.
     pub async fn put_file_by_path(
         &self,
         file_path: impl AsRef<Path>,
         file_name: Option<impl Into<String>>,
         file_type: Option<(Mime, FileTypeLevel)>,
+        expected_hash: Option<&[u8; 32]>,
     ) -> Result<DatalithFile, DatalithWriteError> {
         let file_path = file_path.as_ref();
 
         let hash = get_hash_by_path(file_path).await?;
 
+        check_expected_hash(&hash, expected_hash)?;
+
         let _put_guard = PutGuard::new(self.clone(), hash).await;
 
         if let Some(file) = self.get_file_by_hash(&hash).await? {
@@ -685,13 +1180,24 @@ impl Datalith {
         let expired_at =
             if temporary { Some(self.get_expired_timestamp(created_at)) } else { None };
 
+        let temporary_file_path = self.get_temporary_file_path(Uuid::new_v4()).await?;
+        let _file_guard = TemporaryFileGuard::new(temporary_file_path.as_path());
+
+        self.free_space_checker().check(file_size as u64)?;
+
+        fs::copy(file_path, temporary_file_path.as_path()).await?;
+
+        let compression = self
+            .compress_temporary_file(&file_type, file_size as u64, temporary_file_path.as_path())
+            .await?;
+
         let mut tx = self.0.db.begin().await?;
 
         #[rustfmt::skip]
         let result = sqlx::query(
             "
-                INSERT INTO `files` (`id`, `hash`, `created_at`, `file_size`, `file_type`, `file_name`, `expired_at`)
-                    VALUES (?, ?, ?, ?, ?, ?, ?)
+                INSERT INTO `files` (`id`, `hash`, `created_at`, `file_size`, `file_type`, `file_name`, `compression`, `expired_at`)
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?)
             ",
         )
         .bind(id)
@@ -700,19 +1206,17 @@ impl Datalith {
         .bind(file_size)
         .bind(file_type.essence_str())
         .bind(file_name.as_str())
+        .bind(compression.to_i64())
         .bind(expired_at)
         .execute(&mut *tx)
         .await?;
 
         debug_assert!(result.rows_affected() > 0);
 
-        let original_file_path = file_path;
-        let file_path = self.get_file_path(id).await?;
-
         // protect this id before actually store in the DB
         let open_guard = OpenGuard::new(self.clone(), id).await;
 
-        fs::copy(original_file_path, file_path).await?;
+        self.0.storage.put_from_temporary_file(id, temporary_file_path.as_path()).await?;
 
         tx.commit().await?;
 
@@ -720,10 +1224,14 @@ impl Datalith {
             self.clone(),
             open_guard,
             id,
+            hash,
             created_at,
             file_size as u64,
             file_type,
             file_name,
+            compression,
+            None,
+            false,
             expired_at.is_some(),
             true,
         );
@@ -731,13 +1239,14 @@ impl Datalith {
         Ok(file)
     }
 
-    /// Input a file into Datalith using a reader.
+    /// Input a file into Datalith using a reader. If `expected_hash` is set, the SHA-256 hash of the data has to match it.
     pub async fn put_file_by_reader(
         &self,
         reader: impl AsyncRead + Unpin,
         file_name: Option<impl Into<String>>,
         file_type: Option<(Mime, FileTypeLevel)>,
         expected_reader_length: Option<u64>,
+        expected_hash: Option<&[u8; 32]>,
     ) -> Result<DatalithFile, DatalithWriteError> {
         let temporary_file_path = self.get_temporary_file_path(Uuid::new_v4()).await?;
 
@@ -745,9 +1254,58 @@ impl Datalith {
             reader,
             temporary_file_path.as_path(),
             expected_reader_length,
+            self.free_space_checker(),
         )
         .await?;
-        let mut file_guard = TemporaryFileGuard::new(temporary_file_path.as_path());
+        let _file_guard = TemporaryFileGuard::new(temporary_file_path.as_path());
+
+        check_expected_hash(&hash, expected_hash)?;
+
+        let _put_guard = PutGuard::new(self.clone(), hash).await;
+
+        if let Some(file) = self.get_file_by_hash(&hash).await? {
+            #[rustfmt::skip]
+            let result = sqlx::query(
+                "
+                    UPDATE
+                        `files`
+                    SET
+                        `count` = `count` + 1
+                    WHERE
+                        `id` = ?
+                ",
+            )
+            .bind(file.id())
+            .execute(&self.0.db)
+            .await?;
+
+            debug_assert!(result.rows_affected() > 0);
+
+            Ok(file)
+        } else {
+            self.put_file_by_reader_inner(
+                hash,
+                temporary_file_path,
+                file_size,
+                file_name,
+                file_type,
+                false,
+            )
+            .await
+        }
+    }
+
+    /// Input a file which has been completely written in the temporary directory into Datalith. The temporary file will be moved or removed.
+    pub(crate) async fn put_file_by_temporary_file(
+        &self,
+        temporary_file_path: PathBuf,
+        file_name: Option<impl Into<String>>,
+        file_type: Option<(Mime, FileTypeLevel)>,
+    ) -> Result<DatalithFile, DatalithWriteError> {
+        let _file_guard = TemporaryFileGuard::new(temporary_file_path.as_path());
+
+        let file_size = fs::metadata(temporary_file_path.as_path()).await?.len();
+        let hash = get_hash_by_path(temporary_file_path.as_path()).await?;
 
         let _put_guard = PutGuard::new(self.clone(), hash).await;
 
@@ -774,7 +1332,6 @@ impl Datalith {
             self.put_file_by_reader_inner(
                 hash,
                 temporary_file_path,
-                &mut file_guard,
                 file_size,
                 file_name,
                 file_type,
@@ -789,7 +1346,6 @@ impl Datalith {
         &self,
         hash: [u8; 32],
         temporary_file_path: PathBuf,
-        file_guard: &mut TemporaryFileGuard,
         file_size: u64,
         file_name: Option<impl Into<String>>,
         file_type: Option<(Mime, FileTypeLevel)>,
@@ -805,13 +1361,17 @@ impl Datalith {
         let expired_at =
             if temporary { Some(self.get_expired_timestamp(created_at)) } else { None };
 
+        let compression = self
+            .compress_temporary_file(&file_type, file_size, temporary_file_path.as_path())
+            .await?;
+
         let mut tx = self.0.db.begin().await?;
 
         #[rustfmt::skip]
         let result = sqlx::query(
             "
-                INSERT INTO `files` (`id`, `hash`, `created_at`, `file_size`, `file_type`, `file_name`, `expired_at`)
-                    VALUES (?, ?, ?, ?, ?, ?, ?)
+                INSERT INTO `files` (`id`, `hash`, `created_at`, `file_size`, `file_type`, `file_name`, `compression`, `expired_at`)
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?)
             ",
         )
         .bind(id)
@@ -820,20 +1380,17 @@ impl Datalith {
         .bind(file_size as i64)
         .bind(file_type.essence_str())
         .bind(file_name.as_str())
+        .bind(compression.to_i64())
         .bind(expired_at)
         .execute(&mut *tx)
         .await?;
 
         debug_assert!(result.rows_affected() > 0);
 
-        let original_file_path = temporary_file_path;
-        let file_path = self.get_file_path(id).await?;
-
         // protect this id before actually store in the DB
         let open_guard = OpenGuard::new(self.clone(), id).await;
 
-        fs::rename(original_file_path, file_path).await?;
-        file_guard.set_moved();
+        self.0.storage.put_from_temporary_file(id, temporary_file_path.as_path()).await?;
 
         tx.commit().await?;
 
@@ -841,10 +1398,14 @@ impl Datalith {
             self.clone(),
             open_guard,
             id,
+            hash,
             created_at,
             file_size,
             file_type,
             file_name,
+            compression,
+            None,
+            false,
             expired_at.is_some(),
             true,
         );
@@ -855,7 +1416,7 @@ impl Datalith {
 
 // Temporary Upload
 impl Datalith {
-    /// Temporarily input a file into Datalith using a buffer.
+    /// Temporarily input a file into Datalith using a buffer. If `expected_hash` is set, the SHA-256 hash of the data has to match it.
     ///
     /// The term `temporarily` means the file can be retrieved using the `get_file_by_id` function only once. After that, it cannot be retrieved again.
     pub async fn put_file_by_buffer_temporarily(
@@ -863,13 +1424,18 @@ impl Datalith {
         buffer: impl AsRef<[u8]>,
         file_name: Option<impl Into<String>>,
         file_type: Option<(Mime, FileTypeLevel)>,
+        expected_hash: Option<&[u8; 32]>,
     ) -> Result<DatalithFile, DatalithWriteError> {
+        if expected_hash.is_some() {
+            check_expected_hash(&get_hash_by_buffer(buffer.as_ref()), expected_hash)?;
+        }
+
         let hash = get_random_hash(); // we can assume this hash will not be duplicated
 
         self.put_file_by_buffer_inner(hash, buffer.as_ref(), file_name, file_type, true).await
     }
 
-    /// Temporarily input a file into Datalith using a file path.
+    /// Temporarily input a file into Datalith using a file path. If `expected_hash` is set, the SHA-256 hash of the file has to match it.
     ///
     /// The term `temporarily` means the file can be retrieved using the `get_file_by_id` function only once. After that, it cannot be retrieved again.
     pub async fn put_file_by_path_temporarily(
@@ -877,13 +1443,18 @@ impl Datalith {
         file_path: impl AsRef<Path>,
         file_name: Option<impl Into<String>>,
         file_type: Option<(Mime, FileTypeLevel)>,
+        expected_hash: Option<&[u8; 32]>,
     ) -> Result<DatalithFile, DatalithWriteError> {
+        if expected_hash.is_some() {
+            check_expected_hash(&get_hash_by_path(file_path.as_ref()).await?, expected_hash)?;
+        }
+
         let hash = get_random_hash(); // we can assume this hash will not be duplicated
 
         self.put_file_by_path_inner(hash, file_path.as_ref(), file_name, file_type, true).await
     }
 
-    /// Temporarily input a file into Datalith using a reader.
+    /// Temporarily input a file into Datalith using a reader. If `expected_hash` is set, the SHA-256 hash of the data has to match it.
     ///
     /// The term `temporarily` means the file can be retrieved using the `get_file_by_id` function only once. After that, it cannot be retrieved again.
     pub async fn put_file_by_reader_temporarily(
@@ -892,23 +1463,43 @@ impl Datalith {
         file_name: Option<impl Into<String>>,
         file_type: Option<(Mime, FileTypeLevel)>,
         expected_reader_length: Option<u64>,
+        expected_hash: Option<&[u8; 32]>,
     ) -> Result<DatalithFile, DatalithWriteError> {
         let temporary_file_path = self.get_temporary_file_path(Uuid::new_v4()).await?;
 
         let hash = get_random_hash(); // we can assume this hash will not be duplicated
 
-        let file_size = get_file_size_by_reader_and_copy_to_file(
-            reader,
-            temporary_file_path.as_path(),
-            expected_reader_length,
-        )
-        .await?;
-        let mut file_guard = TemporaryFileGuard::new(temporary_file_path.as_path());
+        // the data is only hashed if it has to be checked
+        let (file_size, actual_hash) = if expected_hash.is_some() {
+            let (file_size, actual_hash) = get_file_size_and_hash_by_reader_and_copy_to_file(
+                reader,
+                temporary_file_path.as_path(),
+                expected_reader_length,
+                self.free_space_checker(),
+            )
+            .await?;
+
+            (file_size, Some(actual_hash))
+        } else {
+            let file_size = get_file_size_by_reader_and_copy_to_file(
+                reader,
+                temporary_file_path.as_path(),
+                expected_reader_length,
+                self.free_space_checker(),
+            )
+            .await?;
+
+            (file_size, None)
+        };
+        let _file_guard = TemporaryFileGuard::new(temporary_file_path.as_path());
+
+        if let Some(actual_hash) = actual_hash {
+            check_expected_hash(&actual_hash, expected_hash)?;
+        }
 
         self.put_file_by_reader_inner(
             hash,
             temporary_file_path,
-            &mut file_guard,
             file_size,
             file_name,
             file_type,
@@ -925,10 +1516,11 @@ impl Datalith {
         let current_timestamp = get_current_timestamp();
 
         #[rustfmt::skip]
-        let rows: Vec<(Uuid,)> = sqlx::query_as(
+        let rows: Vec<(Uuid, i64)> = sqlx::query_as(
             "
                 SELECT
-                    `id`
+                    `id`,
+                    `file_size`
                 FROM
                     `files`
                 WHERE
@@ -941,21 +1533,35 @@ impl Datalith {
 
         let mut tasks = JoinSet::new();
 
-        for (id,) in rows {
+        for (id, file_size) in rows {
             let datalith = self.clone();
 
             tasks.spawn(async move {
+                let mut tx = datalith.0.db.begin().await?;
+
                 #[rustfmt::skip]
-                sqlx::query(
+                let resources: Vec<(Uuid, String)> = sqlx::query_as(
                     "
                         DELETE FROM
                             `resources`
                         WHERE
                             `file_id` = ?
+                        RETURNING
+                            `id`,
+                            `namespace`
                     ",
                 )
                 .bind(id)
-                .execute(&datalith.0.db).await?;
+                .fetch_all(&mut *tx).await?;
+
+                for (resource_id, namespace) in resources {
+                    delete_metadata(&mut tx, resource_id).await?;
+                    delete_search_document(&mut tx, resource_id).await?;
+
+                    add_usage(&mut tx, namespace.as_str(), -file_size, -1).await?;
+                }
+
+                tx.commit().await?;
 
                 // files are all temporary, so the count should always be 1
                 // we don't need a loop to ensure that if a file is uploaded multiple times, it should be deleted multiple times
@@ -978,45 +1584,28 @@ impl Datalith {
         Ok(counter)
     }
 
-    /// Clear untracked files in the file system.
-    pub async fn clear_untracked_files(&self) -> Result<usize, DatalithReadError> {
-        let file_directory = self.get_file_directory().await?;
-
-        let mut counter = 0usize;
-
-        for dir in file_directory.read_dir()? {
-            let entry = dir?;
-
-            let path = entry.path();
-
-            let metadata = fs::metadata(path.as_path()).await?;
-
-            if metadata.is_dir() {
-                allow_not_found_error(fs::remove_dir_all(path).await)?;
-                counter += 1;
-
-                continue;
-            } else if metadata.is_symlink() {
-                allow_not_found_error(fs::remove_file(path).await)?;
-                counter += 1;
-
-                continue;
-            }
-
-            let file_id = if let Some(file_id) = path
-                .file_name()
-                .and_then(|e| e.to_str())
-                .and_then(|e| u128::from_str_radix(e, 16).ok())
-                .map(Uuid::from_u128)
-            {
-                file_id
-            } else {
-                allow_not_found_error(fs::remove_file(path.as_path()).await)?;
-                counter += 1;
+    /// Encrypt the stored files which were put before the encryption was enabled. Return the number of the encrypted files.
+    ///
+    /// This should be run when no file is being read, for example, before starting a service.
+    #[cfg(feature = "encryption")]
+    pub async fn encrypt_existing_files(&self) -> Result<usize, DatalithReadError> {
+        match self.0.encrypted_storage.as_ref() {
+            Some(encrypted_storage) => {
+                let temporary_directory = self.get_temporary_directory().await?;
+
+                Ok(encrypted_storage.encrypt_existing_files(temporary_directory.as_path()).await?)
+            },
+            None => {
+                Err(io::Error::new(ErrorKind::InvalidInput, "the encryption is not enabled").into())
+            },
+        }
+    }
 
-                continue;
-            };
+    /// Clear untracked files in the storage.
+    pub async fn clear_untracked_files(&self) -> Result<usize, DatalithReadError> {
+        let mut counter = self.0.storage.clear_unrecognized().await?;
 
+        for file_id in self.0.storage.list().await? {
             if !self.check_file_exist(file_id).await? {
                 {
                     let opening_files = self.0._opening_files.lock().unwrap();
@@ -1026,7 +1615,7 @@ impl Datalith {
                     }
                 }
 
-                allow_not_found_error(fs::remove_file(path.as_path()).await)?;
+                self.0.storage.delete(file_id).await?;
                 counter += 1;
             }
         }
@@ -1108,13 +1697,18 @@ impl Datalith {
         };
 
         #[rustfmt::skip]
-        let row: Option<(i64, u64, String, String)> = sqlx::query_as(
+        #[allow(clippy::type_complexity)]
+        let row: Option<(Vec<u8>, i64, u64, String, String, i64, Option<i64>, Option<i64>)> = sqlx::query_as(
             "
                 SELECT
+                    `hash`,
                     `created_at`,
                     `file_size`,
                     `file_type`,
-                    `file_name`
+                    `file_name`,
+                    `compression`,
+                    `verified_at`,
+                    `quarantined_at`
                 FROM
                     `files`
                 WHERE
@@ -1127,7 +1721,17 @@ impl Datalith {
         .fetch_optional(&self.0.db)
         .await?;
 
-        if let Some((created_at, file_size, file_type, file_name)) = row {
+        if let Some((
+            hash,
+            created_at,
+            file_size,
+            file_type,
+            file_name,
+            compression,
+            verified_at,
+            quarantined_at,
+        )) = row
+        {
             let created_at = DateTime::from_timestamp_millis(created_at).unwrap();
             let file_type = Mime::from_str(&file_type).unwrap();
 
@@ -1135,10 +1739,15 @@ impl Datalith {
                 self.clone(),
                 guard,
                 id,
+                hash.try_into().unwrap(),
                 created_at,
                 file_size,
                 file_type,
                 file_name,
+                DatalithCompression::from_i64(compression),
+                verified_at
+                    .map(|verified_at| DateTime::from_timestamp_millis(verified_at).unwrap()),
+                quarantined_at.is_some(),
                 is_temporary,
                 false,
             );
@@ -1157,7 +1766,17 @@ impl Datalith {
 
         #[rustfmt::skip]
         #[allow(clippy::type_complexity)]
-        let row: Option<(Uuid, i64, u64, String, String, Option<i64>)> = sqlx::query_as(
+        let row: Option<(
+            Uuid,
+            i64,
+            u64,
+            String,
+            String,
+            i64,
+            Option<i64>,
+            Option<i64>,
+            Option<i64>,
+        )> = sqlx::query_as(
             "
                 SELECT
                     `id`,
@@ -1165,6 +1784,9 @@ impl Datalith {
                     `file_size`,
                     `file_type`,
                     `file_name`,
+                    `compression`,
+                    `verified_at`,
+                    `quarantined_at`,
                     `expired_at`
                 FROM
                     `files`
@@ -1178,7 +1800,18 @@ impl Datalith {
         .fetch_optional(&self.0.db)
         .await?;
 
-        if let Some((id, created_at, file_size, file_type, file_name, expired_at)) = row {
+        if let Some((
+            id,
+            created_at,
+            file_size,
+            file_type,
+            file_name,
+            compression,
+            verified_at,
+            quarantined_at,
+            expired_at,
+        )) = row
+        {
             // protect ID
             let guard = OpenGuard::new(self.clone(), id).await;
 
@@ -1217,10 +1850,15 @@ impl Datalith {
                 self.clone(),
                 guard,
                 id,
+                *hash,
                 created_at,
                 file_size,
                 file_type,
                 file_name,
+                DatalithCompression::from_i64(compression),
+                verified_at
+                    .map(|verified_at| DateTime::from_timestamp_millis(verified_at).unwrap()),
+                quarantined_at.is_some(),
                 is_temporary,
                 false,
             );
@@ -1317,6 +1955,58 @@ impl Datalith {
             return Ok((ids, pagination));
         }
     }
+
+    /// List file IDs by seeking from a cursor. Unlike `list_file_ids`, pages do not shift when files are added or removed during the iteration.
+    pub async fn list_file_ids_by_cursor(
+        &self,
+        options: &DatalithCursorOptions<DatalithFileSortKey>,
+    ) -> Result<DatalithCursorPage<DatalithFileSortKey>, DatalithReadError> {
+        let sort_column = options.sort().0.sort_column();
+        let current_timestamp = get_current_timestamp();
+
+        let total_items = if options.count_total {
+            #[rustfmt::skip]
+            let row: (u32,) = sqlx::query_as(
+                "
+                    SELECT
+                        COUNT(*)
+                    FROM
+                        `files`
+                    WHERE
+                        (`expired_at` IS NULL OR `expired_at` > ?)
+                ",
+            )
+            .bind(current_timestamp)
+            .fetch_one(&self.0.db)
+            .await?;
+
+            Some(row.0 as usize)
+        } else {
+            None
+        };
+
+        #[rustfmt::skip]
+        let mut builder = QueryBuilder::new(format!(
+            "
+                SELECT
+                    {},
+                    `files`.`id`
+                FROM
+                    `files`
+                WHERE
+                    (`files`.`expired_at` IS NULL OR `files`.`expired_at` > ",
+            sort_column.column
+        ));
+
+        builder.push_bind(current_timestamp);
+        builder.push(")");
+        options.push_seek_conditions(&mut builder, &sort_column, "`files`.`id`");
+        options.push_order_by_limit(&mut builder, &sort_column, "`files`.`id`");
+
+        let rows = builder.build().fetch_all(&self.0.db).await?;
+
+        Ok(options.to_page(rows, &sort_column, total_items)?)
+    }
 }
 
 // Delete
@@ -1440,9 +2130,7 @@ impl Datalith {
             },
         }
 
-        let file_path = self.get_file_path(id).await?;
-
-        allow_not_found_error(fs::remove_file(file_path).await)?;
+        self.0.storage.delete(id).await?;
 
         tx.commit().await?;
 
@@ -1486,13 +2174,32 @@ async fn handle_file_type(
     }
 }
 
+/// Check the SHA-256 hash of the data against the one expected by the client, if any.
+#[inline]
+#[allow(clippy::result_large_err)]
+fn check_expected_hash(
+    hash: &[u8; 32],
+    expected_hash: Option<&[u8; 32]>,
+) -> Result<(), DatalithWriteError> {
+    match expected_hash {
+        Some(expected_hash) if expected_hash != hash => Err(DatalithWriteError::HashMismatch {
+            hash:          *hash,
+            expected_hash: *expected_hash,
+        }),
+        _ => Ok(()),
+    }
+}
+
 pub(crate) async fn get_file_size_by_reader_and_copy_to_file(
     mut reader: impl AsyncRead + Unpin,
     file_path: impl AsRef<Path>,
     expected_reader_length: Option<u64>,
+    mut free_space_checker: FreeSpaceChecker,
 ) -> Result<u64, DatalithWriteError> {
     let file_path = file_path.as_ref();
 
+    free_space_checker.check(expected_reader_length.unwrap_or(0))?;
+
     let mut file = File::create(file_path).await?;
 
     // copy the data
@@ -1523,6 +2230,11 @@ pub(crate) async fn get_file_size_by_reader_and_copy_to_file(
                 },
             };
 
+            if let Err(error) = free_space_checker.check_written(c as u64) {
+                fs::remove_file(file_path).await?;
+                return Err(error);
+            }
+
             match file.write_all(&buffer[..c]).await {
                 Ok(_) => (),
                 Err(error) => {
@@ -1590,6 +2302,11 @@ pub(crate) async fn get_file_size_by_reader_and_copy_to_file(
                 },
             };
 
+            if let Err(error) = free_space_checker.check_written(c as u64) {
+                fs::remove_file(file_path).await?;
+                return Err(error);
+            }
+
             match file.write_all(&buffer[..c]).await {
                 Ok(_) => (),
                 Err(error) => {
@@ -1611,9 +2328,12 @@ async fn get_file_size_and_hash_by_reader_and_copy_to_file(
     mut reader: impl AsyncRead + Unpin,
     file_path: impl AsRef<Path>,
     expected_reader_length: Option<u64>,
+    mut free_space_checker: FreeSpaceChecker,
 ) -> Result<(u64, [u8; 32]), DatalithWriteError> {
     let file_path = file_path.as_ref();
 
+    free_space_checker.check(expected_reader_length.unwrap_or(0))?;
+
     let mut hasher = Sha256::new();
     let mut file = File::create(file_path).await?;
 
@@ -1645,6 +2365,11 @@ async fn get_file_size_and_hash_by_reader_and_copy_to_file(
                 },
             };
 
+            if let Err(error) = free_space_checker.check_written(c as u64) {
+                fs::remove_file(file_path).await?;
+                return Err(error);
+            }
+
             match file.write_all(&buffer[..c]).await {
                 Ok(_) => (),
                 Err(error) => {
@@ -1714,6 +2439,11 @@ async fn get_file_size_and_hash_by_reader_and_copy_to_file(
                 },
             };
 
+            if let Err(error) = free_space_checker.check_written(c as u64) {
+                fs::remove_file(file_path).await?;
+                return Err(error);
+            }
+
             match file.write_all(&buffer[..c]).await {
                 Ok(_) => (),
                 Err(error) => {
@@ -0,0 +1,78 @@
+use std::path::PathBuf;
+
+#[cfg(feature = "encryption")]
+use crate::DatalithEncryptionKey;
+use crate::{Datalith, DatalithCreateError, StorageBackend};
+
+/// A builder for creating a [`Datalith`] file storage center.
+#[derive(Debug)]
+pub struct DatalithBuilder {
+    environment_path:     PathBuf,
+    storage_backend:      Option<Box<dyn StorageBackend>>,
+    file_directory_depth: Option<u8>,
+    read_only:            bool,
+    #[cfg(feature = "encryption")]
+    encryption_key:       Option<DatalithEncryptionKey>,
+}
+
+impl DatalithBuilder {
+    /// Create a builder. `environment_path` is the root path of the environment, where the SQLite DB file and the temporary files are put.
+    #[inline]
+    pub fn new(environment_path: impl Into<PathBuf>) -> Self {
+        Self {
+            environment_path:                              environment_path.into(),
+            storage_backend:                               None,
+            file_directory_depth:                          None,
+            read_only:                                     false,
+            #[cfg(feature = "encryption")]
+            encryption_key:                                None,
+        }
+    }
+
+    /// Set the storage backend for the data of files. If it is not set, [`LocalStorageBackend`](crate::LocalStorageBackend) with the `datalith.files` directory of the environment is used.
+    #[inline]
+    pub fn storage_backend(mut self, storage_backend: impl StorageBackend) -> Self {
+        self.storage_backend = Some(Box::new(storage_backend));
+
+        self
+    }
+
+    /// Set the depth of nested directories in the `datalith.files` directory, which is only used by the default storage backend. The depth is decided when the environment is created, or when an environment which stores files in a flat directory is migrated, and it is fixed afterwards. Building with a different depth fails with [`DatalithCreateError::FileDirectoryDepthMismatch`]. The default depth is [`DEFAULT_FILE_DIRECTORY_DEPTH`](crate::DEFAULT_FILE_DIRECTORY_DEPTH).
+    #[inline]
+    pub fn file_directory_depth(mut self, file_directory_depth: u8) -> Self {
+        self.file_directory_depth = Some(file_directory_depth);
+
+        self
+    }
+
+    /// Open an existing environment read-only. The environment is not locked, so it can be opened while another instance is running on it, e.g. to back it up. The database schema must be up to date, and any operation which writes fails.
+    #[inline]
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+
+        self
+    }
+
+    /// Set the key for encrypting the data of files at rest. The files are encrypted before being passed to the storage backend.
+    #[cfg(feature = "encryption")]
+    #[inline]
+    pub fn encryption_key(mut self, encryption_key: DatalithEncryptionKey) -> Self {
+        self.encryption_key = Some(encryption_key);
+
+        self
+    }
+
+    /// Create the Datalith file storage center.
+    #[inline]
+    pub async fn build(self) -> Result<Datalith, DatalithCreateError> {
+        Datalith::new_with_storage_backend(
+            self.environment_path.as_path(),
+            self.storage_backend,
+            self.file_directory_depth,
+            self.read_only,
+            #[cfg(feature = "encryption")]
+            self.encryption_key,
+        )
+        .await
+    }
+}
@@ -6,13 +6,30 @@ use std::{
 
 use mime::Mime;
 
+use crate::functions::encode_hex;
+
 /// Errors occurred during Datalith creation.
 #[derive(Debug)]
 pub enum DatalithCreateError {
     IOError(io::Error),
     SQLError(sqlx::Error),
-    DatabaseTooNewError { app_db_version: u32, current_db_version: u32 },
-    DatabaseTooOldError { app_db_version: u32, current_db_version: u32 },
+    DatabaseTooNewError {
+        app_db_version:     u32,
+        current_db_version: u32,
+    },
+    DatabaseTooOldError {
+        app_db_version:     u32,
+        current_db_version: u32,
+    },
+    MigrationError {
+        version: u32,
+        error:   sqlx::Error,
+    },
+    /// The requested depth of nested directories of the file directory differs from the depth of the environment.
+    FileDirectoryDepthMismatch {
+        requested_depth: u8,
+        current_depth:   u8,
+    },
     AlreadyRun,
 }
 
@@ -40,8 +57,8 @@ impl Display for DatalithCreateError {
                 app_db_version,
                 current_db_version,
             } => f.write_fmt(format_args!(
-                "this application is too old to use the database ({app_db_version} < \
-                 {current_db_version})"
+                "the database (version {current_db_version}) was created by a newer version of \
+                 this application, which only supports up to version {app_db_version}"
             )),
             Self::DatabaseTooOldError {
                 app_db_version,
@@ -50,6 +67,19 @@ impl Display for DatalithCreateError {
                 "this application is too new to upgrade the database ({app_db_version} > \
                  {current_db_version})"
             )),
+            Self::MigrationError {
+                version,
+                error,
+            } => f.write_fmt(format_args!(
+                "failed to migrate the database to version {version}: {error}"
+            )),
+            Self::FileDirectoryDepthMismatch {
+                requested_depth,
+                current_depth,
+            } => f.write_fmt(format_args!(
+                "the depth of the file directory is {current_depth} and cannot be changed to \
+                 {requested_depth}"
+            )),
             Self::AlreadyRun => f.write_str("there is already an existing instance"),
         }
     }
@@ -93,8 +123,37 @@ impl Error for DatalithReadError {}
 /// Errors occurred during Datalith write operations.
 #[derive(Debug)]
 pub enum DatalithWriteError {
-    FileTypeInvalid { file_type: Mime, expected_file_type: Mime },
-    FileLengthTooLarge { expected_file_length: u64, actual_file_length: u64 },
+    FileTypeInvalid {
+        file_type:          Mime,
+        expected_file_type: Mime,
+    },
+    FileLengthTooLarge {
+        expected_file_length: u64,
+        actual_file_length:   u64,
+    },
+    /// The quota of the namespace, or the global quota if `namespace` is `None`, would be exceeded.
+    QuotaExceeded {
+        namespace: Option<String>,
+    },
+    /// The metadata key or value, or the tag named `name` is invalid.
+    MetadataInvalid {
+        name: String,
+    },
+    /// The SHA-256 hash of the data does not match the one expected by the client, so the data was probably corrupted during the transfer.
+    HashMismatch {
+        hash:          [u8; 32],
+        expected_hash: [u8; 32],
+    },
+    /// The free space of the volume of the environment would fall below the low-water mark.
+    InsufficientDiskSpace {
+        available_space: u64,
+        required_space:  u64,
+    },
+    /// The metadata has been changed since the version expected by the client.
+    MetadataVersionMismatch {
+        metadata_version:          u64,
+        expected_metadata_version: u64,
+    },
     IOError(io::Error),
     SQLError(sqlx::Error),
 }
@@ -140,6 +199,39 @@ impl Display for DatalithWriteError {
                 "the file length {actual_file_length:?} is larger than the expected one (expect: \
                  {expected_file_length:?})"
             )),
+            Self::QuotaExceeded {
+                namespace,
+            } => match namespace {
+                Some(namespace) => f.write_fmt(format_args!(
+                    "the quota of the namespace {namespace:?} would be exceeded"
+                )),
+                None => f.write_str("the global quota would be exceeded"),
+            },
+            Self::MetadataInvalid {
+                name,
+            } => f.write_fmt(format_args!("the metadata {name:?} is invalid")),
+            Self::HashMismatch {
+                hash,
+                expected_hash,
+            } => f.write_fmt(format_args!(
+                "the SHA-256 hash {} does not match the expected one (expect: {})",
+                encode_hex(hash),
+                encode_hex(expected_hash)
+            )),
+            Self::InsufficientDiskSpace {
+                available_space,
+                required_space,
+            } => f.write_fmt(format_args!(
+                "the available disk space {available_space:?} is not enough (expect: \
+                 {required_space:?})"
+            )),
+            Self::MetadataVersionMismatch {
+                metadata_version,
+                expected_metadata_version,
+            } => f.write_fmt(format_args!(
+                "the metadata version {metadata_version:?} does not match the expected one \
+                 (expect: {expected_metadata_version:?})"
+            )),
             Self::IOError(error) => Display::fmt(error, f),
             Self::SQLError(error) => Display::fmt(error, f),
         }
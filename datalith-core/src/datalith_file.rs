@@ -3,19 +3,16 @@ use std::{
     io::SeekFrom,
     ops::Deref,
     pin::Pin,
-    task::{Context, Poll},
+    task::{Context, Poll, ready},
 };
 
 use chrono::{DateTime, Local, TimeZone};
 use educe::Educe;
 use mime::Mime;
-use tokio::{
-    fs::File,
-    io::{AsyncRead, AsyncSeek, ReadBuf},
-};
+use tokio::io::{AsyncRead, AsyncSeek, ReadBuf};
 use uuid::Uuid;
 
-use crate::{Datalith, guard::OpenGuard};
+use crate::{Datalith, DatalithCompression, StorageReader, guard::OpenGuard};
 
 /// A struct that represents a file.
 #[derive(Debug, Educe)]
@@ -27,6 +24,8 @@ pub struct DatalithFile {
     _guard:       OpenGuard,
     id:           Uuid,
     #[educe(Eq(ignore), Hash(ignore))]
+    hash:         [u8; 32],
+    #[educe(Eq(ignore), Hash(ignore))]
     created_at:   DateTime<Local>,
     #[educe(Eq(ignore), Hash(ignore))]
     file_size:    u64,
@@ -35,6 +34,12 @@ pub struct DatalithFile {
     #[educe(Eq(ignore), Hash(ignore))]
     file_name:    String,
     #[educe(Eq(ignore), Hash(ignore))]
+    compression:  DatalithCompression,
+    #[educe(Eq(ignore), Hash(ignore))]
+    verified_at:  Option<DateTime<Local>>,
+    #[educe(Eq(ignore), Hash(ignore))]
+    quarantined:  bool,
+    #[educe(Eq(ignore), Hash(ignore))]
     is_temporary: bool,
     #[educe(Eq(ignore), Hash(ignore))]
     is_new:       bool,
@@ -48,10 +53,14 @@ impl DatalithFile {
         datalith: Datalith,
         guard: OpenGuard,
         id: impl Into<Uuid>,
+        hash: [u8; 32],
         created_at: DateTime<Tz>,
         file_size: impl Into<u64>,
         file_type: Mime,
         file_name: impl Into<String>,
+        compression: DatalithCompression,
+        verified_at: Option<DateTime<Tz>>,
+        quarantined: bool,
         is_temporary: bool,
         is_new: bool,
     ) -> Self
@@ -62,10 +71,14 @@ where {
             _datalith: datalith,
             _guard: guard,
             id,
+            hash,
             created_at: created_at.with_timezone(&Local),
             file_size: file_size.into(),
             file_type,
             file_name: file_name.into(),
+            compression,
+            verified_at: verified_at.map(|verified_at| verified_at.with_timezone(&Local)),
+            quarantined,
             is_temporary,
             is_new,
         }
@@ -79,6 +92,12 @@ impl DatalithFile {
         self.id
     }
 
+    /// Retrieve the SHA-256 hash of the file data.
+    #[inline]
+    pub const fn hash(&self) -> &[u8; 32] {
+        &self.hash
+    }
+
     /// Retrieve the creation time.
     #[inline]
     pub const fn created_at(&self) -> DateTime<Local> {
@@ -103,6 +122,24 @@ impl DatalithFile {
         &self.file_name
     }
 
+    /// Retrieve the compression of the stored data. The file size and the data read from readers are always uncompressed.
+    #[inline]
+    pub const fn compression(&self) -> DatalithCompression {
+        self.compression
+    }
+
+    /// Retrieve the last time the stored data was verified against the hash. Return `None` if it has never been verified.
+    #[inline]
+    pub const fn verified_at(&self) -> Option<DateTime<Local>> {
+        self.verified_at
+    }
+
+    /// Check if this file is quarantined because its stored data was found corrupt. The data of a quarantined file cannot be read.
+    #[inline]
+    pub const fn is_quarantined(&self) -> bool {
+        self.quarantined
+    }
+
     /// Check if this file is temporary.
     #[inline]
     pub const fn is_temporary(&self) -> bool {
@@ -120,9 +157,7 @@ impl DatalithFile {
     /// Create an reader.
     #[inline]
     pub async fn create_reader(&self) -> io::Result<DatalithFileReader<'_>> {
-        let file_path = self._datalith.get_file_path(self.id).await?;
-
-        let file = File::open(file_path).await?;
+        let file = self.open_decoding_reader().await?;
 
         Ok(DatalithFileReader {
             _file: self,
@@ -133,22 +168,61 @@ impl DatalithFile {
     /// Create a readable .
     #[inline]
     pub async fn into_readable(self) -> io::Result<ReadableDatalithFile> {
-        let file_path = self._datalith.get_file_path(self.id).await?;
+        let file = self.open_decoding_reader().await?;
 
-        let file = File::open(file_path).await?;
+        Ok(ReadableDatalithFile {
+            _file: self,
+            file,
+        })
+    }
+
+    /// Create a readable which reads the stored data without decompressing it. The data is compressed as described by the `compression` method.
+    #[inline]
+    pub async fn into_raw_readable(self) -> io::Result<ReadableDatalithFile> {
+        self.check_quarantined()?;
+
+        let file = self._datalith.0.storage.open_reader(self.id).await?;
 
         Ok(ReadableDatalithFile {
             _file: self,
             file,
         })
     }
+
+    /// Retrieve the size (in bytes) of the stored data, which is smaller than the file size if the file is compressed.
+    #[inline]
+    pub async fn stored_size(&self) -> io::Result<u64> {
+        match self._datalith.0.storage.stat(self.id).await? {
+            Some(stat) => Ok(stat.size),
+            None => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    #[inline]
+    async fn open_decoding_reader(&self) -> io::Result<Box<dyn StorageReader>> {
+        self.check_quarantined()?;
+
+        self._datalith.open_decoding_reader(self.id, self.compression, self.file_size).await
+    }
+
+    #[inline]
+    fn check_quarantined(&self) -> io::Result<()> {
+        if self.quarantined {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("the file {} is quarantined because its stored data is corrupt", self.id),
+            ))
+        } else {
+            Ok(())
+        }
+    }
 }
 
 /// A struct that provides an asynchronous read interface for files.
 #[derive(Debug)]
 pub struct DatalithFileReader<'a> {
     _file: &'a DatalithFile,
-    file:  File,
+    file:  Box<dyn StorageReader>,
 }
 
 impl AsyncRead for DatalithFileReader<'_> {
@@ -162,11 +236,39 @@ impl AsyncRead for DatalithFileReader<'_> {
     }
 }
 
+impl AsyncSeek for DatalithFileReader<'_> {
+    #[inline]
+    fn start_seek(mut self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        Pin::new(&mut self.file).start_seek(position)
+    }
+
+    #[inline]
+    fn poll_complete(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Pin::new(&mut self.file).poll_complete(cx)
+    }
+}
+
 /// A struct that represents a file and provides an asynchronous read interface for files.
 #[derive(Debug)]
 pub struct ReadableDatalithFile {
     _file: DatalithFile,
-    file:  File,
+    file:  Box<dyn StorageReader>,
+}
+
+impl ReadableDatalithFile {
+    /// Convert into a reader which only reads `length` bytes starting at the offset `start`.
+    ///
+    /// The seek is performed lazily when the reader is polled for the first time.
+    #[inline]
+    pub fn into_range_reader(self, start: u64, length: u64) -> DatalithFileRangeReader {
+        DatalithFileRangeReader {
+            file: self,
+            seek: RangeSeekState::Pending,
+            start,
+            length,
+            position: 0,
+        }
+    }
 }
 
 impl Deref for ReadableDatalithFile {
@@ -200,3 +302,147 @@ impl AsyncSeek for ReadableDatalithFile {
         Pin::new(&mut self.file).poll_complete(cx)
     }
 }
+
+#[derive(Debug)]
+enum RangeSeekState {
+    Pending,
+    Seeking,
+    Done,
+}
+
+/// A struct that provides an asynchronous read interface for a byte range of a file.
+///
+/// Positions used for seeking are relative to the start of the range.
+#[derive(Debug)]
+pub struct DatalithFileRangeReader {
+    file:     ReadableDatalithFile,
+    seek:     RangeSeekState,
+    start:    u64,
+    length:   u64,
+    position: u64,
+}
+
+impl DatalithFileRangeReader {
+    /// Move to another byte range. The data left in the current range is skipped.
+    #[inline]
+    pub fn set_range(&mut self, start: u64, length: u64) {
+        self.seek = RangeSeekState::Pending;
+        self.start = start;
+        self.length = length;
+        self.position = 0;
+    }
+
+    /// Retrieve the number of bytes which have not been read in the current range.
+    #[inline]
+    pub const fn remaining(&self) -> u64 {
+        self.length.saturating_sub(self.position)
+    }
+
+    /// Convert back to the readable file.
+    #[inline]
+    pub fn into_inner(self) -> ReadableDatalithFile {
+        self.file
+    }
+
+    fn poll_seek(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        loop {
+            match self.seek {
+                RangeSeekState::Pending => {
+                    Pin::new(&mut self.file)
+                        .start_seek(SeekFrom::Start(self.start + self.position))?;
+
+                    self.seek = RangeSeekState::Seeking;
+                },
+                RangeSeekState::Seeking => {
+                    ready!(Pin::new(&mut self.file).poll_complete(cx))?;
+
+                    self.seek = RangeSeekState::Done;
+                },
+                RangeSeekState::Done => return Poll::Ready(Ok(())),
+            }
+        }
+    }
+}
+
+impl Deref for DatalithFileRangeReader {
+    type Target = DatalithFile;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.file
+    }
+}
+
+impl AsyncRead for DatalithFileRangeReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+
+        ready!(this.poll_seek(cx))?;
+
+        let remaining = this.remaining();
+
+        if remaining == 0 {
+            return Poll::Ready(Ok(()));
+        }
+
+        let max = remaining.min(buf.remaining() as u64) as usize;
+
+        let mut sub_buf = buf.take(max);
+
+        ready!(Pin::new(&mut this.file).poll_read(cx, &mut sub_buf))?;
+
+        let c = sub_buf.filled().len();
+
+        if c == 0 && max > 0 {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the file is shorter than the requested range",
+            )));
+        }
+
+        // the bytes have been initialized by the inner reader
+        unsafe {
+            buf.assume_init(c);
+        }
+
+        buf.advance(c);
+
+        this.position += c as u64;
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncSeek for DatalithFileRangeReader {
+    fn start_seek(mut self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        let position = match position {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.length.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+
+        match position {
+            Some(position) => {
+                self.position = position;
+                self.seek = RangeSeekState::Pending;
+
+                Ok(())
+            },
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+
+    #[inline]
+    fn poll_complete(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        ready!(self.poll_seek(cx))?;
+
+        Poll::Ready(Ok(self.position))
+    }
+}
@@ -0,0 +1,119 @@
+use std::{io, path::PathBuf};
+
+use crate::{Datalith, DatalithWriteError};
+
+/// The number of bytes which can be written between two checks of the free space while receiving data.
+const FREE_SPACE_CHECK_INTERVAL: u64 = 4 * 1024 * 1024;
+
+/// The amount of free space which must be kept on the volume of the environment, so that the SQLite database can still be written.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DatalithLowWaterMark {
+    /// An amount of bytes.
+    Bytes(u64),
+    /// A percentage of the total space, from `0.0` to `100.0`.
+    Percent(f64),
+}
+
+impl DatalithLowWaterMark {
+    /// Calculate the amount of free space (in bytes) which must be kept on a volume of `total_space` bytes.
+    #[inline]
+    pub fn to_bytes(self, total_space: u64) -> u64 {
+        match self {
+            Self::Bytes(bytes) => bytes,
+            Self::Percent(percent) => {
+                (total_space as f64 * percent.clamp(0.0, 100.0) / 100.0).ceil() as u64
+            },
+        }
+    }
+}
+
+/// The space of the volume where the environment is located.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DatalithDiskSpace {
+    /// The space (in bytes) which is available to Datalith.
+    pub available_space: u64,
+    /// The total space (in bytes) of the volume.
+    pub total_space:     u64,
+    /// The free space (in bytes) which must be kept, if a low-water mark is set.
+    pub low_water_mark:  Option<u64>,
+}
+
+/// Check the free space before and while writing data into the environment.
+pub(crate) struct FreeSpaceChecker {
+    environment:    PathBuf,
+    low_water_mark: Option<DatalithLowWaterMark>,
+    unchecked_size: u64,
+}
+
+impl FreeSpaceChecker {
+    /// Make sure that `size` more bytes can be written without falling below the low-water mark.
+    #[allow(clippy::result_large_err)]
+    pub(crate) fn check(&self, size: u64) -> Result<(), DatalithWriteError> {
+        let Some(low_water_mark) = self.low_water_mark else {
+            return Ok(());
+        };
+
+        let stats = fs4::statvfs(self.environment.as_path())?;
+
+        let available_space = stats.available_space();
+        let required_space = low_water_mark.to_bytes(stats.total_space()).saturating_add(size);
+
+        if available_space < required_space {
+            return Err(DatalithWriteError::InsufficientDiskSpace {
+                available_space,
+                required_space,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Record that `size` bytes are going to be written. The free space is checked once every 4 MiB.
+    #[allow(clippy::result_large_err)]
+    pub(crate) fn check_written(&mut self, size: u64) -> Result<(), DatalithWriteError> {
+        self.unchecked_size += size;
+
+        if self.unchecked_size >= FREE_SPACE_CHECK_INTERVAL {
+            self.check(self.unchecked_size)?;
+
+            self.unchecked_size = 0;
+        }
+
+        Ok(())
+    }
+}
+
+// Disk Space
+impl Datalith {
+    /// Retrieve the low-water mark of the free space.
+    #[inline]
+    pub fn get_low_water_mark(&self) -> Option<DatalithLowWaterMark> {
+        *self.0._low_water_mark.lock().unwrap()
+    }
+
+    /// Set the low-water mark of the free space. Uploads are refused if they would make the free space of the volume of the environment fall below it. `None` means no limit.
+    #[inline]
+    pub fn set_low_water_mark(&self, low_water_mark: Option<DatalithLowWaterMark>) {
+        *self.0._low_water_mark.lock().unwrap() = low_water_mark;
+    }
+
+    /// Retrieve the space of the volume where the environment is located.
+    pub fn get_disk_space(&self) -> io::Result<DatalithDiskSpace> {
+        let stats = fs4::statvfs(self.get_environment())?;
+
+        Ok(DatalithDiskSpace {
+            available_space: stats.available_space(),
+            total_space:     stats.total_space(),
+            low_water_mark:  self.get_low_water_mark().map(|e| e.to_bytes(stats.total_space())),
+        })
+    }
+
+    #[inline]
+    pub(crate) fn free_space_checker(&self) -> FreeSpaceChecker {
+        FreeSpaceChecker {
+            environment:    self.get_environment().to_path_buf(),
+            low_water_mark: self.get_low_water_mark(),
+            unchecked_size: 0,
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use chrono::{DateTime, Utc};
+use sqlx::{QueryBuilder, Sqlite};
+
+/// A condition on a name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DatalithNameMatch {
+    /// The name contains this string (case-sensitive).
+    Contains(String),
+    /// The name matches this glob pattern (case-sensitive), as in `*.png` or `report-202[45]-??.pdf`.
+    Glob(String),
+}
+
+/// Push the SQL condition which matches a name `column`.
+pub(crate) fn push_name_condition(
+    builder: &mut QueryBuilder<'_, Sqlite>,
+    column: &str,
+    name: &DatalithNameMatch,
+) {
+    match name {
+        DatalithNameMatch::Contains(s) => {
+            builder.push(format_args!(" AND instr({column}, "));
+            builder.push_bind(s.clone());
+            builder.push(") > 0");
+        },
+        DatalithNameMatch::Glob(pattern) => {
+            builder.push(format_args!(" AND {column} GLOB "));
+            builder.push_bind(pattern.clone());
+        },
+    }
+}
+
+/// Push the SQL condition which matches a MIME type `column`. A type ending with `/*`, as in `image/*`, matches every subtype.
+pub(crate) fn push_file_type_condition(
+    builder: &mut QueryBuilder<'_, Sqlite>,
+    column: &str,
+    file_type: &str,
+) {
+    match file_type.strip_suffix('*') {
+        Some(prefix) if prefix.ends_with('/') => {
+            // the characters which are special to GLOB are put in brackets so that they match literally
+            let mut pattern = String::with_capacity(prefix.len() + 1);
+
+            for c in prefix.chars() {
+                match c {
+                    '*' | '?' | '[' => {
+                        pattern.push('[');
+                        pattern.push(c);
+                        pattern.push(']');
+                    },
+                    _ => pattern.push(c),
+                }
+            }
+
+            pattern.push('*');
+
+            builder.push(format_args!(" AND {column} GLOB "));
+            builder.push_bind(pattern);
+        },
+        _ => {
+            builder.push(format_args!(" AND {column} = "));
+            builder.push_bind(file_type.to_string());
+        },
+    }
+}
+
+/// Push the SQL conditions which match a timestamp `column` at or after `since` and before `before`.
+pub(crate) fn push_time_range_conditions(
+    builder: &mut QueryBuilder<'_, Sqlite>,
+    column: &str,
+    since: Option<DateTime<Utc>>,
+    before: Option<DateTime<Utc>>,
+) {
+    if let Some(since) = since {
+        builder.push(format_args!(" AND {column} >= "));
+        builder.push_bind(since.timestamp_millis());
+    }
+
+    if let Some(before) = before {
+        builder.push(format_args!(" AND {column} < "));
+        builder.push_bind(before.timestamp_millis());
+    }
+}
+
+/// Push the SQL conditions which match an integer `column` from `min` to `max`, inclusive.
+pub(crate) fn push_integer_range_conditions(
+    builder: &mut QueryBuilder<'_, Sqlite>,
+    column: &str,
+    min: Option<u64>,
+    max: Option<u64>,
+) {
+    if let Some(min) = min {
+        builder.push(format_args!(" AND {column} >= "));
+        builder.push_bind(min.min(i64::MAX as u64) as i64);
+    }
+
+    if let Some(max) = max {
+        builder.push(format_args!(" AND {column} <= "));
+        builder.push_bind(max.min(i64::MAX as u64) as i64);
+    }
+}
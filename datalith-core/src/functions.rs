@@ -202,6 +202,37 @@ pub(crate) fn get_random_hash() -> [u8; 32] {
     data
 }
 
+#[inline]
+pub(crate) fn encode_hex(data: impl AsRef<[u8]>) -> String {
+    let data = data.as_ref();
+
+    let mut s = String::with_capacity(data.len() * 2);
+
+    for b in data {
+        s.push_str(&format!("{b:02x}"));
+    }
+
+    s
+}
+
+pub(crate) fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) || !s.is_ascii() {
+        return None;
+    }
+
+    (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok()).collect()
+}
+
+/// Parse a SHA-256 hash from its hex string, in either case.
+#[inline]
+pub fn parse_sha256_hex(s: &str) -> Option<[u8; 32]> {
+    if s.len() != 64 {
+        return None;
+    }
+
+    decode_hex(s)?.try_into().ok()
+}
+
 #[inline]
 pub(crate) fn allow_not_found_error(result: io::Result<()>) -> io::Result<()> {
     match result {
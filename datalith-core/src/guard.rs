@@ -179,18 +179,53 @@ impl DeleteGuard {
     }
 }
 
+#[derive(Debug)]
+pub(crate) struct UploadSessionGuard {
+    _datalith: Datalith,
+    id:        Uuid,
+}
+
+impl Drop for UploadSessionGuard {
+    #[inline]
+    fn drop(&mut self) {
+        let mut appending_upload_sessions =
+            self._datalith.0._appending_upload_sessions.lock().unwrap();
+
+        appending_upload_sessions.remove(&self.id);
+    }
+}
+
+impl UploadSessionGuard {
+    /// Try to acquire the upload session. Return `None` if it is being used.
+    pub fn try_new(datalith: Datalith, id: impl Into<Uuid>) -> Option<Self> {
+        let id = id.into();
+
+        {
+            let mut appending_upload_sessions =
+                datalith.0._appending_upload_sessions.lock().unwrap();
+
+            if !appending_upload_sessions.insert(id) {
+                return None;
+            }
+        }
+
+        Some(Self {
+            _datalith: datalith,
+            id,
+        })
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct TemporaryFileGuard {
-    moved:     bool,
     file_path: PathBuf,
 }
 
 impl Drop for TemporaryFileGuard {
+    /// Remove the temporary file if it has not been moved away.
     #[inline]
     fn drop(&mut self) {
-        if !self.moved {
-            let _ = fs::remove_file(self.file_path.as_path());
-        }
+        let _ = fs::remove_file(self.file_path.as_path());
     }
 }
 
@@ -200,13 +235,7 @@ impl TemporaryFileGuard {
         let file_path = file_path.into();
 
         Self {
-            moved: false,
             file_path,
         }
     }
-
-    #[inline]
-    pub fn set_moved(&mut self) {
-        self.moved = true;
-    }
 }
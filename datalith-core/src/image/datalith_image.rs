@@ -2,7 +2,7 @@ use chrono::{DateTime, Local, TimeZone};
 use educe::Educe;
 use uuid::Uuid;
 
-use crate::DatalithFile;
+use crate::{DatalithFile, DatalithMetadata};
 
 /// A struct that represents an image.
 #[derive(Debug, Educe)]
@@ -25,6 +25,10 @@ pub struct DatalithImage {
     fallback_thumbnails: Vec<DatalithFile>,
     #[educe(Eq(ignore), Hash(ignore))]
     has_alpha_channel:   bool,
+    #[educe(Eq(ignore), Hash(ignore))]
+    metadata:            DatalithMetadata,
+    #[educe(Eq(ignore), Hash(ignore))]
+    metadata_version:    u64,
 }
 
 impl DatalithImage {
@@ -41,6 +45,8 @@ impl DatalithImage {
         thumbnails: Vec<DatalithFile>,
         fallback_thumbnails: Vec<DatalithFile>,
         has_alpha_channel: bool,
+        metadata: DatalithMetadata,
+        metadata_version: u64,
     ) -> Self
 where {
         let id = id.into();
@@ -56,8 +62,17 @@ where {
             thumbnails,
             fallback_thumbnails,
             has_alpha_channel,
+            metadata,
+            metadata_version,
         }
     }
+
+    /// Replace the user-defined metadata and its version.
+    #[inline]
+    pub(crate) fn set_metadata(&mut self, metadata: DatalithMetadata, metadata_version: u64) {
+        self.metadata = metadata;
+        self.metadata_version = metadata_version;
+    }
 }
 
 impl DatalithImage {
@@ -114,6 +129,18 @@ impl DatalithImage {
     pub const fn has_alpha_channel(&self) -> bool {
         self.has_alpha_channel
     }
+
+    /// Retrieve the user-defined metadata.
+    #[inline]
+    pub const fn metadata(&self) -> &DatalithMetadata {
+        &self.metadata
+    }
+
+    /// Retrieve the version of the user-defined metadata, which is increased whenever the metadata is changed.
+    #[inline]
+    pub const fn metadata_version(&self) -> u64 {
+        self.metadata_version
+    }
 }
 
 impl DatalithImage {
@@ -0,0 +1,165 @@
+use mime::Mime;
+
+use crate::{DatalithImage, MIME_WEBP};
+
+/// The formats in which an image can be served.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DatalithImageFormat {
+    WebP,
+    Jpeg,
+    Png,
+    Avif,
+}
+
+impl DatalithImageFormat {
+    /// Get the format by its name or file extension, as in `webp`, `jpg` or `jpeg`, `png` and `avif`, case-insensitively.
+    #[inline]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "webp" => Some(Self::WebP),
+            "jpg" | "jpeg" => Some(Self::Jpeg),
+            "png" => Some(Self::Png),
+            "avif" => Some(Self::Avif),
+            _ => None,
+        }
+    }
+
+    /// Get the format of a MIME type.
+    #[inline]
+    pub fn from_mime(mime_type: &Mime) -> Option<Self> {
+        match mime_type.essence_str() {
+            "image/webp" => Some(Self::WebP),
+            "image/jpeg" => Some(Self::Jpeg),
+            "image/png" => Some(Self::Png),
+            "image/avif" => Some(Self::Avif),
+            _ => None,
+        }
+    }
+
+    /// Get the MIME type.
+    #[inline]
+    pub fn mime_type(self) -> Mime {
+        match self {
+            Self::WebP => MIME_WEBP.clone(),
+            Self::Jpeg => mime::IMAGE_JPEG,
+            Self::Png => mime::IMAGE_PNG,
+            Self::Avif => "image/avif".parse().unwrap(),
+        }
+    }
+
+    /// Get the file extension.
+    #[inline]
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::WebP => "webp",
+            Self::Jpeg => "jpg",
+            Self::Png => "png",
+            Self::Avif => "avif",
+        }
+    }
+
+    /// Get the quality value (from `0.0` to `1.0`) of this format in an `Accept` header. The most specific media range which matches is used, and a missing header accepts everything.
+    #[inline]
+    pub fn quality_in(self, accept: Option<&str>) -> f32 {
+        self.match_in(accept).0
+    }
+
+    /// Get the quality value of this format in an `Accept` header, and whether the format is listed explicitly rather than matched by a wildcard.
+    fn match_in(self, accept: Option<&str>) -> (f32, bool) {
+        let Some(accept) = accept else {
+            return (1.0, false);
+        };
+
+        let mime_type = self.mime_type();
+
+        // (specificity, quality)
+        let mut best: Option<(u8, f32)> = None;
+
+        for item in accept.split(',') {
+            let mut parameters = item.split(';');
+
+            let Some((top, sub)) = parameters.next().unwrap_or_default().trim().split_once('/')
+            else {
+                continue;
+            };
+
+            let (top, sub) = (top.trim(), sub.trim());
+
+            let specificity = if top == "*" && sub == "*" {
+                0
+            } else if top.eq_ignore_ascii_case(mime_type.type_().as_str()) {
+                if sub == "*" {
+                    1
+                } else if sub.eq_ignore_ascii_case(mime_type.subtype().as_str()) {
+                    2
+                } else {
+                    continue;
+                }
+            } else {
+                continue;
+            };
+
+            let mut quality = 1.0;
+
+            for parameter in parameters {
+                if let Some((name, value)) = parameter.split_once('=')
+                    && name.trim().eq_ignore_ascii_case("q")
+                {
+                    quality = value.trim().parse::<f32>().unwrap_or(0.0).clamp(0.0, 1.0);
+                }
+            }
+
+            if best.is_none_or(|(best_specificity, _)| specificity > best_specificity) {
+                best = Some((specificity, quality));
+            }
+        }
+
+        match best {
+            Some((specificity, quality)) => (quality, specificity == 2),
+            None => (0.0, false),
+        }
+    }
+}
+
+/// Choose the format which an `Accept` header prefers among `formats`. If formats have the same quality value, the ones listed explicitly in the header are preferred to the ones matched by wildcards (as in `image/*`), and the rest are chosen in the order of `formats`. Return `None` if none of them is acceptable.
+pub fn negotiate_image_format(
+    accept: Option<&str>,
+    formats: &[DatalithImageFormat],
+) -> Option<DatalithImageFormat> {
+    let mut best: Option<(DatalithImageFormat, f32, bool)> = None;
+
+    for &format in formats {
+        let (quality, explicit) = format.match_in(accept);
+
+        if quality > 0.0
+            && best.is_none_or(|(_, best_quality, best_explicit)| {
+                quality > best_quality || quality == best_quality && explicit && !best_explicit
+            })
+        {
+            best = Some((format, quality, explicit));
+        }
+    }
+
+    best.map(|(format, ..)| format)
+}
+
+impl DatalithImage {
+    /// Retrieve the format of the thumbnails. (WebP)
+    #[inline]
+    pub const fn thumbnail_format(&self) -> DatalithImageFormat {
+        DatalithImageFormat::WebP
+    }
+
+    /// Retrieve the format of the fallback thumbnails. (PNG if the image has an alpha channel, or JPEG)
+    #[inline]
+    pub const fn fallback_thumbnail_format(&self) -> DatalithImageFormat {
+        if self.has_alpha_channel() { DatalithImageFormat::Png } else { DatalithImageFormat::Jpeg }
+    }
+
+    /// Choose the stored format of the thumbnails which an `Accept` header prefers. The fallback format is chosen if both formats are equally preferred (as when WebP is only matched by a wildcard) or neither is acceptable, since it is supported everywhere.
+    #[inline]
+    pub fn negotiate_thumbnail_format(&self, accept: Option<&str>) -> DatalithImageFormat {
+        negotiate_image_format(accept, &[self.fallback_thumbnail_format(), self.thumbnail_format()])
+            .unwrap_or_else(|| self.fallback_thumbnail_format())
+    }
+}
@@ -1,13 +1,15 @@
 mod datalith_image;
 mod datalith_image_errors;
+mod format;
 mod sync;
 
 use std::{collections::HashSet, path::Path, str::FromStr, sync::atomic::Ordering};
 
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Local, Utc};
 pub use datalith_image::*;
 pub use datalith_image_errors::*;
 use educe::Educe;
+pub use format::*;
 use image_convert::{
     Crop, ImageResource, JPGConfig, MagickError, PNGConfig, WEBPConfig, compute_output_size,
     fetch_magic_wand, identify_ping, to_jpg, to_png, to_webp,
@@ -16,15 +18,23 @@ use mime::Mime;
 use once_cell::sync::Lazy;
 use rdb_pagination::{Pagination, PaginationOptions, SqlJoin, SqlOrderByComponent, prelude::*};
 use regex::Regex;
+use sqlx::{QueryBuilder, Sqlite};
 use tokio::{io::AsyncRead, task, task::JoinSet};
 use uuid::Uuid;
 
 use crate::{
-    Datalith, DatalithFile, DatalithReadError, DatalithResource, FileTypeLevel,
+    Datalith, DatalithCursorOptions, DatalithCursorPage, DatalithFile, DatalithMetadata,
+    DatalithMetadataUpdate, DatalithNameMatch, DatalithReadError, DatalithResource,
+    DatalithSortKey, FileTypeLevel,
+    cursors::SortColumn,
     datalith::get_file_size_by_reader_and_copy_to_file,
+    filters::{push_name_condition, push_time_range_conditions},
     functions::get_file_name,
     guard::{DeleteGuard, TemporaryFileGuard},
     image::sync::ReadOnlyImageResource,
+    metadata::{delete_metadata, push_metadata_conditions, read_metadata},
+    quotas::add_usage,
+    search::{delete_search_document, index_search_document},
 };
 
 pub static MIME_WEBP: Lazy<Mime> = Lazy::new(|| Mime::from_str("image/webp").unwrap());
@@ -36,10 +46,107 @@ pub static MIME_WEBP: Lazy<Mime> = Lazy::new(|| Mime::from_str("image/webp").unw
 pub struct DatalithImageOrderBy {
     #[educe(Default = 102)]
     #[orderByOptions((images, id), unique)]
-    pub id:         OrderMethod,
+    pub id:                OrderMethod,
     #[educe(Default = -101)]
     #[orderByOptions((images, created_at))]
-    pub created_at: OrderMethod,
+    pub created_at:        OrderMethod,
+    #[orderByOptions((images, image_width))]
+    pub image_width:       OrderMethod,
+    #[orderByOptions((images, image_height))]
+    pub image_height:      OrderMethod,
+    #[orderByOptions((images, has_alpha_channel))]
+    pub has_alpha_channel: OrderMethod,
+}
+
+/// The keys which a cursor listing of images can be sorted by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DatalithImageSortKey {
+    #[default]
+    CreatedAt,
+    ImageWidth,
+    ImageHeight,
+    HasAlphaChannel,
+}
+
+impl DatalithSortKey for DatalithImageSortKey {
+    #[inline]
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::CreatedAt => "created_at",
+            Self::ImageWidth => "image_width",
+            Self::ImageHeight => "image_height",
+            Self::HasAlphaChannel => "has_alpha_channel",
+        }
+    }
+
+    #[inline]
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "created_at" => Some(Self::CreatedAt),
+            "image_width" => Some(Self::ImageWidth),
+            "image_height" => Some(Self::ImageHeight),
+            "has_alpha_channel" => Some(Self::HasAlphaChannel),
+            _ => None,
+        }
+    }
+}
+
+impl DatalithImageSortKey {
+    #[inline]
+    const fn sort_column(self) -> SortColumn {
+        match self {
+            Self::CreatedAt => SortColumn {
+                column: "`images`.`created_at`", text: false
+            },
+            Self::ImageWidth => SortColumn {
+                column: "`images`.`image_width`", text: false
+            },
+            Self::ImageHeight => SortColumn {
+                column: "`images`.`image_height`", text: false
+            },
+            Self::HasAlphaChannel => SortColumn {
+                column: "`images`.`has_alpha_channel`",
+                text:   false,
+            },
+        }
+    }
+}
+
+/// A struct that defines the conditions for listing images. The default value matches every image.
+#[derive(Debug, Clone, Default)]
+pub struct DatalithImageFilter {
+    /// Match the images which have all of these key/value pairs and tags.
+    pub metadata:          DatalithMetadata,
+    /// Match the images created at or after this time.
+    pub created_since:     Option<DateTime<Utc>>,
+    /// Match the images created before this time.
+    pub created_before:    Option<DateTime<Utc>>,
+    /// Match the images whose file stems match.
+    pub image_stem:        Option<DatalithNameMatch>,
+    /// Match only the images with an alpha channel if `true`, or only the images without one if `false`.
+    pub has_alpha_channel: Option<bool>,
+}
+
+impl DatalithImageFilter {
+    fn push_sql_conditions(&self, builder: &mut QueryBuilder<'_, Sqlite>) {
+        push_metadata_conditions(builder, "images", &self.metadata);
+
+        push_time_range_conditions(
+            builder,
+            "`images`.`created_at`",
+            self.created_since,
+            self.created_before,
+        );
+
+        if let Some(image_stem) = self.image_stem.as_ref() {
+            push_name_condition(builder, "`images`.`image_stem`", image_stem);
+        }
+
+        if let Some(has_alpha_channel) = self.has_alpha_channel {
+            builder.push(" AND `images`.`has_alpha_channel` = ");
+            builder.push_bind(has_alpha_channel);
+        }
+    }
 }
 
 /// The width-to-height ratio which this image should be. The image will be center cropped to fit the condition.
@@ -126,6 +233,7 @@ impl Datalith {
                     input.as_u8_slice().unwrap(),
                     file_name,
                     Some((file_type, FileTypeLevel::Manual)),
+                    None,
                 )
                 .await?;
 
@@ -201,6 +309,7 @@ impl Datalith {
                     file_path,
                     file_name.clone(),
                     Some((file_type.clone(), FileTypeLevel::Manual)),
+                    None,
                 )
                 .await?;
 
@@ -255,6 +364,7 @@ impl Datalith {
             reader,
             temporary_file_path.as_path(),
             expected_reader_length,
+            self.free_space_checker(),
         )
         .await?;
         let _file_guard = TemporaryFileGuard::new(temporary_file_path.as_path());
@@ -294,7 +404,7 @@ impl Datalith {
         .await
     }
 
-    /// Convert a resource into an image.
+    /// Convert a resource into an image. The metadata of the resource is kept.
     #[inline]
     pub async fn convert_resource_to_image(
         &self,
@@ -303,20 +413,43 @@ impl Datalith {
         max_height: Option<u16>,
         center_crop: Option<CenterCrop>,
     ) -> Result<DatalithImage, DatalithImageWriteError> {
-        let image =
+        let mut image =
             self.put_image_by_resource(&resource, max_width, max_height, center_crop).await?;
 
         let resource_id = resource.id();
+        let metadata = resource.metadata().clone();
 
         drop(resource);
 
-        match self.delete_resource_by_id(resource_id).await {
-            Ok(_) => Ok(image),
+        let result: Result<(), DatalithImageWriteError> = async {
+            if !metadata.is_empty() {
+                let (metadata, metadata_version) = self
+                    .update_image_metadata_with_version(
+                        image.id(),
+                        &DatalithMetadataUpdate::from(metadata),
+                        None,
+                    )
+                    .await?
+                    .unwrap_or_default();
+
+                image.set_metadata(metadata, metadata_version);
+            }
+
+            self.delete_resource_by_id(resource_id).await?;
+
+            Ok(())
+        }
+        .await;
+
+        match result {
+            Ok(()) => Ok(image),
             Err(error) => {
                 // fallback
 
                 let image_id = image.id();
 
+                drop(image);
+
                 if let Err(error) = self.delete_image_by_id(image_id).await {
                     tracing::warn!(
                         "cannot fallback `convert_resource_to_image` (resource_id = \
@@ -324,7 +457,7 @@ impl Datalith {
                     );
                 }
 
-                Err(error.into())
+                Err(error)
             },
         }
     }
@@ -507,6 +640,7 @@ impl Datalith {
                         output.as_slice(),
                         Some(file_name),
                         Some((MIME_WEBP.clone(), FileTypeLevel::Manual)),
+                        None,
                     )
                     .await
                 {
@@ -583,6 +717,7 @@ impl Datalith {
                         output.as_slice(),
                         Some(file_name),
                         Some((file_type, FileTypeLevel::Manual)),
+                        None,
                     )
                     .await
                 {
@@ -633,8 +768,8 @@ impl Datalith {
             #[rustfmt::skip]
             let result = sqlx::query(
                 "
-                    INSERT INTO `images` (`id`, `created_at`, `image_stem`, `image_width`, `image_height`, `original_file_id`, `has_alpha_channel`)
-                        VALUES (?, ?, ?, ?, ?, ?, ?)
+                    INSERT INTO `images` (`id`, `created_at`, `image_stem`, `image_width`, `image_height`, `original_file_id`, `has_alpha_channel`, `namespace`)
+                        VALUES (?, ?, ?, ?, ?, ?, ?, ?)
                 ",
             )
             .bind(id)
@@ -644,6 +779,7 @@ impl Datalith {
             .bind(image_height)
             .bind(original_file.as_ref().map(|e| e.id()))
             .bind(has_alpha_channel)
+            .bind(self.get_namespace())
             .execute(&mut *tx)
             .await;
 
@@ -659,6 +795,14 @@ impl Datalith {
             };
 
             debug_assert!(result.rows_affected() > 0);
+
+            if let Err(error) = index_search_document(&mut tx, id).await {
+                drop(tx);
+
+                recover_thumbnails_and_original_files!();
+
+                return Err(error.into());
+            }
         }
 
         // insert into image_thumbnails
@@ -712,6 +856,24 @@ impl Datalith {
             debug_assert_eq!(max_image_multiplier as u64 * 2, result.rows_affected());
         }
 
+        // count the usage
+        {
+            let bytes = original_file
+                .iter()
+                .chain(thumbnails.iter())
+                .chain(fallback_thumbnails.iter())
+                .map(|file| file.file_size())
+                .sum();
+
+            if let Err(error) = self.add_usage_within_quota(&mut tx, bytes).await {
+                drop(tx);
+
+                recover_thumbnails_and_original_files!();
+
+                return Err(error.into());
+            }
+        }
+
         if let Err(error) = tx.commit().await {
             recover_thumbnails_and_original_files!();
 
@@ -728,6 +890,8 @@ impl Datalith {
             thumbnails,
             fallback_thumbnails,
             has_alpha_channel,
+            DatalithMetadata::default(),
+            0,
         );
 
         Ok(image)
@@ -774,9 +938,11 @@ impl Datalith {
                     `images`
                 WHERE
                     `id` = ?
+                        AND `namespace` = ?
             ",
         )
         .bind(id.into())
+        .bind(self.get_namespace())
         .fetch_optional(&self.0.db)
         .await?;
 
@@ -814,7 +980,7 @@ impl Datalith {
 
         #[allow(clippy::type_complexity)]
         #[rustfmt::skip]
-        let row: Option<(i64, String, u16, u16, Option<Uuid>, bool)> = sqlx::query_as(
+        let row: Option<(i64, String, u16, u16, Option<Uuid>, bool, i64)> = sqlx::query_as(
             "
                 SELECT
                     `created_at`,
@@ -822,14 +988,17 @@ impl Datalith {
                     `image_width`,
                     `image_height`,
                     `original_file_id`,
-                    `has_alpha_channel`
+                    `has_alpha_channel`,
+                    `metadata_version`
                 FROM
                     `images`
                 WHERE
                     `id` = ?
+                        AND `namespace` = ?
             ",
         )
         .bind(image_id)
+        .bind(self.get_namespace())
         .fetch_optional(&self.0.db)
         .await?;
 
@@ -840,6 +1009,7 @@ impl Datalith {
             image_height,
             original_file_id,
             has_alpha_channel,
+            metadata_version,
         )) = row
         {
             let original_file = if let Some(original_file_id) = original_file_id {
@@ -876,6 +1046,7 @@ impl Datalith {
             };
 
             let created_at = DateTime::from_timestamp_millis(created_at).unwrap();
+            let metadata = read_metadata(&mut *self.0.db.acquire().await?, image_id).await?;
 
             let image = DatalithImage::new(
                 image_id,
@@ -887,6 +1058,8 @@ impl Datalith {
                 thumbnails,
                 fallback_thumbnails,
                 has_alpha_channel,
+                metadata,
+                metadata_version as u64,
             );
 
             Ok(Some(image))
@@ -895,9 +1068,10 @@ impl Datalith {
         }
     }
 
-    /// List image IDs.
+    /// List image IDs in the namespace which match the filter.
     pub async fn list_image_ids(
         &self,
+        filter: &DatalithImageFilter,
         mut pagination_options: PaginationOptions<DatalithImageOrderBy>,
     ) -> Result<(Vec<Uuid>, Pagination), DatalithReadError> {
         loop {
@@ -919,16 +1093,20 @@ impl Datalith {
             let total_items = {
                 let row: (u32,) = {
                     #[rustfmt::skip]
-                    let query = sqlx::query_as(
+                    let mut builder = QueryBuilder::new(
                         "
                             SELECT
                                 COUNT(*)
                             FROM
                                 `images`
-                        "
+                            WHERE
+                                `namespace` = "
                     );
 
-                    query.fetch_one(&mut *tx).await?
+                    builder.push_bind(self.get_namespace());
+                    filter.push_sql_conditions(&mut builder);
+
+                    builder.build_query_as().fetch_one(&mut *tx).await?
                 };
 
                 row.0
@@ -936,21 +1114,22 @@ impl Datalith {
 
             let rows: Vec<(Uuid,)> = {
                 #[rustfmt::skip]
-                let sql = format!(
+                let mut builder = QueryBuilder::new(format!(
                     "
                         SELECT
                             `id`
                         FROM
                             `images`
                         {sql_join}
-                        {sql_order_by}
-                        {sql_limit_offset}
-                    "
-                );
+                        WHERE
+                            `namespace` = "
+                ));
 
-                let query = sqlx::query_as(&sql);
+                builder.push_bind(self.get_namespace());
+                filter.push_sql_conditions(&mut builder);
+                builder.push(format_args!(" {sql_order_by} {sql_limit_offset}"));
 
-                query.fetch_all(&mut *tx).await?
+                builder.build_query_as().fetch_all(&mut *tx).await?
             };
 
             let total_items = total_items as usize;
@@ -977,6 +1156,59 @@ impl Datalith {
             return Ok((ids, pagination));
         }
     }
+
+    /// List image IDs in the namespace which match the filter by seeking from a cursor. Unlike `list_image_ids`, pages do not shift when images are added or removed during the iteration.
+    pub async fn list_image_ids_by_cursor(
+        &self,
+        filter: &DatalithImageFilter,
+        options: &DatalithCursorOptions<DatalithImageSortKey>,
+    ) -> Result<DatalithCursorPage<DatalithImageSortKey>, DatalithReadError> {
+        let sort_column = options.sort().0.sort_column();
+
+        let total_items = if options.count_total {
+            #[rustfmt::skip]
+            let mut builder = QueryBuilder::new(
+                "
+                    SELECT
+                        COUNT(*)
+                    FROM
+                        `images`
+                    WHERE
+                        `images`.`namespace` = "
+            );
+
+            builder.push_bind(self.get_namespace());
+            filter.push_sql_conditions(&mut builder);
+
+            let row: (u32,) = builder.build_query_as().fetch_one(&self.0.db).await?;
+
+            Some(row.0 as usize)
+        } else {
+            None
+        };
+
+        #[rustfmt::skip]
+        let mut builder = QueryBuilder::new(format!(
+            "
+                SELECT
+                    {},
+                    `images`.`id`
+                FROM
+                    `images`
+                WHERE
+                    `images`.`namespace` = ",
+            sort_column.column
+        ));
+
+        builder.push_bind(self.get_namespace());
+        filter.push_sql_conditions(&mut builder);
+        options.push_seek_conditions(&mut builder, &sort_column, "`images`.`id`");
+        options.push_order_by_limit(&mut builder, &sort_column, "`images`.`id`");
+
+        let rows = builder.build().fetch_all(&self.0.db).await?;
+
+        Ok(options.to_page(rows, &sort_column, total_items)?)
+    }
 }
 
 // Delete
@@ -987,6 +1219,14 @@ impl Datalith {
         let image = self.get_image_by_id(id).await?;
 
         if let Some(image) = image {
+            let bytes: u64 = image
+                .original_file()
+                .into_iter()
+                .chain(image.thumbnails())
+                .chain(image.fallback_thumbnails())
+                .map(|file| file.file_size())
+                .sum();
+
             let mut file_ids = HashSet::with_capacity(image.thumbnails().len() * 2 + 1);
 
             for file in image.thumbnails().iter().chain(image.fallback_thumbnails()) {
@@ -1028,9 +1268,11 @@ impl Datalith {
                         `images`
                     WHERE
                         `id` = ?
+                            AND `namespace` = ?
                 ",
             )
             .bind(id)
+            .bind(self.get_namespace())
             .execute(&mut *tx)
             .await?;
 
@@ -1038,6 +1280,11 @@ impl Datalith {
                 return Ok(false);
             }
 
+            delete_metadata(&mut tx, id).await?;
+            delete_search_document(&mut tx, id).await?;
+
+            add_usage(&mut tx, self.get_namespace(), -(bytes as i64), -1).await?;
+
             tx.commit().await?;
 
             // delete related files
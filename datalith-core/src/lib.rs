@@ -9,6 +9,10 @@ A file management system powered by SQLite for metadata storage and the file sys
 * `Resource`: Represents a regular file in any type. One or more `Resource` entries can point to the same `File`.
 * `Image`: Represents any image in a format supported by Datalith. Each `Image` point to multiple `File`s because it includes **the original image file** as well as **thumbnails** in different resolutions and types.
 
+## Compression
+
+With the `compression` feature, stored files can be compressed with Zstandard. Compression is opt-in: no file is compressed until a policy is set by `Datalith::set_compression_policy`, as in `DatalithCompressionPolicy::text()`. Compressed and uncompressed files can be read in the same way.
+
 ## Examples
 
 #### Put a File
@@ -21,7 +25,7 @@ use tokio::io::AsyncReadExt;
 # async fn main() {
 let datalith = Datalith::new("datalith").await.unwrap();
 
-let file = datalith.put_file_by_buffer(b"Hello world!", Some("plain.txt"), Some((mime::TEXT_PLAIN_UTF_8, FileTypeLevel::Manual))).await.unwrap();
+let file = datalith.put_file_by_buffer(b"Hello world!", Some("plain.txt"), Some((mime::TEXT_PLAIN_UTF_8, FileTypeLevel::Manual)), None).await.unwrap();
 
 let mut reader = file.create_reader().await.unwrap();
 
@@ -66,7 +70,7 @@ use datalith_core::{mime, Datalith, FileTypeLevel};
 # async fn main() {
 let datalith = Datalith::new("datalith").await.unwrap();
 
-let file_id = datalith.put_file_by_buffer_temporarily(b"Hello world!", Some("plain.txt"), Some((mime::TEXT_PLAIN_UTF_8, FileTypeLevel::Manual))).await.unwrap().id();
+let file_id = datalith.put_file_by_buffer_temporarily(b"Hello world!", Some("plain.txt"), Some((mime::TEXT_PLAIN_UTF_8, FileTypeLevel::Manual)), None).await.unwrap().id();
 let file = datalith.get_file_by_id(file_id).await.unwrap().unwrap(); // A temporary file can be retrieved using the `get_file_by_id` function only once. After that, it cannot be retrieved again.
 
 // do something
@@ -85,7 +89,7 @@ use tokio::io::AsyncReadExt;
 # async fn main() {
 let datalith = Datalith::new("datalith").await.unwrap();
 
-let resource = datalith.put_resource_by_buffer(b"Hello world!", Some("plain.txt"), Some((mime::TEXT_PLAIN_UTF_8, FileTypeLevel::Manual))).await.unwrap();
+let resource = datalith.put_resource_by_buffer(b"Hello world!", Some("plain.txt"), Some((mime::TEXT_PLAIN_UTF_8, FileTypeLevel::Manual)), None).await.unwrap();
 
 let mut reader = resource.file().create_reader().await.unwrap();
 
@@ -130,7 +134,7 @@ use datalith_core::{mime, Datalith, FileTypeLevel};
 # async fn main() {
 let datalith = Datalith::new("datalith").await.unwrap();
 
-let resource_id = datalith.put_resource_by_buffer_temporarily(b"Hello world!", Some("plain.txt"), Some((mime::TEXT_PLAIN_UTF_8, FileTypeLevel::Manual))).await.unwrap().id();
+let resource_id = datalith.put_resource_by_buffer_temporarily(b"Hello world!", Some("plain.txt"), Some((mime::TEXT_PLAIN_UTF_8, FileTypeLevel::Manual)), None).await.unwrap().id();
 let resource = datalith.get_resource_by_id(resource_id).await.unwrap().unwrap(); // A temporary resource can be retrieved using the `get_resource_by_id` function only once. After that, it cannot be retrieved again.
 
 // do something
@@ -170,13 +174,22 @@ datalith.close().await;
 
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
+pub extern crate async_trait;
 pub extern crate chrono;
 pub extern crate mime;
 pub extern crate uuid;
 
+mod api_keys;
+mod backup;
+mod compression;
+mod consistency;
+mod cursors;
 mod datalith;
+mod datalith_builder;
 mod datalith_errors;
 mod datalith_file;
+mod disk_space;
+mod filters;
 mod functions;
 mod guard;
 #[cfg(feature = "image-convert")]
@@ -185,20 +198,48 @@ mod image;
 mod magic_cookie_pool;
 #[cfg(feature = "manager")]
 mod manager;
+mod metadata;
+mod migrations;
+mod namespaces;
+mod quotas;
 mod resources;
-
+mod search;
+mod signed_urls;
+mod storage;
+mod upload_sessions;
+mod verification;
+
+pub use api_keys::*;
+pub use compression::*;
+pub use consistency::*;
+pub use cursors::{
+    DEFAULT_CURSOR_LIMIT, DatalithCursor, DatalithCursorOptions, DatalithCursorPage,
+    DatalithSortKey,
+};
 pub use datalith::*;
+pub use datalith_builder::*;
 pub use datalith_errors::*;
 pub use datalith_file::*;
+pub use disk_space::*;
+pub use filters::DatalithNameMatch;
 #[cfg(feature = "image-convert")]
 pub use functions::get_image_extension;
+pub use functions::parse_sha256_hex;
 #[cfg(feature = "image-convert")]
 pub use image::*;
 #[cfg(feature = "manager")]
 pub use manager::*;
+pub use metadata::*;
 use mime::{APPLICATION_OCTET_STREAM, Mime};
+pub use namespaces::*;
+pub use quotas::*;
 pub use rdb_pagination::{OrderMethod, OrderMethodValue, Pagination, PaginationOptions};
 pub use resources::*;
+pub use search::{DatalithSearchHit, DatalithSearchHitKind, DatalithSearchOrderBy};
+pub use signed_urls::*;
+pub use storage::*;
+pub use upload_sessions::*;
+pub use verification::*;
 
 /// The default mime type.
 pub const DEFAULT_MIME_TYPE: Mime = APPLICATION_OCTET_STREAM;
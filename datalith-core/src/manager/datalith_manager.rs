@@ -2,6 +2,7 @@ use std::{
     fmt,
     fmt::{Debug, Formatter},
     ops::Deref,
+    sync::Arc,
     time::Duration,
 };
 
@@ -67,6 +68,90 @@ impl DatalithManager {
                 .await?;
         }
 
+        {
+            let datalith = datalith.clone();
+
+            scheduler
+                .add(Job::new_repeated_async(Duration::from_secs(60 * 10), move |_uuid, _l| {
+                    let datalith = datalith.clone();
+
+                    Box::pin(async move {
+                        match datalith.clear_expired_upload_sessions().await {
+                            Ok(count) => match count {
+                                0 => {
+                                    tracing::debug!("no expired upload session needs to be deleted")
+                                },
+                                1 => tracing::info!("one expired upload session has been deleted"),
+                                _ => tracing::info!(
+                                    "{count} expired upload sessions have been deleted"
+                                ),
+                            },
+                            Err(error) => {
+                                tracing::warn!("{error}");
+                            },
+                        }
+                    })
+                })?)
+                .await?;
+        }
+
+        {
+            let datalith = datalith.clone();
+            let verifying = Arc::new(tokio::sync::Mutex::new(()));
+
+            scheduler
+                .add(Job::new_async_tz("0 30 * * * *", Local, move |_uuid, _l| {
+                    let datalith = datalith.clone();
+                    let verifying = verifying.clone();
+
+                    Box::pin(async move {
+                        // a throttled verification may take longer than the interval
+                        let Ok(_verifying) = verifying.try_lock() else {
+                            tracing::debug!("the previous verification is still running");
+
+                            return;
+                        };
+
+                        let verify_options = datalith.get_background_verify_options();
+
+                        match datalith.verify_files(&verify_options).await {
+                            Ok(report) => {
+                                for (id, problem) in report.problems.iter() {
+                                    tracing::warn!("the file {id} is problematic: {problem}");
+                                }
+
+                                match report.verified {
+                                    0 => tracing::debug!("no file needs to be verified"),
+                                    1 => tracing::info!("one file has been verified"),
+                                    _ => tracing::info!(
+                                        "{} files have been verified",
+                                        report.verified
+                                    ),
+                                }
+
+                                if report.quarantined > 0 {
+                                    tracing::warn!(
+                                        "{} corrupt files have been quarantined",
+                                        report.quarantined
+                                    );
+                                }
+
+                                if report.released > 0 {
+                                    tracing::info!(
+                                        "{} quarantined files have been released",
+                                        report.released
+                                    );
+                                }
+                            },
+                            Err(error) => {
+                                tracing::warn!("{error}");
+                            },
+                        }
+                    })
+                })?)
+                .await?;
+        }
+
         scheduler.start().await?;
 
         Ok(Self {
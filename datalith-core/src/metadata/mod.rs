@@ -0,0 +1,463 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection};
+use uuid::Uuid;
+
+use crate::{
+    Datalith, DatalithWriteError, functions::get_current_timestamp, search::index_search_document,
+};
+
+/// The maximum length of a metadata key.
+pub const MAX_METADATA_KEY_LENGTH: usize = 64;
+/// The maximum length (in bytes) of a metadata value.
+pub const MAX_METADATA_VALUE_LENGTH: usize = 1024;
+/// The maximum length (in bytes) of a tag.
+pub const MAX_TAG_LENGTH: usize = 64;
+
+/// Check whether a string can be used as a metadata key. A key consists of 1 to 64 lowercase ASCII letters, digits, `-` and `_`, so that it can also be carried by an HTTP header name.
+#[inline]
+pub fn is_valid_metadata_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.len() <= MAX_METADATA_KEY_LENGTH
+        && key
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-' || b == b'_')
+}
+
+/// Check whether a string can be used as a metadata value. A value is at most 1024 bytes without control characters.
+#[inline]
+pub fn is_valid_metadata_value(value: &str) -> bool {
+    value.len() <= MAX_METADATA_VALUE_LENGTH && !value.chars().any(char::is_control)
+}
+
+/// Check whether a string can be used as a tag. A tag is 1 to 64 bytes without control characters and commas.
+#[inline]
+pub fn is_valid_tag(tag: &str) -> bool {
+    !tag.is_empty()
+        && tag.len() <= MAX_TAG_LENGTH
+        && !tag.chars().any(|c| c.is_control() || c == ',')
+}
+
+/// User-defined metadata of a resource or an image.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DatalithMetadata {
+    /// The key/value pairs.
+    pub values: BTreeMap<String, String>,
+    /// The tags.
+    pub tags:   BTreeSet<String>,
+}
+
+impl DatalithMetadata {
+    /// Check whether there is no key/value pair and no tag.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty() && self.tags.is_empty()
+    }
+}
+
+/// The changes to the metadata of a resource or an image.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DatalithMetadataUpdate {
+    /// The key/value pairs to set. A `None` value removes the key.
+    pub values:      BTreeMap<String, Option<String>>,
+    /// The tags to add.
+    pub add_tags:    BTreeSet<String>,
+    /// The tags to remove.
+    pub remove_tags: BTreeSet<String>,
+}
+
+impl DatalithMetadataUpdate {
+    /// Check whether nothing is changed.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty() && self.add_tags.is_empty() && self.remove_tags.is_empty()
+    }
+
+    /// Check the keys, values and tags.
+    #[allow(clippy::result_large_err)]
+    pub fn validate(&self) -> Result<(), DatalithWriteError> {
+        for (key, value) in self.values.iter() {
+            if !is_valid_metadata_key(key) || !value.as_deref().is_none_or(is_valid_metadata_value)
+            {
+                return Err(DatalithWriteError::MetadataInvalid {
+                    name: key.clone()
+                });
+            }
+        }
+
+        for tag in self.add_tags.iter() {
+            if !is_valid_tag(tag) {
+                return Err(DatalithWriteError::MetadataInvalid {
+                    name: tag.clone()
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl From<DatalithMetadata> for DatalithMetadataUpdate {
+    #[inline]
+    fn from(metadata: DatalithMetadata) -> Self {
+        Self {
+            values:      metadata
+                .values
+                .into_iter()
+                .map(|(key, value)| (key, Some(value)))
+                .collect(),
+            add_tags:    metadata.tags,
+            remove_tags: BTreeSet::new(),
+        }
+    }
+}
+
+/// Read the metadata of a resource or an image.
+pub(crate) async fn read_metadata(
+    conn: &mut SqliteConnection,
+    owner_id: Uuid,
+) -> Result<DatalithMetadata, sqlx::Error> {
+    #[rustfmt::skip]
+    let values: Vec<(String, String)> = sqlx::query_as(
+        "
+            SELECT
+                `key`,
+                `value`
+            FROM
+                `metadata`
+            WHERE
+                `owner_id` = ?
+        ",
+    )
+    .bind(owner_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    #[rustfmt::skip]
+    let tags: Vec<(String,)> = sqlx::query_as(
+        "
+            SELECT
+                `tag`
+            FROM
+                `tags`
+            WHERE
+                `owner_id` = ?
+        ",
+    )
+    .bind(owner_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(DatalithMetadata {
+        values: values.into_iter().collect(),
+        tags:   tags.into_iter().map(|(tag,)| tag).collect(),
+    })
+}
+
+/// Apply the changes to the metadata of a resource or an image. The changes should have been validated.
+async fn apply_metadata_update(
+    conn: &mut SqliteConnection,
+    owner_id: Uuid,
+    update: &DatalithMetadataUpdate,
+) -> Result<(), sqlx::Error> {
+    for (key, value) in update.values.iter() {
+        match value {
+            Some(value) => {
+                #[rustfmt::skip]
+                sqlx::query(
+                    "
+                        INSERT INTO `metadata` (`owner_id`, `key`, `value`)
+                            VALUES (?, ?, ?)
+                        ON CONFLICT (`owner_id`, `key`) DO UPDATE SET
+                            `value` = `excluded`.`value`
+                    ",
+                )
+                .bind(owner_id)
+                .bind(key)
+                .bind(value)
+                .execute(&mut *conn)
+                .await?;
+            },
+            None => {
+                #[rustfmt::skip]
+                sqlx::query(
+                    "
+                        DELETE FROM
+                            `metadata`
+                        WHERE
+                            `owner_id` = ?
+                                AND `key` = ?
+                    ",
+                )
+                .bind(owner_id)
+                .bind(key)
+                .execute(&mut *conn)
+                .await?;
+            },
+        }
+    }
+
+    for tag in update.remove_tags.iter() {
+        #[rustfmt::skip]
+        sqlx::query(
+            "
+                DELETE FROM
+                    `tags`
+                WHERE
+                    `owner_id` = ?
+                        AND `tag` = ?
+            ",
+        )
+        .bind(owner_id)
+        .bind(tag)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    for tag in update.add_tags.iter() {
+        #[rustfmt::skip]
+        sqlx::query(
+            "
+                INSERT OR IGNORE INTO `tags` (`owner_id`, `tag`)
+                    VALUES (?, ?)
+            ",
+        )
+        .bind(owner_id)
+        .bind(tag)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Delete the metadata of a resource or an image.
+pub(crate) async fn delete_metadata(
+    conn: &mut SqliteConnection,
+    owner_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    #[rustfmt::skip]
+    sqlx::query(
+        "
+            DELETE FROM
+                `metadata`
+            WHERE
+                `owner_id` = ?
+        ",
+    )
+    .bind(owner_id)
+    .execute(&mut *conn)
+    .await?;
+
+    #[rustfmt::skip]
+    sqlx::query(
+        "
+            DELETE FROM
+                `tags`
+            WHERE
+                `owner_id` = ?
+        ",
+    )
+    .bind(owner_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Push the SQL conditions which match the rows of `table` having all the key/value pairs and tags in `metadata`.
+pub(crate) fn push_metadata_conditions(
+    builder: &mut QueryBuilder<'_, Sqlite>,
+    table: &str,
+    metadata: &DatalithMetadata,
+) {
+    for (key, value) in metadata.values.iter() {
+        builder.push(format_args!(
+            " AND EXISTS (SELECT 1 FROM `metadata` WHERE `metadata`.`owner_id` = `{table}`.`id` \
+             AND `metadata`.`key` = "
+        ));
+        builder.push_bind(key.clone());
+        builder.push(" AND `metadata`.`value` = ");
+        builder.push_bind(value.clone());
+        builder.push(")");
+    }
+
+    for tag in metadata.tags.iter() {
+        builder.push(format_args!(
+            " AND EXISTS (SELECT 1 FROM `tags` WHERE `tags`.`owner_id` = `{table}`.`id` AND \
+             `tags`.`tag` = "
+        ));
+        builder.push_bind(tag.clone());
+        builder.push(")");
+    }
+}
+
+/// Push the SQL conditions which match the resource or the image `id` in `namespace`. If `current_timestamp` exists, expired rows are not matched.
+fn push_owner_conditions(
+    builder: &mut QueryBuilder<'_, Sqlite>,
+    id: Uuid,
+    namespace: &str,
+    current_timestamp: Option<i64>,
+) {
+    builder.push(" WHERE `id` = ");
+    builder.push_bind(id);
+    builder.push(" AND `namespace` = ");
+    builder.push_bind(namespace.to_string());
+
+    if let Some(current_timestamp) = current_timestamp {
+        builder.push(" AND ( `expired_at` IS NULL OR `expired_at` > ");
+        builder.push_bind(current_timestamp);
+        builder.push(" )");
+    }
+}
+
+/// Increase the metadata version of a resource or an image in `table`, only if it is `expected_metadata_version` when that exists. Return the new version, or `None` if the row does not exist.
+async fn increase_metadata_version(
+    conn: &mut SqliteConnection,
+    table: &str,
+    id: Uuid,
+    namespace: &str,
+    current_timestamp: Option<i64>,
+    expected_metadata_version: Option<u64>,
+) -> Result<Option<u64>, DatalithWriteError> {
+    let mut builder = QueryBuilder::<Sqlite>::new(format!(
+        "UPDATE `{table}` SET `metadata_version` = `metadata_version` + 1"
+    ));
+    push_owner_conditions(&mut builder, id, namespace, current_timestamp);
+
+    if let Some(expected_metadata_version) = expected_metadata_version {
+        builder.push(" AND `metadata_version` = ");
+        builder.push_bind(expected_metadata_version as i64);
+    }
+
+    builder.push(" RETURNING `metadata_version`");
+
+    let rows: Vec<(i64,)> = builder.build_query_as().fetch_all(&mut *conn).await?;
+
+    if let Some(&(metadata_version,)) = rows.first() {
+        return Ok(Some(metadata_version as u64));
+    }
+
+    let Some(expected_metadata_version) = expected_metadata_version else {
+        return Ok(None);
+    };
+
+    // tell a changed version from a missing row
+    let mut builder =
+        QueryBuilder::<Sqlite>::new(format!("SELECT `metadata_version` FROM `{table}`"));
+    push_owner_conditions(&mut builder, id, namespace, current_timestamp);
+
+    let row: Option<(i64,)> = builder.build_query_as().fetch_optional(&mut *conn).await?;
+
+    match row {
+        Some((metadata_version,)) => Err(DatalithWriteError::MetadataVersionMismatch {
+            metadata_version: metadata_version as u64,
+            expected_metadata_version,
+        }),
+        None => Ok(None),
+    }
+}
+
+// Metadata
+impl Datalith {
+    /// Change the metadata of a resource in the namespace. Return the new metadata, or `None` if the resource does not exist.
+    #[inline]
+    pub async fn update_resource_metadata(
+        &self,
+        id: impl Into<Uuid>,
+        update: &DatalithMetadataUpdate,
+    ) -> Result<Option<DatalithMetadata>, DatalithWriteError> {
+        Ok(self
+            .update_resource_metadata_with_version(id, update, None)
+            .await?
+            .map(|(metadata, _)| metadata))
+    }
+
+    /// Change the metadata of a resource in the namespace if its version is `expected_metadata_version`, which is checked and increased atomically. Return the new metadata and its version, or `None` if the resource does not exist.
+    pub async fn update_resource_metadata_with_version(
+        &self,
+        id: impl Into<Uuid>,
+        update: &DatalithMetadataUpdate,
+        expected_metadata_version: Option<u64>,
+    ) -> Result<Option<(DatalithMetadata, u64)>, DatalithWriteError> {
+        update.validate()?;
+
+        let id = id.into();
+        let current_timestamp = get_current_timestamp();
+
+        let mut tx = self.0.db.begin().await?;
+
+        let metadata_version = increase_metadata_version(
+            &mut tx,
+            "resources",
+            id,
+            self.get_namespace(),
+            Some(current_timestamp),
+            expected_metadata_version,
+        )
+        .await?;
+
+        let Some(metadata_version) = metadata_version else {
+            return Ok(None);
+        };
+
+        apply_metadata_update(&mut tx, id, update).await?;
+        index_search_document(&mut tx, id).await?;
+
+        let metadata = read_metadata(&mut tx, id).await?;
+
+        tx.commit().await?;
+
+        Ok(Some((metadata, metadata_version)))
+    }
+
+    /// Change the metadata of an image in the namespace. Return the new metadata, or `None` if the image does not exist.
+    #[inline]
+    pub async fn update_image_metadata(
+        &self,
+        id: impl Into<Uuid>,
+        update: &DatalithMetadataUpdate,
+    ) -> Result<Option<DatalithMetadata>, DatalithWriteError> {
+        Ok(self
+            .update_image_metadata_with_version(id, update, None)
+            .await?
+            .map(|(metadata, _)| metadata))
+    }
+
+    /// Change the metadata of an image in the namespace if its version is `expected_metadata_version`, which is checked and increased atomically. Return the new metadata and its version, or `None` if the image does not exist.
+    pub async fn update_image_metadata_with_version(
+        &self,
+        id: impl Into<Uuid>,
+        update: &DatalithMetadataUpdate,
+        expected_metadata_version: Option<u64>,
+    ) -> Result<Option<(DatalithMetadata, u64)>, DatalithWriteError> {
+        update.validate()?;
+
+        let id = id.into();
+
+        let mut tx = self.0.db.begin().await?;
+
+        let metadata_version = increase_metadata_version(
+            &mut tx,
+            "images",
+            id,
+            self.get_namespace(),
+            None,
+            expected_metadata_version,
+        )
+        .await?;
+
+        let Some(metadata_version) = metadata_version else {
+            return Ok(None);
+        };
+
+        apply_metadata_update(&mut tx, id, update).await?;
+        index_search_document(&mut tx, id).await?;
+
+        let metadata = read_metadata(&mut tx, id).await?;
+
+        tx.commit().await?;
+
+        Ok(Some((metadata, metadata_version)))
+    }
+}
@@ -0,0 +1,133 @@
+use std::{future::Future, path::PathBuf, pin::Pin};
+
+use sqlx::SqliteConnection;
+
+use crate::{LocalStorageBackend, datalith::TABLE_DB_INFORMATION, quotas::recalculate_usage};
+
+/// What a Rust step of a migration may need to know about the environment being migrated.
+pub(crate) struct MigrationContext {
+    /// The directory where the default storage backend stores files.
+    pub(crate) file_directory:       PathBuf,
+    /// The depth of nested directories used if the environment has not decided one.
+    pub(crate) file_directory_depth: u8,
+}
+
+/// A Rust step of a migration. It is executed after the SQL script of the migration, inside the same transaction.
+pub(crate) type MigrationStep =
+    for<'a> fn(
+        &'a mut SqliteConnection,
+        &'a MigrationContext,
+    ) -> Pin<Box<dyn Future<Output = Result<(), sqlx::Error>> + Send + 'a>>;
+
+/// A schema migration which upgrades the database from `version - 1` to `version`.
+pub(crate) struct Migration {
+    pub(crate) version: u32,
+    /// SQL statements separated by `;\n`.
+    pub(crate) sql:     &'static str,
+    pub(crate) step:    Option<MigrationStep>,
+}
+
+/// All the schema migrations, in order. Version 1 is created by `schema.sql` and has no migration.
+///
+/// To change the schema, update `schema.sql` for new databases and append a migration here for existing ones.
+pub(crate) const MIGRATIONS: &[Migration] = &[
+    // upload sessions
+    Migration {
+        version: 2, sql: include_str!("../sql/upgrade_2.sql"), step: None
+    },
+    // API keys
+    Migration {
+        version: 3, sql: include_str!("../sql/upgrade_3.sql"), step: None
+    },
+    // file compression
+    Migration {
+        version: 4, sql: include_str!("../sql/upgrade_4.sql"), step: None
+    },
+    // nested file directories. The stored files are moved out of the flat directory
+    Migration {
+        version: 5,
+        sql:     "",
+        step:    Some(|conn, context| Box::pin(decide_file_directory_depth(conn, context))),
+    },
+    // file verification
+    Migration {
+        version: 6, sql: include_str!("../sql/upgrade_6.sql"), step: None
+    },
+    // namespaces
+    Migration {
+        version: 7, sql: include_str!("../sql/upgrade_7.sql"), step: None
+    },
+    // quotas. The usage is counted from the existing resources and images
+    Migration {
+        version: 8,
+        sql:     include_str!("../sql/upgrade_8.sql"),
+        step:    Some(|conn, _| Box::pin(recalculate_usage(conn))),
+    },
+    // metadata and tags
+    Migration {
+        version: 9, sql: include_str!("../sql/upgrade_9.sql"), step: None
+    },
+    // indexes for filtering and sorting listings
+    Migration {
+        version: 10, sql: include_str!("../sql/upgrade_10.sql"), step: None
+    },
+    // full-text search. The existing resources and images are indexed
+    Migration {
+        version: 11, sql: include_str!("../sql/upgrade_11.sql"), step: None
+    },
+    // versions of the metadata of resources and images
+    Migration {
+        version: 12, sql: include_str!("../sql/upgrade_12.sql"), step: None
+    },
+];
+
+/// The database version this application uses, which is the version of the last migration.
+pub(crate) const DATABASE_VERSION: u32 = {
+    let mut version = 1;
+    let mut i = 0;
+
+    while i < MIGRATIONS.len() {
+        assert!(MIGRATIONS[i].version == version + 1, "migrations must be consecutive");
+
+        version = MIGRATIONS[i].version;
+        i += 1;
+    }
+
+    version
+};
+
+/// Record the depth of nested directories of the file directory, and move the stored files to where they belong with that depth.
+///
+/// Moving files cannot be rolled back, but it can be repeated, so a failed migration can simply be run again.
+pub(crate) async fn decide_file_directory_depth(
+    conn: &mut SqliteConnection,
+    context: &MigrationContext,
+) -> Result<(), sqlx::Error> {
+    let storage = LocalStorageBackend::with_depth(
+        context.file_directory.as_path(),
+        context.file_directory_depth,
+    );
+
+    let counter = storage.relayout().await?;
+
+    if counter > 0 {
+        tracing::info!(
+            "{counter} stored files have been moved into directories of depth {}",
+            storage.get_depth()
+        );
+    }
+
+    #[rustfmt::skip]
+    sqlx::query(&format!(
+        "
+            INSERT INTO `{TABLE_DB_INFORMATION}`
+                VALUES
+                    ('file_directory_depth', ?)
+        "
+    ))
+    .bind(storage.get_depth().to_string())
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
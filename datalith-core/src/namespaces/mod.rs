@@ -0,0 +1,38 @@
+use std::sync::Arc;
+
+use crate::Datalith;
+
+/// The namespace which resources and images belong to if no namespace is specified.
+pub const DEFAULT_NAMESPACE: &str = "";
+/// The maximum length of a namespace.
+pub const MAX_NAMESPACE_LENGTH: usize = 64;
+
+/// Check whether a string can be used as a namespace. A namespace consists of at most 64 ASCII letters, digits, `-` and `_`. An empty string is the default namespace.
+#[inline]
+pub fn is_valid_namespace(namespace: &str) -> bool {
+    namespace.len() <= MAX_NAMESPACE_LENGTH
+        && namespace.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+// Namespace
+impl Datalith {
+    /// Retrieve the namespace of this handle.
+    #[inline]
+    pub fn get_namespace(&self) -> &str {
+        &self.1
+    }
+
+    /// Create a handle which puts, gets, lists and deletes resources and images in the namespace. Return `None` if the namespace is invalid.
+    ///
+    /// The handle shares everything else with this one, and identical data put in different namespaces is still stored only once.
+    #[inline]
+    pub fn with_namespace(&self, namespace: impl AsRef<str>) -> Option<Self> {
+        let namespace = namespace.as_ref();
+
+        if !is_valid_namespace(namespace) {
+            return None;
+        }
+
+        Some(Self(self.0.clone(), Arc::from(namespace)))
+    }
+}
@@ -0,0 +1,355 @@
+use std::collections::HashMap;
+
+use sqlx::SqliteConnection;
+
+use crate::{Datalith, DatalithReadError, DatalithWriteError};
+
+/// The limits of the total size and the number of resources and images. `None` means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DatalithQuota {
+    /// The maximum total size (in bytes).
+    pub max_bytes: Option<u64>,
+    /// The maximum number of resources and images.
+    pub max_count: Option<u64>,
+}
+
+impl DatalithQuota {
+    /// Check whether the usage is within this quota.
+    #[inline]
+    pub fn allows(&self, usage: DatalithUsage) -> bool {
+        self.max_bytes.is_none_or(|max_bytes| usage.bytes <= max_bytes)
+            && self.max_count.is_none_or(|max_count| usage.count <= max_count)
+    }
+}
+
+/// The total size and the number of resources and images.
+///
+/// The size is logical, which means a file shared by multiple resources or images is counted once per reference. Files put directly are not counted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DatalithUsage {
+    /// The total size (in bytes).
+    pub bytes: u64,
+    /// The number of resources and images.
+    pub count: u64,
+}
+
+/// Add (or subtract if negative) the usage of a namespace.
+pub(crate) async fn add_usage(
+    conn: &mut SqliteConnection,
+    namespace: &str,
+    bytes: i64,
+    count: i64,
+) -> Result<(), sqlx::Error> {
+    #[rustfmt::skip]
+    sqlx::query(
+        "
+            INSERT INTO `namespace_usage` (`namespace`, `total_bytes`, `total_count`)
+                VALUES (?, ?, ?)
+            ON CONFLICT (`namespace`) DO UPDATE SET
+                `total_bytes` = `total_bytes` + `excluded`.`total_bytes`,
+                `total_count` = `total_count` + `excluded`.`total_count`
+        ",
+    )
+    .bind(namespace)
+    .bind(bytes)
+    .bind(count)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Count the usage of every namespace from the resources and images.
+pub(crate) async fn calculate_usage(
+    conn: &mut SqliteConnection,
+) -> Result<HashMap<String, DatalithUsage>, sqlx::Error> {
+    #[rustfmt::skip]
+    let rows: Vec<(String, i64, i64)> = sqlx::query_as(
+        "
+            SELECT
+                `namespace`,
+                SUM(`bytes`),
+                COUNT(*)
+            FROM
+                (
+                    SELECT
+                        `resources`.`namespace` AS `namespace`,
+                        IFNULL(`files`.`file_size`, 0) AS `bytes`
+                    FROM
+                        `resources`
+                            LEFT JOIN `files` ON `files`.`id` = `resources`.`file_id`
+                    UNION ALL
+                    SELECT
+                        `images`.`namespace` AS `namespace`,
+                        IFNULL((SELECT `file_size` FROM `files` WHERE `id` = `images`.`original_file_id`), 0)
+                            + IFNULL((
+                                SELECT
+                                    SUM(`files`.`file_size`)
+                                FROM
+                                    `image_thumbnails`
+                                        JOIN `files` ON `files`.`id` = `image_thumbnails`.`file_id`
+                                WHERE
+                                    `image_thumbnails`.`image_id` = `images`.`id`
+                            ), 0) AS `bytes`
+                    FROM
+                        `images`
+                )
+            GROUP BY
+                `namespace`
+        ",
+    )
+    .fetch_all(conn)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(namespace, bytes, count)| {
+            (namespace, DatalithUsage {
+                bytes: bytes as u64, count: count as u64
+            })
+        })
+        .collect())
+}
+
+/// Rebuild the usage of every namespace from the resources and images. The quotas are kept.
+pub(crate) async fn recalculate_usage(conn: &mut SqliteConnection) -> Result<(), sqlx::Error> {
+    let usages = calculate_usage(&mut *conn).await?;
+
+    #[rustfmt::skip]
+    sqlx::query(
+        "
+            UPDATE
+                `namespace_usage`
+            SET
+                `total_bytes` = 0,
+                `total_count` = 0
+        ",
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    for (namespace, usage) in usages {
+        add_usage(&mut *conn, namespace.as_str(), usage.bytes as i64, usage.count as i64).await?;
+    }
+
+    Ok(())
+}
+
+/// Read the usage recorded for every namespace.
+pub(crate) async fn read_recorded_usage(
+    conn: &mut SqliteConnection,
+) -> Result<HashMap<String, DatalithUsage>, sqlx::Error> {
+    #[rustfmt::skip]
+    let rows: Vec<(String, i64, i64)> = sqlx::query_as(
+        "
+            SELECT
+                `namespace`,
+                `total_bytes`,
+                `total_count`
+            FROM
+                `namespace_usage`
+        ",
+    )
+    .fetch_all(conn)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(namespace, bytes, count)| {
+            (namespace, DatalithUsage {
+                bytes: bytes as u64, count: count as u64
+            })
+        })
+        .collect())
+}
+
+// Quota
+impl Datalith {
+    /// Retrieve the quota of all namespaces as a whole.
+    #[inline]
+    pub fn get_global_quota(&self) -> DatalithQuota {
+        *self.0._global_quota.lock().unwrap()
+    }
+
+    /// Set the quota of all namespaces as a whole.
+    #[inline]
+    pub fn set_global_quota(&self, quota: DatalithQuota) {
+        *self.0._global_quota.lock().unwrap() = quota;
+    }
+
+    /// Retrieve the quota of the namespace of this handle.
+    pub async fn get_namespace_quota(&self) -> Result<DatalithQuota, DatalithReadError> {
+        #[rustfmt::skip]
+        let row: Option<(Option<i64>, Option<i64>)> = sqlx::query_as(
+            "
+                SELECT
+                    `max_bytes`,
+                    `max_count`
+                FROM
+                    `namespace_usage`
+                WHERE
+                    `namespace` = ?
+            ",
+        )
+        .bind(self.get_namespace())
+        .fetch_optional(&self.0.db)
+        .await?;
+
+        Ok(row
+            .map(|(max_bytes, max_count)| DatalithQuota {
+                max_bytes: max_bytes.map(|e| e as u64),
+                max_count: max_count.map(|e| e as u64),
+            })
+            .unwrap_or_default())
+    }
+
+    /// Set the quota of the namespace of this handle. The quota is stored in the database, and it is checked in addition to the global quota.
+    pub async fn set_namespace_quota(
+        &self,
+        quota: DatalithQuota,
+    ) -> Result<(), DatalithWriteError> {
+        #[rustfmt::skip]
+        sqlx::query(
+            "
+                INSERT INTO `namespace_usage` (`namespace`, `max_bytes`, `max_count`)
+                    VALUES (?, ?, ?)
+                ON CONFLICT (`namespace`) DO UPDATE SET
+                    `max_bytes` = `excluded`.`max_bytes`,
+                    `max_count` = `excluded`.`max_count`
+            ",
+        )
+        .bind(self.get_namespace())
+        .bind(quota.max_bytes.map(|e| e as i64))
+        .bind(quota.max_count.map(|e| e as i64))
+        .execute(&self.0.db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Retrieve the usage of the namespace of this handle.
+    pub async fn get_usage(&self) -> Result<DatalithUsage, DatalithReadError> {
+        #[rustfmt::skip]
+        let row: Option<(i64, i64)> = sqlx::query_as(
+            "
+                SELECT
+                    `total_bytes`,
+                    `total_count`
+                FROM
+                    `namespace_usage`
+                WHERE
+                    `namespace` = ?
+            ",
+        )
+        .bind(self.get_namespace())
+        .fetch_optional(&self.0.db)
+        .await?;
+
+        Ok(row
+            .map(|(bytes, count)| DatalithUsage {
+                bytes: bytes as u64, count: count as u64
+            })
+            .unwrap_or_default())
+    }
+
+    /// Retrieve the usage of all namespaces as a whole.
+    pub async fn get_global_usage(&self) -> Result<DatalithUsage, DatalithReadError> {
+        let mut conn = self.0.db.acquire().await?;
+
+        Ok(get_global_usage(&mut conn).await?)
+    }
+
+    /// List the namespaces which have ever had a resource, an image or a quota.
+    pub async fn list_namespaces(&self) -> Result<Vec<String>, DatalithReadError> {
+        #[rustfmt::skip]
+        let rows: Vec<(String,)> = sqlx::query_as(
+            "
+                SELECT
+                    `namespace`
+                FROM
+                    `namespace_usage`
+                ORDER BY
+                    `namespace` ASC
+            ",
+        )
+        .fetch_all(&self.0.db)
+        .await?;
+
+        Ok(rows.into_iter().map(|(namespace,)| namespace).collect())
+    }
+
+    /// Add the usage of a new resource or image to the namespace of this handle, and check the quotas. This should be called inside the transaction which inserts the resource or image, after the first write, so that the check cannot be raced.
+    pub(crate) async fn add_usage_within_quota(
+        &self,
+        conn: &mut SqliteConnection,
+        bytes: u64,
+    ) -> Result<(), DatalithWriteError> {
+        let namespace = self.get_namespace();
+
+        add_usage(&mut *conn, namespace, bytes as i64, 1).await?;
+
+        #[rustfmt::skip]
+        let (total_bytes, total_count, max_bytes, max_count): (i64, i64, Option<i64>, Option<i64>) = sqlx::query_as(
+            "
+                SELECT
+                    `total_bytes`,
+                    `total_count`,
+                    `max_bytes`,
+                    `max_count`
+                FROM
+                    `namespace_usage`
+                WHERE
+                    `namespace` = ?
+            ",
+        )
+        .bind(namespace)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        let namespace_quota = DatalithQuota {
+            max_bytes: max_bytes.map(|e| e as u64),
+            max_count: max_count.map(|e| e as u64),
+        };
+
+        if !namespace_quota
+            .allows(DatalithUsage {
+                bytes: total_bytes as u64, count: total_count as u64
+            })
+        {
+            return Err(DatalithWriteError::QuotaExceeded {
+                namespace: Some(namespace.to_string()),
+            });
+        }
+
+        let global_quota = self.get_global_quota();
+
+        if global_quota != DatalithQuota::default()
+            && !global_quota.allows(get_global_usage(conn).await?)
+        {
+            return Err(DatalithWriteError::QuotaExceeded {
+                namespace: None
+            });
+        }
+
+        Ok(())
+    }
+}
+
+async fn get_global_usage(conn: &mut SqliteConnection) -> Result<DatalithUsage, sqlx::Error> {
+    #[rustfmt::skip]
+    let (bytes, count): (i64, i64) = sqlx::query_as(
+        "
+            SELECT
+                IFNULL(SUM(`total_bytes`), 0),
+                IFNULL(SUM(`total_count`), 0)
+            FROM
+                `namespace_usage`
+        ",
+    )
+    .fetch_one(conn)
+    .await?;
+
+    Ok(DatalithUsage {
+        bytes: bytes as u64, count: count as u64
+    })
+}
@@ -3,26 +3,31 @@ use educe::Educe;
 use mime::Mime;
 use uuid::Uuid;
 
-use crate::DatalithFile;
+use crate::{DatalithFile, DatalithMetadata};
 
 /// A struct that represents an resource.
 #[derive(Debug, Educe)]
 #[educe(PartialEq, Eq, Hash)]
 pub struct DatalithResource {
-    id:           Uuid,
+    id:               Uuid,
     #[educe(Eq(ignore), Hash(ignore))]
-    created_at:   DateTime<Local>,
+    created_at:       DateTime<Local>,
     #[educe(Eq(ignore), Hash(ignore))]
-    file_type:    Mime,
+    file_type:        Mime,
     #[educe(Eq(ignore), Hash(ignore))]
-    file_name:    String,
+    file_name:        String,
     #[educe(Eq(ignore), Hash(ignore))]
-    file:         DatalithFile,
+    file:             DatalithFile,
     #[educe(Eq(ignore), Hash(ignore))]
-    is_temporary: bool,
+    is_temporary:     bool,
+    #[educe(Eq(ignore), Hash(ignore))]
+    metadata:         DatalithMetadata,
+    #[educe(Eq(ignore), Hash(ignore))]
+    metadata_version: u64,
 }
 
 impl DatalithResource {
+    #[allow(clippy::too_many_arguments)]
     /// Create a resource instance.
     #[inline]
     pub(crate) fn new<Tz: TimeZone>(
@@ -32,6 +37,8 @@ impl DatalithResource {
         file_name: impl Into<String>,
         file: DatalithFile,
         is_temporary: bool,
+        metadata: DatalithMetadata,
+        metadata_version: u64,
     ) -> Self
 where {
         let id = id.into();
@@ -44,6 +51,8 @@ where {
             file_name,
             file,
             is_temporary,
+            metadata,
+            metadata_version,
         }
     }
 }
@@ -84,6 +93,18 @@ impl DatalithResource {
     pub const fn is_temporary(&self) -> bool {
         self.is_temporary
     }
+
+    /// Retrieve the user-defined metadata.
+    #[inline]
+    pub const fn metadata(&self) -> &DatalithMetadata {
+        &self.metadata
+    }
+
+    /// Retrieve the version of the user-defined metadata, which is increased whenever the metadata is changed.
+    #[inline]
+    pub const fn metadata_version(&self) -> u64 {
+        self.metadata_version
+    }
 }
 
 impl From<DatalithResource> for DatalithFile {
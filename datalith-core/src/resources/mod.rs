@@ -10,19 +10,29 @@ use rdb_pagination::{
     OrderByOptions, OrderMethod, Pagination, PaginationOptions, SqlJoin, SqlOrderByComponent,
     prelude::*,
 };
+use sqlx::{QueryBuilder, Sqlite};
 use tokio::io::AsyncRead;
 use uuid::Uuid;
 
 use crate::{
-    Datalith, DatalithFile, DatalithReadError, DatalithWriteError, FileTypeLevel,
+    Datalith, DatalithCursorOptions, DatalithCursorPage, DatalithFile, DatalithMetadata,
+    DatalithNameMatch, DatalithReadError, DatalithSortKey, DatalithWriteError, FileTypeLevel,
+    cursors::SortColumn,
+    filters::{
+        push_file_type_condition, push_integer_range_conditions, push_name_condition,
+        push_time_range_conditions,
+    },
     functions::{get_current_timestamp, get_file_name},
-    guard::DeleteGuard,
+    guard::{DeleteGuard, PutGuard},
+    metadata::{delete_metadata, push_metadata_conditions, read_metadata},
+    quotas::add_usage,
+    search::{delete_search_document, index_search_document},
 };
 
 /// A struct that defines the ordering options for querying resources.
 #[derive(Debug, Clone, Educe, OrderByOptions)]
 #[educe(Default)]
-#[orderByOptions(name = resources)]
+#[orderByOptions(name = resources, join((resources, file_id), (files, id)))]
 pub struct DatalithResourceOrderBy {
     #[educe(Default = 102)]
     #[orderByOptions((resources, id), unique)]
@@ -30,26 +40,163 @@ pub struct DatalithResourceOrderBy {
     #[educe(Default = -101)]
     #[orderByOptions((resources, created_at))]
     pub created_at: OrderMethod,
+    #[orderByOptions((resources, file_name))]
+    pub file_name:  OrderMethod,
+    #[orderByOptions((files, file_size))]
+    pub file_size:  OrderMethod,
+    #[orderByOptions((resources, file_type))]
+    pub file_type:  OrderMethod,
+}
+
+/// The keys which a cursor listing of resources can be sorted by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DatalithResourceSortKey {
+    #[default]
+    CreatedAt,
+    FileName,
+    FileSize,
+    FileType,
+}
+
+impl DatalithSortKey for DatalithResourceSortKey {
+    #[inline]
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::CreatedAt => "created_at",
+            Self::FileName => "file_name",
+            Self::FileSize => "file_size",
+            Self::FileType => "file_type",
+        }
+    }
+
+    #[inline]
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "created_at" => Some(Self::CreatedAt),
+            "file_name" => Some(Self::FileName),
+            "file_size" => Some(Self::FileSize),
+            "file_type" => Some(Self::FileType),
+            _ => None,
+        }
+    }
+}
+
+impl DatalithResourceSortKey {
+    #[inline]
+    const fn sort_column(self) -> SortColumn {
+        match self {
+            Self::CreatedAt => SortColumn {
+                column: "`resources`.`created_at`", text: false
+            },
+            Self::FileName => SortColumn {
+                column: "`resources`.`file_name`", text: true
+            },
+            Self::FileSize => SortColumn {
+                column: "`files`.`file_size`", text: false
+            },
+            Self::FileType => SortColumn {
+                column: "`resources`.`file_type`", text: true
+            },
+        }
+    }
+}
+
+/// A struct that defines the conditions for listing resources. The default value matches every resource.
+#[derive(Debug, Clone, Default)]
+pub struct DatalithResourceFilter {
+    /// Match the resources which have all of these key/value pairs and tags.
+    pub metadata:       DatalithMetadata,
+    /// Match the resources of this MIME type, as in `image/png`, or of this top-level type, as in `image/*`.
+    pub file_type:      Option<String>,
+    /// Match the resources created at or after this time.
+    pub created_since:  Option<DateTime<Utc>>,
+    /// Match the resources created before this time.
+    pub created_before: Option<DateTime<Utc>>,
+    /// Match the resources whose file names match.
+    pub file_name:      Option<DatalithNameMatch>,
+    /// Match the resources of at least this size (in bytes).
+    pub min_file_size:  Option<u64>,
+    /// Match the resources of at most this size (in bytes).
+    pub max_file_size:  Option<u64>,
+    /// Match only the temporary resources if `true`, or only the permanent resources if `false`.
+    pub temporary:      Option<bool>,
+}
+
+impl DatalithResourceFilter {
+    /// Push the SQL conditions, following a `WHERE`, which match the unexpired resources in `namespace` and this filter.
+    fn push_list_conditions(
+        &self,
+        builder: &mut QueryBuilder<'_, Sqlite>,
+        namespace: &str,
+        current_timestamp: i64,
+    ) {
+        builder.push(" `resources`.`namespace` = ");
+        builder.push_bind(namespace.to_string());
+        builder.push(" AND (`resources`.`expired_at` IS NULL OR `resources`.`expired_at` > ");
+        builder.push_bind(current_timestamp);
+        builder.push(")");
+
+        self.push_sql_conditions(builder);
+    }
+
+    fn push_sql_conditions(&self, builder: &mut QueryBuilder<'_, Sqlite>) {
+        push_metadata_conditions(builder, "resources", &self.metadata);
+
+        if let Some(file_type) = self.file_type.as_deref() {
+            push_file_type_condition(builder, "`resources`.`file_type`", file_type);
+        }
+
+        push_time_range_conditions(
+            builder,
+            "`resources`.`created_at`",
+            self.created_since,
+            self.created_before,
+        );
+
+        if let Some(file_name) = self.file_name.as_ref() {
+            push_name_condition(builder, "`resources`.`file_name`", file_name);
+        }
+
+        push_integer_range_conditions(
+            builder,
+            "(SELECT `file_size` FROM `files` WHERE `files`.`id` = `resources`.`file_id`)",
+            self.min_file_size,
+            self.max_file_size,
+        );
+
+        match self.temporary {
+            Some(true) => {
+                builder.push(" AND `resources`.`expired_at` IS NOT NULL");
+            },
+            Some(false) => {
+                builder.push(" AND `resources`.`expired_at` IS NULL");
+            },
+            None => (),
+        }
+    }
 }
 
 // Upload
 impl Datalith {
-    /// Input a resource into Datalith using a buffer.
+    /// Input a resource into Datalith using a buffer. If `expected_hash` is set, the SHA-256 hash of the data has to match it.
     #[inline]
     pub async fn put_resource_by_buffer(
         &self,
         buffer: impl AsRef<[u8]>,
         file_name: Option<impl Into<String>>,
         file_type: Option<(Mime, FileTypeLevel)>,
+        expected_hash: Option<&[u8; 32]>,
     ) -> Result<DatalithResource, DatalithWriteError> {
         let file_name = file_name.map(|e| e.into());
 
-        let file = self.put_file_by_buffer(buffer, file_name.clone(), file_type.clone()).await?;
+        let file = self
+            .put_file_by_buffer(buffer, file_name.clone(), file_type.clone(), expected_hash)
+            .await?;
 
         self.put_resource(file, file_name, file_type, false).await
     }
 
-    /// Temporarily input a resource into Datalith using a buffer.
+    /// Temporarily input a resource into Datalith using a buffer. If `expected_hash` is set, the SHA-256 hash of the data has to match it.
     ///
     /// The term `temporarily` means the file can be retrieved using the `get_resource_by_id` function only once. After that, it cannot be retrieved again.
     #[inline]
@@ -58,30 +205,36 @@ impl Datalith {
         buffer: impl AsRef<[u8]>,
         file_name: Option<impl Into<String>>,
         file_type: Option<(Mime, FileTypeLevel)>,
+        expected_hash: Option<&[u8; 32]>,
     ) -> Result<DatalithResource, DatalithWriteError> {
         let file_name = file_name.map(|e| e.into());
 
-        let file = self.put_file_by_buffer(buffer, file_name.clone(), file_type.clone()).await?;
+        let file = self
+            .put_file_by_buffer(buffer, file_name.clone(), file_type.clone(), expected_hash)
+            .await?;
 
         self.put_resource(file, file_name, file_type, true).await
     }
 
-    /// Input a resource into Datalith using a file path.
+    /// Input a resource into Datalith using a file path. If `expected_hash` is set, the SHA-256 hash of the data has to match it.
     #[inline]
     pub async fn put_resource_by_path(
         &self,
         file_path: impl AsRef<Path>,
         file_name: Option<impl Into<String>>,
         file_type: Option<(Mime, FileTypeLevel)>,
+        expected_hash: Option<&[u8; 32]>,
     ) -> Result<DatalithResource, DatalithWriteError> {
         let file_name = file_name.map(|e| e.into());
 
-        let file = self.put_file_by_path(file_path, file_name.clone(), file_type.clone()).await?;
+        let file = self
+            .put_file_by_path(file_path, file_name.clone(), file_type.clone(), expected_hash)
+            .await?;
 
         self.put_resource(file, file_name, file_type, false).await
     }
 
-    /// Temporarily input a resource into Datalith using a file path.
+    /// Temporarily input a resource into Datalith using a file path. If `expected_hash` is set, the SHA-256 hash of the data has to match it.
     ///
     /// The term `temporarily` means the file can be retrieved using the `get_resource_by_id` function only once. After that, it cannot be retrieved again.
     #[inline]
@@ -90,15 +243,18 @@ impl Datalith {
         file_path: impl AsRef<Path>,
         file_name: Option<impl Into<String>>,
         file_type: Option<(Mime, FileTypeLevel)>,
+        expected_hash: Option<&[u8; 32]>,
     ) -> Result<DatalithResource, DatalithWriteError> {
         let file_name = file_name.map(|e| e.into());
 
-        let file = self.put_file_by_path(file_path, file_name.clone(), file_type.clone()).await?;
+        let file = self
+            .put_file_by_path(file_path, file_name.clone(), file_type.clone(), expected_hash)
+            .await?;
 
         self.put_resource(file, file_name, file_type, true).await
     }
 
-    /// Input a resource into Datalith using a reader.
+    /// Input a resource into Datalith using a reader. If `expected_hash` is set, the SHA-256 hash of the data has to match it.
     #[inline]
     pub async fn put_resource_by_reader(
         &self,
@@ -106,6 +262,7 @@ impl Datalith {
         file_name: Option<impl Into<String>>,
         file_type: Option<(Mime, FileTypeLevel)>,
         expected_reader_length: Option<u64>,
+        expected_hash: Option<&[u8; 32]>,
     ) -> Result<DatalithResource, DatalithWriteError> {
         let file_name = file_name.map(|e| e.into());
 
@@ -115,13 +272,14 @@ impl Datalith {
                 file_name.clone(),
                 file_type.clone(),
                 expected_reader_length,
+                expected_hash,
             )
             .await?;
 
         self.put_resource(file, file_name, file_type, false).await
     }
 
-    /// Temporarily input a resource into Datalith using a reader.
+    /// Temporarily input a resource into Datalith using a reader. If `expected_hash` is set, the SHA-256 hash of the data has to match it.
     ///
     /// The term `temporarily` means the file can be retrieved using the `get_resource_by_id` function only once. After that, it cannot be retrieved again.
     #[inline]
@@ -131,6 +289,7 @@ impl Datalith {
         file_name: Option<impl Into<String>>,
         file_type: Option<(Mime, FileTypeLevel)>,
         expected_reader_length: Option<u64>,
+        expected_hash: Option<&[u8; 32]>,
     ) -> Result<DatalithResource, DatalithWriteError> {
         let file_name = file_name.map(|e| e.into());
 
@@ -140,13 +299,79 @@ impl Datalith {
                 file_name.clone(),
                 file_type.clone(),
                 expected_reader_length,
+                expected_hash,
             )
             .await?;
 
         self.put_resource(file, file_name, file_type, true).await
     }
 
-    async fn put_resource(
+    /// Input a resource into Datalith using the SHA-256 hash of its data, without sending the data again. The data has to be stored for a resource in the namespace already, as reported by the `check_hash_exist` function. Return `None` if it is not.
+    #[inline]
+    pub async fn put_resource_by_hash(
+        &self,
+        hash: &[u8; 32],
+        file_name: Option<impl Into<String>>,
+        file_type: Option<(Mime, FileTypeLevel)>,
+    ) -> Result<Option<DatalithResource>, DatalithWriteError> {
+        self.put_resource_by_hash_inner(hash, file_name.map(|e| e.into()), file_type, false).await
+    }
+
+    /// Temporarily input a resource into Datalith using the SHA-256 hash of its data, without sending the data again. The data has to be stored for a resource in the namespace already, as reported by the `check_hash_exist` function. Return `None` if it is not.
+    ///
+    /// The term `temporarily` means the file can be retrieved using the `get_resource_by_id` function only once. After that, it cannot be retrieved again.
+    #[inline]
+    pub async fn put_resource_by_hash_temporarily(
+        &self,
+        hash: &[u8; 32],
+        file_name: Option<impl Into<String>>,
+        file_type: Option<(Mime, FileTypeLevel)>,
+    ) -> Result<Option<DatalithResource>, DatalithWriteError> {
+        self.put_resource_by_hash_inner(hash, file_name.map(|e| e.into()), file_type, true).await
+    }
+
+    async fn put_resource_by_hash_inner(
+        &self,
+        hash: &[u8; 32],
+        file_name: Option<String>,
+        file_type: Option<(Mime, FileTypeLevel)>,
+        temporary: bool,
+    ) -> Result<Option<DatalithResource>, DatalithWriteError> {
+        let file = {
+            let _put_guard = PutGuard::new(self.clone(), *hash).await;
+
+            if self.find_resource_id_by_hash(hash, true).await?.is_none() {
+                return Ok(None);
+            }
+
+            let Some(file) = self.get_file_by_hash(hash).await? else {
+                return Ok(None);
+            };
+
+            #[rustfmt::skip]
+            let result = sqlx::query(
+                "
+                    UPDATE
+                        `files`
+                    SET
+                        `count` = `count` + 1
+                    WHERE
+                        `id` = ?
+                ",
+            )
+            .bind(file.id())
+            .execute(&self.0.db)
+            .await?;
+
+            debug_assert!(result.rows_affected() > 0);
+
+            file
+        };
+
+        self.put_resource(file, file_name, file_type, temporary).await.map(Some)
+    }
+
+    pub(crate) async fn put_resource(
         &self,
         file: DatalithFile,
         file_name: Option<String>,
@@ -189,13 +414,22 @@ impl Datalith {
 
         let id = Uuid::new_v4();
 
-        // insert resources
+        // insert resources and count the usage
         {
+            let mut tx = match self.0.db.begin().await {
+                Ok(tx) => tx,
+                Err(error) => {
+                    recover_file!();
+
+                    return Err(error.into());
+                },
+            };
+
             #[rustfmt::skip]
             let result = sqlx::query(
                 "
-                    INSERT INTO `resources` (`id`, `created_at`, `file_name`, `file_type`, `file_id`, `expired_at`)
-                        VALUES (?, ?, ?, ?, ?, ?)
+                    INSERT INTO `resources` (`id`, `created_at`, `file_name`, `file_type`, `file_id`, `expired_at`, `namespace`)
+                        VALUES (?, ?, ?, ?, ?, ?, ?)
                 ",
             )
             .bind(id)
@@ -204,12 +438,15 @@ impl Datalith {
             .bind(file_type.essence_str())
             .bind(file.id())
             .bind(expired_at)
-            .execute(&self.0.db)
+            .bind(self.get_namespace())
+            .execute(&mut *tx)
             .await;
 
             let result = match result {
                 Ok(result) => result,
                 Err(error) => {
+                    drop(tx);
+
                     recover_file!();
 
                     return Err(error.into());
@@ -217,9 +454,40 @@ impl Datalith {
             };
 
             debug_assert!(result.rows_affected() > 0);
+
+            if let Err(error) = index_search_document(&mut tx, id).await {
+                drop(tx);
+
+                recover_file!();
+
+                return Err(error.into());
+            }
+
+            if let Err(error) = self.add_usage_within_quota(&mut tx, file.file_size()).await {
+                drop(tx);
+
+                recover_file!();
+
+                return Err(error);
+            }
+
+            if let Err(error) = tx.commit().await {
+                recover_file!();
+
+                return Err(error.into());
+            }
         }
 
-        Ok(DatalithResource::new(id, created_at, file_type, file_name, file, expired_at.is_some()))
+        Ok(DatalithResource::new(
+            id,
+            created_at,
+            file_type,
+            file_name,
+            file,
+            expired_at.is_some(),
+            DatalithMetadata::default(),
+            0,
+        ))
     }
 }
 
@@ -241,10 +509,12 @@ impl Datalith {
                     `resources`
                 WHERE
                     `id` = ?
+                        AND `namespace` = ?
                         AND ( `expired_at` IS NULL OR `expired_at` > ? )
             ",
         )
         .bind(id.into())
+        .bind(self.get_namespace())
         .bind(current_timestamp)
         .fetch_optional(&self.0.db)
         .await?;
@@ -252,6 +522,60 @@ impl Datalith {
         Ok(row.is_some())
     }
 
+    /// Check whether the data with the SHA-256 hash is stored for a resource in the namespace and can be read. If so, it does not have to be sent again, and the `put_resource_by_hash` function can be used instead.
+    #[inline]
+    pub async fn check_hash_exist(&self, hash: &[u8; 32]) -> Result<bool, DatalithReadError> {
+        Ok(self.find_resource_id_by_hash(hash, true).await?.is_some())
+    }
+
+    /// Retrieve the metadata of the newest permanent resource in the namespace whose data has the SHA-256 hash. Temporary resources are not matched, so that they are not consumed.
+    pub async fn get_resource_by_hash(
+        &self,
+        hash: &[u8; 32],
+    ) -> Result<Option<DatalithResource>, DatalithReadError> {
+        match self.find_resource_id_by_hash(hash, false).await? {
+            Some(id) => self.peek_resource_by_id(id).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Find the newest unexpired resource in the namespace whose data has the hash and is not quarantined.
+    async fn find_resource_id_by_hash(
+        &self,
+        hash: &[u8; 32],
+        include_temporary: bool,
+    ) -> Result<Option<Uuid>, sqlx::Error> {
+        let current_timestamp = get_current_timestamp();
+
+        #[rustfmt::skip]
+        let row: Option<(Uuid,)> = sqlx::query_as(
+            "
+                SELECT
+                    `resources`.`id`
+                FROM
+                    `resources`
+                    INNER JOIN `files` ON `files`.`id` = `resources`.`file_id`
+                WHERE
+                    `files`.`hash` = ?
+                        AND `files`.`quarantined_at` IS NULL
+                        AND `resources`.`namespace` = ?
+                        AND ( `resources`.`expired_at` IS NULL OR ( ? AND `resources`.`expired_at` > ? ) )
+                ORDER BY
+                    `resources`.`created_at` DESC
+                LIMIT
+                    1
+            ",
+        )
+        .bind(hash.to_vec())
+        .bind(self.get_namespace())
+        .bind(include_temporary)
+        .bind(current_timestamp)
+        .fetch_optional(&self.0.db)
+        .await?;
+
+        Ok(row.map(|(id,)| id))
+    }
+
     /// Retrieve the resource metadata using an ID.
     pub async fn get_resource_by_id(
         &self,
@@ -270,11 +594,13 @@ impl Datalith {
                         `expired_at` = ?
                     WHERE
                         `id` = ?
+                            AND `namespace` = ?
                             AND `expired_at` > ?
                 ",
             )
             .bind(current_timestamp)
             .bind(id)
+            .bind(self.get_namespace())
             .bind(current_timestamp)
             .execute(&self.0.db)
             .await?;
@@ -283,30 +609,34 @@ impl Datalith {
         };
 
         #[rustfmt::skip]
-        let row: Option<(i64, String, String, Uuid)> = sqlx::query_as(
+        let row: Option<(i64, String, String, Uuid, i64)> = sqlx::query_as(
             "
                 SELECT
                     `created_at`,
                     `file_type`,
                     `file_name`,
-                    `file_id`
+                    `file_id`,
+                    `metadata_version`
                 FROM
                     `resources`
                 WHERE
                     `id` = ?
+                        AND `namespace` = ?
                         AND ( `expired_at` IS NULL OR `expired_at` = ? )
             ",
         )
         .bind(id)
+        .bind(self.get_namespace())
         .bind(current_timestamp)
         .fetch_optional(&self.0.db)
         .await?;
 
-        if let Some((created_at, file_type, file_name, file_id)) = row {
+        if let Some((created_at, file_type, file_name, file_id, metadata_version)) = row {
             let file = self.get_file_by_id(file_id).await?;
 
             if let Some(file) = file {
                 let created_at = DateTime::from_timestamp_millis(created_at).unwrap();
+                let metadata = read_metadata(&mut *self.0.db.acquire().await?, id).await?;
 
                 return Ok(Some(DatalithResource::new(
                     id,
@@ -315,6 +645,66 @@ impl Datalith {
                     file_name,
                     file,
                     is_temporary,
+                    metadata,
+                    metadata_version as u64,
+                )));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Retrieve the resource metadata using an ID. Unlike `get_resource_by_id`, a temporary resource can still be retrieved again after calling this function.
+    pub async fn peek_resource_by_id(
+        &self,
+        id: impl Into<Uuid>,
+    ) -> Result<Option<DatalithResource>, DatalithReadError> {
+        let current_timestamp = get_current_timestamp();
+
+        let id = id.into();
+
+        #[allow(clippy::type_complexity)]
+        #[rustfmt::skip]
+        let row: Option<(i64, String, String, Uuid, Option<i64>, i64)> = sqlx::query_as(
+            "
+                SELECT
+                    `created_at`,
+                    `file_type`,
+                    `file_name`,
+                    `file_id`,
+                    `expired_at`,
+                    `metadata_version`
+                FROM
+                    `resources`
+                WHERE
+                    `id` = ?
+                        AND `namespace` = ?
+                        AND ( `expired_at` IS NULL OR `expired_at` > ? )
+            ",
+        )
+        .bind(id)
+        .bind(self.get_namespace())
+        .bind(current_timestamp)
+        .fetch_optional(&self.0.db)
+        .await?;
+
+        if let Some((created_at, file_type, file_name, file_id, expired_at, metadata_version)) = row
+        {
+            let file = self.get_file_by_id(file_id).await?;
+
+            if let Some(file) = file {
+                let created_at = DateTime::from_timestamp_millis(created_at).unwrap();
+                let metadata = read_metadata(&mut *self.0.db.acquire().await?, id).await?;
+
+                return Ok(Some(DatalithResource::new(
+                    id,
+                    created_at,
+                    Mime::from_str(&file_type).unwrap(),
+                    file_name,
+                    file,
+                    expired_at.is_some(),
+                    metadata,
+                    metadata_version as u64,
                 )));
             }
         }
@@ -322,9 +712,10 @@ impl Datalith {
         Ok(None)
     }
 
-    /// List resource IDs.
+    /// List resource IDs in the namespace which match the filter.
     pub async fn list_resource_ids(
         &self,
+        filter: &DatalithResourceFilter,
         mut pagination_options: PaginationOptions<DatalithResourceOrderBy>,
     ) -> Result<(Vec<Uuid>, Pagination), DatalithReadError> {
         loop {
@@ -341,21 +732,29 @@ impl Datalith {
             );
             pagination_options.to_sqlite_limit_offset(&mut sql_limit_offset);
 
+            let current_timestamp = get_current_timestamp();
+
             let mut tx = self.0.db.begin().await?;
 
             let total_items = {
                 let row: (u32,) = {
                     #[rustfmt::skip]
-                    let query = sqlx::query_as(
+                    let mut builder = QueryBuilder::new(
                         "
                             SELECT
                                 COUNT(*)
                             FROM
                                 `resources`
-                        "
+                            WHERE"
                     );
 
-                    query.fetch_one(&mut *tx).await?
+                    filter.push_list_conditions(
+                        &mut builder,
+                        self.get_namespace(),
+                        current_timestamp,
+                    );
+
+                    builder.build_query_as().fetch_one(&mut *tx).await?
                 };
 
                 row.0
@@ -363,25 +762,20 @@ impl Datalith {
 
             let rows: Vec<(Uuid,)> = {
                 #[rustfmt::skip]
-                let sql = format!(
+                let mut builder = QueryBuilder::new(format!(
                     "
                         SELECT
-                            `id`
+                            `resources`.`id`
                         FROM
                             `resources`
                         {sql_join}
-                        WHERE
-                            (`expired_at` IS NULL OR `expired_at` > ?)
-                        {sql_order_by}
-                        {sql_limit_offset}
-                    "
-                );
+                        WHERE"
+                ));
 
-                let current_timestamp = get_current_timestamp();
+                filter.push_list_conditions(&mut builder, self.get_namespace(), current_timestamp);
+                builder.push(format_args!(" {sql_order_by} {sql_limit_offset}"));
 
-                let query = sqlx::query_as(&sql).bind(current_timestamp);
-
-                query.fetch_all(&mut *tx).await?
+                builder.build_query_as().fetch_all(&mut *tx).await?
             };
 
             let total_items = total_items as usize;
@@ -408,6 +802,57 @@ impl Datalith {
             return Ok((ids, pagination));
         }
     }
+
+    /// List resource IDs in the namespace which match the filter by seeking from a cursor. Unlike `list_resource_ids`, pages do not shift when resources are added or removed during the iteration.
+    pub async fn list_resource_ids_by_cursor(
+        &self,
+        filter: &DatalithResourceFilter,
+        options: &DatalithCursorOptions<DatalithResourceSortKey>,
+    ) -> Result<DatalithCursorPage<DatalithResourceSortKey>, DatalithReadError> {
+        let sort_column = options.sort().0.sort_column();
+        let current_timestamp = get_current_timestamp();
+
+        let total_items = if options.count_total {
+            #[rustfmt::skip]
+            let mut builder = QueryBuilder::new(
+                "
+                    SELECT
+                        COUNT(*)
+                    FROM
+                        `resources`
+                    WHERE"
+            );
+
+            filter.push_list_conditions(&mut builder, self.get_namespace(), current_timestamp);
+
+            let row: (u32,) = builder.build_query_as().fetch_one(&self.0.db).await?;
+
+            Some(row.0 as usize)
+        } else {
+            None
+        };
+
+        #[rustfmt::skip]
+        let mut builder = QueryBuilder::new(format!(
+            "
+                SELECT
+                    {},
+                    `resources`.`id`
+                FROM
+                    `resources`
+                JOIN `files` ON `files`.`id` = `resources`.`file_id`
+                WHERE",
+            sort_column.column
+        ));
+
+        filter.push_list_conditions(&mut builder, self.get_namespace(), current_timestamp);
+        options.push_seek_conditions(&mut builder, &sort_column, "`resources`.`id`");
+        options.push_order_by_limit(&mut builder, &sort_column, "`resources`.`id`");
+
+        let rows = builder.build().fetch_all(&self.0.db).await?;
+
+        Ok(options.to_page(rows, &sort_column, total_items)?)
+    }
 }
 
 // Delete
@@ -421,25 +866,31 @@ impl Datalith {
         let id = id.into();
 
         #[rustfmt::skip]
-        let row: Option<(Uuid,)> = sqlx::query_as(
+        let row: Option<(Uuid, Option<i64>)> = sqlx::query_as(
             "
                 SELECT
-                    `file_id`
+                    `resources`.`file_id`,
+                    `files`.`file_size`
                 FROM
                     `resources`
+                        LEFT JOIN `files` ON `files`.`id` = `resources`.`file_id`
                 WHERE
-                    `id` = ?
+                    `resources`.`id` = ?
+                        AND `resources`.`namespace` = ?
             ",
         )
         .bind(id)
+        .bind(self.get_namespace())
         .fetch_optional(&self.0.db)
         .await?;
 
-        if let Some((file_id,)) = row {
+        if let Some((file_id, file_size)) = row {
             let guard = DeleteGuard::new(self.clone(), file_id).await;
 
             self.wait_for_opening_files(&guard).await?;
 
+            let mut tx = self.0.db.begin().await?;
+
             #[rustfmt::skip]
             let result = sqlx::query(
                 "
@@ -447,16 +898,25 @@ impl Datalith {
                         `resources`
                     WHERE
                         `id` = ?
+                            AND `namespace` = ?
                 ",
             )
             .bind(id)
-            .execute(&self.0.db)
+            .bind(self.get_namespace())
+            .execute(&mut *tx)
             .await?;
 
             if result.rows_affected() == 0 {
                 return Ok(false);
             }
 
+            delete_metadata(&mut tx, id).await?;
+            delete_search_document(&mut tx, id).await?;
+
+            add_usage(&mut tx, self.get_namespace(), -file_size.unwrap_or(0), -1).await?;
+
+            tx.commit().await?;
+
             // delete the related file
 
             self.delete_file_by_id_inner(file_id, guard).await?;
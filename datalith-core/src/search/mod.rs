@@ -0,0 +1,271 @@
+use educe::Educe;
+use rdb_pagination::{
+    OrderByOptions, OrderMethod, Pagination, PaginationOptions, SqlJoin, SqlOrderByComponent,
+    prelude::*,
+};
+use sqlx::SqliteConnection;
+use uuid::Uuid;
+
+use crate::{Datalith, DatalithReadError, functions::get_current_timestamp};
+
+/// A struct that defines the ordering options for searching. The best matches come first by default.
+#[derive(Debug, Clone, Educe, OrderByOptions)]
+#[educe(Default)]
+#[orderByOptions(name = search_index)]
+pub struct DatalithSearchOrderBy {
+    #[educe(Default = 101)]
+    #[orderByOptions((search_index, rank))]
+    pub rank: OrderMethod,
+    #[educe(Default = 102)]
+    #[orderByOptions((search_index, rowid), unique)]
+    pub id:   OrderMethod,
+}
+
+/// The kind of an item found by searching.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DatalithSearchHitKind {
+    Resource,
+    Image,
+}
+
+/// An item found by searching.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatalithSearchHit {
+    /// The ID of the resource or the image.
+    pub id:      Uuid,
+    pub kind:    DatalithSearchHitKind,
+    /// A fragment of the best matching text, in which the matched terms are enclosed in `[` and `]`.
+    pub snippet: String,
+}
+
+/// Convert words into an FTS5 query which matches the documents having a term starting with every word.
+fn to_match_query(query: &str) -> Option<String> {
+    let mut match_query = String::with_capacity(query.len() + 8);
+
+    for word in query.split_whitespace() {
+        // a word without any letter or digit has no term
+        if !word.chars().any(char::is_alphanumeric) {
+            continue;
+        }
+
+        if !match_query.is_empty() {
+            match_query.push(' ');
+        }
+
+        match_query.push('"');
+        match_query.push_str(word.replace('"', "\"\"").as_str());
+        match_query.push_str("\"*");
+    }
+
+    if match_query.is_empty() { None } else { Some(match_query) }
+}
+
+/// Index the name, metadata and tags of a resource or an image for searching. It should be called whenever any of them changes.
+pub(crate) async fn index_search_document(
+    conn: &mut SqliteConnection,
+    owner_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    delete_search_document(conn, owner_id).await?;
+
+    #[rustfmt::skip]
+    sqlx::query(
+        "
+            INSERT INTO `search_documents` (`owner_id`)
+                SELECT `id` FROM `resources` WHERE `id` = ?
+                UNION ALL
+                SELECT `id` FROM `images` WHERE `id` = ?
+        ",
+    )
+    .bind(owner_id)
+    .bind(owner_id)
+    .execute(&mut *conn)
+    .await?;
+
+    #[rustfmt::skip]
+    sqlx::query(
+        "
+            INSERT INTO `search_index` (`rowid`, `name`, `metadata`, `tags`)
+                SELECT
+                    `search_documents`.`id`,
+                    COALESCE(`resources`.`file_name`, `images`.`image_stem`),
+                    ( SELECT group_concat(`value`, char(10)) FROM `metadata` WHERE `metadata`.`owner_id` = `search_documents`.`owner_id` ),
+                    ( SELECT group_concat(`tag`, char(10)) FROM `tags` WHERE `tags`.`owner_id` = `search_documents`.`owner_id` )
+                FROM
+                    `search_documents`
+                        LEFT JOIN `resources` ON `resources`.`id` = `search_documents`.`owner_id`
+                        LEFT JOIN `images` ON `images`.`id` = `search_documents`.`owner_id`
+                WHERE
+                    `search_documents`.`owner_id` = ?
+        ",
+    )
+    .bind(owner_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Remove a resource or an image from the search index.
+pub(crate) async fn delete_search_document(
+    conn: &mut SqliteConnection,
+    owner_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    #[rustfmt::skip]
+    sqlx::query(
+        "
+            DELETE FROM
+                `search_index`
+            WHERE
+                `rowid` IN ( SELECT `id` FROM `search_documents` WHERE `owner_id` = ? )
+        ",
+    )
+    .bind(owner_id)
+    .execute(&mut *conn)
+    .await?;
+
+    #[rustfmt::skip]
+    sqlx::query(
+        "
+            DELETE FROM
+                `search_documents`
+            WHERE
+                `owner_id` = ?
+        ",
+    )
+    .bind(owner_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+// Search
+impl Datalith {
+    /// Search the resources and the images in the namespace by their file names (image stems), metadata values and tags. Every word in `query` has to match the start of a term, case-insensitively and ignoring diacritics.
+    pub async fn search_resources(
+        &self,
+        query: &str,
+        mut pagination_options: PaginationOptions<DatalithSearchOrderBy>,
+    ) -> Result<(Vec<DatalithSearchHit>, Pagination), DatalithReadError> {
+        let Some(match_query) = to_match_query(query) else {
+            let pagination = Pagination::new()
+                .items_per_page(pagination_options.items_per_page)
+                .total_items(0)
+                .page(pagination_options.page);
+
+            return Ok((Vec::new(), pagination));
+        };
+
+        loop {
+            let (joins, order_by_components) = pagination_options.order_by.to_sql();
+
+            let mut sql_join = String::new();
+            let mut sql_order_by = String::new();
+            let mut sql_limit_offset = String::new();
+
+            SqlJoin::format_sqlite_join_clauses(&joins, &mut sql_join);
+            SqlOrderByComponent::format_sqlite_order_by_components(
+                &order_by_components,
+                &mut sql_order_by,
+            );
+            pagination_options.to_sqlite_limit_offset(&mut sql_limit_offset);
+
+            let current_timestamp = get_current_timestamp();
+
+            let mut tx = self.0.db.begin().await?;
+
+            let total_items = {
+                #[rustfmt::skip]
+                let row: (u32,) = sqlx::query_as(
+                    "
+                        SELECT
+                            COUNT(*)
+                        FROM
+                            `search_index`
+                                JOIN `search_documents` ON `search_documents`.`id` = `search_index`.`rowid`
+                                LEFT JOIN `resources` ON `resources`.`id` = `search_documents`.`owner_id`
+                                LEFT JOIN `images` ON `images`.`id` = `search_documents`.`owner_id`
+                        WHERE
+                            `search_index` MATCH ?
+                                AND (
+                                    ( `resources`.`namespace` = ? AND ( `resources`.`expired_at` IS NULL OR `resources`.`expired_at` > ? ) )
+                                        OR `images`.`namespace` = ?
+                                )
+                    ",
+                )
+                .bind(match_query.as_str())
+                .bind(self.get_namespace())
+                .bind(current_timestamp)
+                .bind(self.get_namespace())
+                .fetch_one(&mut *tx)
+                .await?;
+
+                row.0
+            };
+
+            #[rustfmt::skip]
+            let rows: Vec<(Uuid, bool, String)> = sqlx::query_as(&format!(
+                "
+                    SELECT
+                        `search_documents`.`owner_id`,
+                        `resources`.`id` IS NOT NULL,
+                        snippet(`search_index`, -1, '[', ']', '…', 16)
+                    FROM
+                        `search_index`
+                            JOIN `search_documents` ON `search_documents`.`id` = `search_index`.`rowid`
+                            LEFT JOIN `resources` ON `resources`.`id` = `search_documents`.`owner_id`
+                            LEFT JOIN `images` ON `images`.`id` = `search_documents`.`owner_id`
+                            {sql_join}
+                    WHERE
+                        `search_index` MATCH ?
+                            AND (
+                                ( `resources`.`namespace` = ? AND ( `resources`.`expired_at` IS NULL OR `resources`.`expired_at` > ? ) )
+                                    OR `images`.`namespace` = ?
+                            )
+                    {sql_order_by}
+                    {sql_limit_offset}
+                "
+            ))
+            .bind(match_query.as_str())
+            .bind(self.get_namespace())
+            .bind(current_timestamp)
+            .bind(self.get_namespace())
+            .fetch_all(&mut *tx)
+            .await?;
+
+            let total_items = total_items as usize;
+
+            drop(tx);
+
+            let pagination = Pagination::new()
+                .items_per_page(pagination_options.items_per_page)
+                .total_items(total_items)
+                .page(pagination_options.page);
+
+            if rows.is_empty() {
+                if total_items > 0 && pagination_options.page > 1 {
+                    pagination_options.page = pagination.get_total_pages();
+
+                    continue;
+                } else {
+                    return Ok((Vec::new(), pagination));
+                }
+            }
+
+            let hits = rows
+                .into_iter()
+                .map(|(id, is_resource, snippet)| DatalithSearchHit {
+                    id,
+                    kind: if is_resource {
+                        DatalithSearchHitKind::Resource
+                    } else {
+                        DatalithSearchHitKind::Image
+                    },
+                    snippet,
+                })
+                .collect();
+
+            return Ok((hits, pagination));
+        }
+    }
+}
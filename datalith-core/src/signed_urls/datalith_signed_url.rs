@@ -0,0 +1,73 @@
+use chrono::{DateTime, Local};
+
+#[cfg(feature = "image-convert")]
+/// The resolution of an image which a signed URL points to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DatalithImageResolution {
+    /// The original file.
+    Original,
+    /// The thumbnail with the given multiplier.
+    Multiplier(u8),
+}
+
+#[cfg(feature = "image-convert")]
+impl Default for DatalithImageResolution {
+    #[inline]
+    fn default() -> Self {
+        Self::Multiplier(1)
+    }
+}
+
+#[cfg(feature = "image-convert")]
+impl DatalithImageResolution {
+    #[inline]
+    pub(crate) fn to_query_value(self) -> String {
+        match self {
+            Self::Original => String::from("original"),
+            Self::Multiplier(multiplier) => format!("{multiplier}x"),
+        }
+    }
+}
+
+/// A struct that represents the signed query parameters of a fetch URL.
+#[derive(Debug, Clone)]
+pub struct DatalithSignedUrl {
+    expired_at:   DateTime<Local>,
+    signature:    String,
+    query_string: String,
+}
+
+impl DatalithSignedUrl {
+    #[inline]
+    pub(crate) const fn new(
+        expired_at: DateTime<Local>,
+        signature: String,
+        query_string: String,
+    ) -> Self {
+        Self {
+            expired_at,
+            signature,
+            query_string,
+        }
+    }
+}
+
+impl DatalithSignedUrl {
+    /// Retrieve the time when the URL expires.
+    #[inline]
+    pub const fn expired_at(&self) -> DateTime<Local> {
+        self.expired_at
+    }
+
+    /// Retrieve the signature (the `sig` parameter).
+    #[inline]
+    pub const fn signature(&self) -> &String {
+        &self.signature
+    }
+
+    /// Retrieve the query string (without `?`) which should be appended to the fetch URL.
+    #[inline]
+    pub const fn query_string(&self) -> &String {
+        &self.query_string
+    }
+}
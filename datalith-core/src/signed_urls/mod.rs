@@ -0,0 +1,185 @@
+mod datalith_signed_url;
+
+use std::time::Duration;
+
+use chrono::prelude::*;
+pub use datalith_signed_url::*;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use uuid::Uuid;
+
+#[cfg(feature = "image-convert")]
+use crate::DatalithImageFormat;
+use crate::{
+    Datalith,
+    functions::{decode_hex, encode_hex},
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+// Sign
+impl Datalith {
+    /// Create the signed query parameters for fetching a resource. The `download` disposition is covered by the signature.
+    pub fn sign_resource_url(
+        &self,
+        id: impl Into<Uuid>,
+        lifespan: Duration,
+        download: bool,
+    ) -> DatalithSignedUrl {
+        let id = id.into();
+
+        let expires = Self::get_url_expires(lifespan);
+
+        let signature =
+            encode_hex(self.sign_url_message(&Self::resource_url_message(id, expires, download)));
+
+        let mut query_string = format!("expires={expires}&sig={signature}");
+
+        if download {
+            query_string.push_str("&download=1");
+        }
+
+        DatalithSignedUrl::new(
+            DateTime::from_timestamp(expires, 0).unwrap().with_timezone(&Local),
+            signature,
+            query_string,
+        )
+    }
+
+    /// Create the signed query parameters for fetching an image in a specific resolution. The `resolution`, `format`, `fallback` and `download` parameters are covered by the signature.
+    #[cfg(feature = "image-convert")]
+    pub fn sign_image_url(
+        &self,
+        id: impl Into<Uuid>,
+        resolution: DatalithImageResolution,
+        format: Option<DatalithImageFormat>,
+        fallback: bool,
+        lifespan: Duration,
+        download: bool,
+    ) -> DatalithSignedUrl {
+        let id = id.into();
+
+        let expires = Self::get_url_expires(lifespan);
+
+        let signature = encode_hex(self.sign_url_message(&Self::image_url_message(
+            id, resolution, format, fallback, expires, download,
+        )));
+
+        let mut query_string =
+            format!("resolution={}&expires={expires}&sig={signature}", resolution.to_query_value());
+
+        if let Some(format) = format {
+            query_string.push_str("&format=");
+            query_string.push_str(format.extension());
+        }
+
+        if fallback {
+            query_string.push_str("&fallback=1");
+        }
+
+        if download {
+            query_string.push_str("&download=1");
+        }
+
+        DatalithSignedUrl::new(
+            DateTime::from_timestamp(expires, 0).unwrap().with_timezone(&Local),
+            signature,
+            query_string,
+        )
+    }
+}
+
+// Verify
+impl Datalith {
+    /// Check the `expires` (UNIX timestamp in seconds) and `sig` parameters of a URL for fetching a resource.
+    pub fn verify_resource_url(
+        &self,
+        id: impl Into<Uuid>,
+        download: bool,
+        expires: i64,
+        signature: impl AsRef<str>,
+    ) -> bool {
+        self.verify_url_message(
+            &Self::resource_url_message(id.into(), expires, download),
+            expires,
+            signature.as_ref(),
+        )
+    }
+
+    /// Check the `expires` (UNIX timestamp in seconds) and `sig` parameters of a URL for fetching an image.
+    #[cfg(feature = "image-convert")]
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify_image_url(
+        &self,
+        id: impl Into<Uuid>,
+        resolution: DatalithImageResolution,
+        format: Option<DatalithImageFormat>,
+        fallback: bool,
+        download: bool,
+        expires: i64,
+        signature: impl AsRef<str>,
+    ) -> bool {
+        self.verify_url_message(
+            &Self::image_url_message(id.into(), resolution, format, fallback, expires, download),
+            expires,
+            signature.as_ref(),
+        )
+    }
+}
+
+impl Datalith {
+    #[inline]
+    fn get_url_expires(lifespan: Duration) -> i64 {
+        Utc::now().timestamp().saturating_add(lifespan.as_secs().min(i64::MAX as u64) as i64)
+    }
+
+    #[inline]
+    fn resource_url_message(id: Uuid, expires: i64, download: bool) -> String {
+        format!("resource\n{id}\n{expires}\n{}", download as u8)
+    }
+
+    #[cfg(feature = "image-convert")]
+    #[inline]
+    fn image_url_message(
+        id: Uuid,
+        resolution: DatalithImageResolution,
+        format: Option<DatalithImageFormat>,
+        fallback: bool,
+        expires: i64,
+        download: bool,
+    ) -> String {
+        format!(
+            "image\n{id}\n{}\n{}\n{}\n{expires}\n{}",
+            resolution.to_query_value(),
+            format.map(|e| e.extension()).unwrap_or_default(),
+            fallback as u8,
+            download as u8
+        )
+    }
+
+    #[inline]
+    fn create_url_mac(&self, message: &str) -> HmacSha256 {
+        // HMAC can take a key of any size
+        let mut mac = HmacSha256::new_from_slice(&self.0._url_signing_key).unwrap();
+
+        mac.update(message.as_bytes());
+
+        mac
+    }
+
+    #[inline]
+    fn sign_url_message(&self, message: &str) -> [u8; 32] {
+        self.create_url_mac(message).finalize().into_bytes().into()
+    }
+
+    fn verify_url_message(&self, message: &str, expires: i64, signature: &str) -> bool {
+        if expires <= Utc::now().timestamp() {
+            return false;
+        }
+
+        match decode_hex(signature) {
+            Some(signature) => self.create_url_mac(message).verify_slice(&signature).is_ok(),
+            None => false,
+        }
+    }
+}
@@ -1,25 +1,33 @@
 -- File Table
 CREATE TABLE `files` (
     -- UUID (128-bit)
-    `id`          BLOB    NOT NULL PRIMARY KEY,
+    `id`             BLOB    NOT NULL PRIMARY KEY,
     -- hashed by SHA-256
-    `hash`        BLOB    NOT NULL UNIQUE,
+    `hash`           BLOB    NOT NULL UNIQUE,
     -- UNIX timestamp (in milliseconds)
-    `created_at`  INTEGER NOT NULL,
+    `created_at`     INTEGER NOT NULL,
     -- in bytes
-    `file_size`   INTEGER NOT NULL,
+    `file_size`      INTEGER NOT NULL,
     -- MIME type
-    `file_type`   TEXT    NOT NULL,
+    `file_type`      TEXT    NOT NULL,
     -- the file name when it was first created
-    `file_name`   TEXT    NOT NULL,
+    `file_name`      TEXT    NOT NULL,
     -- the number of times this file was created
-    `count`       INTEGER NOT NULL DEFAULT 1,
+    `count`          INTEGER NOT NULL DEFAULT 1,
+    -- 0: none, 1: zstd
+    `compression`    INTEGER NOT NULL DEFAULT 0,
     -- UNIX timestamp (in milliseconds). If this exists, the file is temporary
-    `expired_at`  INTEGER
+    `expired_at`     INTEGER,
+    -- UNIX timestamp (in milliseconds). The last time the stored data was checked against the hash
+    `verified_at`    INTEGER,
+    -- UNIX timestamp (in milliseconds). If this exists, the stored data is corrupt and cannot be read
+    `quarantined_at` INTEGER
 );
 
 CREATE INDEX `files_created_at` ON `files` (`created_at`);
 CREATE INDEX `files_expired_at` ON `files` (`expired_at`);
+CREATE INDEX `files_verified_at` ON `files` (`verified_at`);
+CREATE INDEX `files_file_size` ON `files` (`file_size`);
 
 -- Resource Table
 CREATE TABLE `resources` (
@@ -35,11 +43,19 @@ CREATE TABLE `resources` (
     `file_id`      BLOB    NOT NULL,
     -- UNIX timestamp (in milliseconds). If this exists, the resource is temporary
     `expired_at`  INTEGER,
+    -- the namespace which this resource belongs to. An empty string is the default namespace
+    `namespace`    TEXT    NOT NULL DEFAULT '',
+    -- increased whenever the metadata is changed
+    `metadata_version` INTEGER NOT NULL DEFAULT 0,
 
     FOREIGN KEY (`file_id`) REFERENCES `files` (`id`)
 );
 
 CREATE INDEX `resources_created_at` ON `resources` (`created_at`);
+CREATE INDEX `resources_namespace_created_at` ON `resources` (`namespace`, `created_at`);
+CREATE INDEX `resources_namespace_file_name` ON `resources` (`namespace`, `file_name`);
+CREATE INDEX `resources_namespace_file_type` ON `resources` (`namespace`, `file_type`);
+CREATE INDEX `resources_namespace_expired_at` ON `resources` (`namespace`, `expired_at`);
 
 -- Image Table
 CREATE TABLE `images` (
@@ -57,11 +73,20 @@ CREATE TABLE `images` (
     `original_file_id`   BLOB,
     -- boolean
     `has_alpha_channel`  INTEGER NOT NULL,
+    -- the namespace which this image belongs to. An empty string is the default namespace
+    `namespace`          TEXT    NOT NULL DEFAULT '',
+    -- increased whenever the metadata is changed
+    `metadata_version`   INTEGER NOT NULL DEFAULT 0,
 
     FOREIGN KEY (`original_file_id`) REFERENCES `files` (`id`)
 );
 
 CREATE INDEX `images_created_at` ON `images` (`created_at`);
+CREATE INDEX `images_namespace_created_at` ON `images` (`namespace`, `created_at`);
+CREATE INDEX `images_namespace_image_stem` ON `images` (`namespace`, `image_stem`);
+CREATE INDEX `images_namespace_image_width` ON `images` (`namespace`, `image_width`);
+CREATE INDEX `images_namespace_image_height` ON `images` (`namespace`, `image_height`);
+CREATE INDEX `images_namespace_has_alpha_channel` ON `images` (`namespace`, `has_alpha_channel`);
 
 -- Image Thumbnail Table
 CREATE TABLE `image_thumbnails` (
@@ -76,4 +101,94 @@ CREATE TABLE `image_thumbnails` (
     PRIMARY KEY (`image_id`, `multiplier`, `fallback`),
     FOREIGN KEY (`image_id`) REFERENCES `images` (`id`),
     FOREIGN KEY (`file_id`) REFERENCES `files` (`id`)
-);
\ No newline at end of file
+);
+
+-- Metadata Table
+CREATE TABLE `metadata` (
+    -- UUID (128-bit) of a resource or an image
+    `owner_id`  BLOB    NOT NULL,
+    `key`       TEXT    NOT NULL,
+    `value`     TEXT    NOT NULL,
+
+    PRIMARY KEY (`owner_id`, `key`)
+);
+
+CREATE INDEX `metadata_key_value` ON `metadata` (`key`, `value`);
+
+-- Tag Table
+CREATE TABLE `tags` (
+    -- UUID (128-bit) of a resource or an image
+    `owner_id`  BLOB    NOT NULL,
+    `tag`       TEXT    NOT NULL,
+
+    PRIMARY KEY (`owner_id`, `tag`)
+);
+
+CREATE INDEX `tags_tag` ON `tags` (`tag`);
+
+-- Search Document Table
+CREATE TABLE `search_documents` (
+    -- the rowid of the document in `search_index`
+    `id`        INTEGER NOT NULL PRIMARY KEY,
+    -- UUID (128-bit) of a resource or an image
+    `owner_id`  BLOB    NOT NULL UNIQUE
+);
+
+-- Search Index Table (full-text). `name` is the file name of a resource or the stem of an image, `metadata` is the metadata values, and `tags` is the tags
+CREATE VIRTUAL TABLE `search_index` USING fts5(`name`, `metadata`, `tags`, tokenize = 'unicode61 remove_diacritics 2');
+
+-- names weigh more than tags, and tags weigh more than metadata values
+INSERT INTO `search_index` (`search_index`, `rank`) VALUES ('rank', 'bm25(4.0, 1.0, 2.0)');
+
+-- Namespace Usage Table
+CREATE TABLE `namespace_usage` (
+    `namespace`    TEXT    NOT NULL PRIMARY KEY,
+    -- the total size (in bytes) of the resources and images
+    `total_bytes`  INTEGER NOT NULL DEFAULT 0,
+    -- the number of the resources and images
+    `total_count`  INTEGER NOT NULL DEFAULT 0,
+    -- the maximum total size (in bytes). If this does not exist, the size is unlimited
+    `max_bytes`    INTEGER,
+    -- the maximum number of the resources and images. If this does not exist, the number is unlimited
+    `max_count`    INTEGER
+);
+
+-- Upload Session Table
+CREATE TABLE `upload_sessions` (
+    -- UUID (128-bit)
+    `id`               BLOB    NOT NULL PRIMARY KEY,
+    -- UNIX timestamp (in milliseconds)
+    `created_at`       INTEGER NOT NULL,
+    -- the total length of the upload (in bytes). If this does not exist, the length is deferred
+    `upload_length`    INTEGER,
+    -- the number of bytes which have been received
+    `upload_offset`    INTEGER NOT NULL DEFAULT 0,
+    -- MIME type
+    `file_type`        TEXT,
+    -- 0: ExactMatch, 1: Manual, 2: Fallback
+    `file_type_level`  INTEGER,
+    -- the file name provided when the session was created
+    `file_name`        TEXT,
+    -- UNIX timestamp (in milliseconds). It is extended after each append
+    `expired_at`       INTEGER NOT NULL,
+    -- the namespace where the resource is put when this session is finished. An empty string is the default namespace
+    `namespace`        TEXT    NOT NULL DEFAULT ''
+);
+
+CREATE INDEX `upload_sessions_expired_at` ON `upload_sessions` (`expired_at`);
+
+-- API Key Table
+CREATE TABLE `api_keys` (
+    -- UUID (128-bit)
+    `id`          BLOB    NOT NULL PRIMARY KEY,
+    -- UNIX timestamp (in milliseconds)
+    `created_at`  INTEGER NOT NULL,
+    -- a name for identifying the owner of this key
+    `name`        TEXT    NOT NULL,
+    -- hashed by SHA-256
+    `key_hash`    BLOB    NOT NULL UNIQUE,
+    -- bit flags. 1: read, 2: write, 4: delete
+    `scopes`      INTEGER NOT NULL,
+    -- the namespaces which this key can access, separated by commas. If this does not exist, the key can access all namespaces
+    `namespaces`  TEXT
+);
@@ -0,0 +1,9 @@
+-- Listing Indexes
+CREATE INDEX `files_file_size` ON `files` (`file_size`);
+CREATE INDEX `resources_namespace_file_name` ON `resources` (`namespace`, `file_name`);
+CREATE INDEX `resources_namespace_file_type` ON `resources` (`namespace`, `file_type`);
+CREATE INDEX `resources_namespace_expired_at` ON `resources` (`namespace`, `expired_at`);
+CREATE INDEX `images_namespace_image_stem` ON `images` (`namespace`, `image_stem`);
+CREATE INDEX `images_namespace_image_width` ON `images` (`namespace`, `image_width`);
+CREATE INDEX `images_namespace_image_height` ON `images` (`namespace`, `image_height`);
+CREATE INDEX `images_namespace_has_alpha_channel` ON `images` (`namespace`, `has_alpha_channel`);
@@ -0,0 +1,30 @@
+-- Search Document Table
+CREATE TABLE `search_documents` (
+    -- the rowid of the document in `search_index`
+    `id`        INTEGER NOT NULL PRIMARY KEY,
+    -- UUID (128-bit) of a resource or an image
+    `owner_id`  BLOB    NOT NULL UNIQUE
+);
+
+-- Search Index Table (full-text). `name` is the file name of a resource or the stem of an image, `metadata` is the metadata values, and `tags` is the tags
+CREATE VIRTUAL TABLE `search_index` USING fts5(`name`, `metadata`, `tags`, tokenize = 'unicode61 remove_diacritics 2');
+
+-- names weigh more than tags, and tags weigh more than metadata values
+INSERT INTO `search_index` (`search_index`, `rank`) VALUES ('rank', 'bm25(4.0, 1.0, 2.0)');
+
+-- index the existing resources and images
+INSERT INTO `search_documents` (`owner_id`)
+    SELECT `id` FROM `resources`
+    UNION ALL
+    SELECT `id` FROM `images`;
+
+INSERT INTO `search_index` (`rowid`, `name`, `metadata`, `tags`)
+    SELECT
+        `search_documents`.`id`,
+        COALESCE(`resources`.`file_name`, `images`.`image_stem`),
+        ( SELECT group_concat(`value`, char(10)) FROM `metadata` WHERE `metadata`.`owner_id` = `search_documents`.`owner_id` ),
+        ( SELECT group_concat(`tag`, char(10)) FROM `tags` WHERE `tags`.`owner_id` = `search_documents`.`owner_id` )
+    FROM
+        `search_documents`
+            LEFT JOIN `resources` ON `resources`.`id` = `search_documents`.`owner_id`
+            LEFT JOIN `images` ON `images`.`id` = `search_documents`.`owner_id`;
@@ -0,0 +1,3 @@
+-- Metadata Versions of Resources and Images
+ALTER TABLE `resources` ADD COLUMN `metadata_version` INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE `images` ADD COLUMN `metadata_version` INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,21 @@
+-- Upload Session Table
+CREATE TABLE `upload_sessions` (
+    -- UUID (128-bit)
+    `id`               BLOB    NOT NULL PRIMARY KEY,
+    -- UNIX timestamp (in milliseconds)
+    `created_at`       INTEGER NOT NULL,
+    -- the total length of the upload (in bytes). If this does not exist, the length is deferred
+    `upload_length`    INTEGER,
+    -- the number of bytes which have been received
+    `upload_offset`    INTEGER NOT NULL DEFAULT 0,
+    -- MIME type
+    `file_type`        TEXT,
+    -- 0: ExactMatch, 1: Manual, 2: Fallback
+    `file_type_level`  INTEGER,
+    -- the file name provided when the session was created
+    `file_name`        TEXT,
+    -- UNIX timestamp (in milliseconds). It is extended after each append
+    `expired_at`       INTEGER NOT NULL
+);
+
+CREATE INDEX `upload_sessions_expired_at` ON `upload_sessions` (`expired_at`);
@@ -0,0 +1,13 @@
+-- API Key Table
+CREATE TABLE `api_keys` (
+    -- UUID (128-bit)
+    `id`          BLOB    NOT NULL PRIMARY KEY,
+    -- UNIX timestamp (in milliseconds)
+    `created_at`  INTEGER NOT NULL,
+    -- a name for identifying the owner of this key
+    `name`        TEXT    NOT NULL,
+    -- hashed by SHA-256
+    `key_hash`    BLOB    NOT NULL UNIQUE,
+    -- bit flags. 1: read, 2: write, 4: delete
+    `scopes`      INTEGER NOT NULL
+);
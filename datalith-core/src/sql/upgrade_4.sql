@@ -0,0 +1,2 @@
+-- File Compression
+ALTER TABLE `files` ADD COLUMN `compression` INTEGER NOT NULL DEFAULT 0;
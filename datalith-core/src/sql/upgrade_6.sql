@@ -0,0 +1,5 @@
+-- File Verification
+ALTER TABLE `files` ADD COLUMN `verified_at` INTEGER;
+ALTER TABLE `files` ADD COLUMN `quarantined_at` INTEGER;
+
+CREATE INDEX `files_verified_at` ON `files` (`verified_at`);
@@ -0,0 +1,10 @@
+-- Namespaces
+ALTER TABLE `resources` ADD COLUMN `namespace` TEXT NOT NULL DEFAULT '';
+ALTER TABLE `images` ADD COLUMN `namespace` TEXT NOT NULL DEFAULT '';
+ALTER TABLE `upload_sessions` ADD COLUMN `namespace` TEXT NOT NULL DEFAULT '';
+
+-- The existing API keys can access all namespaces
+ALTER TABLE `api_keys` ADD COLUMN `namespaces` TEXT;
+
+CREATE INDEX `resources_namespace_created_at` ON `resources` (`namespace`, `created_at`);
+CREATE INDEX `images_namespace_created_at` ON `images` (`namespace`, `created_at`);
@@ -0,0 +1,12 @@
+-- Namespace Usage Table
+CREATE TABLE `namespace_usage` (
+    `namespace`    TEXT    NOT NULL PRIMARY KEY,
+    -- the total size (in bytes) of the resources and images
+    `total_bytes`  INTEGER NOT NULL DEFAULT 0,
+    -- the number of the resources and images
+    `total_count`  INTEGER NOT NULL DEFAULT 0,
+    -- the maximum total size (in bytes). If this does not exist, the size is unlimited
+    `max_bytes`    INTEGER,
+    -- the maximum number of the resources and images. If this does not exist, the number is unlimited
+    `max_count`    INTEGER
+);
@@ -0,0 +1,22 @@
+-- Metadata Table
+CREATE TABLE `metadata` (
+    -- UUID (128-bit) of a resource or an image
+    `owner_id`  BLOB    NOT NULL,
+    `key`       TEXT    NOT NULL,
+    `value`     TEXT    NOT NULL,
+
+    PRIMARY KEY (`owner_id`, `key`)
+);
+
+CREATE INDEX `metadata_key_value` ON `metadata` (`key`, `value`);
+
+-- Tag Table
+CREATE TABLE `tags` (
+    -- UUID (128-bit) of a resource or an image
+    `owner_id`  BLOB    NOT NULL,
+    `tag`       TEXT    NOT NULL,
+
+    PRIMARY KEY (`owner_id`, `tag`)
+);
+
+CREATE INDEX `tags_tag` ON `tags` (`tag`);
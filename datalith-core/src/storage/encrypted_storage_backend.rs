@@ -0,0 +1,509 @@
+use std::{
+    env,
+    fmt::{self, Debug, Formatter},
+    io,
+    io::{ErrorKind, SeekFrom},
+    path::{Path, PathBuf},
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use async_trait::async_trait;
+use chacha20poly1305::{KeyInit, XChaCha20Poly1305, XNonce, aead::AeadInPlace};
+use educe::Educe;
+use tokio::{
+    fs,
+    fs::File,
+    io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWriteExt, ReadBuf},
+};
+use uuid::Uuid;
+
+use super::{StorageBackend, StorageReader, StorageStat};
+use crate::{
+    functions::{decode_hex, get_random_hash},
+    guard::TemporaryFileGuard,
+};
+
+/// The magic number at the beginning of every encrypted file.
+const MAGIC: &[u8; 4] = b"DLE1";
+/// The length of the random part of the nonces. The rest 5 bytes are the chunk index and the last-chunk flag.
+const NONCE_PREFIX_SIZE: usize = 19;
+const HEADER_SIZE: u64 = (MAGIC.len() + NONCE_PREFIX_SIZE) as u64;
+/// The size of a plaintext chunk.
+const CHUNK_SIZE: u64 = 64 * 1024;
+const TAG_SIZE: u64 = 16;
+const ENCRYPTED_CHUNK_SIZE: u64 = CHUNK_SIZE + TAG_SIZE;
+
+/// A 256-bit key for encrypting stored files.
+#[derive(Clone)]
+pub struct DatalithEncryptionKey([u8; 32]);
+
+impl Debug for DatalithEncryptionKey {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("DatalithEncryptionKey(..)")
+    }
+}
+
+impl DatalithEncryptionKey {
+    /// Create a key from 32 bytes.
+    #[inline]
+    pub const fn new(key: [u8; 32]) -> Self {
+        Self(key)
+    }
+
+    /// Parse a key from 64 hexadecimal digits.
+    #[inline]
+    pub fn from_hex(hex: impl AsRef<str>) -> io::Result<Self> {
+        match decode_hex(hex.as_ref().trim()).and_then(|key| <[u8; 32]>::try_from(key).ok()) {
+            Some(key) => Ok(Self(key)),
+            None => Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "an encryption key must be 64 hexadecimal digits",
+            )),
+        }
+    }
+
+    /// Read a key from a keyfile, which contains either 32 raw bytes or 64 hexadecimal digits.
+    pub async fn from_file(file_path: impl AsRef<Path>) -> io::Result<Self> {
+        let data = fs::read(file_path).await?;
+
+        match <[u8; 32]>::try_from(data.as_slice()) {
+            Ok(key) => Ok(Self(key)),
+            Err(_) => match std::str::from_utf8(&data) {
+                Ok(hex) => Self::from_hex(hex),
+                Err(_) => Self::from_hex(""),
+            },
+        }
+    }
+
+    /// Read a key (64 hexadecimal digits) from an environment variable.
+    #[inline]
+    pub fn from_env(name: impl AsRef<str>) -> io::Result<Self> {
+        let name = name.as_ref();
+
+        match env::var(name) {
+            Ok(hex) => Self::from_hex(hex),
+            Err(_) => Err(io::Error::new(ErrorKind::NotFound, format!("{name} is not set"))),
+        }
+    }
+}
+
+/// A storage backend which encrypts the data of files before passing them to another storage backend.
+///
+/// Files are encrypted with XChaCha20-Poly1305 in chunks of 64 KiB, so that they can be decrypted while streaming and seeking.
+#[derive(Educe)]
+#[educe(Debug)]
+pub struct EncryptedStorageBackend {
+    inner:  Box<dyn StorageBackend>,
+    #[educe(Debug(ignore))]
+    cipher: XChaCha20Poly1305,
+}
+
+impl EncryptedStorageBackend {
+    /// Wrap a storage backend.
+    #[inline]
+    pub fn new(inner: impl StorageBackend, key: &DatalithEncryptionKey) -> Self {
+        Self::from_boxed(Box::new(inner), key)
+    }
+
+    #[inline]
+    pub(crate) fn from_boxed(inner: Box<dyn StorageBackend>, key: &DatalithEncryptionKey) -> Self {
+        Self {
+            inner,
+            cipher: XChaCha20Poly1305::new((&key.0).into()),
+        }
+    }
+
+    /// Encrypt the files in the inner storage backend which have not been encrypted. Return the number of the encrypted files.
+    ///
+    /// `temporary_directory` is where the intermediate files are written. This should not be run while the files are being read.
+    pub async fn encrypt_existing_files(&self, temporary_directory: &Path) -> io::Result<usize> {
+        let mut counter = 0;
+
+        for id in self.inner.list().await? {
+            let mut reader = self.inner.open_reader(id).await?;
+
+            let mut magic = [0u8; MAGIC.len()];
+
+            if reader.read_exact(&mut magic).await.is_ok() && &magic == MAGIC {
+                continue;
+            }
+
+            reader.seek(SeekFrom::Start(0)).await?;
+
+            let temporary_file_path = temporary_directory.join(Uuid::new_v4().simple().to_string());
+            let _file_guard = TemporaryFileGuard::new(temporary_file_path.as_path());
+
+            {
+                let mut file = File::create(temporary_file_path.as_path()).await?;
+
+                tokio::io::copy(&mut reader, &mut file).await?;
+
+                file.flush().await?;
+            }
+
+            drop(reader);
+
+            self.put_from_temporary_file(id, temporary_file_path.as_path()).await?;
+
+            counter += 1;
+        }
+
+        Ok(counter)
+    }
+
+    #[inline]
+    fn create_nonce(nonce_prefix: &[u8; NONCE_PREFIX_SIZE], index: u64, last: bool) -> XNonce {
+        let mut nonce = [0u8; NONCE_PREFIX_SIZE + 5];
+
+        nonce[..NONCE_PREFIX_SIZE].copy_from_slice(nonce_prefix);
+        nonce[NONCE_PREFIX_SIZE..NONCE_PREFIX_SIZE + 4]
+            .copy_from_slice(&(index as u32).to_be_bytes());
+        nonce[NONCE_PREFIX_SIZE + 4] = last as u8;
+
+        nonce.into()
+    }
+
+    async fn encrypt_file(&self, id: Uuid, from: &Path, to: &Path) -> io::Result<()> {
+        let mut from = File::open(from).await?;
+        let mut to = File::create(to).await?;
+
+        let file_size = from.metadata().await?.len();
+
+        if file_size > CHUNK_SIZE * u32::MAX as u64 {
+            return Err(io::Error::new(ErrorKind::InvalidInput, "the file is too large"));
+        }
+
+        let nonce_prefix: [u8; NONCE_PREFIX_SIZE] =
+            get_random_hash()[..NONCE_PREFIX_SIZE].try_into().unwrap();
+
+        to.write_all(MAGIC).await?;
+        to.write_all(&nonce_prefix).await?;
+
+        let chunk_count = file_size.div_ceil(CHUNK_SIZE).max(1);
+
+        let mut buffer = Vec::with_capacity(ENCRYPTED_CHUNK_SIZE as usize);
+
+        for index in 0..chunk_count {
+            let length = (file_size - index * CHUNK_SIZE).min(CHUNK_SIZE);
+
+            buffer.resize(length as usize, 0);
+
+            from.read_exact(&mut buffer).await?;
+
+            self.cipher
+                .encrypt_in_place(
+                    &Self::create_nonce(&nonce_prefix, index, index + 1 == chunk_count),
+                    id.as_bytes(),
+                    &mut buffer,
+                )
+                .map_err(|_| io::Error::other("cannot encrypt the file"))?;
+
+            to.write_all(&buffer).await?;
+        }
+
+        to.flush().await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl StorageBackend for EncryptedStorageBackend {
+    async fn put_from_temporary_file(
+        &self,
+        id: Uuid,
+        temporary_file_path: &Path,
+    ) -> io::Result<()> {
+        let encrypted_file_path = {
+            let mut path = PathBuf::from(temporary_file_path);
+
+            path.as_mut_os_string().push(".enc");
+
+            path
+        };
+
+        let _file_guard = TemporaryFileGuard::new(encrypted_file_path.as_path());
+
+        self.encrypt_file(id, temporary_file_path, encrypted_file_path.as_path()).await?;
+
+        self.inner.put_from_temporary_file(id, encrypted_file_path.as_path()).await
+    }
+
+    async fn open_reader(&self, id: Uuid) -> io::Result<Box<dyn StorageReader>> {
+        let mut inner = self.inner.open_reader(id).await?;
+
+        let mut header = [0u8; HEADER_SIZE as usize];
+
+        if inner.read_exact(&mut header).await.is_err() || &header[..MAGIC.len()] != MAGIC {
+            return Err(io::Error::new(ErrorKind::InvalidData, "the file is not encrypted"));
+        }
+
+        let encrypted_size = inner.seek(SeekFrom::End(0)).await?;
+
+        let plaintext_size = get_plaintext_size(encrypted_size).ok_or_else(|| {
+            io::Error::new(ErrorKind::InvalidData, "the encrypted file is truncated")
+        })?;
+
+        Ok(Box::new(DecryptingReader {
+            inner,
+            cipher: self.cipher.clone(),
+            id,
+            nonce_prefix: header[MAGIC.len()..].try_into().unwrap(),
+            plaintext_size,
+            position: 0,
+            inner_position: encrypted_size,
+            state: DecryptingState::Idle,
+            buffer: Vec::with_capacity(ENCRYPTED_CHUNK_SIZE as usize),
+            chunk_index: None,
+        }))
+    }
+
+    #[inline]
+    async fn delete(&self, id: Uuid) -> io::Result<()> {
+        self.inner.delete(id).await
+    }
+
+    #[inline]
+    async fn list(&self) -> io::Result<Vec<Uuid>> {
+        self.inner.list().await
+    }
+
+    async fn stat(&self, id: Uuid) -> io::Result<Option<StorageStat>> {
+        match self.inner.stat(id).await? {
+            Some(stat) => Ok(Some(StorageStat {
+                size: get_plaintext_size(stat.size).unwrap_or(0),
+            })),
+            None => Ok(None),
+        }
+    }
+
+    #[inline]
+    async fn clear_unrecognized(&self) -> io::Result<usize> {
+        self.inner.clear_unrecognized().await
+    }
+
+    /// The exported file is still encrypted.
+    #[inline]
+    async fn export_to_file(&self, id: Uuid, file_path: &Path) -> io::Result<()> {
+        self.inner.export_to_file(id, file_path).await
+    }
+}
+
+/// Calculate the size of the plaintext by the size of an encrypted file. Return `None` if the encrypted file is truncated.
+fn get_plaintext_size(encrypted_size: u64) -> Option<u64> {
+    let body_size = encrypted_size.checked_sub(HEADER_SIZE)?;
+
+    let full_chunks = body_size / ENCRYPTED_CHUNK_SIZE;
+    let remainder = body_size % ENCRYPTED_CHUNK_SIZE;
+
+    if remainder == 0 {
+        if full_chunks == 0 {
+            return None;
+        }
+
+        Some(full_chunks * CHUNK_SIZE)
+    } else {
+        Some(full_chunks * CHUNK_SIZE + remainder.checked_sub(TAG_SIZE)?)
+    }
+}
+
+#[derive(Debug)]
+enum DecryptingState {
+    Idle,
+    Seeking { index: u64 },
+    Reading { index: u64, filled: usize },
+}
+
+#[derive(Educe)]
+#[educe(Debug)]
+struct DecryptingReader {
+    inner:          Box<dyn StorageReader>,
+    #[educe(Debug(ignore))]
+    cipher:         XChaCha20Poly1305,
+    id:             Uuid,
+    nonce_prefix:   [u8; NONCE_PREFIX_SIZE],
+    plaintext_size: u64,
+    /// the position in the plaintext
+    position:       u64,
+    /// the position in the encrypted file
+    inner_position: u64,
+    state:          DecryptingState,
+    /// the plaintext of the decrypted chunk
+    #[educe(Debug(ignore))]
+    buffer:         Vec<u8>,
+    chunk_index:    Option<u64>,
+}
+
+impl DecryptingReader {
+    #[inline]
+    fn chunk_count(&self) -> u64 {
+        self.plaintext_size.div_ceil(CHUNK_SIZE).max(1)
+    }
+
+    #[inline]
+    fn encrypted_chunk_size(&self, index: u64) -> usize {
+        ((self.plaintext_size - index * CHUNK_SIZE).min(CHUNK_SIZE) + TAG_SIZE) as usize
+    }
+
+    /// Load and decrypt a chunk into the buffer.
+    fn poll_load_chunk(&mut self, cx: &mut Context<'_>, index: u64) -> Poll<io::Result<()>> {
+        loop {
+            match self.state {
+                DecryptingState::Idle => {
+                    if self.chunk_index == Some(index) {
+                        return Poll::Ready(Ok(()));
+                    }
+
+                    self.chunk_index = None;
+
+                    let offset = HEADER_SIZE + index * ENCRYPTED_CHUNK_SIZE;
+
+                    if self.inner_position == offset {
+                        self.buffer.resize(self.encrypted_chunk_size(index), 0);
+
+                        self.state = DecryptingState::Reading {
+                            index,
+                            filled: 0,
+                        };
+                    } else {
+                        Pin::new(&mut self.inner).start_seek(SeekFrom::Start(offset))?;
+
+                        self.state = DecryptingState::Seeking {
+                            index,
+                        };
+                    }
+                },
+                DecryptingState::Seeking {
+                    index: seeking_index,
+                } => {
+                    self.inner_position = ready!(Pin::new(&mut self.inner).poll_complete(cx))?;
+
+                    if seeking_index == index {
+                        self.buffer.resize(self.encrypted_chunk_size(index), 0);
+
+                        self.state = DecryptingState::Reading {
+                            index,
+                            filled: 0,
+                        };
+                    } else {
+                        self.state = DecryptingState::Idle;
+                    }
+                },
+                DecryptingState::Reading {
+                    index: reading_index,
+                    filled,
+                } => {
+                    if reading_index != index {
+                        self.state = DecryptingState::Idle;
+
+                        continue;
+                    }
+
+                    if filled == self.buffer.len() {
+                        let nonce = EncryptedStorageBackend::create_nonce(
+                            &self.nonce_prefix,
+                            index,
+                            index + 1 == self.chunk_count(),
+                        );
+
+                        self.state = DecryptingState::Idle;
+
+                        self.cipher
+                            .decrypt_in_place(&nonce, self.id.as_bytes(), &mut self.buffer)
+                            .map_err(|_| {
+                                io::Error::new(
+                                    ErrorKind::InvalidData,
+                                    "cannot decrypt the file (the key may be wrong)",
+                                )
+                            })?;
+
+                        self.chunk_index = Some(index);
+
+                        return Poll::Ready(Ok(()));
+                    }
+
+                    let mut read_buf = ReadBuf::new(&mut self.buffer[filled..]);
+
+                    ready!(Pin::new(&mut self.inner).poll_read(cx, &mut read_buf))?;
+
+                    let c = read_buf.filled().len();
+
+                    if c == 0 {
+                        self.state = DecryptingState::Idle;
+
+                        return Poll::Ready(Err(io::Error::new(
+                            ErrorKind::UnexpectedEof,
+                            "the encrypted file is truncated",
+                        )));
+                    }
+
+                    self.inner_position += c as u64;
+
+                    self.state = DecryptingState::Reading {
+                        index,
+                        filled: filled + c,
+                    };
+                },
+            }
+        }
+    }
+}
+
+impl AsyncRead for DecryptingReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+
+        if this.position >= this.plaintext_size || buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+
+        let index = this.position / CHUNK_SIZE;
+
+        ready!(this.poll_load_chunk(cx, index))?;
+
+        let offset = (this.position - index * CHUNK_SIZE) as usize;
+
+        let data = &this.buffer[offset..];
+
+        let c = data.len().min(buf.remaining());
+
+        buf.put_slice(&data[..c]);
+
+        this.position += c as u64;
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncSeek for DecryptingReader {
+    fn start_seek(mut self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        let position = match position {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.plaintext_size.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+
+        match position {
+            Some(position) => {
+                self.position = position;
+
+                Ok(())
+            },
+            None => Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+
+    #[inline]
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Poll::Ready(Ok(self.position))
+    }
+}
@@ -0,0 +1,298 @@
+use std::{
+    io,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use async_trait::async_trait;
+use tokio::fs;
+use uuid::Uuid;
+
+use super::{StorageBackend, StorageReader, StorageStat};
+use crate::functions::allow_not_found_error;
+
+/// The default depth of the nested directories of [`LocalStorageBackend`].
+pub const DEFAULT_FILE_DIRECTORY_DEPTH: u8 = 2;
+/// The maximum depth of the nested directories of [`LocalStorageBackend`].
+pub const MAX_FILE_DIRECTORY_DEPTH: u8 = 8;
+
+/// A storage backend which stores files in a local directory. Each file is named after the hex form of its UUID.
+///
+/// Files are spread into nested directories named by the leading hex digits of their UUIDs, two digits per level. For example, with the depth `2`, the file `abcd0123...` is stored at `ab/cd/abcd0123...`. The depth `0` means all files are put directly in the directory.
+#[derive(Debug, Clone)]
+pub struct LocalStorageBackend {
+    directory: PathBuf,
+    depth:     u8,
+}
+
+impl LocalStorageBackend {
+    /// Create a local storage backend with the default depth of nested directories. The directory is created when the first file is stored.
+    #[inline]
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self::with_depth(directory, DEFAULT_FILE_DIRECTORY_DEPTH)
+    }
+
+    /// Create a local storage backend with a specific depth of nested directories. The depth is clamped to `MAX_FILE_DIRECTORY_DEPTH`.
+    #[inline]
+    pub fn with_depth(directory: impl Into<PathBuf>, depth: u8) -> Self {
+        Self {
+            directory: directory.into(), depth: depth.min(MAX_FILE_DIRECTORY_DEPTH)
+        }
+    }
+
+    /// Retrieve the directory where files are stored.
+    #[inline]
+    pub fn get_directory(&self) -> &Path {
+        self.directory.as_path()
+    }
+
+    /// Retrieve the depth of nested directories.
+    #[inline]
+    pub const fn get_depth(&self) -> u8 {
+        self.depth
+    }
+
+    /// Retrieve the path of a stored file.
+    pub fn get_file_path(&self, id: Uuid) -> PathBuf {
+        if self.depth == 0 {
+            // compatible with the flat layout used before nested directories were introduced
+            return self.directory.join(format!("{:x}", id.as_u128()));
+        }
+
+        let file_name = format!("{:032x}", id.as_u128());
+
+        let mut file_path = self.directory.clone();
+
+        for level in 0..self.depth as usize {
+            file_path.push(&file_name[level * 2..level * 2 + 2]);
+        }
+
+        file_path.push(file_name);
+
+        file_path
+    }
+
+    /// Move every stored file, wherever it is in the directory tree, to where it belongs with the current depth. Empty nested directories are removed. Return the number of the moved files.
+    ///
+    /// It is used to migrate the files stored with another depth and must not run while files are being stored.
+    pub async fn relayout(&self) -> io::Result<usize> {
+        let mut counter = 0usize;
+
+        // (directory path, level)
+        let mut directories = vec![(self.directory.clone(), 0usize)];
+        let mut visited_directories = Vec::new();
+
+        while let Some((directory, level)) = directories.pop() {
+            let mut read_dir = match fs::read_dir(directory.as_path()).await {
+                Ok(read_dir) => read_dir,
+                Err(error) if error.kind() == ErrorKind::NotFound => continue,
+                Err(error) => return Err(error),
+            };
+
+            while let Some(entry) = read_dir.next_entry().await? {
+                let file_type = entry.file_type().await?;
+
+                if file_type.is_dir() {
+                    if level < MAX_FILE_DIRECTORY_DEPTH as usize
+                        && entry.file_name().to_str().is_some_and(Self::is_shard_name)
+                    {
+                        directories.push((entry.path(), level + 1));
+                    }
+                } else if file_type.is_file()
+                    && let Some(id) = entry.file_name().to_str().and_then(Self::parse_file_name)
+                {
+                    let path = entry.path();
+                    let file_path = self.get_file_path(id);
+
+                    if path != file_path {
+                        Self::rename(path.as_path(), file_path.as_path()).await?;
+
+                        counter += 1;
+                    }
+                }
+            }
+
+            if level > 0 {
+                visited_directories.push(directory);
+            }
+        }
+
+        // deeper directories are visited later
+        for directory in visited_directories.into_iter().rev() {
+            // only succeeds if the directory is empty
+            let _ = fs::remove_dir(directory).await;
+        }
+
+        Ok(counter)
+    }
+
+    #[inline]
+    fn parse_file_name(file_name: &str) -> Option<Uuid> {
+        u128::from_str_radix(file_name, 16).ok().map(Uuid::from_u128)
+    }
+
+    #[inline]
+    fn is_shard_name(name: &str) -> bool {
+        name.len() == 2 && name.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+    }
+
+    async fn rename(from: &Path, to: &Path) -> io::Result<()> {
+        match fs::rename(from, to).await {
+            Err(error) if error.kind() == ErrorKind::NotFound => {
+                if let Some(parent) = to.parent() {
+                    fs::create_dir_all(parent).await?;
+                }
+
+                fs::rename(from, to).await
+            },
+            result => result,
+        }
+    }
+}
+
+#[async_trait]
+impl StorageBackend for LocalStorageBackend {
+    async fn put_from_temporary_file(
+        &self,
+        id: Uuid,
+        temporary_file_path: &Path,
+    ) -> io::Result<()> {
+        let file_path = self.get_file_path(id);
+
+        match Self::rename(temporary_file_path, file_path.as_path()).await {
+            Ok(()) => Ok(()),
+            // the directory is on another file system
+            Err(error) if error.kind() == ErrorKind::CrossesDevices => {
+                // do not write into an existing file, which may be hard-linked by a backup
+                allow_not_found_error(fs::remove_file(file_path.as_path()).await)?;
+
+                fs::copy(temporary_file_path, file_path).await?;
+
+                Ok(())
+            },
+            Err(error) => Err(error),
+        }
+    }
+
+    #[inline]
+    async fn open_reader(&self, id: Uuid) -> io::Result<Box<dyn StorageReader>> {
+        let file = fs::File::open(self.get_file_path(id)).await?;
+
+        Ok(Box::new(file))
+    }
+
+    #[inline]
+    async fn delete(&self, id: Uuid) -> io::Result<()> {
+        allow_not_found_error(fs::remove_file(self.get_file_path(id)).await)
+    }
+
+    async fn list(&self) -> io::Result<Vec<Uuid>> {
+        let mut ids = Vec::new();
+
+        // (directory path, level)
+        let mut directories = vec![(self.directory.clone(), 0u8)];
+
+        while let Some((directory, level)) = directories.pop() {
+            let mut read_dir = match fs::read_dir(directory.as_path()).await {
+                Ok(read_dir) => read_dir,
+                Err(error) if error.kind() == ErrorKind::NotFound => continue,
+                Err(error) => return Err(error),
+            };
+
+            while let Some(entry) = read_dir.next_entry().await? {
+                let file_type = entry.file_type().await?;
+
+                if level < self.depth {
+                    if file_type.is_dir()
+                        && entry.file_name().to_str().is_some_and(Self::is_shard_name)
+                    {
+                        directories.push((entry.path(), level + 1));
+                    }
+                } else if file_type.is_file()
+                    && let Some(id) = entry.file_name().to_str().and_then(Self::parse_file_name)
+                    && entry.path() == self.get_file_path(id)
+                {
+                    ids.push(id);
+                }
+            }
+        }
+
+        Ok(ids)
+    }
+
+    async fn stat(&self, id: Uuid) -> io::Result<Option<StorageStat>> {
+        match fs::metadata(self.get_file_path(id)).await {
+            Ok(metadata) => Ok(Some(StorageStat {
+                size: metadata.len()
+            })),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    async fn clear_unrecognized(&self) -> io::Result<usize> {
+        let mut counter = 0usize;
+
+        // (directory path, level)
+        let mut directories = vec![(self.directory.clone(), 0u8)];
+
+        while let Some((directory, level)) = directories.pop() {
+            let mut read_dir = match fs::read_dir(directory.as_path()).await {
+                Ok(read_dir) => read_dir,
+                Err(error) if error.kind() == ErrorKind::NotFound => continue,
+                Err(error) => return Err(error),
+            };
+
+            while let Some(entry) = read_dir.next_entry().await? {
+                let path = entry.path();
+
+                let file_type = entry.file_type().await?;
+
+                let recognized = if level < self.depth {
+                    // nested directories
+                    file_type.is_dir()
+                        && entry.file_name().to_str().is_some_and(Self::is_shard_name)
+                } else {
+                    // stored files
+                    file_type.is_file()
+                        && entry
+                            .file_name()
+                            .to_str()
+                            .and_then(Self::parse_file_name)
+                            .is_some_and(|id| path == self.get_file_path(id))
+                };
+
+                if recognized {
+                    if file_type.is_dir() {
+                        directories.push((path, level + 1));
+                    }
+                } else {
+                    if file_type.is_dir() {
+                        allow_not_found_error(fs::remove_dir_all(path).await)?;
+                    } else {
+                        allow_not_found_error(fs::remove_file(path).await)?;
+                    }
+
+                    counter += 1;
+                }
+            }
+        }
+
+        Ok(counter)
+    }
+
+    /// Stored files are never modified in place, so a hard link is used if possible.
+    async fn export_to_file(&self, id: Uuid, file_path: &Path) -> io::Result<()> {
+        let stored_file_path = self.get_file_path(id);
+
+        match fs::hard_link(stored_file_path.as_path(), file_path).await {
+            Ok(()) => Ok(()),
+            Err(error) if error.kind() == ErrorKind::NotFound => Err(error),
+            Err(_) => {
+                fs::copy(stored_file_path, file_path).await?;
+
+                Ok(())
+            },
+        }
+    }
+}
@@ -0,0 +1,111 @@
+#[cfg(feature = "encryption")]
+mod encrypted_storage_backend;
+mod local_storage_backend;
+
+use std::{fmt::Debug, io, path::Path, sync::Arc};
+
+use async_trait::async_trait;
+#[cfg(feature = "encryption")]
+pub use encrypted_storage_backend::*;
+pub use local_storage_backend::*;
+use tokio::{
+    fs,
+    io::{AsyncRead, AsyncSeek, AsyncWriteExt},
+};
+use uuid::Uuid;
+
+/// A reader of a stored file.
+pub trait StorageReader: AsyncRead + AsyncSeek + Debug + Send + Sync + Unpin {}
+
+impl<T: AsyncRead + AsyncSeek + Debug + Send + Sync + Unpin> StorageReader for T {}
+
+/// The status of a stored file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StorageStat {
+    /// The size of the stored file (in bytes).
+    pub size: u64,
+}
+
+/// The place where the data of files is stored. The metadata of files is always stored in SQLite.
+///
+/// Every stored file is identified by the UUID of the file.
+#[async_trait]
+pub trait StorageBackend: Debug + Send + Sync + 'static {
+    /// Store a file which has been completely written at `temporary_file_path`.
+    ///
+    /// The temporary file may be moved into the storage. If it still exists after this method returns, Datalith removes it.
+    async fn put_from_temporary_file(&self, id: Uuid, temporary_file_path: &Path)
+    -> io::Result<()>;
+
+    /// Open a stored file. The error kind should be `NotFound` if the file does not exist.
+    async fn open_reader(&self, id: Uuid) -> io::Result<Box<dyn StorageReader>>;
+
+    /// Delete a stored file. It should not be an error if the file does not exist.
+    async fn delete(&self, id: Uuid) -> io::Result<()>;
+
+    /// List the IDs of all stored files.
+    async fn list(&self) -> io::Result<Vec<Uuid>>;
+
+    /// Retrieve the status of a stored file. Return `None` if the file does not exist.
+    async fn stat(&self, id: Uuid) -> io::Result<Option<StorageStat>>;
+
+    /// Remove data which does not belong to any file, such as unrecognized objects. Return the number of the removed items.
+    #[inline]
+    async fn clear_unrecognized(&self) -> io::Result<usize> {
+        Ok(0)
+    }
+
+    /// Write a stored file to `file_path` exactly as it is stored, for backups. The parent directory of `file_path` exists.
+    ///
+    /// The default implementation copies the data read by `open_reader`. Backends which transform the data should override it.
+    async fn export_to_file(&self, id: Uuid, file_path: &Path) -> io::Result<()> {
+        let mut reader = self.open_reader(id).await?;
+        let mut file = fs::File::create(file_path).await?;
+
+        tokio::io::copy(&mut reader, &mut file).await?;
+
+        file.flush().await
+    }
+}
+
+#[async_trait]
+impl<T: StorageBackend> StorageBackend for Arc<T> {
+    #[inline]
+    async fn put_from_temporary_file(
+        &self,
+        id: Uuid,
+        temporary_file_path: &Path,
+    ) -> io::Result<()> {
+        self.as_ref().put_from_temporary_file(id, temporary_file_path).await
+    }
+
+    #[inline]
+    async fn open_reader(&self, id: Uuid) -> io::Result<Box<dyn StorageReader>> {
+        self.as_ref().open_reader(id).await
+    }
+
+    #[inline]
+    async fn delete(&self, id: Uuid) -> io::Result<()> {
+        self.as_ref().delete(id).await
+    }
+
+    #[inline]
+    async fn list(&self) -> io::Result<Vec<Uuid>> {
+        self.as_ref().list().await
+    }
+
+    #[inline]
+    async fn stat(&self, id: Uuid) -> io::Result<Option<StorageStat>> {
+        self.as_ref().stat(id).await
+    }
+
+    #[inline]
+    async fn clear_unrecognized(&self) -> io::Result<usize> {
+        self.as_ref().clear_unrecognized().await
+    }
+
+    #[inline]
+    async fn export_to_file(&self, id: Uuid, file_path: &Path) -> io::Result<()> {
+        self.as_ref().export_to_file(id, file_path).await
+    }
+}
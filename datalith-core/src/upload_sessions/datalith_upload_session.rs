@@ -0,0 +1,103 @@
+use chrono::{DateTime, Local, TimeZone};
+use educe::Educe;
+use mime::Mime;
+use uuid::Uuid;
+
+/// A struct that represents an upload session which receives the data of a file in multiple requests.
+#[derive(Debug, Clone, Educe)]
+#[educe(PartialEq, Eq, Hash)]
+pub struct DatalithUploadSession {
+    id:            Uuid,
+    #[educe(Eq(ignore), Hash(ignore))]
+    created_at:    DateTime<Local>,
+    #[educe(Eq(ignore), Hash(ignore))]
+    expired_at:    DateTime<Local>,
+    #[educe(Eq(ignore), Hash(ignore))]
+    upload_length: Option<u64>,
+    #[educe(Eq(ignore), Hash(ignore))]
+    upload_offset: u64,
+    #[educe(Eq(ignore), Hash(ignore))]
+    file_type:     Option<Mime>,
+    #[educe(Eq(ignore), Hash(ignore))]
+    file_name:     Option<String>,
+}
+
+impl DatalithUploadSession {
+    /// Create an upload session instance.
+    #[allow(clippy::too_many_arguments)]
+    #[inline]
+    pub(crate) fn new<Tz1: TimeZone, Tz2: TimeZone>(
+        id: impl Into<Uuid>,
+        created_at: DateTime<Tz1>,
+        expired_at: DateTime<Tz2>,
+        upload_length: Option<u64>,
+        upload_offset: u64,
+        file_type: Option<Mime>,
+        file_name: Option<String>,
+    ) -> Self {
+        let id = id.into();
+
+        Self {
+            id,
+            created_at: created_at.with_timezone(&Local),
+            expired_at: expired_at.with_timezone(&Local),
+            upload_length,
+            upload_offset,
+            file_type,
+            file_name,
+        }
+    }
+}
+
+impl DatalithUploadSession {
+    /// Retrieve the upload session ID (UUID).
+    #[inline]
+    pub const fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// Retrieve the creation time.
+    #[inline]
+    pub const fn created_at(&self) -> DateTime<Local> {
+        self.created_at
+    }
+
+    /// Retrieve the time when this upload session expires if no more data is appended.
+    #[inline]
+    pub const fn expired_at(&self) -> DateTime<Local> {
+        self.expired_at
+    }
+
+    /// Retrieve the total length (in bytes) of the upload. `None` means the length is deferred until the session is finished.
+    #[inline]
+    pub const fn upload_length(&self) -> Option<u64> {
+        self.upload_length
+    }
+
+    /// Retrieve the number of bytes which have been received.
+    #[inline]
+    pub const fn upload_offset(&self) -> u64 {
+        self.upload_offset
+    }
+
+    /// Retrieve the file type (MIME) provided when the session was created.
+    #[inline]
+    pub const fn file_type(&self) -> Option<&Mime> {
+        self.file_type.as_ref()
+    }
+
+    /// Retrieve the file name provided when the session was created.
+    #[inline]
+    pub const fn file_name(&self) -> Option<&String> {
+        self.file_name.as_ref()
+    }
+
+    /// Check if all the data of this upload session has been received.
+    #[inline]
+    pub const fn is_complete(&self) -> bool {
+        match self.upload_length {
+            Some(upload_length) => self.upload_offset == upload_length,
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use std::{
+    error::Error,
+    fmt,
+    fmt::{Display, Formatter},
+    io,
+};
+
+use crate::{DatalithReadError, DatalithWriteError};
+
+/// Errors occurred during Datalith upload session operations.
+#[derive(Debug)]
+pub enum DatalithUploadSessionError {
+    /// The upload session does not exist or has expired.
+    NotFound,
+    /// The upload session is being used by another operation.
+    Locked,
+    OffsetMismatch {
+        offset:          u64,
+        expected_offset: u64,
+    },
+    LengthExceeded {
+        upload_length: u64,
+        actual_length: u64,
+    },
+    Incomplete {
+        upload_length: u64,
+        upload_offset: u64,
+    },
+    DatalithWriteError(DatalithWriteError),
+}
+
+impl From<DatalithReadError> for DatalithUploadSessionError {
+    #[inline]
+    fn from(error: DatalithReadError) -> Self {
+        Self::DatalithWriteError(error.into())
+    }
+}
+
+impl From<DatalithWriteError> for DatalithUploadSessionError {
+    #[inline]
+    fn from(error: DatalithWriteError) -> Self {
+        Self::DatalithWriteError(error)
+    }
+}
+
+impl From<io::Error> for DatalithUploadSessionError {
+    #[inline]
+    fn from(error: io::Error) -> Self {
+        Self::DatalithWriteError(error.into())
+    }
+}
+
+impl From<sqlx::Error> for DatalithUploadSessionError {
+    #[inline]
+    fn from(error: sqlx::Error) -> Self {
+        Self::DatalithWriteError(error.into())
+    }
+}
+
+impl Display for DatalithUploadSessionError {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound => f.write_str("the upload session does not exist"),
+            Self::Locked => f.write_str("the upload session is being used"),
+            Self::OffsetMismatch {
+                offset,
+                expected_offset,
+            } => f.write_fmt(format_args!(
+                "the offset {offset} does not match the upload offset (expect: {expected_offset})"
+            )),
+            Self::LengthExceeded {
+                upload_length,
+                actual_length,
+            } => f.write_fmt(format_args!(
+                "the received length {actual_length} exceeds the upload length (expect: \
+                 {upload_length})"
+            )),
+            Self::Incomplete {
+                upload_length,
+                upload_offset,
+            } => f.write_fmt(format_args!(
+                "the upload session is incomplete ({upload_offset} / {upload_length})"
+            )),
+            Self::DatalithWriteError(error) => Display::fmt(&error, f),
+        }
+    }
+}
+
+impl Error for DatalithUploadSessionError {}
@@ -0,0 +1,533 @@
+mod datalith_upload_session;
+mod datalith_upload_session_errors;
+
+use std::{
+    io::{ErrorKind, SeekFrom},
+    str::FromStr,
+};
+
+use chrono::prelude::*;
+pub use datalith_upload_session::*;
+pub use datalith_upload_session_errors::*;
+use mime::Mime;
+use tokio::{
+    fs,
+    fs::OpenOptions,
+    io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
+};
+use uuid::Uuid;
+
+use crate::{
+    Datalith, DatalithReadError, DatalithResource, DatalithWriteError, FileTypeLevel,
+    functions::{BUFFER_SIZE, allow_not_found_error, get_current_timestamp},
+    guard::UploadSessionGuard,
+};
+
+#[inline]
+fn file_type_level_to_i64(level: FileTypeLevel) -> i64 {
+    match level {
+        FileTypeLevel::ExactMatch => 0,
+        FileTypeLevel::Manual => 1,
+        FileTypeLevel::Fallback => 2,
+    }
+}
+
+#[inline]
+fn i64_to_file_type_level(level: i64) -> FileTypeLevel {
+    match level {
+        0 => FileTypeLevel::ExactMatch,
+        1 => FileTypeLevel::Manual,
+        _ => FileTypeLevel::Fallback,
+    }
+}
+
+// Create
+impl Datalith {
+    /// Create an upload session in the namespace of this handle. The data of the file can be appended to the session in multiple requests and the session can be finished into a resource.
+    ///
+    /// Upload sessions are scoped by namespaces, so a session can only be used through a handle of the same namespace.
+    ///
+    /// If `upload_length` is `None`, the length is deferred and the session can be finished at any offset.
+    pub async fn create_upload_session(
+        &self,
+        upload_length: Option<u64>,
+        file_name: Option<impl Into<String>>,
+        file_type: Option<(Mime, FileTypeLevel)>,
+    ) -> Result<DatalithUploadSession, DatalithWriteError> {
+        let id = Uuid::new_v4();
+        let created_at = Local::now();
+        let expired_at = created_at + self.get_upload_session_lifespan();
+        let file_name = file_name.map(|e| e.into());
+
+        self.free_space_checker().check(upload_length.unwrap_or(0))?;
+
+        #[rustfmt::skip]
+        let result = sqlx::query(
+            "
+                INSERT INTO `upload_sessions` (`id`, `created_at`, `upload_length`, `file_type`, `file_type_level`, `file_name`, `expired_at`, `namespace`)
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            ",
+        )
+        .bind(id)
+        .bind(created_at.timestamp_millis())
+        .bind(upload_length.map(|e| e as i64))
+        .bind(file_type.as_ref().map(|(file_type, _)| file_type.essence_str()))
+        .bind(file_type.as_ref().map(|(_, level)| file_type_level_to_i64(*level)))
+        .bind(file_name.as_deref())
+        .bind(expired_at.timestamp_millis())
+        .bind(self.get_namespace())
+        .execute(&self.0.db)
+        .await?;
+
+        debug_assert!(result.rows_affected() > 0);
+
+        let file_path = self.get_upload_session_file_path(id).await?;
+
+        if let Err(error) = fs::File::create(file_path).await {
+            self.delete_upload_session_row(id).await?;
+
+            return Err(error.into());
+        }
+
+        Ok(DatalithUploadSession::new(
+            id,
+            created_at,
+            expired_at,
+            upload_length,
+            0,
+            file_type.map(|(file_type, _)| file_type),
+            file_name,
+        ))
+    }
+}
+
+// Upload
+impl Datalith {
+    /// Append data to an upload session in the namespace. The `offset` must be equal to the current upload offset of the session.
+    ///
+    /// The received data is kept even if the reader fails or the free space runs low in the middle, so the client can check the upload offset and resume. Return the new upload offset.
+    pub async fn append_upload_session(
+        &self,
+        id: impl Into<Uuid>,
+        offset: u64,
+        mut reader: impl AsyncRead + Unpin,
+    ) -> Result<u64, DatalithUploadSessionError> {
+        let id = id.into();
+
+        let _guard = UploadSessionGuard::try_new(self.clone(), id)
+            .ok_or(DatalithUploadSessionError::Locked)?;
+
+        let (upload_length, mut upload_offset) = {
+            let row = self.get_upload_session_length_and_offset(id).await?;
+
+            row.ok_or(DatalithUploadSessionError::NotFound)?
+        };
+
+        let file_path = self.get_upload_session_file_path(id).await?;
+
+        let mut file =
+            OpenOptions::new().create(true).truncate(false).write(true).open(file_path).await?;
+
+        // the file system is the source of truth for the data which has not been recorded, and vice versa
+        {
+            let file_size = file.metadata().await?.len();
+
+            if file_size < upload_offset {
+                upload_offset = file_size;
+
+                self.update_upload_session_offset(id, upload_offset).await?;
+            } else if file_size > upload_offset {
+                file.set_len(upload_offset).await?;
+            }
+        }
+
+        if offset != upload_offset {
+            return Err(DatalithUploadSessionError::OffsetMismatch {
+                offset,
+                expected_offset: upload_offset,
+            });
+        }
+
+        let mut free_space_checker = self.free_space_checker();
+
+        free_space_checker
+            .check(upload_length.map(|e| e.saturating_sub(upload_offset)).unwrap_or(0))?;
+
+        file.seek(SeekFrom::Start(upload_offset)).await?;
+
+        let mut buffer = vec![0; BUFFER_SIZE];
+        let mut received_length = 0u64;
+        let mut retry_count = 0;
+
+        let write_error: Option<DatalithWriteError> = loop {
+            let c = match reader.read(&mut buffer).await {
+                Ok(0) => break None,
+                Ok(c) => c,
+                Err(error) if error.kind() == ErrorKind::Interrupted => {
+                    retry_count += 1;
+
+                    if retry_count > 5 {
+                        break Some(error.into());
+                    }
+
+                    continue;
+                },
+                Err(error) => break Some(error.into()),
+            };
+
+            if let Some(upload_length) = upload_length {
+                let actual_length = upload_offset + received_length + c as u64;
+
+                if actual_length > upload_length {
+                    // discard the data of this request
+                    file.set_len(upload_offset).await?;
+
+                    return Err(DatalithUploadSessionError::LengthExceeded {
+                        upload_length,
+                        actual_length,
+                    });
+                }
+            }
+
+            if let Err(error) = free_space_checker.check_written(c as u64) {
+                break Some(error);
+            }
+
+            file.write_all(&buffer[..c]).await?;
+
+            received_length += c as u64;
+
+            retry_count = 0;
+        };
+
+        file.flush().await?;
+
+        upload_offset += received_length;
+
+        self.update_upload_session_offset(id, upload_offset).await?;
+
+        match write_error {
+            Some(error) => Err(error.into()),
+            None => Ok(upload_offset),
+        }
+    }
+
+    /// Finish an upload session in the namespace and input the received data as a resource into the namespace. The session will be removed once the resource has been put.
+    pub async fn finish_upload_session(
+        &self,
+        id: impl Into<Uuid>,
+    ) -> Result<DatalithResource, DatalithUploadSessionError> {
+        let id = id.into();
+
+        let _guard = UploadSessionGuard::try_new(self.clone(), id)
+            .ok_or(DatalithUploadSessionError::Locked)?;
+
+        let current_timestamp = get_current_timestamp();
+
+        #[rustfmt::skip]
+        #[allow(clippy::type_complexity)]
+        let row: Option<(Option<i64>, i64, Option<String>, Option<i64>, Option<String>)> = sqlx::query_as(
+            "
+                SELECT
+                    `upload_length`,
+                    `upload_offset`,
+                    `file_type`,
+                    `file_type_level`,
+                    `file_name`
+                FROM
+                    `upload_sessions`
+                WHERE
+                    `id` = ?
+                        AND `namespace` = ?
+                        AND `expired_at` > ?
+            ",
+        )
+        .bind(id)
+        .bind(self.get_namespace())
+        .bind(current_timestamp)
+        .fetch_optional(&self.0.db)
+        .await?;
+
+        let (upload_length, upload_offset, file_type, file_type_level, file_name) =
+            row.ok_or(DatalithUploadSessionError::NotFound)?;
+
+        let upload_length = upload_length.map(|e| e as u64);
+        let mut upload_offset = upload_offset as u64;
+
+        let file_path = self.get_upload_session_file_path(id).await?;
+
+        {
+            let file_size = match fs::metadata(file_path.as_path()).await {
+                Ok(metadata) => metadata.len(),
+                Err(error) if error.kind() == ErrorKind::NotFound => 0,
+                Err(error) => return Err(error.into()),
+            };
+
+            if file_size < upload_offset {
+                upload_offset = file_size;
+
+                self.update_upload_session_offset(id, upload_offset).await?;
+            } else if file_size > upload_offset {
+                let file = OpenOptions::new().write(true).open(file_path.as_path()).await?;
+
+                file.set_len(upload_offset).await?;
+            }
+        }
+
+        if let Some(upload_length) = upload_length
+            && upload_offset != upload_length
+        {
+            return Err(DatalithUploadSessionError::Incomplete {
+                upload_length,
+                upload_offset,
+            });
+        }
+
+        let file_type = match (file_type, file_type_level) {
+            (Some(file_type), Some(level)) => {
+                Mime::from_str(&file_type).ok().map(|e| (e, i64_to_file_type_level(level)))
+            },
+            _ => None,
+        };
+
+        // the partial file is kept until the resource has been put, so a failed finish can be retried
+        let temporary_file_path = self.get_temporary_file_path(Uuid::new_v4()).await?;
+
+        if fs::hard_link(file_path.as_path(), temporary_file_path.as_path()).await.is_err() {
+            fs::copy(file_path.as_path(), temporary_file_path.as_path()).await?;
+        }
+
+        let file = self
+            .put_file_by_temporary_file(temporary_file_path, file_name.clone(), file_type.clone())
+            .await?;
+
+        let resource = self.put_resource(file, file_name, file_type, false).await?;
+
+        self.delete_upload_session_row(id).await?;
+
+        allow_not_found_error(fs::remove_file(file_path).await)?;
+
+        Ok(resource)
+    }
+}
+
+// Download
+impl Datalith {
+    /// Retrieve the upload session in the namespace using an ID.
+    pub async fn get_upload_session_by_id(
+        &self,
+        id: impl Into<Uuid>,
+    ) -> Result<Option<DatalithUploadSession>, DatalithReadError> {
+        let id = id.into();
+
+        let current_timestamp = get_current_timestamp();
+
+        #[rustfmt::skip]
+        #[allow(clippy::type_complexity)]
+        let row: Option<(i64, i64, Option<i64>, i64, Option<String>, Option<String>)> = sqlx::query_as(
+            "
+                SELECT
+                    `created_at`,
+                    `expired_at`,
+                    `upload_length`,
+                    `upload_offset`,
+                    `file_type`,
+                    `file_name`
+                FROM
+                    `upload_sessions`
+                WHERE
+                    `id` = ?
+                        AND `namespace` = ?
+                        AND `expired_at` > ?
+            ",
+        )
+        .bind(id)
+        .bind(self.get_namespace())
+        .bind(current_timestamp)
+        .fetch_optional(&self.0.db)
+        .await?;
+
+        if let Some((created_at, expired_at, upload_length, upload_offset, file_type, file_name)) =
+            row
+        {
+            let created_at = DateTime::from_timestamp_millis(created_at).unwrap();
+            let expired_at = DateTime::from_timestamp_millis(expired_at).unwrap();
+            let file_type = file_type.and_then(|e| Mime::from_str(&e).ok());
+
+            Ok(Some(DatalithUploadSession::new(
+                id,
+                created_at,
+                expired_at,
+                upload_length.map(|e| e as u64),
+                upload_offset as u64,
+                file_type,
+                file_name,
+            )))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn get_upload_session_length_and_offset(
+        &self,
+        id: Uuid,
+    ) -> Result<Option<(Option<u64>, u64)>, DatalithReadError> {
+        let current_timestamp = get_current_timestamp();
+
+        #[rustfmt::skip]
+        let row: Option<(Option<i64>, i64)> = sqlx::query_as(
+            "
+                SELECT
+                    `upload_length`,
+                    `upload_offset`
+                FROM
+                    `upload_sessions`
+                WHERE
+                    `id` = ?
+                        AND `namespace` = ?
+                        AND `expired_at` > ?
+            ",
+        )
+        .bind(id)
+        .bind(self.get_namespace())
+        .bind(current_timestamp)
+        .fetch_optional(&self.0.db)
+        .await?;
+
+        Ok(row.map(|(upload_length, upload_offset)| {
+            (upload_length.map(|e| e as u64), upload_offset as u64)
+        }))
+    }
+
+    async fn update_upload_session_offset(
+        &self,
+        id: Uuid,
+        upload_offset: u64,
+    ) -> Result<(), DatalithReadError> {
+        let expired_at = Local::now() + self.get_upload_session_lifespan();
+
+        #[rustfmt::skip]
+        sqlx::query(
+            "
+                UPDATE
+                    `upload_sessions`
+                SET
+                    `upload_offset` = ?,
+                    `expired_at` = ?
+                WHERE
+                    `id` = ?
+                        AND `namespace` = ?
+            ",
+        )
+        .bind(upload_offset as i64)
+        .bind(expired_at.timestamp_millis())
+        .bind(id)
+        .bind(self.get_namespace())
+        .execute(&self.0.db)
+        .await?;
+
+        Ok(())
+    }
+}
+
+// Delete
+impl Datalith {
+    /// Remove an upload session in the namespace and its received data using an ID.
+    pub async fn delete_upload_session_by_id(
+        &self,
+        id: impl Into<Uuid>,
+    ) -> Result<bool, DatalithUploadSessionError> {
+        let id = id.into();
+
+        let _guard = UploadSessionGuard::try_new(self.clone(), id)
+            .ok_or(DatalithUploadSessionError::Locked)?;
+
+        if !self.delete_upload_session_row(id).await? {
+            return Ok(false);
+        }
+
+        let file_path = self.get_upload_session_file_path(id).await?;
+
+        allow_not_found_error(fs::remove_file(file_path).await)?;
+
+        Ok(true)
+    }
+
+    async fn delete_upload_session_row(&self, id: Uuid) -> Result<bool, DatalithReadError> {
+        #[rustfmt::skip]
+        let result = sqlx::query(
+            "
+                DELETE FROM
+                    `upload_sessions`
+                WHERE
+                    `id` = ?
+                        AND `namespace` = ?
+            ",
+        )
+        .bind(id)
+        .bind(self.get_namespace())
+        .execute(&self.0.db)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+// Clean Up
+impl Datalith {
+    /// Clear expired upload sessions and their received data.
+    pub async fn clear_expired_upload_sessions(&self) -> Result<usize, DatalithReadError> {
+        let current_timestamp = get_current_timestamp();
+
+        #[rustfmt::skip]
+        let rows: Vec<(Uuid,)> = sqlx::query_as(
+            "
+                SELECT
+                    `id`
+                FROM
+                    `upload_sessions`
+                WHERE
+                    `expired_at` <= ?
+            ",
+        )
+        .bind(current_timestamp)
+        .fetch_all(&self.0.db)
+        .await?;
+
+        let mut counter = 0usize;
+
+        for (id,) in rows {
+            // skip the sessions which are being used, they will be checked next time
+            let _guard = match UploadSessionGuard::try_new(self.clone(), id) {
+                Some(guard) => guard,
+                None => continue,
+            };
+
+            // the session may have been renewed after selecting
+            #[rustfmt::skip]
+            let result = sqlx::query(
+                "
+                    DELETE FROM
+                        `upload_sessions`
+                    WHERE
+                        `id` = ?
+                            AND `expired_at` <= ?
+                ",
+            )
+            .bind(id)
+            .bind(current_timestamp)
+            .execute(&self.0.db)
+            .await?;
+
+            if result.rows_affected() > 0 {
+                let file_path = self.get_upload_session_file_path(id).await?;
+
+                allow_not_found_error(fs::remove_file(file_path).await)?;
+
+                counter += 1;
+            }
+        }
+
+        Ok(counter)
+    }
+}
@@ -0,0 +1,343 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    io,
+    io::ErrorKind,
+    time::Duration,
+};
+
+use sha2::{Digest, Sha256};
+use tokio::{
+    io::AsyncReadExt,
+    time::{self, Instant},
+};
+use uuid::Uuid;
+
+use crate::{
+    Datalith, DatalithCompression, DatalithReadError, StorageReader,
+    functions::{calculate_buffer_size, get_current_timestamp},
+    guard::OpenGuard,
+};
+
+/// The options for verifying the stored data of files.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DatalithVerifyOptions {
+    /// Files which have been verified within this duration are skipped. `Duration::ZERO` means all files are verified.
+    pub skip_verified_within: Duration,
+    /// The maximum number of bytes read per second. `None` means unlimited.
+    pub max_read_rate:        Option<u64>,
+    /// Whether to quarantine corrupt files. The data of a quarantined file cannot be read until the file passes a later verification.
+    pub quarantine:           bool,
+}
+
+impl DatalithVerifyOptions {
+    /// The default options of the background verification. Files verified within 7 days are skipped and the reading is limited to 16 MiB per second.
+    #[inline]
+    pub const fn background() -> Self {
+        Self {
+            skip_verified_within: Duration::from_secs(7 * 24 * 60 * 60),
+            max_read_rate:        Some(16 * 1024 * 1024),
+            quarantine:           false,
+        }
+    }
+}
+
+/// A problem found when verifying the stored data of a file.
+#[derive(Debug)]
+pub enum DatalithFileProblem {
+    /// The stored data does not exist.
+    Missing,
+    /// The size of the stored data is different from the file size.
+    SizeMismatch { expected_file_size: u64, actual_file_size: u64 },
+    /// The SHA-256 hash of the stored data is different from the recorded one.
+    HashMismatch,
+    /// The stored data cannot be read.
+    Unreadable(io::Error),
+}
+
+impl DatalithFileProblem {
+    /// Check whether the stored data is corrupt. Unreadable data is regarded as corrupt only if the error is caused by the data itself, such as a truncated compressed stream or a failed decryption.
+    #[inline]
+    pub fn is_corrupt(&self) -> bool {
+        match self {
+            Self::Unreadable(error) => {
+                matches!(error.kind(), ErrorKind::InvalidData | ErrorKind::UnexpectedEof)
+            },
+            _ => true,
+        }
+    }
+}
+
+impl Display for DatalithFileProblem {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing => f.write_str("the stored data is missing"),
+            Self::SizeMismatch {
+                expected_file_size,
+                actual_file_size,
+            } => f.write_fmt(format_args!(
+                "the size of the stored data is {actual_file_size} (expect: {expected_file_size})"
+            )),
+            Self::HashMismatch => f.write_str("the hash of the stored data does not match"),
+            Self::Unreadable(error) => {
+                f.write_fmt(format_args!("the stored data cannot be read: {error}"))
+            },
+        }
+    }
+}
+
+impl Error for DatalithFileProblem {}
+
+/// The result of verifying the stored data of files.
+#[derive(Debug, Default)]
+pub struct DatalithVerifyReport {
+    /// The number of the verified files, including the problematic ones.
+    pub verified:    usize,
+    /// The number of bytes read from the storage.
+    pub read_bytes:  u64,
+    /// The problematic files.
+    pub problems:    Vec<(Uuid, DatalithFileProblem)>,
+    /// The number of the files which have been quarantined in this verification.
+    pub quarantined: usize,
+    /// The number of the quarantined files which have passed this verification and been released.
+    pub released:    usize,
+}
+
+/// Limit the reading rate by sleeping when the data is read faster than allowed.
+struct Throttle {
+    max_read_rate: Option<u64>,
+    start:         Instant,
+    read_bytes:    u64,
+}
+
+impl Throttle {
+    #[inline]
+    fn new(max_read_rate: Option<u64>) -> Self {
+        Self {
+            max_read_rate: max_read_rate.filter(|&rate| rate > 0),
+            start:         Instant::now(),
+            read_bytes:    0,
+        }
+    }
+
+    #[inline]
+    async fn consume(&mut self, c: usize) {
+        self.read_bytes += c as u64;
+
+        if let Some(max_read_rate) = self.max_read_rate {
+            let due =
+                self.start + Duration::from_secs_f64(self.read_bytes as f64 / max_read_rate as f64);
+
+            if due > Instant::now() {
+                time::sleep_until(due).await;
+            }
+        }
+    }
+}
+
+// Verification
+impl Datalith {
+    /// Retrieve the options of the background verification run by `DatalithManager`.
+    #[inline]
+    pub fn get_background_verify_options(&self) -> DatalithVerifyOptions {
+        self.0._background_verify_options.lock().unwrap().clone()
+    }
+
+    /// Set the options of the background verification run by `DatalithManager`.
+    #[inline]
+    pub fn set_background_verify_options(&self, verify_options: DatalithVerifyOptions) {
+        *self.0._background_verify_options.lock().unwrap() = verify_options;
+    }
+
+    /// Re-read the stored data of files and compare it with the recorded hashes and file sizes, starting from the files which have not been verified for the longest time.
+    ///
+    /// Every file which has been checked gets its last-verified time updated, except the ones which cannot be read for reasons unrelated to their data, so that they are retried next time. Quarantined files are verified too, and they are released if their data turns out to be fine.
+    pub async fn verify_files(
+        &self,
+        options: &DatalithVerifyOptions,
+    ) -> Result<DatalithVerifyReport, DatalithReadError> {
+        let current_timestamp = get_current_timestamp();
+        let verified_before =
+            current_timestamp.saturating_sub(options.skip_verified_within.as_millis() as i64);
+
+        #[rustfmt::skip]
+        let file_ids: Vec<(Uuid,)> = sqlx::query_as(
+            "
+                SELECT
+                    `id`
+                FROM
+                    `files`
+                WHERE
+                    ( `verified_at` IS NULL OR `verified_at` <= ? )
+                        AND ( `expired_at` IS NULL OR `expired_at` > ? )
+                ORDER BY
+                    `verified_at` ASC,
+                    `id` ASC
+            ",
+        )
+        .bind(verified_before)
+        .bind(current_timestamp)
+        .fetch_all(&self.0.db)
+        .await?;
+
+        let mut report = DatalithVerifyReport::default();
+        let mut throttle = Throttle::new(options.max_read_rate);
+
+        for (file_id,) in file_ids {
+            self.verify_file_inner(file_id, options.quarantine, &mut throttle, &mut report).await?;
+        }
+
+        report.read_bytes = throttle.read_bytes;
+
+        Ok(report)
+    }
+
+    async fn verify_file_inner(
+        &self,
+        id: Uuid,
+        quarantine: bool,
+        throttle: &mut Throttle,
+        report: &mut DatalithVerifyReport,
+    ) -> Result<(), DatalithReadError> {
+        // protect ID
+        let _guard = OpenGuard::new(self.clone(), id).await;
+
+        // skip deleting files
+        {
+            let deleting_files = self.0._deleting_files.lock().unwrap();
+
+            if deleting_files.contains(&id) {
+                return Ok(());
+            }
+        }
+
+        #[rustfmt::skip]
+        let row: Option<(Vec<u8>, u64, i64, Option<i64>)> = sqlx::query_as(
+            "
+                SELECT
+                    `hash`,
+                    `file_size`,
+                    `compression`,
+                    `quarantined_at`
+                FROM
+                    `files`
+                WHERE
+                    `id` = ?
+            ",
+        )
+        .bind(id)
+        .fetch_optional(&self.0.db)
+        .await?;
+
+        let Some((hash, file_size, compression, quarantined_at)) = row else {
+            // the file has been deleted
+            return Ok(());
+        };
+
+        let problem = match self
+            .open_decoding_reader(id, DatalithCompression::from_i64(compression), file_size)
+            .await
+        {
+            Ok(reader) => check_stored_data(reader, &hash, file_size, throttle).await,
+            Err(error) if error.kind() == ErrorKind::NotFound => Some(DatalithFileProblem::Missing),
+            Err(error) => Some(DatalithFileProblem::Unreadable(error)),
+        };
+
+        report.verified += 1;
+
+        let current_timestamp = get_current_timestamp();
+
+        match problem {
+            None => {
+                #[rustfmt::skip]
+                sqlx::query(
+                    "
+                        UPDATE
+                            `files`
+                        SET
+                            `verified_at` = ?,
+                            `quarantined_at` = NULL
+                        WHERE
+                            `id` = ?
+                    ",
+                )
+                .bind(current_timestamp)
+                .bind(id)
+                .execute(&self.0.db)
+                .await?;
+
+                if quarantined_at.is_some() {
+                    report.released += 1;
+                }
+            },
+            Some(problem) => {
+                if problem.is_corrupt() {
+                    let quarantine = quarantine && quarantined_at.is_none();
+
+                    #[rustfmt::skip]
+                    sqlx::query(
+                        "
+                            UPDATE
+                                `files`
+                            SET
+                                `verified_at` = ?,
+                                `quarantined_at` = IFNULL(`quarantined_at`, ?)
+                            WHERE
+                                `id` = ?
+                        ",
+                    )
+                    .bind(current_timestamp)
+                    .bind(if quarantine { Some(current_timestamp) } else { None })
+                    .bind(id)
+                    .execute(&self.0.db)
+                    .await?;
+
+                    if quarantine {
+                        report.quarantined += 1;
+                    }
+                }
+
+                report.problems.push((id, problem));
+            },
+        }
+
+        Ok(())
+    }
+}
+
+/// Read the stored data and compare it with the hash and the file size.
+async fn check_stored_data(
+    mut reader: Box<dyn StorageReader>,
+    hash: &[u8],
+    file_size: u64,
+    throttle: &mut Throttle,
+) -> Option<DatalithFileProblem> {
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; calculate_buffer_size(file_size)];
+    let mut actual_file_size = 0u64;
+
+    loop {
+        let c = match reader.read(&mut buffer).await {
+            Ok(0) => break,
+            Ok(c) => c,
+            Err(error) => return Some(DatalithFileProblem::Unreadable(error)),
+        };
+
+        hasher.update(&buffer[..c]);
+        actual_file_size += c as u64;
+
+        throttle.consume(c).await;
+    }
+
+    if actual_file_size != file_size {
+        Some(DatalithFileProblem::SizeMismatch {
+            expected_file_size: file_size,
+            actual_file_size,
+        })
+    } else if hasher.finalize().as_slice() != hash {
+        Some(DatalithFileProblem::HashMismatch)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,99 @@
+mod global;
+
+use datalith_core::DatalithApiKeyScopes;
+use global::*;
+
+#[tokio::test]
+async fn api_keys() {
+    let datalith = datalith_init().await;
+
+    {
+        let scopes = DatalithApiKeyScopes {
+            read: true, write: true, delete: false
+        };
+
+        let (api_key, key) = datalith.create_api_key("uploader", scopes, None).await.unwrap();
+
+        assert_eq!("uploader", api_key.name());
+        assert_eq!(scopes, api_key.scopes());
+        assert!(api_key.scopes().contains(DatalithApiKeyScopes::READ));
+        assert!(api_key.scopes().contains(DatalithApiKeyScopes::WRITE));
+        assert!(!api_key.scopes().contains(DatalithApiKeyScopes::DELETE));
+        assert_eq!("read,write", api_key.scopes().to_string());
+
+        let verified_api_key = datalith.verify_api_key(&key).await.unwrap().unwrap();
+        assert_eq!(api_key, verified_api_key);
+        assert_eq!(scopes, verified_api_key.scopes());
+
+        assert!(datalith.verify_api_key(format!("{key}0")).await.unwrap().is_none());
+        assert!(datalith.verify_api_key("").await.unwrap().is_none());
+
+        let (api_key_2, _) =
+            datalith.create_api_key("admin", DatalithApiKeyScopes::ALL, None).await.unwrap();
+
+        let api_keys = datalith.list_api_keys().await.unwrap();
+        assert_eq!(vec![api_key.clone(), api_key_2.clone()], api_keys);
+
+        assert!(datalith.delete_api_key_by_id(api_key.id()).await.unwrap());
+        assert!(!datalith.delete_api_key_by_id(api_key.id()).await.unwrap());
+
+        assert!(datalith.verify_api_key(&key).await.unwrap().is_none());
+        assert_eq!(vec![api_key_2], datalith.list_api_keys().await.unwrap());
+    }
+
+    datalith_close(datalith).await;
+}
+
+#[tokio::test]
+async fn api_key_namespaces() {
+    let datalith = datalith_init().await;
+
+    {
+        let (api_key, key) = datalith
+            .create_api_key(
+                "shop",
+                DatalithApiKeyScopes::READ,
+                Some(vec!["shop".into(), "".into(), "shop".into()]),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(Some(["".to_string(), "shop".to_string()].as_slice()), api_key.namespaces());
+        assert!(api_key.allows_namespace(""));
+        assert!(api_key.allows_namespace("shop"));
+        assert!(!api_key.allows_namespace("blog"));
+
+        let verified_api_key = datalith.verify_api_key(&key).await.unwrap().unwrap();
+        assert_eq!(api_key.namespaces(), verified_api_key.namespaces());
+
+        let (api_key_2, key_2) =
+            datalith.create_api_key("admin", DatalithApiKeyScopes::ALL, None).await.unwrap();
+
+        assert!(api_key_2.namespaces().is_none());
+        assert!(api_key_2.allows_namespace("blog"));
+
+        let verified_api_key_2 = datalith.verify_api_key(&key_2).await.unwrap().unwrap();
+        assert!(verified_api_key_2.namespaces().is_none());
+
+        let api_keys = datalith.list_api_keys().await.unwrap();
+        assert_eq!(api_key.namespaces(), api_keys[0].namespaces());
+        assert!(api_keys[1].namespaces().is_none());
+
+        // invalid namespaces are rejected
+        assert!(
+            datalith
+                .create_api_key("invalid", DatalithApiKeyScopes::READ, Some(vec!["a,b".into()]))
+                .await
+                .is_err()
+        );
+        assert!(
+            datalith
+                .create_api_key("empty", DatalithApiKeyScopes::READ, Some(vec![]))
+                .await
+                .is_err()
+        );
+        assert_eq!(2, datalith.list_api_keys().await.unwrap().len());
+    }
+
+    datalith_close(datalith).await;
+}
@@ -12,7 +12,7 @@ async fn auto_file_extension() {
     {
         let id = {
             let file = datalith
-                .put_file_by_buffer_temporarily(image, Some("MagicLen"), None)
+                .put_file_by_buffer_temporarily(image, Some("MagicLen"), None, None)
                 .await
                 .unwrap();
 
@@ -32,7 +32,8 @@ async fn auto_file_extension() {
 
     {
         let id = {
-            let file = datalith.put_file_by_buffer(image, Some("MagicLen"), None).await.unwrap();
+            let file =
+                datalith.put_file_by_buffer(image, Some("MagicLen"), None, None).await.unwrap();
 
             #[cfg(feature = "magic")]
             assert_eq!(&mime::IMAGE_PNG, file.file_type());
@@ -51,7 +52,7 @@ async fn auto_file_extension() {
     {
         let id = {
             let file = datalith
-                .put_file_by_path_temporarily(IMAGE_PATH, Some("MagicLen"), None)
+                .put_file_by_path_temporarily(IMAGE_PATH, Some("MagicLen"), None, None)
                 .await
                 .unwrap();
 
@@ -68,7 +69,8 @@ async fn auto_file_extension() {
 
     {
         let id = {
-            let file = datalith.put_file_by_path(IMAGE_PATH, Some("MagicLen"), None).await.unwrap();
+            let file =
+                datalith.put_file_by_path(IMAGE_PATH, Some("MagicLen"), None, None).await.unwrap();
 
             #[cfg(feature = "magic")]
             assert_eq!(&mime::IMAGE_PNG, file.file_type());
@@ -86,7 +88,13 @@ async fn auto_file_extension() {
             let mut file = File::open(IMAGE_PATH).await.unwrap();
 
             let file = datalith
-                .put_file_by_reader_temporarily(&mut file, Some("MagicLen"), None, Some(IMAGE_SIZE))
+                .put_file_by_reader_temporarily(
+                    &mut file,
+                    Some("MagicLen"),
+                    None,
+                    Some(IMAGE_SIZE),
+                    None,
+                )
                 .await
                 .unwrap();
 
@@ -109,7 +117,7 @@ async fn auto_file_extension() {
             let mut file = File::open(IMAGE_PATH).await.unwrap();
 
             let file = datalith
-                .put_file_by_reader(&mut file, Some("MagicLen"), None, Some(IMAGE_SIZE))
+                .put_file_by_reader(&mut file, Some("MagicLen"), None, Some(IMAGE_SIZE), None)
                 .await
                 .unwrap();
 
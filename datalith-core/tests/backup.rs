@@ -0,0 +1,203 @@
+mod global;
+
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use datalith_core::{
+    Datalith, DatalithCreateError, DatalithReadError, FileTypeLevel, LocalStorageBackend,
+    PATH_FILE_DIRECTORY, mime,
+};
+use global::*;
+use tokio::{fs, io::AsyncReadExt};
+
+#[inline]
+fn get_backup_path() -> PathBuf {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+
+    Path::new("tests").join("db").join(format!("backup_{}", timestamp.as_micros()))
+}
+
+#[tokio::test]
+async fn backup() {
+    let datalith = datalith_init().await;
+
+    let resource = datalith
+        .put_resource_by_buffer(
+            b"Hello world!",
+            Some("plain.txt"),
+            Some((mime::TEXT_PLAIN_UTF_8, FileTypeLevel::Manual)),
+            None,
+        )
+        .await
+        .unwrap();
+    let resource_id = resource.id();
+    drop(resource);
+
+    let file = datalith
+        .put_file_by_buffer(IMAGE_DATA.as_ref(), Some("image.png"), None, None)
+        .await
+        .unwrap();
+    let file_id = file.id();
+    drop(file);
+
+    let deleted_file =
+        datalith.put_file_by_buffer(b"deleted", Some("deleted.txt"), None, None).await.unwrap();
+    let deleted_file_id = deleted_file.id();
+    drop(deleted_file);
+    assert!(datalith.delete_file_by_id(deleted_file_id).await.unwrap());
+
+    let session = datalith.create_upload_session(Some(3), Some("abc.txt"), None).await.unwrap();
+    let session_id = session.id();
+
+    let backup_path = get_backup_path();
+
+    assert_eq!(2, datalith.backup_to(backup_path.as_path()).await.unwrap());
+
+    // the directory is not empty now
+    match datalith.backup_to(backup_path.as_path()).await {
+        Err(DatalithReadError::IOError(error)) => {
+            assert_eq!(ErrorKind::AlreadyExists, error.kind())
+        },
+        result => panic!("unexpected result: {result:?}"),
+    }
+
+    // the original Datalith is still usable and changes after the backup do not affect it
+    assert!(datalith.delete_file_by_id(file_id).await.unwrap());
+
+    {
+        let backup = Datalith::new(backup_path.as_path()).await.unwrap();
+
+        let resource = backup.get_resource_by_id(resource_id).await.unwrap().unwrap();
+
+        let mut buffer = String::new();
+        resource.file().create_reader().await.unwrap().read_to_string(&mut buffer).await.unwrap();
+        assert_eq!("Hello world!", buffer);
+
+        let file = backup.get_file_by_id(file_id).await.unwrap().unwrap();
+
+        let mut buffer = Vec::new();
+        file.create_reader().await.unwrap().read_to_end(&mut buffer).await.unwrap();
+        assert_eq!(IMAGE_DATA.as_ref(), buffer);
+
+        drop(file);
+        drop(resource);
+
+        assert!(backup.get_file_by_id(deleted_file_id).await.unwrap().is_none());
+        assert!(backup.get_upload_session_by_id(session_id).await.unwrap().is_none());
+
+        assert_eq!(0, backup.clear_untracked_files().await.unwrap());
+
+        backup.drop_datalith().await.unwrap();
+    }
+
+    assert!(fs::metadata(backup_path.as_path()).await.is_err());
+
+    datalith_close(datalith).await;
+}
+
+#[tokio::test]
+async fn backup_failed() {
+    let datalith = datalith_init().await;
+
+    let file = datalith
+        .put_file_by_buffer(IMAGE_DATA.as_ref(), Some("image.png"), None, None)
+        .await
+        .unwrap();
+    let file_id = file.id();
+    drop(file);
+
+    // lose the data of the file
+    fs::remove_file(
+        LocalStorageBackend::new(datalith.get_environment().join(PATH_FILE_DIRECTORY))
+            .get_file_path(file_id),
+    )
+    .await
+    .unwrap();
+
+    let backup_path = get_backup_path();
+
+    match datalith.backup_to(backup_path.as_path()).await {
+        Err(DatalithReadError::IOError(error)) => assert_eq!(ErrorKind::NotFound, error.kind()),
+        result => panic!("unexpected result: {result:?}"),
+    }
+
+    // the directory created for the backup is removed
+    assert!(!fs::try_exists(backup_path.as_path()).await.unwrap());
+
+    // an existing directory is kept, but emptied
+    fs::create_dir_all(backup_path.as_path()).await.unwrap();
+
+    assert!(datalith.backup_to(backup_path.as_path()).await.is_err());
+
+    assert!(
+        fs::read_dir(backup_path.as_path()).await.unwrap().next_entry().await.unwrap().is_none()
+    );
+
+    fs::remove_dir(backup_path).await.unwrap();
+
+    datalith_close(datalith).await;
+}
+
+#[tokio::test]
+async fn backup_read_only() {
+    let datalith = datalith_init().await;
+
+    let resource = datalith
+        .put_resource_by_buffer(
+            b"Hello world!",
+            Some("plain.txt"),
+            Some((mime::TEXT_PLAIN_UTF_8, FileTypeLevel::Manual)),
+            None,
+        )
+        .await
+        .unwrap();
+    let resource_id = resource.id();
+    drop(resource);
+
+    // the environment is locked by the running instance, but can be opened read-only
+    assert!(matches!(
+        Datalith::new(datalith.get_environment()).await,
+        Err(DatalithCreateError::AlreadyRun)
+    ));
+
+    let read_only =
+        Datalith::builder(datalith.get_environment()).read_only(true).build().await.unwrap();
+    assert!(read_only.is_read_only());
+    assert!(!datalith.is_read_only());
+
+    assert!(read_only.check_resource_exist(resource_id).await.unwrap());
+    assert!(read_only.put_file_by_buffer(b"read-only", None::<&str>, None, None).await.is_err());
+    assert_eq!(0, datalith.clear_untracked_files().await.unwrap());
+
+    let backup_path = get_backup_path();
+
+    assert_eq!(1, read_only.backup_to(backup_path.as_path()).await.unwrap());
+
+    // a read-only instance cannot remove the environment
+    match read_only.drop_datalith().await {
+        Err(error) => assert_eq!(ErrorKind::PermissionDenied, error.kind()),
+        result => panic!("unexpected result: {result:?}"),
+    }
+
+    // the running instance is still usable
+    assert!(datalith.delete_resource_by_id(resource_id).await.unwrap());
+
+    {
+        let backup = Datalith::new(backup_path.as_path()).await.unwrap();
+
+        let resource = backup.get_resource_by_id(resource_id).await.unwrap().unwrap();
+
+        let mut buffer = String::new();
+        resource.file().create_reader().await.unwrap().read_to_string(&mut buffer).await.unwrap();
+        assert_eq!("Hello world!", buffer);
+
+        drop(resource);
+
+        backup.drop_datalith().await.unwrap();
+    }
+
+    datalith_close(datalith).await;
+}
@@ -15,8 +15,10 @@ async fn clear_expired_files() {
     let image = IMAGE_DATA.as_ref();
 
     {
-        let file =
-            datalith.put_file_by_buffer_temporarily(image, Some("image.png"), None).await.unwrap();
+        let file = datalith
+            .put_file_by_buffer_temporarily(image, Some("image.png"), None, None)
+            .await
+            .unwrap();
 
         let file_id = file.id();
 
@@ -46,28 +48,39 @@ async fn clear_untracked_files() {
         let image = IMAGE_DATA.as_ref();
 
         let id_1 = datalith
-            .put_file_by_buffer_temporarily(image, Some("image.png"), None)
+            .put_file_by_buffer_temporarily(image, Some("image.png"), None, None)
             .await
             .unwrap()
             .id();
-        let id_2 = datalith.put_file_by_buffer(image, Some("image.png"), None).await.unwrap().id();
+        let id_2 =
+            datalith.put_file_by_buffer(image, Some("image.png"), None, None).await.unwrap().id();
 
         assert_eq!(0, datalith.clear_untracked_files().await.unwrap());
 
-        let file_path_hello = environment.join(PATH_FILE_DIRECTORY).join("hello.txt");
-        let file_path_uuid =
-            environment.join(PATH_FILE_DIRECTORY).join("70b7c850506e4fa98a4a713aca21f594");
+        let file_directory = environment.join(PATH_FILE_DIRECTORY);
+
+        let file_path_hello = file_directory.join("hello.txt");
+        let file_path_uuid = file_directory.join("70b7c850506e4fa98a4a713aca21f594");
+        let file_path_nested_uuid =
+            file_directory.join("70").join("b7").join("70b7c850506e4fa98a4a713aca21f595");
+        let file_path_nested_hello = file_directory.join("70").join("hello.txt");
+        let directory_path_junk = file_directory.join("junk");
+
+        fs::create_dir_all(file_path_nested_uuid.parent().unwrap()).await.unwrap();
+        fs::create_dir_all(directory_path_junk.as_path()).await.unwrap();
 
         fs::write(file_path_hello.as_path(), b"Hello world!").await.unwrap();
         fs::write(file_path_uuid.as_path(), b"Hello world!").await.unwrap();
+        fs::write(file_path_nested_uuid.as_path(), b"Hello world!").await.unwrap();
+        fs::write(file_path_nested_hello.as_path(), b"Hello world!").await.unwrap();
 
-        assert!(fs::try_exists(file_path_hello.as_path()).await.unwrap());
-        assert!(fs::try_exists(file_path_uuid.as_path()).await.unwrap());
-
-        assert_eq!(2, datalith.clear_untracked_files().await.unwrap());
+        assert_eq!(5, datalith.clear_untracked_files().await.unwrap());
 
         assert!(!fs::try_exists(file_path_hello.as_path()).await.unwrap());
         assert!(!fs::try_exists(file_path_uuid.as_path()).await.unwrap());
+        assert!(!fs::try_exists(file_path_nested_uuid.as_path()).await.unwrap());
+        assert!(!fs::try_exists(file_path_nested_hello.as_path()).await.unwrap());
+        assert!(!fs::try_exists(directory_path_junk.as_path()).await.unwrap());
 
         assert!(datalith.check_file_exist(id_1).await.unwrap());
         assert!(datalith.check_file_exist(id_2).await.unwrap());
@@ -0,0 +1,165 @@
+#![cfg(feature = "compression")]
+
+mod global;
+
+use std::io::SeekFrom;
+
+use datalith_core::{
+    Datalith, DatalithCompression, DatalithCompressionPolicy, FileTypeLevel, LocalStorageBackend,
+    PATH_FILE_DIRECTORY, Uuid, mime,
+};
+use global::*;
+use tokio::{
+    fs,
+    io::{AsyncReadExt, AsyncSeekExt},
+};
+
+#[inline]
+async fn read_stored_file(datalith: &Datalith, id: Uuid) -> Vec<u8> {
+    let file_path = LocalStorageBackend::new(datalith.get_environment().join(PATH_FILE_DIRECTORY))
+        .get_file_path(id);
+
+    fs::read(file_path).await.unwrap()
+}
+
+#[test]
+fn choose() {
+    let policy = DatalithCompressionPolicy::text();
+
+    assert_eq!(DatalithCompression::Zstd, policy.choose(&mime::TEXT_PLAIN_UTF_8, 4096));
+    assert_eq!(DatalithCompression::Zstd, policy.choose(&mime::APPLICATION_JSON, 4096));
+    assert_eq!(
+        DatalithCompression::Zstd,
+        policy.choose(&"application/ld+json".parse().unwrap(), 4096)
+    );
+    assert_eq!(DatalithCompression::None, policy.choose(&mime::TEXT_PLAIN_UTF_8, 16));
+    assert_eq!(DatalithCompression::None, policy.choose(&mime::IMAGE_PNG, 4096));
+    assert_eq!(DatalithCompression::None, policy.choose(&mime::APPLICATION_OCTET_STREAM, 4096));
+
+    let policy = DatalithCompressionPolicy::disabled();
+
+    assert_eq!(DatalithCompression::None, policy.choose(&mime::TEXT_PLAIN_UTF_8, 4096));
+
+    // compression is opt-in
+    assert_eq!(policy, DatalithCompressionPolicy::default());
+}
+
+#[tokio::test]
+async fn compression() {
+    let datalith = datalith_init().await;
+
+    datalith.set_compression_policy(DatalithCompressionPolicy::text());
+
+    let data = (0..300_000u32).map(|i| format!("line {}\n", i % 1000)).collect::<String>();
+    let data = data.as_bytes();
+
+    {
+        let file = datalith
+            .put_file_by_buffer(
+                data,
+                Some("text.txt"),
+                Some((mime::TEXT_PLAIN_UTF_8, FileTypeLevel::Manual)),
+                None,
+            )
+            .await
+            .unwrap();
+        let file_id = file.id();
+
+        assert_eq!(DatalithCompression::Zstd, file.compression());
+        assert_eq!(data.len() as u64, file.file_size());
+
+        let stored_data = read_stored_file(&datalith, file_id).await;
+        assert!(stored_data.starts_with(&[0x28, 0xB5, 0x2F, 0xFD]));
+        // the seek table
+        assert!(stored_data.ends_with(&[0xB1, 0xEA, 0x92, 0x8F]));
+        assert!(stored_data.len() < data.len());
+        assert_eq!(stored_data.len() as u64, file.stored_size().await.unwrap());
+
+        let mut buffer = Vec::new();
+        file.create_reader().await.unwrap().read_to_end(&mut buffer).await.unwrap();
+        assert_eq!(data, buffer);
+
+        drop(file);
+
+        let file = datalith.get_file_by_id(file_id).await.unwrap().unwrap();
+
+        assert_eq!(DatalithCompression::Zstd, file.compression());
+        assert_eq!(data.len() as u64, file.file_size());
+
+        // seek forward, backward and from the end, within and across frames
+        let mut reader = file.create_reader().await.unwrap();
+        let mut buffer = [0u8; 16];
+
+        reader.seek(SeekFrom::Start(300_000)).await.unwrap();
+        reader.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(&data[300_000..300_016], &buffer);
+
+        reader.seek(SeekFrom::Start(2_000_000)).await.unwrap();
+        reader.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(&data[2_000_000..2_000_016], &buffer);
+
+        reader.seek(SeekFrom::Current(-1_000_000)).await.unwrap();
+        reader.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(&data[1_000_016..1_000_032], &buffer);
+
+        // across the boundary of two frames
+        reader.seek(SeekFrom::Start(1024 * 1024 - 8)).await.unwrap();
+        reader.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(&data[1024 * 1024 - 8..1024 * 1024 + 8], &buffer);
+
+        reader.seek(SeekFrom::Start(10)).await.unwrap();
+        reader.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(&data[10..26], &buffer);
+
+        reader.seek(SeekFrom::End(-16)).await.unwrap();
+        reader.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(&data[data.len() - 16..], &buffer);
+
+        drop(reader);
+
+        // the raw data is the stored data
+        let mut raw_buffer = Vec::new();
+        file.into_raw_readable().await.unwrap().read_to_end(&mut raw_buffer).await.unwrap();
+        assert_eq!(stored_data, raw_buffer);
+
+        assert!(datalith.delete_file_by_id(file_id).await.unwrap());
+    }
+
+    {
+        let file = datalith
+            .put_file_by_buffer(IMAGE_DATA.as_ref(), Some("image.png"), None, None)
+            .await
+            .unwrap();
+
+        assert_eq!(DatalithCompression::None, file.compression());
+        assert_eq!(IMAGE_DATA.as_ref(), read_stored_file(&datalith, file.id()).await.as_slice());
+
+        let file_id = file.id();
+        drop(file);
+        assert!(datalith.delete_file_by_id(file_id).await.unwrap());
+    }
+
+    {
+        datalith.set_compression_policy(DatalithCompressionPolicy::disabled());
+
+        let file = datalith
+            .put_file_by_reader(
+                data,
+                Some("text.txt"),
+                Some((mime::TEXT_PLAIN_UTF_8, FileTypeLevel::Manual)),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(DatalithCompression::None, file.compression());
+        assert_eq!(data, read_stored_file(&datalith, file.id()).await.as_slice());
+
+        let file_id = file.id();
+        drop(file);
+        assert!(datalith.delete_file_by_id(file_id).await.unwrap());
+    }
+
+    datalith_close(datalith).await;
+}
@@ -0,0 +1,257 @@
+mod global;
+
+use datalith_core::{
+    Datalith, FileTypeLevel, LocalStorageBackend, PATH_DB_FILE, PATH_FILE_DIRECTORY, Uuid, mime,
+};
+use global::*;
+use sqlx::{Connection, SqliteConnection, sqlite::SqliteConnectOptions};
+use tokio::{fs, io::AsyncReadExt};
+
+/// Open another connection to the database, without enforcing foreign keys, to break the metadata.
+async fn connect(datalith: &Datalith) -> SqliteConnection {
+    let options = SqliteConnectOptions::new()
+        .filename(datalith.get_environment().join(PATH_DB_FILE))
+        .foreign_keys(false);
+
+    SqliteConnection::connect_with(&options).await.unwrap()
+}
+
+async fn put_resource(datalith: &Datalith, data: &'static [u8]) -> (Uuid, Uuid) {
+    let resource = datalith
+        .put_resource_by_buffer(
+            data,
+            Some("text.txt"),
+            Some((mime::TEXT_PLAIN, FileTypeLevel::Manual)),
+            None,
+        )
+        .await
+        .unwrap();
+
+    (resource.id(), resource.file().id())
+}
+
+async fn insert_image(
+    conn: &mut SqliteConnection,
+    original_file_id: Uuid,
+    thumbnail_file_id: Uuid,
+) -> Uuid {
+    let image_id = Uuid::new_v4();
+
+    sqlx::query(
+        "INSERT INTO `images` (`id`, `created_at`, `image_stem`, `image_width`, `image_height`, \
+         `original_file_id`, `has_alpha_channel`) VALUES (?, 0, 'image', 1, 1, ?, 0)",
+    )
+    .bind(image_id)
+    .bind(original_file_id)
+    .execute(&mut *conn)
+    .await
+    .unwrap();
+
+    sqlx::query(
+        "INSERT INTO `image_thumbnails` (`image_id`, `multiplier`, `fallback`, `file_id`) VALUES \
+         (?, 1, 0, ?)",
+    )
+    .bind(image_id)
+    .bind(thumbnail_file_id)
+    .execute(&mut *conn)
+    .await
+    .unwrap();
+
+    image_id
+}
+
+#[tokio::test]
+async fn check_consistency() {
+    let datalith = datalith_init().await;
+
+    assert!(datalith.check_consistency(false).await.unwrap().is_consistent());
+
+    let (fine_resource_id, fine_file_id) = put_resource(&datalith, b"fine").await;
+    let (missing_resource_id, missing_file_id) = put_resource(&datalith, b"missing").await;
+    let (_, miscounted_file_id) = put_resource(&datalith, b"miscounted").await;
+    let (_, miscounted_file_id_2) = put_resource(&datalith, b"miscounted").await;
+    assert_eq!(miscounted_file_id, miscounted_file_id_2);
+
+    // a file put directly is not referenced by anything
+    let direct_file_id = datalith
+        .put_file_by_buffer(
+            b"direct",
+            Some("direct.txt"),
+            Some((mime::TEXT_PLAIN, FileTypeLevel::Manual)),
+            None,
+        )
+        .await
+        .unwrap()
+        .id();
+    let orphan_file_id = datalith
+        .put_file_by_buffer(
+            b"orphan",
+            Some("orphan.txt"),
+            Some((mime::TEXT_PLAIN, FileTypeLevel::Manual)),
+            None,
+        )
+        .await
+        .unwrap()
+        .id();
+
+    fs::remove_file(
+        LocalStorageBackend::new(datalith.get_environment().join(PATH_FILE_DIRECTORY))
+            .get_file_path(missing_file_id),
+    )
+    .await
+    .unwrap();
+
+    let (dangling_resource_id, dangling_image_id, orphan_image_id, missing_image_id) = {
+        let mut conn = connect(&datalith).await;
+
+        sqlx::query("UPDATE `files` SET `count` = 1 WHERE `id` = ?")
+            .bind(miscounted_file_id)
+            .execute(&mut conn)
+            .await
+            .unwrap();
+
+        let dangling_resource_id = Uuid::new_v4();
+
+        sqlx::query(
+            "INSERT INTO `resources` (`id`, `created_at`, `file_type`, `file_name`, `file_id`) \
+             VALUES (?, 0, 'text/plain', 'dangling.txt', ?)",
+        )
+        .bind(dangling_resource_id)
+        .bind(Uuid::new_v4())
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+        // the original file is fine, but the thumbnail does not exist
+        let dangling_image_id = insert_image(&mut conn, fine_file_id, Uuid::new_v4()).await;
+
+        // the orphan file is only referenced by this broken image
+        let orphan_image_id = insert_image(&mut conn, orphan_file_id, Uuid::new_v4()).await;
+
+        // a thumbnail of a nonexistent image
+        let missing_image_id = Uuid::new_v4();
+
+        sqlx::query(
+            "INSERT INTO `image_thumbnails` (`image_id`, `multiplier`, `fallback`, `file_id`) \
+             VALUES (?, 1, 0, ?)",
+        )
+        .bind(missing_image_id)
+        .bind(miscounted_file_id)
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+        conn.close().await.unwrap();
+
+        (dangling_resource_id, dangling_image_id, orphan_image_id, missing_image_id)
+    };
+
+    let mut expected_dangling_resources = vec![missing_resource_id, dangling_resource_id];
+    expected_dangling_resources.sort_unstable();
+
+    let mut expected_dangling_images = vec![dangling_image_id, orphan_image_id, missing_image_id];
+    expected_dangling_images.sort_unstable();
+
+    // the orphan file may have been put directly, so its count is not a problem
+    let expected_count_mismatches = vec![(miscounted_file_id, 1, 2)];
+
+    // only check
+    {
+        let report = datalith.check_consistency(false).await.unwrap();
+
+        assert!(!report.is_consistent());
+        assert!(!report.repaired);
+        assert_eq!(vec![missing_file_id], report.missing_file_data);
+        assert_eq!(expected_dangling_resources, report.dangling_resources);
+        assert_eq!(expected_dangling_images, report.dangling_images);
+        assert_eq!(expected_count_mismatches, report.count_mismatches);
+        // the broken records were inserted without counting the usage
+        assert_eq!(vec![String::new()], report.usage_mismatches);
+
+        // nothing is changed
+        assert_eq!(report, datalith.check_consistency(false).await.unwrap());
+    }
+
+    // repair
+    {
+        let report = datalith.check_consistency(true).await.unwrap();
+
+        assert!(report.repaired);
+        assert_eq!(vec![missing_file_id], report.missing_file_data);
+        assert_eq!(expected_count_mismatches, report.count_mismatches);
+
+        assert!(datalith.check_consistency(false).await.unwrap().is_consistent());
+    }
+
+    assert!(datalith.get_resource_by_id(missing_resource_id).await.unwrap().is_none());
+    assert!(datalith.get_resource_by_id(dangling_resource_id).await.unwrap().is_none());
+    assert!(!datalith.check_file_exist(missing_file_id).await.unwrap());
+    assert!(datalith.check_file_exist(orphan_file_id).await.unwrap());
+    assert!(datalith.check_file_exist(direct_file_id).await.unwrap());
+
+    let resource = datalith.get_resource_by_id(fine_resource_id).await.unwrap().unwrap();
+
+    let mut buffer = Vec::new();
+    resource.file().create_reader().await.unwrap().read_to_end(&mut buffer).await.unwrap();
+    assert_eq!(b"fine", buffer.as_slice());
+
+    drop(resource);
+
+    // the count has been fixed, so the file is deleted with its last reference
+    let miscounted_resource_ids = {
+        let mut conn = connect(&datalith).await;
+
+        let rows: Vec<(Uuid,)> = sqlx::query_as("SELECT `id` FROM `resources` WHERE `file_id` = ?")
+            .bind(miscounted_file_id)
+            .fetch_all(&mut conn)
+            .await
+            .unwrap();
+
+        conn.close().await.unwrap();
+
+        rows.into_iter().map(|(id,)| id).collect::<Vec<Uuid>>()
+    };
+
+    assert_eq!(2, miscounted_resource_ids.len());
+
+    assert!(datalith.delete_resource_by_id(miscounted_resource_ids[0]).await.unwrap());
+    assert!(datalith.check_file_exist(miscounted_file_id).await.unwrap());
+
+    assert!(datalith.delete_resource_by_id(miscounted_resource_ids[1]).await.unwrap());
+    assert!(!datalith.check_file_exist(miscounted_file_id).await.unwrap());
+
+    datalith_close(datalith).await;
+}
+
+#[tokio::test]
+async fn direct_put_and_reference() {
+    let datalith = datalith_init().await;
+
+    let file_id = datalith
+        .put_file_by_buffer(
+            b"shared",
+            Some("shared.txt"),
+            Some((mime::TEXT_PLAIN, FileTypeLevel::Manual)),
+            None,
+        )
+        .await
+        .unwrap()
+        .id();
+
+    let (resource_id, resource_file_id) = put_resource(&datalith, b"shared").await;
+    assert_eq!(file_id, resource_file_id);
+
+    // the count 2 covers the direct put and the resource
+    let report = datalith.check_consistency(true).await.unwrap();
+    assert!(report.is_consistent());
+    assert!(!report.repaired);
+
+    // the data is kept for the direct put after the resource is deleted
+    assert!(datalith.delete_resource_by_id(resource_id).await.unwrap());
+    assert!(datalith.check_file_exist(file_id).await.unwrap());
+
+    assert!(datalith.delete_file_by_id(file_id).await.unwrap());
+    assert!(!datalith.check_file_exist(file_id).await.unwrap());
+
+    datalith_close(datalith).await;
+}
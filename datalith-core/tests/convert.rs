@@ -11,7 +11,7 @@ pub async fn put_image_by_resource() {
     let image_data = IMAGE_DATA.as_ref();
 
     let resource =
-        datalith.put_resource_by_buffer(image_data, Some("image.png"), None).await.unwrap();
+        datalith.put_resource_by_buffer(image_data, Some("image.png"), None, None).await.unwrap();
 
     let image = datalith.put_image_by_resource(&resource, Some(32), None, None).await.unwrap();
     assert_eq!("image", image.image_stem());
@@ -36,7 +36,7 @@ pub async fn convert_resource_to_image() {
     let image_data = IMAGE_DATA.as_ref();
 
     let resource =
-        datalith.put_resource_by_buffer(image_data, Some("image.png"), None).await.unwrap();
+        datalith.put_resource_by_buffer(image_data, Some("image.png"), None, None).await.unwrap();
     let resource_id = resource.id();
 
     let image = datalith.convert_resource_to_image(resource, Some(32), None, None).await.unwrap();
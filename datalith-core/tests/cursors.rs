@@ -0,0 +1,185 @@
+mod global;
+
+use std::time::Duration;
+
+use datalith_core::{
+    Datalith, DatalithCursor, DatalithCursorOptions, DatalithFileSortKey, DatalithResourceFilter,
+    DatalithResourceSortKey, FileTypeLevel, mime, uuid::Uuid,
+};
+use global::*;
+use tokio::time;
+
+async fn put_text(datalith: &Datalith, text: &str) -> Uuid {
+    datalith
+        .put_resource_by_buffer(
+            text.as_bytes(),
+            Some("plain.txt"),
+            Some((mime::TEXT_PLAIN, FileTypeLevel::Manual)),
+            None,
+        )
+        .await
+        .unwrap()
+        .id()
+}
+
+async fn list_all(
+    datalith: &Datalith,
+    mut options: DatalithCursorOptions<DatalithResourceSortKey>,
+) -> Vec<Uuid> {
+    let filter = DatalithResourceFilter::default();
+
+    let mut ids = Vec::new();
+
+    loop {
+        let page = datalith.list_resource_ids_by_cursor(&filter, &options).await.unwrap();
+
+        assert!(page.ids.len() <= options.limit);
+
+        ids.extend(page.ids);
+
+        match page.next_cursor {
+            Some(cursor) => {
+                // the cursor survives a round trip through its string form
+                options.cursor = Some(DatalithCursor::decode(&cursor.to_string()).unwrap());
+            },
+            None => break,
+        }
+    }
+
+    ids
+}
+
+#[tokio::test]
+async fn iterate_resources() {
+    let datalith = datalith_init().await;
+
+    let mut ids = Vec::with_capacity(5);
+
+    for i in 0..5 {
+        ids.push(put_text(&datalith, &"x".repeat(i % 2 + 1)).await);
+
+        time::sleep(Duration::from_millis(2)).await;
+    }
+
+    // the newest first by default
+    let mut expected = ids.clone();
+    expected.reverse();
+
+    assert_eq!(
+        expected,
+        list_all(&datalith, DatalithCursorOptions {
+            limit: 2,
+            ..DatalithCursorOptions::default()
+        })
+        .await
+    );
+
+    // ties are broken by IDs
+    let mut expected = ids.clone();
+    expected.sort_by_key(|id| (ids.iter().position(|e| e == id).unwrap() % 2, *id));
+
+    assert_eq!(
+        expected,
+        list_all(&datalith, DatalithCursorOptions {
+            sort_key: DatalithResourceSortKey::FileSize,
+            descending: false,
+            limit: 2,
+            ..DatalithCursorOptions::default()
+        })
+        .await
+    );
+
+    // counting
+    let page = datalith
+        .list_resource_ids_by_cursor(&DatalithResourceFilter::default(), &DatalithCursorOptions {
+            limit: 3,
+            count_total: true,
+            ..DatalithCursorOptions::default()
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(3, page.ids.len());
+    assert_eq!(Some(5), page.total_items);
+    assert!(page.next_cursor.is_some());
+
+    datalith_close(datalith).await;
+}
+
+#[tokio::test]
+async fn stable_iteration() {
+    let datalith = datalith_init().await;
+
+    let mut ids = Vec::with_capacity(4);
+
+    for i in 0..4 {
+        ids.push(put_text(&datalith, &i.to_string()).await);
+
+        time::sleep(Duration::from_millis(2)).await;
+    }
+
+    let filter = DatalithResourceFilter::default();
+
+    let mut options = DatalithCursorOptions {
+        limit: 2,
+        ..DatalithCursorOptions::default()
+    };
+
+    let page = datalith.list_resource_ids_by_cursor(&filter, &options).await.unwrap();
+    assert_eq!(vec![ids[3], ids[2]], page.ids);
+    assert_eq!(None, page.total_items);
+
+    // newer resources do not shift the next page, and deleted ones are simply skipped
+    put_text(&datalith, "new").await;
+    assert!(datalith.delete_resource_by_id(ids[2]).await.unwrap());
+
+    options.cursor = page.next_cursor;
+
+    // the key of the cursor is kept
+    options.sort_key = DatalithResourceSortKey::FileName;
+
+    let page = datalith.list_resource_ids_by_cursor(&filter, &options).await.unwrap();
+    assert_eq!(vec![ids[1], ids[0]], page.ids);
+    assert!(page.next_cursor.is_none());
+
+    datalith_close(datalith).await;
+}
+
+#[tokio::test]
+async fn iterate_files() {
+    let datalith = datalith_init().await;
+
+    for i in 0..3 {
+        datalith
+            .put_file_by_buffer(i.to_string().as_bytes(), Some("plain.txt"), None, None)
+            .await
+            .unwrap();
+    }
+
+    let mut options = DatalithCursorOptions {
+        sort_key: DatalithFileSortKey::FileName,
+        descending: false,
+        limit: 2,
+        count_total: true,
+        ..DatalithCursorOptions::default()
+    };
+
+    let page = datalith.list_file_ids_by_cursor(&options).await.unwrap();
+    assert_eq!(2, page.ids.len());
+    assert_eq!(Some(3), page.total_items);
+
+    options.cursor = page.next_cursor;
+
+    let page = datalith.list_file_ids_by_cursor(&options).await.unwrap();
+    assert_eq!(1, page.ids.len());
+    assert!(page.next_cursor.is_none());
+
+    datalith_close(datalith).await;
+}
+
+#[test]
+fn decode_cursor() {
+    assert!(DatalithCursor::<DatalithResourceSortKey>::decode("").is_none());
+    assert!(DatalithCursor::<DatalithResourceSortKey>::decode("zz").is_none());
+    assert!(DatalithCursor::<DatalithResourceSortKey>::decode("00112233").is_none());
+}
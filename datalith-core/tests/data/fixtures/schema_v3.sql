@@ -0,0 +1,135 @@
+-- A database created by version 3 of the schema. It is used to test the migrations.
+
+-- DB Information Table
+CREATE TABLE `sys_db_information` (
+    `key`   TEXT PRIMARY KEY NOT NULL,
+    `value` TEXT
+);
+
+INSERT INTO `sys_db_information` VALUES ('version', '3'), ('create_time', '2024-01-01T00:00:00+00:00');
+
+-- File Table
+CREATE TABLE `files` (
+    -- UUID (128-bit)
+    `id`          BLOB    NOT NULL PRIMARY KEY,
+    -- hashed by SHA-256
+    `hash`        BLOB    NOT NULL UNIQUE,
+    -- UNIX timestamp (in milliseconds)
+    `created_at`  INTEGER NOT NULL,
+    -- in bytes
+    `file_size`   INTEGER NOT NULL,
+    -- MIME type
+    `file_type`   TEXT    NOT NULL,
+    -- the file name when it was first created
+    `file_name`   TEXT    NOT NULL,
+    -- the number of times this file was created
+    `count`       INTEGER NOT NULL DEFAULT 1,
+    -- UNIX timestamp (in milliseconds). If this exists, the file is temporary
+    `expired_at`  INTEGER
+);
+
+CREATE INDEX `files_created_at` ON `files` (`created_at`);
+CREATE INDEX `files_expired_at` ON `files` (`expired_at`);
+
+-- Resource Table
+CREATE TABLE `resources` (
+    -- UUID (128-bit)
+    `id`           BLOB    NOT NULL PRIMARY KEY,
+    -- UNIX timestamp (in milliseconds)
+    `created_at`   INTEGER NOT NULL,
+    -- MIME type
+    `file_type`    TEXT    NOT NULL,
+    -- the file name when it was first created
+    `file_name`    TEXT    NOT NULL,
+    -- UUID (128-bit)
+    `file_id`      BLOB    NOT NULL,
+    -- UNIX timestamp (in milliseconds). If this exists, the resource is temporary
+    `expired_at`  INTEGER,
+
+    FOREIGN KEY (`file_id`) REFERENCES `files` (`id`)
+);
+
+CREATE INDEX `resources_created_at` ON `resources` (`created_at`);
+
+-- Image Table
+CREATE TABLE `images` (
+    -- UUID (128-bit)
+    `id`                 BLOB    NOT NULL PRIMARY KEY,
+    -- UNIX timestamp (in milliseconds)
+    `created_at`         INTEGER NOT NULL,
+    -- the file stem of this image
+    `image_stem`         TEXT    NOT NULL,
+    -- the width of 1x image (in pixels)
+    `image_width`        INTEGER NOT NULL,
+    -- the height of 1x image (in pixels)
+    `image_height`       INTEGER NOT NULL,
+    -- UUID (128-bit)
+    `original_file_id`   BLOB,
+    -- boolean
+    `has_alpha_channel`  INTEGER NOT NULL,
+
+    FOREIGN KEY (`original_file_id`) REFERENCES `files` (`id`)
+);
+
+CREATE INDEX `images_created_at` ON `images` (`created_at`);
+
+-- Image Thumbnail Table
+CREATE TABLE `image_thumbnails` (
+    -- UUID (128-bit)
+    `image_id`     BLOB    NOT NULL,
+    `multiplier`   INTEGER NOT NULL,
+    -- boolean
+    `fallback`     INTEGER NOT NULL,
+    -- UUID (128-bit)
+    `file_id`      BLOB    NOT NULL,
+
+    PRIMARY KEY (`image_id`, `multiplier`, `fallback`),
+    FOREIGN KEY (`image_id`) REFERENCES `images` (`id`),
+    FOREIGN KEY (`file_id`) REFERENCES `files` (`id`)
+);
+
+-- Upload Session Table
+CREATE TABLE `upload_sessions` (
+    -- UUID (128-bit)
+    `id`               BLOB    NOT NULL PRIMARY KEY,
+    -- UNIX timestamp (in milliseconds)
+    `created_at`       INTEGER NOT NULL,
+    -- the total length of the upload (in bytes). If this does not exist, the length is deferred
+    `upload_length`    INTEGER,
+    -- the number of bytes which have been received
+    `upload_offset`    INTEGER NOT NULL DEFAULT 0,
+    -- MIME type
+    `file_type`        TEXT,
+    -- 0: ExactMatch, 1: Manual, 2: Fallback
+    `file_type_level`  INTEGER,
+    -- the file name provided when the session was created
+    `file_name`        TEXT,
+    -- UNIX timestamp (in milliseconds). It is extended after each append
+    `expired_at`       INTEGER NOT NULL
+);
+
+CREATE INDEX `upload_sessions_expired_at` ON `upload_sessions` (`expired_at`);
+
+-- API Key Table
+CREATE TABLE `api_keys` (
+    -- UUID (128-bit)
+    `id`          BLOB    NOT NULL PRIMARY KEY,
+    -- UNIX timestamp (in milliseconds)
+    `created_at`  INTEGER NOT NULL,
+    -- a name for identifying the owner of this key
+    `name`        TEXT    NOT NULL,
+    -- hashed by SHA-256
+    `key_hash`    BLOB    NOT NULL UNIQUE,
+    -- bit flags. 1: read, 2: write, 4: delete
+    `scopes`      INTEGER NOT NULL
+);
+
+-- Data
+INSERT INTO `files` (`id`, `hash`, `created_at`, `file_size`, `file_type`, `file_name`, `count`, `expired_at`)
+    VALUES (X'6a1c5e0b8c2f4d7e9a3b1f0e2d4c6b8a', X'c0535e4be2b79ffd93291305436bf889314e4a3faec05ecffcbb7df31ad9e51a', 1704067200000, 12, 'text/plain', 'plain.txt', 1, NULL);
+
+INSERT INTO `resources` (`id`, `created_at`, `file_type`, `file_name`, `file_id`, `expired_at`)
+    VALUES (X'1b2c3d4e5f60718293a4b5c6d7e8f901', 1704067200000, 'text/plain', 'plain.txt', X'6a1c5e0b8c2f4d7e9a3b1f0e2d4c6b8a', NULL);
+
+INSERT INTO `api_keys` (`id`, `created_at`, `name`, `key_hash`, `scopes`)
+    VALUES (X'0f1e2d3c4b5a69788796a5b4c3d2e1f0', 1704067200000, 'fixture', X'a77b2dd79ac55df1eb1c64d4bfab8ce773165bda1003e66419b9d69a03341a1b', 3);
@@ -0,0 +1,127 @@
+mod global;
+
+use datalith_core::{
+    DatalithLowWaterMark, DatalithUploadSessionError, DatalithWriteError,
+    PATH_TEMPORARY_FILE_DIRECTORY,
+};
+use global::*;
+
+#[test]
+fn low_water_mark_to_bytes() {
+    assert_eq!(1024, DatalithLowWaterMark::Bytes(1024).to_bytes(4096));
+    assert_eq!(410, DatalithLowWaterMark::Percent(10.0).to_bytes(4096));
+    assert_eq!(4096, DatalithLowWaterMark::Percent(150.0).to_bytes(4096));
+}
+
+#[tokio::test]
+async fn disk_space() {
+    let datalith = datalith_init().await;
+
+    let disk_space = datalith.get_disk_space().unwrap();
+    assert!(disk_space.total_space > 0);
+    assert!(disk_space.available_space <= disk_space.total_space);
+    assert_eq!(None, disk_space.low_water_mark);
+
+    datalith.set_low_water_mark(Some(DatalithLowWaterMark::Percent(50.0)));
+    assert_eq!(Some(DatalithLowWaterMark::Percent(50.0)), datalith.get_low_water_mark());
+
+    let disk_space = datalith.get_disk_space().unwrap();
+    assert_eq!(Some(disk_space.total_space.div_ceil(2)), disk_space.low_water_mark);
+
+    datalith_close(datalith).await;
+}
+
+#[tokio::test]
+async fn low_water_mark() {
+    let datalith = datalith_init().await;
+
+    let existing_file_id = datalith
+        .put_file_by_buffer(b"Hello world!", Some("plain.txt"), None, None)
+        .await
+        .unwrap()
+        .id();
+
+    // no volume can keep this much free space
+    datalith.set_low_water_mark(Some(DatalithLowWaterMark::Bytes(u64::MAX)));
+
+    assert!(matches!(
+        datalith.put_file_by_buffer(IMAGE_DATA.as_ref(), Some("image.png"), None, None).await,
+        Err(DatalithWriteError::InsufficientDiskSpace { .. })
+    ));
+    assert!(matches!(
+        datalith.put_file_by_path(IMAGE_PATH, None::<&str>, None, None).await,
+        Err(DatalithWriteError::InsufficientDiskSpace { .. })
+    ));
+    assert!(matches!(
+        datalith
+            .put_file_by_reader(
+                IMAGE_DATA.as_ref(),
+                Some("image.png"),
+                None,
+                Some(IMAGE_SIZE),
+                None
+            )
+            .await,
+        Err(DatalithWriteError::InsufficientDiskSpace { .. })
+    ));
+    assert!(matches!(
+        datalith
+            .put_file_by_reader_temporarily(
+                IMAGE_DATA.as_ref(),
+                Some("image.png"),
+                None,
+                None,
+                None
+            )
+            .await,
+        Err(DatalithWriteError::InsufficientDiskSpace { .. })
+    ));
+    assert!(matches!(
+        datalith.create_upload_session(Some(IMAGE_SIZE), Some("image.png"), None).await,
+        Err(DatalithWriteError::InsufficientDiskSpace { .. })
+    ));
+
+    // no half-written temporary files are left
+    let mut entries =
+        tokio::fs::read_dir(datalith.get_environment().join(PATH_TEMPORARY_FILE_DIRECTORY))
+            .await
+            .unwrap();
+
+    while let Some(entry) = entries.next_entry().await.unwrap() {
+        assert!(entry.file_type().await.unwrap().is_dir(), "{:?}", entry.path());
+    }
+
+    // the data which has been stored does not need more space
+    assert_eq!(
+        existing_file_id,
+        datalith
+            .put_file_by_buffer(b"Hello world!", Some("plain.txt"), None, None)
+            .await
+            .unwrap()
+            .id()
+    );
+
+    datalith.set_low_water_mark(None);
+
+    let session = datalith.create_upload_session(None, Some("image.png"), None).await.unwrap();
+
+    datalith.set_low_water_mark(Some(DatalithLowWaterMark::Percent(100.0)));
+
+    // the data of a session is refused, and the session is kept
+    assert!(matches!(
+        datalith.append_upload_session(session.id(), 0, IMAGE_DATA.as_ref()).await,
+        Err(DatalithUploadSessionError::DatalithWriteError(
+            DatalithWriteError::InsufficientDiskSpace { .. }
+        ))
+    ));
+    assert_eq!(
+        0,
+        datalith.get_upload_session_by_id(session.id()).await.unwrap().unwrap().upload_offset()
+    );
+
+    datalith.set_low_water_mark(None);
+
+    datalith.put_file_by_buffer(IMAGE_DATA.as_ref(), Some("image.png"), None, None).await.unwrap();
+
+    datalith_close(datalith).await;
+}
@@ -0,0 +1,165 @@
+#![cfg(feature = "encryption")]
+
+mod global;
+
+use std::{
+    io::SeekFrom,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use datalith_core::{
+    Datalith, DatalithEncryptionKey, LocalStorageBackend, PATH_FILE_DIRECTORY, Uuid,
+};
+use global::*;
+use tokio::{
+    fs,
+    io::{AsyncReadExt, AsyncSeekExt},
+};
+
+const KEY: [u8; 32] = [7; 32];
+
+#[inline]
+fn get_environment_path() -> PathBuf {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+
+    Path::new("tests").join("db").join(format!("encryption_{}", timestamp.as_micros()))
+}
+
+#[inline]
+async fn read_stored_file(datalith: &Datalith, id: Uuid) -> Vec<u8> {
+    let file_path = LocalStorageBackend::new(datalith.get_environment().join(PATH_FILE_DIRECTORY))
+        .get_file_path(id);
+
+    fs::read(file_path).await.unwrap()
+}
+
+#[tokio::test]
+async fn encryption() {
+    let datalith = Datalith::builder(get_environment_path())
+        .encryption_key(DatalithEncryptionKey::new(KEY))
+        .build()
+        .await
+        .unwrap();
+
+    // larger than a few chunks and not aligned to the chunk size
+    let data = (0..300_000u32).map(|i| (i * 31 % 251) as u8).collect::<Vec<u8>>();
+
+    {
+        let file = datalith.put_file_by_buffer(&data, Some("data.bin"), None, None).await.unwrap();
+        let file_id = file.id();
+
+        assert_eq!(data.len() as u64, file.file_size());
+
+        let stored_data = read_stored_file(&datalith, file_id).await;
+        assert!(stored_data.starts_with(b"DLE1"));
+        assert!(!stored_data.windows(1024).any(|w| w == &data[..1024]));
+
+        assert_eq!(
+            Some(data.len() as u64),
+            datalith.get_storage_backend().stat(file_id).await.unwrap().map(|stat| stat.size)
+        );
+
+        let mut reader = file.create_reader().await.unwrap();
+        let mut buffer = Vec::new();
+        reader.read_to_end(&mut buffer).await.unwrap();
+        assert_eq!(data, buffer);
+
+        // seek across the chunk boundaries
+        let mut buffer = vec![0; 10_000];
+        reader.seek(SeekFrom::Start(60_000)).await.unwrap();
+        reader.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(&data[60_000..70_000], buffer);
+
+        reader.seek(SeekFrom::End(-5_000)).await.unwrap();
+        let mut buffer = Vec::new();
+        reader.read_to_end(&mut buffer).await.unwrap();
+        assert_eq!(&data[data.len() - 5_000..], buffer);
+
+        drop(reader);
+        drop(file);
+
+        // the hash for deduplication is over the plaintext
+        let file = datalith
+            .put_file_by_reader(
+                data.as_slice(),
+                Some("data.bin"),
+                None,
+                Some(data.len() as u64),
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(file_id, file.id());
+        drop(file);
+
+        let file = datalith.put_file_by_buffer([], Some("empty.bin"), None, None).await.unwrap();
+        let mut reader = file.create_reader().await.unwrap();
+        let mut buffer = Vec::new();
+        reader.read_to_end(&mut buffer).await.unwrap();
+        assert!(buffer.is_empty());
+    }
+
+    datalith_close(datalith).await;
+}
+
+#[tokio::test]
+async fn encrypt_existing_files() {
+    let environment_path = get_environment_path();
+
+    let image = IMAGE_DATA.as_ref();
+
+    let file_id = {
+        let datalith = Datalith::new(environment_path.as_path()).await.unwrap();
+
+        let file_id =
+            datalith.put_file_by_buffer(image, Some("image.png"), None, None).await.unwrap().id();
+
+        assert_eq!(image, read_stored_file(&datalith, file_id).await);
+
+        datalith.close().await;
+
+        file_id
+    };
+
+    let datalith = Datalith::builder(environment_path.as_path())
+        .encryption_key(DatalithEncryptionKey::new(KEY))
+        .build()
+        .await
+        .unwrap();
+
+    {
+        let file = datalith.get_file_by_id(file_id).await.unwrap().unwrap();
+        assert!(file.create_reader().await.is_err());
+        drop(file);
+
+        assert_eq!(1, datalith.encrypt_existing_files().await.unwrap());
+        assert_eq!(0, datalith.encrypt_existing_files().await.unwrap());
+
+        assert_ne!(image, read_stored_file(&datalith, file_id).await);
+
+        let file = datalith.get_file_by_id(file_id).await.unwrap().unwrap();
+        let mut reader = file.create_reader().await.unwrap();
+        let mut buffer = Vec::new();
+        reader.read_to_end(&mut buffer).await.unwrap();
+        assert_eq!(image, buffer);
+    }
+
+    datalith.close().await;
+
+    // a wrong key cannot decrypt the files
+    let datalith = Datalith::builder(environment_path.as_path())
+        .encryption_key(DatalithEncryptionKey::from_hex("01".repeat(32)).unwrap())
+        .build()
+        .await
+        .unwrap();
+
+    {
+        let file = datalith.get_file_by_id(file_id).await.unwrap().unwrap();
+        let mut reader = file.create_reader().await.unwrap();
+        let mut buffer = Vec::new();
+        assert!(reader.read_to_end(&mut buffer).await.is_err());
+    }
+
+    datalith_close(datalith).await;
+}
@@ -0,0 +1,144 @@
+mod global;
+
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use datalith_core::{
+    Datalith, DatalithCreateError, LocalStorageBackend, PATH_FILE_DIRECTORY, Uuid,
+};
+use global::*;
+use tokio::{fs, io::AsyncReadExt};
+
+#[inline]
+fn get_environment_path(name: &str) -> PathBuf {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+
+    Path::new("tests").join("db").join(format!("{name}_{}", timestamp.as_micros()))
+}
+
+#[test]
+fn file_path() {
+    let id = Uuid::from_str("0abcdef0-1234-5678-9abc-def012345678").unwrap();
+
+    let storage = LocalStorageBackend::new("files");
+    assert_eq!(
+        Path::new("files").join("0a").join("bc").join("0abcdef0123456789abcdef012345678"),
+        storage.get_file_path(id)
+    );
+
+    let storage = LocalStorageBackend::with_depth("files", 1);
+    assert_eq!(
+        Path::new("files").join("0a").join("0abcdef0123456789abcdef012345678"),
+        storage.get_file_path(id)
+    );
+
+    // the flat layout does not pad the name
+    let storage = LocalStorageBackend::with_depth("files", 0);
+    assert_eq!(
+        Path::new("files").join("abcdef0123456789abcdef012345678"),
+        storage.get_file_path(id)
+    );
+}
+
+#[tokio::test]
+async fn custom_depth() {
+    let environment = get_environment_path("custom_depth");
+
+    let datalith =
+        Datalith::builder(environment.as_path()).file_directory_depth(3).build().await.unwrap();
+
+    let file = datalith
+        .put_file_by_buffer(IMAGE_DATA.as_ref(), Some("image.png"), None, None)
+        .await
+        .unwrap();
+    let file_id = file.id();
+    drop(file);
+
+    let file_path = LocalStorageBackend::with_depth(environment.join(PATH_FILE_DIRECTORY), 3)
+        .get_file_path(file_id);
+    assert_eq!(IMAGE_DATA.as_ref(), fs::read(file_path.as_path()).await.unwrap());
+
+    datalith.close().await;
+
+    // the depth cannot be changed after the environment is created
+    match Datalith::builder(environment.as_path()).file_directory_depth(1).build().await {
+        Err(DatalithCreateError::FileDirectoryDepthMismatch {
+            requested_depth,
+            current_depth,
+        }) => {
+            assert_eq!(1, requested_depth);
+            assert_eq!(3, current_depth);
+        },
+        result => panic!("unexpected result: {result:?}"),
+    }
+
+    let datalith =
+        Datalith::builder(environment.as_path()).file_directory_depth(3).build().await.unwrap();
+
+    let file = datalith.get_file_by_id(file_id).await.unwrap().unwrap();
+
+    let mut buffer = Vec::new();
+    file.create_reader().await.unwrap().read_to_end(&mut buffer).await.unwrap();
+    assert_eq!(IMAGE_DATA.as_ref(), buffer);
+
+    drop(file);
+
+    assert!(fs::try_exists(file_path.as_path()).await.unwrap());
+    assert_eq!(0, datalith.clear_untracked_files().await.unwrap());
+
+    datalith_close(datalith).await;
+}
+
+#[tokio::test]
+async fn relayout() {
+    let directory = get_environment_path("relayout");
+
+    let ids = [
+        Uuid::from_str("0abcdef0-1234-5678-9abc-def012345678").unwrap(),
+        Uuid::from_str("ffffffff-ffff-ffff-ffff-ffffffffffff").unwrap(),
+        Uuid::from_str("00000000-0000-0000-0000-000000000001").unwrap(),
+    ];
+
+    // store files in the flat layout
+    let flat_storage = LocalStorageBackend::with_depth(directory.as_path(), 0);
+
+    fs::create_dir_all(directory.as_path()).await.unwrap();
+
+    for id in ids {
+        fs::write(flat_storage.get_file_path(id), id.to_string()).await.unwrap();
+    }
+
+    fs::write(directory.join("hello.txt"), b"Hello world!").await.unwrap();
+
+    let storage = LocalStorageBackend::new(directory.as_path());
+
+    assert_eq!(ids.len(), storage.relayout().await.unwrap());
+
+    for id in ids {
+        assert_eq!(id.to_string().as_bytes(), fs::read(storage.get_file_path(id)).await.unwrap());
+    }
+
+    // files which are not stored files are left untouched
+    assert!(fs::try_exists(directory.join("hello.txt")).await.unwrap());
+
+    // nothing to move now
+    assert_eq!(0, storage.relayout().await.unwrap());
+
+    // move back, and the nested directories are removed
+    assert_eq!(ids.len(), flat_storage.relayout().await.unwrap());
+
+    for id in ids {
+        assert!(fs::try_exists(flat_storage.get_file_path(id)).await.unwrap());
+    }
+
+    let mut read_dir = fs::read_dir(directory.as_path()).await.unwrap();
+
+    while let Some(entry) = read_dir.next_entry().await.unwrap() {
+        assert!(entry.file_type().await.unwrap().is_file());
+    }
+
+    fs::remove_dir_all(directory).await.unwrap();
+}
@@ -0,0 +1,80 @@
+mod global;
+
+use datalith_core::{FileTypeLevel, mime, parse_sha256_hex};
+use global::*;
+use tokio::io::AsyncReadExt;
+
+const HASH: &str = "c0535e4be2b79ffd93291305436bf889314e4a3faec05ecffcbb7df31ad9e51a";
+
+#[tokio::test]
+async fn hash_lookup() {
+    let datalith = datalith_init().await;
+
+    let hash = parse_sha256_hex(HASH).unwrap();
+    assert_eq!(Some(hash), parse_sha256_hex(&HASH.to_uppercase()));
+    assert!(parse_sha256_hex(&HASH[2..]).is_none());
+    assert!(parse_sha256_hex(&HASH.replace('c', "g")).is_none());
+
+    assert!(!datalith.check_hash_exist(&hash).await.unwrap());
+    assert!(datalith.get_resource_by_hash(&hash).await.unwrap().is_none());
+    assert!(datalith.put_resource_by_hash(&hash, Some("copy.txt"), None).await.unwrap().is_none());
+
+    // temporary resources can be reused, but are not retrieved by the hash
+    let temporary_id = datalith
+        .put_resource_by_buffer_temporarily(
+            b"Hello world!",
+            Some("plain.txt"),
+            Some((mime::TEXT_PLAIN, FileTypeLevel::Manual)),
+            None,
+        )
+        .await
+        .unwrap()
+        .id();
+
+    assert!(datalith.check_hash_exist(&hash).await.unwrap());
+    assert!(datalith.get_resource_by_hash(&hash).await.unwrap().is_none());
+    assert!(datalith.check_resource_exist(temporary_id).await.unwrap());
+
+    let (id, file_id) = {
+        let resource =
+            datalith.put_resource_by_hash(&hash, Some("copy.txt"), None).await.unwrap().unwrap();
+
+        assert_ne!(temporary_id, resource.id());
+        assert_eq!("copy.txt", resource.file_name());
+        assert_eq!(&mime::TEXT_PLAIN, resource.file_type());
+        assert!(!resource.is_temporary());
+        assert!(!resource.file().is_new());
+
+        (resource.id(), resource.file().id())
+    };
+
+    {
+        let resource = datalith.get_resource_by_hash(&hash).await.unwrap().unwrap();
+
+        assert_eq!(id, resource.id());
+        assert_eq!(file_id, resource.file().id());
+        assert_eq!(&hash, resource.file().hash());
+    }
+
+    // the data is kept while any of the resources is
+    assert!(datalith.delete_resource_by_id(temporary_id).await.unwrap());
+
+    {
+        let resource = datalith.get_resource_by_id(id).await.unwrap().unwrap();
+
+        let mut buffer = Vec::new();
+        resource.file().create_reader().await.unwrap().read_to_end(&mut buffer).await.unwrap();
+        assert_eq!(b"Hello world!", buffer.as_slice());
+    }
+
+    // other namespaces cannot see the data
+    let shop = datalith.with_namespace("shop").unwrap();
+    assert!(!shop.check_hash_exist(&hash).await.unwrap());
+    assert!(shop.get_resource_by_hash(&hash).await.unwrap().is_none());
+    assert!(shop.put_resource_by_hash(&hash, None::<&str>, None).await.unwrap().is_none());
+
+    assert!(datalith.delete_resource_by_id(id).await.unwrap());
+    assert!(!datalith.check_hash_exist(&hash).await.unwrap());
+
+    datalith_close(datalith).await;
+}
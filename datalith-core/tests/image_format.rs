@@ -0,0 +1,99 @@
+#![cfg(feature = "image-convert")]
+
+mod global;
+
+use datalith_core::{DatalithImageFormat, negotiate_image_format};
+use global::*;
+
+const FORMATS: [DatalithImageFormat; 2] = [DatalithImageFormat::WebP, DatalithImageFormat::Jpeg];
+
+#[test]
+fn negotiate_formats() {
+    // everything is acceptable without the header, and the first format is preferred
+    assert_eq!(Some(DatalithImageFormat::WebP), negotiate_image_format(None, &FORMATS));
+    assert_eq!(Some(DatalithImageFormat::WebP), negotiate_image_format(Some("*/*"), &FORMATS));
+
+    // a browser which supports WebP
+    assert_eq!(
+        Some(DatalithImageFormat::WebP),
+        negotiate_image_format(
+            Some("image/avif,image/webp,image/apng,image/svg+xml,image/*,*/*;q=0.8"),
+            &FORMATS
+        )
+    );
+
+    // an old browser
+    assert_eq!(
+        Some(DatalithImageFormat::Jpeg),
+        negotiate_image_format(Some("image/png,image/jpeg;q=0.9,*/*;q=0.5"), &FORMATS)
+    );
+
+    // explicitly listed formats are preferred to the ones matched by wildcards
+    assert_eq!(
+        Some(DatalithImageFormat::Jpeg),
+        negotiate_image_format(Some("image/jpeg,image/*"), &FORMATS)
+    );
+
+    // IE 11, which does not support WebP
+    assert_eq!(
+        Some(DatalithImageFormat::Jpeg),
+        negotiate_image_format(Some("image/png, image/svg+xml, image/*;q=0.8, */*;q=0.5"), &[
+            DatalithImageFormat::Jpeg,
+            DatalithImageFormat::WebP
+        ])
+    );
+
+    // the most specific media range is used
+    assert_eq!(
+        Some(DatalithImageFormat::Jpeg),
+        negotiate_image_format(Some("image/*, image/webp;q=0"), &FORMATS)
+    );
+    assert_eq!(
+        Some(DatalithImageFormat::Jpeg),
+        negotiate_image_format(Some("image/webp;q=0.4, IMAGE/JPEG;q=0.6"), &FORMATS)
+    );
+
+    // nothing is acceptable
+    assert_eq!(None, negotiate_image_format(Some("text/html"), &FORMATS));
+    assert_eq!(None, negotiate_image_format(Some("image/*;q=0"), &FORMATS));
+
+    assert_eq!(Some(DatalithImageFormat::Jpeg), DatalithImageFormat::from_name("JPEG"));
+    assert_eq!(None, DatalithImageFormat::from_name("gif"));
+}
+
+#[tokio::test]
+async fn negotiate_thumbnail_format() {
+    let datalith = datalith_init().await;
+
+    // the test image has an alpha channel
+    let image = datalith
+        .put_image_by_buffer(IMAGE_DATA.to_vec(), Some("image.png"), Some(32), None, None, false)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        DatalithImageFormat::WebP,
+        image.negotiate_thumbnail_format(Some("image/webp,*/*;q=0.8"))
+    );
+
+    // WebP is only chosen if it is listed explicitly
+    assert_eq!(image.fallback_thumbnail_format(), image.negotiate_thumbnail_format(None));
+    assert_eq!(image.fallback_thumbnail_format(), image.negotiate_thumbnail_format(Some("*/*")));
+    assert_eq!(
+        image.fallback_thumbnail_format(),
+        image
+            .negotiate_thumbnail_format(Some("image/png, image/svg+xml, image/*;q=0.8, */*;q=0.5"))
+    );
+    assert_eq!(
+        image.fallback_thumbnail_format(),
+        image.negotiate_thumbnail_format(Some("image/png,image/jpeg,*/*;q=0.5"))
+    );
+
+    // the fallback format is supported everywhere
+    assert_eq!(
+        image.fallback_thumbnail_format(),
+        image.negotiate_thumbnail_format(Some("text/html"))
+    );
+
+    datalith_close(datalith).await;
+}
@@ -0,0 +1,308 @@
+mod global;
+
+use std::time::Duration;
+
+use datalith_core::{
+    Datalith, DatalithNameMatch, DatalithResourceFilter, DatalithResourceOrderBy, FileTypeLevel,
+    OrderMethod, PaginationOptions, mime, uuid::Uuid,
+};
+use global::*;
+use tokio::time;
+
+async fn list(
+    datalith: &Datalith,
+    filter: DatalithResourceFilter,
+    order_by: DatalithResourceOrderBy,
+) -> Vec<Uuid> {
+    let (ids, pagination) = datalith
+        .list_resource_ids(&filter, PaginationOptions::default().order_by(order_by))
+        .await
+        .unwrap();
+
+    assert_eq!(ids.len(), pagination.get_total_items());
+
+    ids
+}
+
+#[tokio::test]
+async fn filter_resources() {
+    let datalith = datalith_init().await;
+
+    let text = datalith
+        .put_resource_by_buffer(
+            b"Hello world!",
+            Some("hello.txt"),
+            Some((mime::TEXT_PLAIN, FileTypeLevel::Manual)),
+            None,
+        )
+        .await
+        .unwrap();
+    let text_id = text.id();
+    let text_created_at = text.created_at().to_utc();
+    drop(text);
+
+    time::sleep(Duration::from_millis(10)).await;
+
+    let image = datalith
+        .put_resource_by_buffer(
+            IMAGE_DATA.as_ref(),
+            Some("image.png"),
+            Some((mime::IMAGE_PNG, FileTypeLevel::Manual)),
+            None,
+        )
+        .await
+        .unwrap();
+    let image_id = image.id();
+    let image_created_at = image.created_at().to_utc();
+    drop(image);
+
+    time::sleep(Duration::from_millis(10)).await;
+
+    let temporary_id = datalith
+        .put_resource_by_buffer_temporarily(
+            b"Hi!",
+            Some("report-2024.csv"),
+            Some((mime::TEXT_CSV, FileTypeLevel::Manual)),
+            None,
+        )
+        .await
+        .unwrap()
+        .id();
+
+    let order_by = DatalithResourceOrderBy {
+        id: 102.into(),
+        created_at: 101.into(),
+        ..DatalithResourceOrderBy::default()
+    };
+
+    let filter = |f: fn(&mut DatalithResourceFilter)| {
+        let mut filter = DatalithResourceFilter::default();
+
+        f(&mut filter);
+
+        filter
+    };
+
+    // MIME types
+    assert_eq!(
+        vec![image_id],
+        list(&datalith, filter(|f| f.file_type = Some("image/png".into())), order_by.clone()).await
+    );
+    assert_eq!(
+        vec![text_id, temporary_id],
+        list(&datalith, filter(|f| f.file_type = Some("text/*".into())), order_by.clone()).await
+    );
+    assert!(
+        list(&datalith, filter(|f| f.file_type = Some("text/".into())), order_by.clone())
+            .await
+            .is_empty()
+    );
+
+    // creation times
+    assert_eq!(
+        vec![image_id, temporary_id],
+        list(
+            &datalith,
+            DatalithResourceFilter {
+                created_since: Some(image_created_at),
+                ..DatalithResourceFilter::default()
+            },
+            order_by.clone()
+        )
+        .await
+    );
+    assert_eq!(
+        vec![text_id],
+        list(
+            &datalith,
+            DatalithResourceFilter {
+                created_since: Some(text_created_at),
+                created_before: Some(image_created_at),
+                ..DatalithResourceFilter::default()
+            },
+            order_by.clone()
+        )
+        .await
+    );
+
+    // file names
+    assert_eq!(
+        vec![text_id],
+        list(
+            &datalith,
+            filter(|f| f.file_name = Some(DatalithNameMatch::Contains("llo".into()))),
+            order_by.clone()
+        )
+        .await
+    );
+    assert_eq!(
+        vec![temporary_id],
+        list(
+            &datalith,
+            filter(|f| f.file_name = Some(DatalithNameMatch::Glob("report-20[0-9][0-9].*".into()))),
+            order_by.clone()
+        )
+        .await
+    );
+    assert!(
+        list(
+            &datalith,
+            filter(|f| f.file_name = Some(DatalithNameMatch::Glob("*.PNG".into()))),
+            order_by.clone()
+        )
+        .await
+        .is_empty()
+    );
+
+    // sizes
+    assert_eq!(
+        vec![text_id, image_id],
+        list(&datalith, filter(|f| f.min_file_size = Some(12)), order_by.clone()).await
+    );
+    assert_eq!(
+        vec![text_id, temporary_id],
+        list(
+            &datalith,
+            filter(|f| {
+                f.min_file_size = Some(1);
+                f.max_file_size = Some(12);
+            }),
+            order_by.clone()
+        )
+        .await
+    );
+
+    // temporary or permanent
+    assert_eq!(
+        vec![temporary_id],
+        list(&datalith, filter(|f| f.temporary = Some(true)), order_by.clone()).await
+    );
+    assert_eq!(
+        vec![text_id, image_id],
+        list(&datalith, filter(|f| f.temporary = Some(false)), order_by.clone()).await
+    );
+
+    datalith_close(datalith).await;
+}
+
+#[tokio::test]
+async fn sort_resources() {
+    let datalith = datalith_init().await;
+
+    let mut ids = Vec::with_capacity(3);
+
+    for (data, file_name, file_type) in [
+        (&b"Hello world!"[..], "b.txt", mime::TEXT_PLAIN),
+        (IMAGE_DATA.as_ref(), "c.png", mime::IMAGE_PNG),
+        (&b"Hi!"[..], "a.csv", mime::TEXT_CSV),
+    ] {
+        ids.push(
+            datalith
+                .put_resource_by_buffer(
+                    data,
+                    Some(file_name),
+                    Some((file_type, FileTypeLevel::Manual)),
+                    None,
+                )
+                .await
+                .unwrap()
+                .id(),
+        );
+    }
+
+    let order_by = |f: fn(&mut DatalithResourceOrderBy)| {
+        let mut order_by = DatalithResourceOrderBy {
+            id: 102.into(),
+            created_at: OrderMethod::default(),
+            ..DatalithResourceOrderBy::default()
+        };
+
+        f(&mut order_by);
+
+        order_by
+    };
+
+    let filter = DatalithResourceFilter::default;
+
+    assert_eq!(
+        vec![ids[2], ids[0], ids[1]],
+        list(&datalith, filter(), order_by(|o| o.file_name = 101.into())).await
+    );
+    assert_eq!(
+        vec![ids[1], ids[0], ids[2]],
+        list(&datalith, filter(), order_by(|o| o.file_size = (-101).into())).await
+    );
+    assert_eq!(
+        vec![ids[1], ids[2], ids[0]],
+        list(&datalith, filter(), order_by(|o| o.file_type = 101.into())).await
+    );
+
+    datalith_close(datalith).await;
+}
+
+#[cfg(feature = "image-convert")]
+#[tokio::test]
+async fn filter_and_sort_images() {
+    use datalith_core::{DatalithImageFilter, DatalithImageOrderBy};
+
+    let datalith = datalith_init().await;
+
+    let large_id = datalith
+        .put_image_by_buffer(IMAGE_DATA.to_vec(), Some("large.png"), Some(64), None, None, true)
+        .await
+        .unwrap()
+        .id();
+    let small_id = datalith
+        .put_image_by_buffer(IMAGE_DATA.to_vec(), Some("small.png"), Some(32), None, None, true)
+        .await
+        .unwrap()
+        .id();
+
+    let order_by = DatalithImageOrderBy {
+        id: 102.into(),
+        created_at: OrderMethod::default(),
+        image_width: 101.into(),
+        ..DatalithImageOrderBy::default()
+    };
+
+    let list = |filter: DatalithImageFilter| {
+        let datalith = datalith.clone();
+        let order_by = order_by.clone();
+
+        async move {
+            datalith
+                .list_image_ids(&filter, PaginationOptions::default().order_by(order_by))
+                .await
+                .unwrap()
+                .0
+        }
+    };
+
+    assert_eq!(vec![small_id, large_id], list(DatalithImageFilter::default()).await);
+    assert_eq!(
+        vec![large_id],
+        list(DatalithImageFilter {
+            image_stem: Some(DatalithNameMatch::Glob("l*".into())),
+            ..DatalithImageFilter::default()
+        })
+        .await
+    );
+    assert_eq!(
+        vec![small_id, large_id],
+        list(DatalithImageFilter {
+            has_alpha_channel: Some(true),
+            ..DatalithImageFilter::default()
+        })
+        .await
+    );
+    assert!(
+        list(DatalithImageFilter {
+            has_alpha_channel: Some(false),
+            ..DatalithImageFilter::default()
+        })
+        .await
+        .is_empty()
+    );
+
+    datalith_close(datalith).await;
+}
@@ -0,0 +1,340 @@
+mod global;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use datalith_core::{
+    DatalithMetadata, DatalithMetadataUpdate, DatalithResourceFilter, DatalithWriteError,
+    FileTypeLevel, PaginationOptions, is_valid_metadata_key, is_valid_metadata_value, is_valid_tag,
+    mime,
+};
+use global::*;
+
+fn metadata(values: &[(&str, &str)], tags: &[&str]) -> DatalithMetadata {
+    DatalithMetadata {
+        values: values.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        tags:   tags.iter().map(|t| t.to_string()).collect(),
+    }
+}
+
+#[test]
+fn validation() {
+    assert!(is_valid_metadata_key("uploader-id"));
+    assert!(is_valid_metadata_key("owner_2"));
+    assert!(!is_valid_metadata_key(""));
+    assert!(!is_valid_metadata_key("Owner"));
+    assert!(!is_valid_metadata_key("a b"));
+    assert!(!is_valid_metadata_key(&"a".repeat(65)));
+
+    assert!(is_valid_metadata_value(""));
+    assert!(is_valid_metadata_value("Hello, world!"));
+    assert!(!is_valid_metadata_value("a\nb"));
+    assert!(!is_valid_metadata_value(&"a".repeat(1025)));
+
+    assert!(is_valid_tag("paid"));
+    assert!(!is_valid_tag(""));
+    assert!(!is_valid_tag("a,b"));
+
+    // removing a tag which cannot exist is harmless
+    assert!(
+        DatalithMetadataUpdate {
+            remove_tags: BTreeSet::from(["a,b".to_string()]),
+            ..DatalithMetadataUpdate::default()
+        }
+        .validate()
+        .is_ok()
+    );
+}
+
+#[tokio::test]
+async fn resource_metadata() {
+    let datalith = datalith_init().await;
+
+    let id = datalith
+        .put_resource_by_buffer(
+            b"Hello world!",
+            Some("plain.txt"),
+            Some((mime::TEXT_PLAIN, FileTypeLevel::Manual)),
+            None,
+        )
+        .await
+        .unwrap()
+        .id();
+
+    assert!(datalith.peek_resource_by_id(id).await.unwrap().unwrap().metadata().is_empty());
+
+    let expected = metadata(&[("category", "invoice"), ("draft", "1")], &["unpaid"]);
+
+    assert_eq!(
+        Some(expected.clone()),
+        datalith.update_resource_metadata(id, &expected.clone().into()).await.unwrap()
+    );
+    assert_eq!(&expected, datalith.peek_resource_by_id(id).await.unwrap().unwrap().metadata());
+
+    let update = DatalithMetadataUpdate {
+        values:      BTreeMap::from([
+            ("category".to_string(), Some("receipt".to_string())),
+            ("draft".to_string(), None),
+        ]),
+        add_tags:    BTreeSet::from(["paid".to_string()]),
+        remove_tags: BTreeSet::from(["unpaid".to_string()]),
+    };
+
+    let expected = metadata(&[("category", "receipt")], &["paid"]);
+
+    assert_eq!(
+        Some(expected.clone()),
+        datalith.update_resource_metadata(id, &update).await.unwrap()
+    );
+    assert_eq!(&expected, datalith.get_resource_by_id(id).await.unwrap().unwrap().metadata());
+
+    // invalid changes are refused as a whole
+    let update = DatalithMetadataUpdate {
+        values: BTreeMap::from([
+            ("category".to_string(), Some("memo".to_string())),
+            ("Bad Key".to_string(), Some("x".to_string())),
+        ]),
+        ..DatalithMetadataUpdate::default()
+    };
+
+    match datalith.update_resource_metadata(id, &update).await {
+        Err(DatalithWriteError::MetadataInvalid {
+            name,
+        }) => assert_eq!("Bad Key", name),
+        result => panic!("unexpected result: {result:?}"),
+    }
+    assert_eq!(&expected, datalith.peek_resource_by_id(id).await.unwrap().unwrap().metadata());
+
+    // the metadata is deleted along with the resource
+    assert!(datalith.delete_resource_by_id(id).await.unwrap());
+    assert_eq!(
+        None,
+        datalith.update_resource_metadata(id, &DatalithMetadataUpdate::default()).await.unwrap()
+    );
+
+    assert!(datalith.check_consistency(false).await.unwrap().is_consistent());
+
+    datalith_close(datalith).await;
+}
+
+#[tokio::test]
+async fn list_by_metadata() {
+    let datalith = datalith_init().await;
+
+    let mut ids = Vec::with_capacity(3);
+
+    for (i, (values, tags)) in [
+        (&[("category", "invoice")][..], &["paid"][..]),
+        (&[("category", "invoice")][..], &["unpaid"][..]),
+        (&[("category", "receipt")][..], &["paid"][..]),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let id = datalith
+            .put_resource_by_buffer(
+                format!("Hello world {i}!").as_bytes(),
+                Some("plain.txt"),
+                Some((mime::TEXT_PLAIN, FileTypeLevel::Manual)),
+                None,
+            )
+            .await
+            .unwrap()
+            .id();
+
+        datalith.update_resource_metadata(id, &metadata(values, tags).into()).await.unwrap();
+
+        ids.push(id);
+    }
+
+    let list = |metadata: DatalithMetadata| {
+        let datalith = datalith.clone();
+
+        async move {
+            let filter = DatalithResourceFilter {
+                metadata,
+                ..DatalithResourceFilter::default()
+            };
+
+            let (mut ids, pagination) =
+                datalith.list_resource_ids(&filter, PaginationOptions::default()).await.unwrap();
+
+            assert_eq!(ids.len(), pagination.get_total_items());
+
+            ids.sort();
+            ids
+        }
+    };
+
+    let sorted = |mut v: Vec<_>| {
+        v.sort();
+        v
+    };
+
+    assert_eq!(sorted(ids.clone()), list(DatalithMetadata::default()).await);
+    assert_eq!(sorted(vec![ids[0], ids[1]]), list(metadata(&[("category", "invoice")], &[])).await);
+    assert_eq!(sorted(vec![ids[0], ids[2]]), list(metadata(&[], &["paid"])).await);
+    assert_eq!(vec![ids[0]], list(metadata(&[("category", "invoice")], &["paid"])).await);
+    assert!(list(metadata(&[("category", "memo")], &[])).await.is_empty());
+
+    datalith_close(datalith).await;
+}
+
+#[tokio::test]
+async fn namespace_isolation() {
+    let datalith = datalith_init().await;
+
+    let shop = datalith.with_namespace("shop").unwrap();
+
+    let id = shop
+        .put_resource_by_buffer(
+            b"Hello world!",
+            Some("plain.txt"),
+            Some((mime::TEXT_PLAIN, FileTypeLevel::Manual)),
+            None,
+        )
+        .await
+        .unwrap()
+        .id();
+
+    let update = DatalithMetadataUpdate::from(metadata(&[("category", "invoice")], &[]));
+
+    assert_eq!(None, datalith.update_resource_metadata(id, &update).await.unwrap());
+    assert!(shop.peek_resource_by_id(id).await.unwrap().unwrap().metadata().is_empty());
+
+    assert!(shop.update_resource_metadata(id, &update).await.unwrap().is_some());
+    assert!(
+        datalith
+            .list_resource_ids(
+                &DatalithResourceFilter {
+                    metadata: metadata(&[("category", "invoice")], &[]),
+                    ..DatalithResourceFilter::default()
+                },
+                PaginationOptions::default()
+            )
+            .await
+            .unwrap()
+            .0
+            .is_empty()
+    );
+
+    datalith_close(datalith).await;
+}
+
+#[tokio::test]
+async fn metadata_version() {
+    let datalith = datalith_init().await;
+
+    let id = datalith
+        .put_resource_by_buffer(
+            b"Hello world!",
+            Some("plain.txt"),
+            Some((mime::TEXT_PLAIN, FileTypeLevel::Manual)),
+            None,
+        )
+        .await
+        .unwrap()
+        .id();
+
+    assert_eq!(0, datalith.peek_resource_by_id(id).await.unwrap().unwrap().metadata_version());
+
+    let update = DatalithMetadataUpdate::from(metadata(&[("category", "invoice")], &[]));
+
+    let (_, metadata_version) = datalith
+        .update_resource_metadata_with_version(id, &update, Some(0))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(1, metadata_version);
+
+    // the version changes even if the data does not
+    datalith.update_resource_metadata(id, &update).await.unwrap().unwrap();
+    assert_eq!(2, datalith.peek_resource_by_id(id).await.unwrap().unwrap().metadata_version());
+
+    // a stale version is refused without changing anything
+    let stale_update = DatalithMetadataUpdate::from(metadata(&[("category", "receipt")], &[]));
+
+    match datalith.update_resource_metadata_with_version(id, &stale_update, Some(1)).await {
+        Err(DatalithWriteError::MetadataVersionMismatch {
+            metadata_version,
+            expected_metadata_version,
+        }) => assert_eq!((2, 1), (metadata_version, expected_metadata_version)),
+        result => panic!("unexpected result: {result:?}"),
+    }
+
+    let resource = datalith.peek_resource_by_id(id).await.unwrap().unwrap();
+    assert_eq!(&metadata(&[("category", "invoice")], &[]), resource.metadata());
+    assert_eq!(2, resource.metadata_version());
+
+    drop(resource);
+
+    // a missing resource is not a version mismatch
+    assert!(datalith.delete_resource_by_id(id).await.unwrap());
+    assert_eq!(
+        None,
+        datalith.update_resource_metadata_with_version(id, &update, Some(2)).await.unwrap()
+    );
+
+    datalith_close(datalith).await;
+}
+
+#[cfg(feature = "image-convert")]
+#[tokio::test]
+async fn image_metadata() {
+    let datalith = datalith_init().await;
+
+    let resource_id = datalith
+        .put_resource_by_buffer(IMAGE_DATA.as_ref(), Some("image.png"), None, None)
+        .await
+        .unwrap()
+        .id();
+
+    let expected = metadata(&[("uploader-id", "42")], &["avatar"]);
+
+    datalith.update_resource_metadata(resource_id, &expected.clone().into()).await.unwrap();
+
+    let resource = datalith.get_resource_by_id(resource_id).await.unwrap().unwrap();
+
+    // the metadata of a resource is kept when it is converted to an image
+    let image = datalith.convert_resource_to_image(resource, Some(32), None, None).await.unwrap();
+    assert_eq!(&expected, image.metadata());
+    assert_eq!(1, image.metadata_version());
+
+    let image_id = image.id();
+    drop(image);
+
+    let update = DatalithMetadataUpdate {
+        remove_tags: BTreeSet::from(["avatar".to_string()]),
+        ..DatalithMetadataUpdate::default()
+    };
+
+    let expected = metadata(&[("uploader-id", "42")], &[]);
+
+    assert_eq!(
+        Some(expected.clone()),
+        datalith.update_image_metadata(image_id, &update).await.unwrap()
+    );
+    assert_eq!(&expected, datalith.get_image_by_id(image_id).await.unwrap().unwrap().metadata());
+
+    assert!(matches!(
+        datalith.update_image_metadata_with_version(image_id, &update, Some(1)).await,
+        Err(DatalithWriteError::MetadataVersionMismatch {
+            metadata_version: 2,
+            ..
+        })
+    ));
+
+    let filter = datalith_core::DatalithImageFilter {
+        metadata: expected,
+        ..datalith_core::DatalithImageFilter::default()
+    };
+
+    assert_eq!(
+        vec![image_id],
+        datalith.list_image_ids(&filter, PaginationOptions::default()).await.unwrap().0
+    );
+
+    assert!(datalith.delete_image_by_id(image_id).await.unwrap());
+    assert_eq!(None, datalith.update_image_metadata(image_id, &update).await.unwrap());
+
+    datalith_close(datalith).await;
+}
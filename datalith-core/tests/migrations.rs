@@ -0,0 +1,264 @@
+mod global;
+
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use datalith_core::{
+    Datalith, DatalithApiKeyScopes, DatalithCreateError, DatalithUsage, FileTypeLevel,
+    PATH_DB_FILE, PATH_FILE_DIRECTORY, PaginationOptions, Uuid,
+    mime::{self, Mime},
+};
+use global::*;
+use sqlx::{
+    Connection, SqliteConnection,
+    sqlite::{SqliteConnectOptions, SqliteJournalMode},
+};
+use tokio::{fs, io::AsyncReadExt};
+
+const FIXTURE_V1: &str = include_str!("data/fixtures/schema_v1.sql");
+const FIXTURE_V3: &str = include_str!("data/fixtures/schema_v3.sql");
+
+const FILE_ID: &str = "6a1c5e0b-8c2f-4d7e-9a3b-1f0e2d4c6b8a";
+const RESOURCE_ID: &str = "1b2c3d4e-5f60-7182-93a4-b5c6d7e8f901";
+const FILE_DATA: &[u8] = b"Hello world!";
+
+#[inline]
+fn get_environment_path(name: &str) -> PathBuf {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+
+    Path::new("tests").join("db").join(format!("{name}_{}", timestamp.as_micros()))
+}
+
+/// Create an environment from a SQL fixture, as if it had been created by an older version.
+async fn create_environment_from_fixture(name: &str, fixture: &str) -> PathBuf {
+    let environment = get_environment_path(name);
+
+    fs::create_dir_all(environment.join(PATH_FILE_DIRECTORY)).await.unwrap();
+
+    {
+        let options = SqliteConnectOptions::new()
+            .filename(environment.join(PATH_DB_FILE))
+            .journal_mode(SqliteJournalMode::Wal)
+            .create_if_missing(true);
+
+        let mut conn = SqliteConnection::connect_with(&options).await.unwrap();
+
+        sqlx::raw_sql(fixture).execute(&mut conn).await.unwrap();
+
+        conn.close().await.unwrap();
+    }
+
+    // older versions stored files directly in the file directory, named by the hex of their IDs
+    let file_id = Uuid::from_str(FILE_ID).unwrap();
+
+    fs::write(
+        environment.join(PATH_FILE_DIRECTORY).join(format!("{:x}", file_id.as_u128())),
+        FILE_DATA,
+    )
+    .await
+    .unwrap();
+
+    environment
+}
+
+async fn read_recorded_version(environment: &Path) -> u32 {
+    let options = SqliteConnectOptions::new().filename(environment.join(PATH_DB_FILE));
+
+    let mut conn = SqliteConnection::connect_with(&options).await.unwrap();
+
+    let (version,): (String,) =
+        sqlx::query_as("SELECT `value` FROM `sys_db_information` WHERE `key` = 'version'")
+            .fetch_one(&mut conn)
+            .await
+            .unwrap();
+
+    conn.close().await.unwrap();
+
+    version.parse().unwrap()
+}
+
+async fn current_database_version() -> u32 {
+    let datalith = datalith_init().await;
+
+    let version = datalith.get_database_version();
+
+    datalith_close(datalith).await;
+
+    version
+}
+
+async fn check_fixture_data(datalith: &Datalith) {
+    let resource =
+        datalith.get_resource_by_id(Uuid::from_str(RESOURCE_ID).unwrap()).await.unwrap().unwrap();
+
+    assert_eq!(Uuid::from_str(FILE_ID).unwrap(), resource.file().id());
+    assert_eq!(&Mime::from_str("text/plain").unwrap(), resource.file_type());
+    assert_eq!("plain.txt", resource.file_name());
+    assert_eq!(FILE_DATA.len() as u64, resource.file().file_size());
+
+    let mut buffer = Vec::new();
+    resource.file().create_reader().await.unwrap().read_to_end(&mut buffer).await.unwrap();
+    assert_eq!(FILE_DATA, buffer);
+
+    // the existing resources are indexed for searching
+    let (hits, _) = datalith.search_resources("plain", PaginationOptions::default()).await.unwrap();
+    assert_eq!(
+        vec![Uuid::from_str(RESOURCE_ID).unwrap()],
+        hits.into_iter().map(|e| e.id).collect::<Vec<_>>()
+    );
+
+    // the usage is counted from the existing resources
+    assert_eq!(
+        DatalithUsage {
+            bytes: FILE_DATA.len() as u64, count: 1
+        },
+        datalith.get_usage().await.unwrap()
+    );
+
+    // the same data is deduplicated against the migrated file
+    let file = datalith
+        .put_file_by_buffer(
+            FILE_DATA,
+            Some("another.txt"),
+            Some((mime::TEXT_PLAIN, FileTypeLevel::Manual)),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(Uuid::from_str(FILE_ID).unwrap(), file.id());
+    assert!(!file.is_new());
+}
+
+#[tokio::test]
+async fn migrate_from_v1() {
+    let app_db_version = current_database_version().await;
+
+    let environment = create_environment_from_fixture("migrate_from_v1", FIXTURE_V1).await;
+
+    let datalith = Datalith::new(environment.as_path()).await.unwrap();
+
+    assert_eq!(app_db_version, datalith.get_database_version());
+
+    check_fixture_data(&datalith).await;
+
+    // tables added by migrations are usable
+    let session = datalith.create_upload_session(Some(3), Some("abc.txt"), None).await.unwrap();
+    assert!(datalith.delete_upload_session_by_id(session.id()).await.unwrap());
+
+    let (api_key, key) =
+        datalith.create_api_key("test", DatalithApiKeyScopes::READ, None).await.unwrap();
+    assert_eq!(api_key.id(), datalith.verify_api_key(key).await.unwrap().unwrap().id());
+
+    datalith.close().await;
+
+    assert_eq!(app_db_version, read_recorded_version(environment.as_path()).await);
+
+    // reopening does not migrate again
+    let datalith = Datalith::new(environment.as_path()).await.unwrap();
+
+    assert_eq!(app_db_version, datalith.get_database_version());
+
+    datalith_close(datalith).await;
+}
+
+#[tokio::test]
+async fn migrate_from_v3() {
+    let app_db_version = current_database_version().await;
+
+    let environment = create_environment_from_fixture("migrate_from_v3", FIXTURE_V3).await;
+
+    let datalith = Datalith::new(environment.as_path()).await.unwrap();
+
+    assert_eq!(app_db_version, datalith.get_database_version());
+
+    check_fixture_data(&datalith).await;
+
+    let api_key = datalith.verify_api_key("dlk_fixture").await.unwrap().unwrap();
+    assert_eq!("fixture", api_key.name());
+
+    datalith.close().await;
+
+    assert_eq!(app_db_version, read_recorded_version(environment.as_path()).await);
+
+    fs::remove_dir_all(environment).await.unwrap();
+}
+
+#[tokio::test]
+async fn migrate_flat_file_directory() {
+    let environment =
+        create_environment_from_fixture("migrate_flat_file_directory", FIXTURE_V1).await;
+
+    let datalith =
+        Datalith::builder(environment.as_path()).file_directory_depth(1).build().await.unwrap();
+
+    check_fixture_data(&datalith).await;
+
+    datalith.close().await;
+
+    // the stored file has been moved out of the flat directory
+    let file_id = Uuid::from_str(FILE_ID).unwrap();
+    let file_name = format!("{:032x}", file_id.as_u128());
+
+    assert_eq!(
+        FILE_DATA,
+        fs::read(environment.join(PATH_FILE_DIRECTORY).join(&file_name[..2]).join(&file_name))
+            .await
+            .unwrap()
+    );
+    assert!(
+        !fs::try_exists(
+            environment.join(PATH_FILE_DIRECTORY).join(format!("{:x}", file_id.as_u128()))
+        )
+        .await
+        .unwrap()
+    );
+
+    // the depth has been decided by the migration
+    let datalith = Datalith::new(environment.as_path()).await.unwrap();
+
+    check_fixture_data(&datalith).await;
+
+    datalith.close().await;
+
+    match Datalith::builder(environment.as_path()).file_directory_depth(2).build().await {
+        Err(DatalithCreateError::FileDirectoryDepthMismatch {
+            requested_depth,
+            current_depth,
+        }) => {
+            assert_eq!(2, requested_depth);
+            assert_eq!(1, current_depth);
+        },
+        result => panic!("unexpected result: {result:?}"),
+    }
+
+    fs::remove_dir_all(environment).await.unwrap();
+}
+
+#[tokio::test]
+async fn database_too_new() {
+    let app_db_version = current_database_version().await;
+
+    let fixture =
+        FIXTURE_V1.replace("('version', '1')", &format!("('version', '{}')", app_db_version + 1));
+
+    let environment = create_environment_from_fixture("database_too_new", &fixture).await;
+
+    match Datalith::new(environment.as_path()).await {
+        Err(DatalithCreateError::DatabaseTooNewError {
+            app_db_version: a,
+            current_db_version: c,
+        }) => {
+            assert_eq!(app_db_version, a);
+            assert_eq!(app_db_version + 1, c);
+        },
+        result => panic!("unexpected result: {result:?}"),
+    }
+
+    // the database is left untouched
+    assert_eq!(app_db_version + 1, read_recorded_version(environment.as_path()).await);
+
+    fs::remove_dir_all(environment).await.unwrap();
+}
@@ -0,0 +1,178 @@
+mod global;
+
+use datalith_core::{
+    DEFAULT_NAMESPACE, DatalithResourceFilter, DatalithUploadSessionError, FileTypeLevel,
+    PaginationOptions, mime,
+};
+use global::*;
+
+#[tokio::test]
+async fn with_namespace() {
+    let datalith = datalith_init().await;
+
+    assert_eq!(DEFAULT_NAMESPACE, datalith.get_namespace());
+
+    let shop = datalith.with_namespace("shop").unwrap();
+    assert_eq!("shop", shop.get_namespace());
+    assert_eq!("my_blog-2", datalith.with_namespace("my_blog-2").unwrap().get_namespace());
+    assert_eq!(DEFAULT_NAMESPACE, shop.with_namespace("").unwrap().get_namespace());
+
+    assert!(datalith.with_namespace("a/b").is_none());
+    assert!(datalith.with_namespace("a b").is_none());
+    assert!(datalith.with_namespace("a".repeat(65)).is_none());
+
+    datalith_close(datalith).await;
+}
+
+#[tokio::test]
+async fn resource_isolation() {
+    let datalith = datalith_init().await;
+
+    let shop = datalith.with_namespace("shop").unwrap();
+    let blog = datalith.with_namespace("blog").unwrap();
+
+    let resource = shop
+        .put_resource_by_buffer(
+            b"Hello world!",
+            Some("shop.txt"),
+            Some((mime::TEXT_PLAIN, FileTypeLevel::Manual)),
+            None,
+        )
+        .await
+        .unwrap();
+    let shop_resource_id = resource.id();
+    let file_id = resource.file().id();
+    drop(resource);
+
+    let resource = blog
+        .put_resource_by_buffer(
+            b"Hello world!",
+            Some("blog.txt"),
+            Some((mime::TEXT_PLAIN, FileTypeLevel::Manual)),
+            None,
+        )
+        .await
+        .unwrap();
+    let blog_resource_id = resource.id();
+
+    // the data is stored only once
+    assert_eq!(file_id, resource.file().id());
+    drop(resource);
+
+    // a resource is only visible in its namespace
+    assert!(shop.check_resource_exist(shop_resource_id).await.unwrap());
+    assert!(!blog.check_resource_exist(shop_resource_id).await.unwrap());
+    assert!(!datalith.check_resource_exist(shop_resource_id).await.unwrap());
+    assert!(blog.peek_resource_by_id(shop_resource_id).await.unwrap().is_none());
+    assert!(datalith.get_resource_by_id(blog_resource_id).await.unwrap().is_none());
+    assert_eq!(
+        "shop.txt",
+        shop.get_resource_by_id(shop_resource_id).await.unwrap().unwrap().file_name()
+    );
+
+    let (ids, pagination) = shop
+        .list_resource_ids(&DatalithResourceFilter::default(), PaginationOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(vec![shop_resource_id], ids);
+    assert_eq!(1, pagination.get_total_items());
+
+    let (ids, _) = datalith
+        .list_resource_ids(&DatalithResourceFilter::default(), PaginationOptions::default())
+        .await
+        .unwrap();
+    assert!(ids.is_empty());
+
+    // a resource cannot be deleted from another namespace
+    assert!(!blog.delete_resource_by_id(shop_resource_id).await.unwrap());
+    assert!(!datalith.delete_resource_by_id(shop_resource_id).await.unwrap());
+    assert!(shop.check_resource_exist(shop_resource_id).await.unwrap());
+
+    // the shared file is kept until every namespace deletes its resource
+    assert!(shop.delete_resource_by_id(shop_resource_id).await.unwrap());
+    assert!(datalith.check_file_exist(file_id).await.unwrap());
+    assert!(blog.check_resource_exist(blog_resource_id).await.unwrap());
+
+    assert!(blog.delete_resource_by_id(blog_resource_id).await.unwrap());
+    assert!(!datalith.check_file_exist(file_id).await.unwrap());
+
+    datalith_close(datalith).await;
+}
+
+#[cfg(feature = "image-convert")]
+#[tokio::test]
+async fn image_isolation() {
+    let datalith = datalith_init().await;
+
+    let shop = datalith.with_namespace("shop").unwrap();
+
+    let image_id = shop
+        .put_image_by_buffer(IMAGE_DATA.to_vec(), Some("image.png"), Some(32), None, None, true)
+        .await
+        .unwrap()
+        .id();
+
+    assert!(shop.check_image_exist(image_id).await.unwrap());
+    assert!(!datalith.check_image_exist(image_id).await.unwrap());
+    assert!(datalith.get_image_by_id(image_id).await.unwrap().is_none());
+
+    let (ids, _) = shop
+        .list_image_ids(
+            &datalith_core::DatalithImageFilter::default(),
+            PaginationOptions::default(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(vec![image_id], ids);
+
+    let (ids, _) = datalith
+        .list_image_ids(
+            &datalith_core::DatalithImageFilter::default(),
+            PaginationOptions::default(),
+        )
+        .await
+        .unwrap();
+    assert!(ids.is_empty());
+
+    assert!(!datalith.delete_image_by_id(image_id).await.unwrap());
+    assert!(shop.delete_image_by_id(image_id).await.unwrap());
+    assert!(!shop.check_image_exist(image_id).await.unwrap());
+
+    datalith_close(datalith).await;
+}
+
+#[tokio::test]
+async fn upload_session_isolation() {
+    let datalith = datalith_init().await;
+
+    let shop = datalith.with_namespace("shop").unwrap();
+    let blog = datalith.with_namespace("blog").unwrap();
+
+    let id = shop.create_upload_session(Some(12), Some("plain.txt"), None).await.unwrap().id();
+
+    assert!(shop.get_upload_session_by_id(id).await.unwrap().is_some());
+    assert!(blog.get_upload_session_by_id(id).await.unwrap().is_none());
+
+    assert!(matches!(
+        blog.append_upload_session(id, 0, b"Hello".as_slice()).await,
+        Err(DatalithUploadSessionError::NotFound)
+    ));
+    assert_eq!(5, shop.append_upload_session(id, 0, b"Hello".as_slice()).await.unwrap());
+
+    assert!(!blog.delete_upload_session_by_id(id).await.unwrap());
+    assert_eq!(12, shop.append_upload_session(id, 5, b" world!".as_slice()).await.unwrap());
+
+    assert!(matches!(
+        blog.finish_upload_session(id).await,
+        Err(DatalithUploadSessionError::NotFound)
+    ));
+
+    let resource_id = shop.finish_upload_session(id).await.unwrap().id();
+
+    assert!(shop.check_resource_exist(resource_id).await.unwrap());
+    assert!(!blog.check_resource_exist(resource_id).await.unwrap());
+
+    assert!(shop.delete_resource_by_id(resource_id).await.unwrap());
+
+    datalith_close(datalith).await;
+}
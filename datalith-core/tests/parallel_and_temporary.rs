@@ -2,7 +2,7 @@ mod global;
 
 use std::time::Duration;
 
-use datalith_core::PaginationOptions;
+use datalith_core::{DatalithResourceFilter, PaginationOptions};
 use global::*;
 use tokio::time;
 
@@ -14,10 +14,10 @@ async fn parallel_and_temporary() {
 
     {
         let (file_1, file_2, file_3, file_4) = tokio::join!(
-            datalith.put_file_by_buffer_temporarily(image, Some("image.png"), None),
-            datalith.put_file_by_buffer_temporarily(image, Some("image.png"), None),
-            datalith.put_file_by_buffer(image, Some("image.png"), None),
-            datalith.put_file_by_buffer(image, Some("image.png"), None),
+            datalith.put_file_by_buffer_temporarily(image, Some("image.png"), None, None),
+            datalith.put_file_by_buffer_temporarily(image, Some("image.png"), None, None),
+            datalith.put_file_by_buffer(image, Some("image.png"), None, None),
+            datalith.put_file_by_buffer(image, Some("image.png"), None, None),
         );
 
         let file_1 = file_1.unwrap();
@@ -85,10 +85,10 @@ async fn resource_parallel_and_temporary() {
 
     {
         let (resource_1, resource_2, resource_3, resource_4) = tokio::join!(
-            datalith.put_resource_by_buffer_temporarily(image, Some("image.png"), None),
-            datalith.put_resource_by_buffer_temporarily(image, Some("image.png"), None),
-            datalith.put_resource_by_buffer(image, Some("image.png"), None),
-            datalith.put_resource_by_buffer(image, Some("image.png"), None),
+            datalith.put_resource_by_buffer_temporarily(image, Some("image.png"), None, None),
+            datalith.put_resource_by_buffer_temporarily(image, Some("image.png"), None, None),
+            datalith.put_resource_by_buffer(image, Some("image.png"), None, None),
+            datalith.put_resource_by_buffer(image, Some("image.png"), None, None),
         );
 
         let resource_1 = resource_1.unwrap();
@@ -101,7 +101,10 @@ async fn resource_parallel_and_temporary() {
         assert_ne!(resource_2, resource_3);
         assert_ne!(resource_3, resource_4);
 
-        let (file_ids, _) = datalith.list_resource_ids(PaginationOptions::default()).await.unwrap();
+        let (file_ids, _) = datalith
+            .list_resource_ids(&DatalithResourceFilter::default(), PaginationOptions::default())
+            .await
+            .unwrap();
         assert_eq!(4, file_ids.len());
 
         let (id_1, id_2, id_3, id_4) =
@@ -236,7 +239,13 @@ async fn image_parallel() {
         assert_ne!(image_1.thumbnails()[0], image_3.thumbnails()[0]);
         assert_eq!(image_1.thumbnails()[2], image_3.thumbnails()[1]);
 
-        let (image_ids, _) = datalith.list_image_ids(PaginationOptions::default()).await.unwrap();
+        let (image_ids, _) = datalith
+            .list_image_ids(
+                &datalith_core::DatalithImageFilter::default(),
+                PaginationOptions::default(),
+            )
+            .await
+            .unwrap();
         assert_eq!(3, image_ids.len());
 
         let id_1 = image_1.id();
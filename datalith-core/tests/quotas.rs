@@ -0,0 +1,219 @@
+mod global;
+
+use datalith_core::{
+    DatalithQuota, DatalithUsage, DatalithWriteError, FileTypeLevel, PaginationOptions, mime,
+};
+use global::*;
+
+#[tokio::test]
+async fn usage() {
+    let datalith = datalith_init().await;
+
+    let shop = datalith.with_namespace("shop").unwrap();
+
+    assert_eq!(DatalithUsage::default(), datalith.get_usage().await.unwrap());
+
+    let resource_1 = datalith
+        .put_resource_by_buffer(
+            b"Hello world!",
+            Some("1.txt"),
+            Some((mime::TEXT_PLAIN, FileTypeLevel::Manual)),
+            None,
+        )
+        .await
+        .unwrap()
+        .id();
+    // the same data is counted once per resource
+    let resource_2 = datalith
+        .put_resource_by_buffer(
+            b"Hello world!",
+            Some("2.txt"),
+            Some((mime::TEXT_PLAIN, FileTypeLevel::Manual)),
+            None,
+        )
+        .await
+        .unwrap()
+        .id();
+    shop.put_resource_by_buffer(IMAGE_DATA.as_ref(), Some("image.png"), None, None).await.unwrap();
+
+    // files put directly are not counted
+    datalith
+        .put_file_by_buffer(
+            b"Hi!",
+            Some("plain.txt"),
+            Some((mime::TEXT_PLAIN, FileTypeLevel::Manual)),
+            None,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        DatalithUsage {
+            bytes: 24, count: 2
+        },
+        datalith.get_usage().await.unwrap()
+    );
+    assert_eq!(
+        DatalithUsage {
+            bytes: IMAGE_SIZE, count: 1
+        },
+        shop.get_usage().await.unwrap()
+    );
+    assert_eq!(
+        DatalithUsage {
+            bytes: IMAGE_SIZE + 24, count: 3
+        },
+        shop.get_global_usage().await.unwrap()
+    );
+    assert_eq!(vec!["", "shop"], datalith.list_namespaces().await.unwrap());
+
+    assert!(datalith.delete_resource_by_id(resource_1).await.unwrap());
+    assert!(datalith.delete_resource_by_id(resource_2).await.unwrap());
+
+    assert_eq!(DatalithUsage::default(), datalith.get_usage().await.unwrap());
+    assert_eq!(
+        DatalithUsage {
+            bytes: IMAGE_SIZE, count: 1
+        },
+        datalith.get_global_usage().await.unwrap()
+    );
+
+    assert!(datalith.check_consistency(false).await.unwrap().is_consistent());
+
+    datalith_close(datalith).await;
+}
+
+#[tokio::test]
+async fn namespace_quota() {
+    let datalith = datalith_init().await;
+
+    let shop = datalith.with_namespace("shop").unwrap();
+
+    let quota = DatalithQuota {
+        max_bytes: Some(IMAGE_SIZE + 10), max_count: Some(2)
+    };
+
+    shop.set_namespace_quota(quota).await.unwrap();
+    assert_eq!(quota, shop.get_namespace_quota().await.unwrap());
+    assert_eq!(DatalithQuota::default(), datalith.get_namespace_quota().await.unwrap());
+
+    shop.put_resource_by_buffer(IMAGE_DATA.as_ref(), Some("image.png"), None, None).await.unwrap();
+
+    // too large
+    let file_count = datalith.list_file_ids(PaginationOptions::default()).await.unwrap().0.len();
+
+    match shop
+        .put_resource_by_buffer(
+            b"Hello world!",
+            Some("plain.txt"),
+            Some((mime::TEXT_PLAIN, FileTypeLevel::Manual)),
+            None,
+        )
+        .await
+    {
+        Err(DatalithWriteError::QuotaExceeded {
+            namespace,
+        }) => assert_eq!(Some("shop"), namespace.as_deref()),
+        result => panic!("unexpected result: {result:?}"),
+    }
+
+    // the data of the rejected resource is not kept
+    assert_eq!(
+        file_count,
+        datalith.list_file_ids(PaginationOptions::default()).await.unwrap().0.len()
+    );
+
+    // other namespaces are not limited
+    datalith
+        .put_resource_by_buffer(
+            b"Hello world!",
+            Some("plain.txt"),
+            Some((mime::TEXT_PLAIN, FileTypeLevel::Manual)),
+            None,
+        )
+        .await
+        .unwrap();
+
+    // small enough
+    shop.put_resource_by_buffer(
+        b"Hi!",
+        Some("plain.txt"),
+        Some((mime::TEXT_PLAIN, FileTypeLevel::Manual)),
+        None,
+    )
+    .await
+    .unwrap();
+
+    // too many
+    assert!(matches!(
+        shop.put_resource_by_buffer(
+            b"",
+            Some("empty.txt"),
+            Some((mime::TEXT_PLAIN, FileTypeLevel::Manual)),
+            None,
+        )
+        .await,
+        Err(DatalithWriteError::QuotaExceeded { .. })
+    ));
+
+    assert_eq!(
+        DatalithUsage {
+            bytes: IMAGE_SIZE + 3, count: 2
+        },
+        shop.get_usage().await.unwrap()
+    );
+
+    // unlimited again
+    shop.set_namespace_quota(DatalithQuota::default()).await.unwrap();
+
+    shop.put_resource_by_buffer(
+        b"",
+        Some("empty.txt"),
+        Some((mime::TEXT_PLAIN, FileTypeLevel::Manual)),
+        None,
+    )
+    .await
+    .unwrap();
+
+    datalith_close(datalith).await;
+}
+
+#[tokio::test]
+async fn global_quota() {
+    let datalith = datalith_init().await;
+
+    let shop = datalith.with_namespace("shop").unwrap();
+
+    datalith.set_global_quota(DatalithQuota {
+        max_bytes: None, max_count: Some(1)
+    });
+
+    shop.put_resource_by_buffer(
+        b"Hello world!",
+        Some("plain.txt"),
+        Some((mime::TEXT_PLAIN, FileTypeLevel::Manual)),
+        None,
+    )
+    .await
+    .unwrap();
+
+    match datalith
+        .put_resource_by_buffer(
+            b"Hello world!",
+            Some("plain.txt"),
+            Some((mime::TEXT_PLAIN, FileTypeLevel::Manual)),
+            None,
+        )
+        .await
+    {
+        Err(DatalithWriteError::QuotaExceeded {
+            namespace,
+        }) => assert_eq!(None, namespace),
+        result => panic!("unexpected result: {result:?}"),
+    }
+
+    assert_eq!(DatalithUsage::default(), datalith.get_usage().await.unwrap());
+    assert_eq!(1, datalith.get_global_usage().await.unwrap().count);
+
+    datalith_close(datalith).await;
+}
@@ -0,0 +1,63 @@
+mod global;
+
+use std::io::{ErrorKind, SeekFrom};
+
+use global::*;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+#[tokio::test]
+async fn range_read() {
+    let datalith = datalith_init().await;
+
+    let image = IMAGE_DATA.as_ref();
+
+    {
+        let file = datalith.put_file_by_buffer(image, Some("image.png"), None, None).await.unwrap();
+
+        let mut reader = file.into_readable().await.unwrap().into_range_reader(100, 200);
+
+        let mut buffer = Vec::new();
+        reader.read_to_end(&mut buffer).await.unwrap();
+        assert_eq!(&image[100..300], buffer);
+        assert_eq!(0, reader.remaining());
+
+        // move backward
+        reader.set_range(0, 10);
+
+        let mut buffer = Vec::new();
+        reader.read_to_end(&mut buffer).await.unwrap();
+        assert_eq!(&image[..10], buffer);
+
+        // skip the remaining data of the current range
+        reader.set_range(IMAGE_SIZE - 50, 1000);
+
+        let mut buffer = [0u8; 20];
+        reader.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(&image[IMAGE_SIZE as usize - 50..IMAGE_SIZE as usize - 30], buffer);
+
+        // seek inside the range
+        reader.set_range(1000, 100);
+
+        assert_eq!(90, reader.seek(SeekFrom::End(-10)).await.unwrap());
+
+        let mut buffer = Vec::new();
+        reader.read_to_end(&mut buffer).await.unwrap();
+        assert_eq!(&image[1090..1100], buffer);
+
+        assert_eq!(20, reader.seek(SeekFrom::Start(20)).await.unwrap());
+        assert_eq!(80, reader.remaining());
+
+        reader.set_range(IMAGE_SIZE - 10, 20);
+
+        let mut buffer = Vec::new();
+        let error = reader.read_to_end(&mut buffer).await.unwrap_err();
+        assert_eq!(ErrorKind::UnexpectedEof, error.kind());
+        assert_eq!(&image[IMAGE_SIZE as usize - 10..], buffer);
+
+        let file_id = reader.into_inner().id();
+
+        assert!(datalith.delete_file_by_id(file_id).await.unwrap());
+    }
+
+    datalith_close(datalith).await;
+}
@@ -0,0 +1,141 @@
+mod global;
+
+use datalith_core::{
+    Datalith, DatalithMetadata, DatalithMetadataUpdate, DatalithSearchHit, DatalithSearchHitKind,
+    FileTypeLevel, PaginationOptions, mime, uuid::Uuid,
+};
+use global::*;
+
+async fn put_text(datalith: &Datalith, text: &str, file_name: &str) -> Uuid {
+    datalith
+        .put_resource_by_buffer(
+            text.as_bytes(),
+            Some(file_name),
+            Some((mime::TEXT_PLAIN, FileTypeLevel::Manual)),
+            None,
+        )
+        .await
+        .unwrap()
+        .id()
+}
+
+async fn search(datalith: &Datalith, query: &str) -> Vec<DatalithSearchHit> {
+    let (hits, pagination) =
+        datalith.search_resources(query, PaginationOptions::default()).await.unwrap();
+
+    assert_eq!(hits.len(), pagination.get_total_items());
+
+    hits
+}
+
+async fn search_ids(datalith: &Datalith, query: &str) -> Vec<Uuid> {
+    search(datalith, query).await.into_iter().map(|hit| hit.id).collect()
+}
+
+#[tokio::test]
+async fn search_resources() {
+    let datalith = datalith_init().await;
+
+    let report_id = put_text(&datalith, "1", "annual-report_2024.txt").await;
+    let notes_id = put_text(&datalith, "2", "notes.txt").await;
+
+    datalith
+        .update_resource_metadata(
+            notes_id,
+            &DatalithMetadata {
+                values: [(
+                    "description".to_string(),
+                    "Summary of the annual résumé review".to_string(),
+                )]
+                .into(),
+                tags:   ["meeting".to_string()].into(),
+            }
+            .into(),
+        )
+        .await
+        .unwrap();
+
+    // file names are split into words
+    let hits = search(&datalith, "report").await;
+    assert_eq!(1, hits.len());
+    assert_eq!(report_id, hits[0].id);
+    assert_eq!(DatalithSearchHitKind::Resource, hits[0].kind);
+    assert!(hits[0].snippet.contains("[report]"));
+
+    // metadata values and tags, case-insensitively, ignoring diacritics and by prefixes
+    assert_eq!(vec![notes_id], search_ids(&datalith, "RESUME").await);
+    assert_eq!(vec![notes_id], search_ids(&datalith, "meet").await);
+    assert!(search(&datalith, "meeting").await[0].snippet.contains("[meeting]"));
+
+    // every word has to match, and the names weigh more than the metadata values
+    assert_eq!(vec![report_id, notes_id], search_ids(&datalith, "annual").await);
+    assert_eq!(vec![notes_id], search_ids(&datalith, "annual review").await);
+    assert!(search_ids(&datalith, "annual holiday").await.is_empty());
+
+    // FTS5 syntax is treated as words
+    assert_eq!(vec![report_id], search_ids(&datalith, "\"report\"* (2024").await);
+    assert!(search_ids(&datalith, "").await.is_empty());
+    assert!(search_ids(&datalith, "* - \"").await.is_empty());
+
+    // pagination
+    let (hits, pagination) = datalith
+        .search_resources("annual", PaginationOptions::default().page(2).items_per_page(1))
+        .await
+        .unwrap();
+    assert_eq!(vec![notes_id], hits.into_iter().map(|hit| hit.id).collect::<Vec<_>>());
+    assert_eq!(2, pagination.get_total_items());
+
+    // changes of the metadata are followed
+    datalith
+        .update_resource_metadata(notes_id, &DatalithMetadataUpdate {
+            values: [("description".to_string(), None)].into(),
+            ..DatalithMetadataUpdate::default()
+        })
+        .await
+        .unwrap();
+    assert!(search_ids(&datalith, "review").await.is_empty());
+    assert_eq!(vec![notes_id], search_ids(&datalith, "meeting").await);
+
+    // other namespaces are not searched
+    let shop = datalith.with_namespace("shop").unwrap();
+    assert!(search_ids(&shop, "report").await.is_empty());
+
+    let shop_report_id = put_text(&shop, "3", "report.txt").await;
+    assert_eq!(vec![shop_report_id], search_ids(&shop, "report").await);
+    assert_eq!(vec![report_id], search_ids(&datalith, "report").await);
+
+    // deleted resources are removed from the index
+    assert!(datalith.delete_resource_by_id(report_id).await.unwrap());
+    assert!(search_ids(&datalith, "report").await.is_empty());
+
+    datalith_close(datalith).await;
+}
+
+#[cfg(feature = "image-convert")]
+#[tokio::test]
+async fn search_images() {
+    let datalith = datalith_init().await;
+
+    let id = datalith
+        .put_image_by_buffer(
+            IMAGE_DATA.to_vec(),
+            Some("sunset-beach.png"),
+            Some(32),
+            None,
+            None,
+            true,
+        )
+        .await
+        .unwrap()
+        .id();
+
+    let hits = search(&datalith, "beach").await;
+    assert_eq!(1, hits.len());
+    assert_eq!(id, hits[0].id);
+    assert_eq!(DatalithSearchHitKind::Image, hits[0].kind);
+
+    assert!(datalith.delete_image_by_id(id).await.unwrap());
+    assert!(search_ids(&datalith, "beach").await.is_empty());
+
+    datalith_close(datalith).await;
+}
@@ -0,0 +1,192 @@
+mod global;
+
+use std::time::Duration;
+
+use datalith_core::{Datalith, Uuid};
+#[cfg(feature = "image-convert")]
+use datalith_core::{DatalithImageFormat, DatalithImageResolution};
+use global::*;
+
+#[inline]
+fn get_query_value<'a>(query_string: &'a str, key: &str) -> Option<&'a str> {
+    query_string.split('&').find_map(|pair| {
+        pair.split_once('=').and_then(|(k, v)| if k == key { Some(v) } else { None })
+    })
+}
+
+#[tokio::test]
+async fn sign_resource_url() {
+    let datalith = datalith_init().await;
+
+    {
+        let id = Uuid::new_v4();
+
+        let signed_url = datalith.sign_resource_url(id, Duration::from_secs(60), true);
+
+        let query_string = signed_url.query_string();
+        let expires = get_query_value(query_string, "expires").unwrap().parse::<i64>().unwrap();
+        let signature = get_query_value(query_string, "sig").unwrap();
+
+        assert_eq!(signature, signed_url.signature());
+        assert_eq!(Some("1"), get_query_value(query_string, "download"));
+        assert_eq!(expires, signed_url.expired_at().timestamp());
+
+        assert!(datalith.verify_resource_url(id, true, expires, signature));
+
+        // the disposition is covered by the signature
+        assert!(!datalith.verify_resource_url(id, false, expires, signature));
+        assert!(!datalith.verify_resource_url(Uuid::new_v4(), true, expires, signature));
+        assert!(!datalith.verify_resource_url(id, true, expires + 1, signature));
+        assert!(!datalith.verify_resource_url(id, true, expires, "00"));
+        assert!(!datalith.verify_resource_url(id, true, expires, "zz"));
+
+        let signed_url = datalith.sign_resource_url(id, Duration::ZERO, false);
+
+        let query_string = signed_url.query_string();
+        let expires = get_query_value(query_string, "expires").unwrap().parse::<i64>().unwrap();
+
+        assert!(!datalith.verify_resource_url(
+            id,
+            false,
+            expires,
+            get_query_value(query_string, "sig").unwrap()
+        ));
+    }
+
+    // the signing key is kept after reopening
+    {
+        let environment = datalith.get_environment().to_path_buf();
+
+        let id = Uuid::new_v4();
+
+        let signed_url = datalith.sign_resource_url(id, Duration::from_secs(60), false);
+        let expires = signed_url.expired_at().timestamp();
+
+        datalith.close().await;
+
+        let datalith = Datalith::new(environment).await.unwrap();
+
+        assert!(datalith.verify_resource_url(id, false, expires, signed_url.signature()));
+
+        datalith_close(datalith).await;
+    }
+}
+
+#[cfg(feature = "image-convert")]
+#[tokio::test]
+async fn sign_image_url() {
+    let datalith = datalith_init().await;
+
+    {
+        let id = Uuid::new_v4();
+
+        let signed_url = datalith.sign_image_url(
+            id,
+            DatalithImageResolution::Multiplier(2),
+            None,
+            true,
+            Duration::from_secs(60),
+            false,
+        );
+
+        let query_string = signed_url.query_string();
+        let expires = signed_url.expired_at().timestamp();
+        let signature = signed_url.signature();
+
+        assert_eq!(Some("2x"), get_query_value(query_string, "resolution"));
+        assert_eq!(Some("1"), get_query_value(query_string, "fallback"));
+        assert_eq!(None, get_query_value(query_string, "download"));
+
+        assert!(datalith.verify_image_url(
+            id,
+            DatalithImageResolution::Multiplier(2),
+            None,
+            true,
+            false,
+            expires,
+            signature
+        ));
+        assert!(!datalith.verify_image_url(
+            id,
+            DatalithImageResolution::Original,
+            None,
+            true,
+            false,
+            expires,
+            signature
+        ));
+        assert!(!datalith.verify_image_url(
+            id,
+            DatalithImageResolution::Multiplier(2),
+            None,
+            false,
+            false,
+            expires,
+            signature
+        ));
+        assert!(!datalith.verify_image_url(
+            id,
+            DatalithImageResolution::Multiplier(2),
+            None,
+            true,
+            true,
+            expires,
+            signature
+        ));
+        assert!(!datalith.verify_image_url(
+            id,
+            DatalithImageResolution::Multiplier(2),
+            Some(DatalithImageFormat::WebP),
+            true,
+            false,
+            expires,
+            signature
+        ));
+
+        // the format is covered by the signature
+        let signed_url = datalith.sign_image_url(
+            id,
+            DatalithImageResolution::Original,
+            Some(DatalithImageFormat::Jpeg),
+            false,
+            Duration::from_secs(60),
+            false,
+        );
+
+        let query_string = signed_url.query_string();
+        let expires = signed_url.expired_at().timestamp();
+        let signature = signed_url.signature();
+
+        assert_eq!(Some("jpg"), get_query_value(query_string, "format"));
+
+        assert!(datalith.verify_image_url(
+            id,
+            DatalithImageResolution::Original,
+            Some(DatalithImageFormat::Jpeg),
+            false,
+            false,
+            expires,
+            signature
+        ));
+        assert!(!datalith.verify_image_url(
+            id,
+            DatalithImageResolution::Original,
+            Some(DatalithImageFormat::Png),
+            false,
+            false,
+            expires,
+            signature
+        ));
+        assert!(!datalith.verify_image_url(
+            id,
+            DatalithImageResolution::Original,
+            None,
+            false,
+            false,
+            expires,
+            signature
+        ));
+    }
+
+    datalith_close(datalith).await;
+}
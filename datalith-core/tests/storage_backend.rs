@@ -0,0 +1,127 @@
+mod global;
+
+use std::{
+    collections::HashMap,
+    io,
+    io::Cursor,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use datalith_core::{
+    Datalith, StorageBackend, StorageReader, StorageStat, Uuid, async_trait::async_trait,
+};
+use global::*;
+use tokio::{fs, io::AsyncReadExt};
+
+/// A storage backend which keeps files in memory, standing in for a remote object storage.
+#[derive(Debug, Clone, Default)]
+struct MemoryStorageBackend {
+    files: Arc<Mutex<HashMap<Uuid, Vec<u8>>>>,
+}
+
+#[async_trait]
+impl StorageBackend for MemoryStorageBackend {
+    async fn put_from_temporary_file(
+        &self,
+        id: Uuid,
+        temporary_file_path: &Path,
+    ) -> io::Result<()> {
+        let data = fs::read(temporary_file_path).await?;
+
+        self.files.lock().unwrap().insert(id, data);
+
+        Ok(())
+    }
+
+    async fn open_reader(&self, id: Uuid) -> io::Result<Box<dyn StorageReader>> {
+        match self.files.lock().unwrap().get(&id) {
+            Some(data) => Ok(Box::new(Cursor::new(data.clone()))),
+            None => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    async fn delete(&self, id: Uuid) -> io::Result<()> {
+        self.files.lock().unwrap().remove(&id);
+
+        Ok(())
+    }
+
+    async fn list(&self) -> io::Result<Vec<Uuid>> {
+        Ok(self.files.lock().unwrap().keys().copied().collect())
+    }
+
+    async fn stat(&self, id: Uuid) -> io::Result<Option<StorageStat>> {
+        Ok(self.files.lock().unwrap().get(&id).map(|data| StorageStat {
+            size: data.len() as u64
+        }))
+    }
+}
+
+#[tokio::test]
+async fn custom_storage_backend() {
+    let storage_backend = MemoryStorageBackend::default();
+
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+
+    let datalith = Datalith::builder(
+        Path::new("tests").join("db").join(format!("storage_{}", timestamp.as_micros())),
+    )
+    .storage_backend(storage_backend.clone())
+    .build()
+    .await
+    .unwrap();
+
+    let image = IMAGE_DATA.as_ref();
+
+    {
+        let file = datalith.put_file_by_buffer(image, Some("image.png"), None, None).await.unwrap();
+        let file_id = file.id();
+
+        assert_eq!(
+            Some(StorageStat {
+                size: IMAGE_SIZE
+            }),
+            datalith.get_storage_backend().stat(file_id).await.unwrap()
+        );
+
+        let mut reader = file.create_reader().await.unwrap();
+        let mut buffer = Vec::with_capacity(file.file_size() as usize);
+        reader.read_to_end(&mut buffer).await.unwrap();
+        assert_eq!(image, buffer);
+
+        drop(file);
+
+        let file = datalith.put_file_by_path(IMAGE_PATH, None::<&str>, None, None).await.unwrap();
+        assert_eq!(file_id, file.id());
+        drop(file);
+
+        let file = datalith
+            .put_file_by_reader(image, Some("image.png"), None, Some(IMAGE_SIZE), None)
+            .await
+            .unwrap();
+        assert_eq!(file_id, file.id());
+        drop(file);
+
+        assert_eq!(vec![file_id], storage_backend.list().await.unwrap());
+
+        // untracked data in the storage backend
+        let untracked_id = Uuid::new_v4();
+        storage_backend.files.lock().unwrap().insert(untracked_id, b"Hello world!".to_vec());
+
+        assert_eq!(1, datalith.clear_untracked_files().await.unwrap());
+        assert_eq!(None, storage_backend.stat(untracked_id).await.unwrap());
+
+        for _ in 0..3 {
+            assert!(datalith.delete_file_by_id(file_id).await.unwrap());
+        }
+
+        assert!(storage_backend.list().await.unwrap().is_empty());
+
+        // no file is stored locally
+        assert!(!fs::try_exists(datalith.get_environment().join("datalith.files")).await.unwrap());
+    }
+
+    datalith_close(datalith).await;
+}
@@ -1,8 +1,7 @@
 mod global;
 
+use datalith_core::{DatalithWriteError, PATH_TEMPORARY_FILE_DIRECTORY, PaginationOptions};
 use global::*;
-#[cfg(feature = "image-convert")]
-use rdb_pagination::PaginationOptions;
 use tokio::{fs::File, io::AsyncReadExt};
 
 #[tokio::test]
@@ -14,7 +13,7 @@ async fn upload_download_data() {
     {
         let id = {
             let file = datalith
-                .put_file_by_buffer_temporarily(image, Some("image.png"), None)
+                .put_file_by_buffer_temporarily(image, Some("image.png"), None, None)
                 .await
                 .unwrap();
 
@@ -57,7 +56,8 @@ async fn upload_download_data() {
 
     {
         let id = {
-            let file = datalith.put_file_by_buffer(image, Some("image.png"), None).await.unwrap();
+            let file =
+                datalith.put_file_by_buffer(image, Some("image.png"), None, None).await.unwrap();
 
             #[cfg(feature = "magic")]
             assert_eq!(&mime::IMAGE_PNG, file.file_type());
@@ -102,7 +102,7 @@ async fn upload_download_data() {
     {
         let id = {
             let file = datalith
-                .put_file_by_path_temporarily(IMAGE_PATH, None::<&str>, None)
+                .put_file_by_path_temporarily(IMAGE_PATH, None::<&str>, None, None)
                 .await
                 .unwrap();
 
@@ -144,7 +144,8 @@ async fn upload_download_data() {
 
     {
         let id = {
-            let file = datalith.put_file_by_path(IMAGE_PATH, None::<&str>, None).await.unwrap();
+            let file =
+                datalith.put_file_by_path(IMAGE_PATH, None::<&str>, None, None).await.unwrap();
 
             assert_eq!(&mime::IMAGE_PNG, file.file_type());
             assert_eq!(IMAGE_SIZE, file.file_size());
@@ -195,6 +196,7 @@ async fn upload_download_data() {
                     Some("image.png"),
                     None,
                     Some(IMAGE_SIZE),
+                    None,
                 )
                 .await
                 .unwrap();
@@ -241,7 +243,7 @@ async fn upload_download_data() {
             let mut file = File::open(IMAGE_PATH).await.unwrap();
 
             let file = datalith
-                .put_file_by_reader(&mut file, Some("image.png"), None, Some(IMAGE_SIZE))
+                .put_file_by_reader(&mut file, Some("image.png"), None, Some(IMAGE_SIZE), None)
                 .await
                 .unwrap();
 
@@ -297,7 +299,7 @@ async fn resource_upload_download_data() {
     {
         let id = {
             let resource = datalith
-                .put_resource_by_buffer_temporarily(image, Some("image.png"), None)
+                .put_resource_by_buffer_temporarily(image, Some("image.png"), None, None)
                 .await
                 .unwrap();
 
@@ -322,6 +324,14 @@ async fn resource_upload_download_data() {
             resource.id()
         };
 
+        // peek
+        {
+            let resource = datalith.peek_resource_by_id(id).await.unwrap().unwrap();
+
+            assert_eq!("image.png", resource.file_name());
+            assert!(resource.is_temporary());
+        }
+
         // get
         {
             let resource = datalith.get_resource_by_id(id).await.unwrap().unwrap();
@@ -347,13 +357,16 @@ async fn resource_upload_download_data() {
 
         // temporarily resources can only get once
         assert!(datalith.get_resource_by_id(id).await.unwrap().is_none());
+        assert!(datalith.peek_resource_by_id(id).await.unwrap().is_none());
         assert!(!datalith.check_resource_exist(id).await.unwrap());
     }
 
     {
         let id = {
-            let resource =
-                datalith.put_resource_by_buffer(image, Some("image.png"), None).await.unwrap();
+            let resource = datalith
+                .put_resource_by_buffer(image, Some("image.png"), None, None)
+                .await
+                .unwrap();
 
             #[cfg(feature = "magic")]
             assert_eq!(&mime::IMAGE_PNG, resource.file_type());
@@ -411,7 +424,7 @@ async fn resource_upload_download_data() {
     {
         let id = {
             let resource = datalith
-                .put_resource_by_path_temporarily(IMAGE_PATH, Some("image.png"), None)
+                .put_resource_by_path_temporarily(IMAGE_PATH, Some("image.png"), None, None)
                 .await
                 .unwrap();
 
@@ -467,8 +480,10 @@ async fn resource_upload_download_data() {
 
     {
         let id = {
-            let resource =
-                datalith.put_resource_by_path(IMAGE_PATH, Some("image.png"), None).await.unwrap();
+            let resource = datalith
+                .put_resource_by_path(IMAGE_PATH, Some("image.png"), None, None)
+                .await
+                .unwrap();
 
             #[cfg(feature = "magic")]
             assert_eq!(&mime::IMAGE_PNG, resource.file_type());
@@ -533,6 +548,7 @@ async fn resource_upload_download_data() {
                     Some("image.png"),
                     None,
                     Some(IMAGE_SIZE),
+                    None,
                 )
                 .await
                 .unwrap();
@@ -592,7 +608,7 @@ async fn resource_upload_download_data() {
             let mut file = File::open(IMAGE_PATH).await.unwrap();
 
             let resource = datalith
-                .put_resource_by_reader(&mut file, Some("image.png"), None, Some(IMAGE_SIZE))
+                .put_resource_by_reader(&mut file, Some("image.png"), None, Some(IMAGE_SIZE), None)
                 .await
                 .unwrap();
 
@@ -842,3 +858,150 @@ async fn image_upload_download_data() {
 
     datalith_close(datalith).await;
 }
+
+#[tokio::test]
+async fn file_hash() {
+    const HASH: &str = "c0535e4be2b79ffd93291305436bf889314e4a3faec05ecffcbb7df31ad9e51a";
+
+    let datalith = datalith_init().await;
+
+    let id = {
+        let file = datalith
+            .put_file_by_buffer(b"Hello world!", Some("plain.txt"), None, None)
+            .await
+            .unwrap();
+
+        assert_eq!(HASH, to_hex(file.hash()));
+
+        file.id()
+    };
+
+    let file = datalith.get_file_by_id(id).await.unwrap().unwrap();
+    assert_eq!(HASH, to_hex(file.hash()));
+    drop(file);
+
+    datalith_close(datalith).await;
+}
+
+#[tokio::test]
+async fn expected_hash() {
+    let datalith = datalith_init().await;
+
+    let wrong_hash = [0u8; 32];
+    let mut hashes = Vec::with_capacity(2);
+
+    for temporary in [false, true] {
+        let result = if temporary {
+            datalith
+                .put_file_by_reader_temporarily(
+                    IMAGE_DATA.as_ref(),
+                    Some("image.png"),
+                    None,
+                    Some(IMAGE_SIZE),
+                    Some(&wrong_hash),
+                )
+                .await
+        } else {
+            datalith
+                .put_file_by_reader(
+                    IMAGE_DATA.as_ref(),
+                    Some("image.png"),
+                    None,
+                    Some(IMAGE_SIZE),
+                    Some(&wrong_hash),
+                )
+                .await
+        };
+
+        match result {
+            Err(DatalithWriteError::HashMismatch {
+                hash: actual_hash,
+                expected_hash,
+            }) => {
+                assert_eq!(wrong_hash, expected_hash);
+
+                hashes.push(actual_hash);
+            },
+            _ => panic!("the hash should not match"),
+        }
+    }
+
+    // the data is hashed even if it is put temporarily
+    assert_eq!(hashes[0], hashes[1]);
+
+    let hash = hashes[0];
+
+    assert!(matches!(
+        datalith.put_file_by_path(IMAGE_PATH, None::<&str>, None, Some(&wrong_hash)).await,
+        Err(DatalithWriteError::HashMismatch { .. })
+    ));
+    assert!(matches!(
+        datalith
+            .put_resource_by_path_temporarily(IMAGE_PATH, None::<&str>, None, Some(&wrong_hash))
+            .await,
+        Err(DatalithWriteError::HashMismatch { .. })
+    ));
+    assert!(matches!(
+        datalith
+            .put_resource_by_buffer(IMAGE_DATA.as_ref(), None::<&str>, None, Some(&wrong_hash))
+            .await,
+        Err(DatalithWriteError::HashMismatch { .. })
+    ));
+    assert!(matches!(
+        datalith
+            .put_file_by_buffer_temporarily(
+                IMAGE_DATA.as_ref(),
+                None::<&str>,
+                None,
+                Some(&wrong_hash)
+            )
+            .await,
+        Err(DatalithWriteError::HashMismatch { .. })
+    ));
+
+    // nothing is stored, and no temporary files are left
+    assert_eq!(0, datalith.list_file_ids(PaginationOptions::default()).await.unwrap().0.len());
+
+    let mut entries =
+        tokio::fs::read_dir(datalith.get_environment().join(PATH_TEMPORARY_FILE_DIRECTORY))
+            .await
+            .unwrap();
+
+    while let Some(entry) = entries.next_entry().await.unwrap() {
+        assert!(entry.file_type().await.unwrap().is_dir(), "{:?}", entry.path());
+    }
+
+    // the matched hash is accepted
+    let file = datalith
+        .put_file_by_reader(
+            IMAGE_DATA.as_ref(),
+            Some("image.png"),
+            None,
+            Some(IMAGE_SIZE),
+            Some(&hash),
+        )
+        .await
+        .unwrap();
+    assert_eq!(&hash, file.hash());
+    drop(file);
+
+    let file = datalith
+        .put_file_by_path_temporarily(IMAGE_PATH, None::<&str>, None, Some(&hash))
+        .await
+        .unwrap();
+    assert!(file.is_temporary());
+    drop(file);
+
+    let resource = datalith
+        .put_resource_by_buffer_temporarily(IMAGE_DATA.as_ref(), None::<&str>, None, Some(&hash))
+        .await
+        .unwrap();
+    assert_eq!(&hash, resource.file().hash());
+    drop(resource);
+
+    datalith_close(datalith).await;
+}
+
+fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{b:02x}")).collect()
+}
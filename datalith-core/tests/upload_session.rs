@@ -0,0 +1,178 @@
+mod global;
+
+use std::time::Duration;
+
+use datalith_core::{DatalithQuota, DatalithUploadSessionError, DatalithWriteError};
+use global::*;
+use tokio::{io::AsyncReadExt, time};
+
+#[tokio::test]
+async fn upload_session() {
+    let datalith = datalith_init().await;
+
+    let image = IMAGE_DATA.as_ref();
+
+    {
+        let session = datalith
+            .create_upload_session(Some(IMAGE_SIZE), Some("image.png"), None)
+            .await
+            .unwrap();
+        let id = session.id();
+
+        assert_eq!(Some(IMAGE_SIZE), session.upload_length());
+        assert_eq!(0, session.upload_offset());
+
+        assert_eq!(1000, datalith.append_upload_session(id, 0, &image[..1000]).await.unwrap());
+
+        match datalith.append_upload_session(id, 0, &image[1000..]).await {
+            Err(DatalithUploadSessionError::OffsetMismatch {
+                offset: 0,
+                expected_offset: 1000,
+            }) => (),
+            result => panic!("{result:?}"),
+        }
+
+        match datalith.finish_upload_session(id).await {
+            Err(DatalithUploadSessionError::Incomplete {
+                upload_offset: 1000, ..
+            }) => (),
+            result => panic!("{result:?}"),
+        }
+
+        // too much data
+        match datalith
+            .append_upload_session(id, 1000, [&image[1000..], b"0"].concat().as_slice())
+            .await
+        {
+            Err(DatalithUploadSessionError::LengthExceeded {
+                ..
+            }) => (),
+            result => panic!("{result:?}"),
+        }
+
+        let session = datalith.get_upload_session_by_id(id).await.unwrap().unwrap();
+        assert_eq!(1000, session.upload_offset());
+        assert!(!session.is_complete());
+
+        assert_eq!(
+            IMAGE_SIZE,
+            datalith.append_upload_session(id, 1000, &image[1000..]).await.unwrap()
+        );
+        assert!(datalith.get_upload_session_by_id(id).await.unwrap().unwrap().is_complete());
+
+        let resource = datalith.finish_upload_session(id).await.unwrap();
+        assert_eq!("image.png", resource.file_name());
+        assert_eq!(IMAGE_SIZE, resource.file().file_size());
+
+        let mut buffer = Vec::new();
+        resource.file().create_reader().await.unwrap().read_to_end(&mut buffer).await.unwrap();
+        assert_eq!(image, buffer);
+
+        assert!(datalith.get_upload_session_by_id(id).await.unwrap().is_none());
+
+        let resource_id = resource.id();
+        drop(resource);
+        assert!(datalith.delete_resource_by_id(resource_id).await.unwrap());
+    }
+
+    // deferred length
+    {
+        let session = datalith.create_upload_session(None, None::<&str>, None).await.unwrap();
+        let id = session.id();
+
+        assert_eq!(IMAGE_SIZE, datalith.append_upload_session(id, 0, image).await.unwrap());
+
+        let resource = datalith.finish_upload_session(id).await.unwrap();
+        assert_eq!(IMAGE_SIZE, resource.file().file_size());
+
+        let resource_id = resource.id();
+        drop(resource);
+        assert!(datalith.delete_resource_by_id(resource_id).await.unwrap());
+    }
+
+    // a failed finish keeps the session and its data
+    {
+        let session =
+            datalith.create_upload_session(Some(IMAGE_SIZE), None::<&str>, None).await.unwrap();
+        let id = session.id();
+
+        assert_eq!(IMAGE_SIZE, datalith.append_upload_session(id, 0, image).await.unwrap());
+
+        datalith.set_global_quota(DatalithQuota {
+            max_count: Some(0),
+            ..DatalithQuota::default()
+        });
+
+        match datalith.finish_upload_session(id).await {
+            Err(DatalithUploadSessionError::DatalithWriteError(
+                DatalithWriteError::QuotaExceeded {
+                    ..
+                },
+            )) => (),
+            result => panic!("{result:?}"),
+        }
+
+        assert!(datalith.get_upload_session_by_id(id).await.unwrap().unwrap().is_complete());
+
+        datalith.set_global_quota(DatalithQuota::default());
+
+        let resource = datalith.finish_upload_session(id).await.unwrap();
+
+        let mut buffer = Vec::new();
+        resource.file().create_reader().await.unwrap().read_to_end(&mut buffer).await.unwrap();
+        assert_eq!(image, buffer);
+
+        let resource_id = resource.id();
+        drop(resource);
+        assert!(datalith.delete_resource_by_id(resource_id).await.unwrap());
+    }
+
+    // termination
+    {
+        let session =
+            datalith.create_upload_session(Some(IMAGE_SIZE), None::<&str>, None).await.unwrap();
+        let id = session.id();
+
+        assert!(datalith.delete_upload_session_by_id(id).await.unwrap());
+        assert!(!datalith.delete_upload_session_by_id(id).await.unwrap());
+
+        match datalith.append_upload_session(id, 0, image).await {
+            Err(DatalithUploadSessionError::NotFound) => (),
+            result => panic!("{result:?}"),
+        }
+    }
+
+    datalith_close(datalith).await;
+}
+
+#[tokio::test]
+async fn upload_session_expiration() {
+    let datalith = datalith_init().await;
+
+    datalith.set_upload_session_lifespan(Duration::from_millis(500));
+
+    {
+        let session =
+            datalith.create_upload_session(Some(IMAGE_SIZE), None::<&str>, None).await.unwrap();
+        let id = session.id();
+
+        assert_eq!(0, datalith.clear_expired_upload_sessions().await.unwrap());
+
+        time::sleep(Duration::from_millis(300)).await;
+
+        // appending data extends the lifespan
+        assert_eq!(1000, datalith.append_upload_session(id, 0, &IMAGE_DATA[..1000]).await.unwrap());
+
+        time::sleep(Duration::from_millis(300)).await;
+
+        assert_eq!(0, datalith.clear_expired_upload_sessions().await.unwrap());
+        assert!(datalith.get_upload_session_by_id(id).await.unwrap().is_some());
+
+        time::sleep(Duration::from_millis(300)).await;
+
+        assert!(datalith.get_upload_session_by_id(id).await.unwrap().is_none());
+        assert_eq!(1, datalith.clear_expired_upload_sessions().await.unwrap());
+    }
+
+    datalith_close(datalith).await;
+}
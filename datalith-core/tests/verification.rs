@@ -0,0 +1,181 @@
+mod global;
+
+use std::{
+    io::ErrorKind,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use datalith_core::{
+    Datalith, DatalithFileProblem, DatalithVerifyOptions, FileTypeLevel, LocalStorageBackend,
+    PATH_FILE_DIRECTORY, Uuid, mime,
+};
+use global::*;
+use tokio::{fs, io::AsyncReadExt};
+
+#[inline]
+fn get_stored_file_path(datalith: &Datalith, id: Uuid) -> PathBuf {
+    LocalStorageBackend::new(datalith.get_environment().join(PATH_FILE_DIRECTORY)).get_file_path(id)
+}
+
+#[tokio::test]
+async fn verify_files() {
+    let datalith = datalith_init().await;
+
+    let image_id = datalith
+        .put_file_by_buffer(IMAGE_DATA.as_ref(), Some("image.png"), None, None)
+        .await
+        .unwrap()
+        .id();
+    let truncated_id = datalith
+        .put_file_by_buffer(
+            b"Hello world!",
+            Some("truncated.txt"),
+            Some((mime::TEXT_PLAIN, FileTypeLevel::Manual)),
+            None,
+        )
+        .await
+        .unwrap()
+        .id();
+    let missing_id = datalith
+        .put_file_by_buffer(
+            b"Goodbye world!",
+            Some("missing.txt"),
+            Some((mime::TEXT_PLAIN, FileTypeLevel::Manual)),
+            None,
+        )
+        .await
+        .unwrap()
+        .id();
+
+    assert!(datalith.get_file_by_id(image_id).await.unwrap().unwrap().verified_at().is_none());
+
+    // all files are fine
+    {
+        let report = datalith.verify_files(&DatalithVerifyOptions::default()).await.unwrap();
+
+        assert_eq!(3, report.verified);
+        assert!(report.problems.is_empty());
+        assert_eq!(IMAGE_SIZE + 12 + 14, report.read_bytes);
+
+        assert!(datalith.get_file_by_id(image_id).await.unwrap().unwrap().verified_at().is_some());
+    }
+
+    // recently verified files are skipped
+    {
+        let report = datalith
+            .verify_files(&DatalithVerifyOptions {
+                skip_verified_within: Duration::from_secs(60),
+                ..DatalithVerifyOptions::default()
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(0, report.verified);
+    }
+
+    // damage the stored data
+    let mut damaged_image = IMAGE_DATA.to_vec();
+    damaged_image[100] ^= 0xFF;
+
+    fs::write(get_stored_file_path(&datalith, image_id), damaged_image).await.unwrap();
+    fs::write(get_stored_file_path(&datalith, truncated_id), b"Hello").await.unwrap();
+    fs::remove_file(get_stored_file_path(&datalith, missing_id)).await.unwrap();
+
+    // report without quarantining
+    {
+        let report = datalith.verify_files(&DatalithVerifyOptions::default()).await.unwrap();
+
+        assert_eq!(3, report.verified);
+        assert_eq!(3, report.problems.len());
+        assert_eq!(0, report.quarantined);
+
+        for (id, problem) in report.problems.iter() {
+            if *id == image_id {
+                assert!(matches!(problem, DatalithFileProblem::HashMismatch));
+            } else if *id == truncated_id {
+                assert!(matches!(problem, DatalithFileProblem::SizeMismatch {
+                    expected_file_size: 12,
+                    actual_file_size:   5,
+                }));
+            } else {
+                assert_eq!(missing_id, *id);
+                assert!(matches!(problem, DatalithFileProblem::Missing));
+            }
+        }
+
+        let file = datalith.get_file_by_id(image_id).await.unwrap().unwrap();
+        assert!(!file.is_quarantined());
+        assert!(file.create_reader().await.is_ok());
+    }
+
+    // quarantine
+    {
+        let report = datalith
+            .verify_files(&DatalithVerifyOptions {
+                quarantine: true,
+                ..DatalithVerifyOptions::default()
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(3, report.problems.len());
+        assert_eq!(3, report.quarantined);
+
+        for id in [image_id, truncated_id, missing_id] {
+            let file = datalith.get_file_by_id(id).await.unwrap().unwrap();
+
+            assert!(file.is_quarantined());
+            assert_eq!(ErrorKind::InvalidData, file.create_reader().await.unwrap_err().kind());
+        }
+    }
+
+    // release after the data is restored
+    {
+        fs::write(get_stored_file_path(&datalith, image_id), IMAGE_DATA.as_ref()).await.unwrap();
+
+        let report = datalith
+            .verify_files(&DatalithVerifyOptions {
+                quarantine: true,
+                ..DatalithVerifyOptions::default()
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(2, report.problems.len());
+        assert_eq!(0, report.quarantined);
+        assert_eq!(1, report.released);
+
+        let file = datalith.get_file_by_id(image_id).await.unwrap().unwrap();
+        assert!(!file.is_quarantined());
+
+        let mut buffer = Vec::new();
+        file.create_reader().await.unwrap().read_to_end(&mut buffer).await.unwrap();
+        assert_eq!(IMAGE_DATA.as_ref(), buffer);
+    }
+
+    datalith_close(datalith).await;
+}
+
+#[tokio::test]
+async fn verify_files_throttled() {
+    let datalith = datalith_init().await;
+
+    datalith.put_file_by_buffer(IMAGE_DATA.as_ref(), Some("image.png"), None, None).await.unwrap();
+
+    let start = Instant::now();
+
+    let report = datalith
+        .verify_files(&DatalithVerifyOptions {
+            max_read_rate: Some(IMAGE_SIZE * 2),
+            ..DatalithVerifyOptions::default()
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(1, report.verified);
+    assert!(report.problems.is_empty());
+    assert!(start.elapsed() >= Duration::from_millis(400));
+
+    datalith_close(datalith).await;
+}
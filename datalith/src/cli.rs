@@ -7,8 +7,12 @@ use std::{
 };
 
 use byte_unit::Byte;
-use clap::{CommandFactory, FromArgMatches, Parser};
+use clap::{
+    CommandFactory, FromArgMatches, Parser, Subcommand,
+    builder::{BoolishValueParser, NonEmptyStringValueParser},
+};
 use concat_with::concat_line;
+use datalith_core::{DatalithApiKeyScopes, DatalithLowWaterMark, uuid::Uuid};
 use terminal_size::terminal_size;
 
 const APP_NAME: &str = "Datalith";
@@ -20,6 +24,12 @@ const APP_ABOUT: &str = concat!(
     concat_line!(prefix "datalith ",
         "                     # Start the service using the current working directory as the root of the environment",
         "--environment ./db   # Start the service using `./db` as the root of the environment",
+        "--auth               # Start the service and require API keys",
+        "--api-key my-secret  # Start the service and require API keys, accepting `my-secret` as a key with all scopes",
+        "api-key create --name uploader --scopes read,write   # Create an API key which can read and upload files",
+        "api-key create --name shop --namespace shop          # Create an API key which can only read files in the `shop` namespace",
+        "api-key list                                         # List all API keys",
+        "quota set --namespace shop --max-bytes \"10 GiB\"      # Limit the total size of the resources and images in the `shop` namespace",
     )
 );
 
@@ -29,6 +39,9 @@ const APP_ABOUT: &str = concat!(
 #[command(version = CARGO_PKG_VERSION)]
 #[command(author = CARGO_PKG_AUTHORS)]
 pub struct CLIArgs {
+    #[command(subcommand)]
+    pub command: Option<CLICommands>,
+
     #[arg(long, visible_alias = "addr", env = "DATALITH_ADDRESS")]
     #[cfg_attr(debug_assertions, arg(default_value = "127.0.0.1"))]
     #[cfg_attr(not(debug_assertions), arg(default_value = "0.0.0.0"))]
@@ -47,11 +60,37 @@ pub struct CLIArgs {
     #[arg(help = "Assign the root path of the environment. This should be a directory path")]
     pub environment: PathBuf,
 
+    #[arg(long, env = "DATALITH_FILE_DIRECTORY_DEPTH")]
+    #[arg(value_parser = clap::value_parser!(u8).range(0..=8))]
+    #[arg(help = "Assign the depth of nested directories for storing files")]
+    #[arg(long_help = "Assign the depth of nested directories for storing files. It is decided \
+                       when the environment is created, and the environment cannot be opened \
+                       with a different depth afterwards. The default depth is 2")]
+    pub file_directory_depth: Option<u8>,
+
     #[arg(long, env = "DATALITH_MAX_FILE_SIZE")]
     #[arg(default_value = "2 GiB")]
     #[arg(help = "Assign the maximum file size (in bytes) for each of the uploaded files")]
     pub max_file_size: Byte,
 
+    #[arg(long, env = "DATALITH_QUOTA_BYTES")]
+    #[arg(help = "Assign the maximum total size (in bytes) of the resources and images in all \
+                  namespaces")]
+    pub quota_bytes: Option<Byte>,
+
+    #[arg(long, env = "DATALITH_QUOTA_COUNT")]
+    #[arg(help = "Assign the maximum number of the resources and images in all namespaces")]
+    pub quota_count: Option<u64>,
+
+    #[arg(long, env = "DATALITH_LOW_WATER_MARK")]
+    #[arg(help = "Assign the free disk space which must be kept, in bytes or in percent (e.g. \
+                  \"1 GiB\" or \"5%\")")]
+    #[arg(long_help = "Assign the free disk space which must be kept, in bytes or in percent \
+                       (e.g. \"1 GiB\" or \"5%\"). Uploads are refused if they would make the \
+                       free space of the volume of the environment fall below it")]
+    #[arg(value_parser = parse_low_water_mark)]
+    pub low_water_mark: Option<DatalithLowWaterMark>,
+
     #[arg(long, env = "DATALITH_TEMPORARY_FILE_LIFESPAN")]
     #[arg(default_value = "60")]
     #[arg(help = "Assign the lifespan (in seconds) for each of the uploaded temporary files")]
@@ -60,6 +99,64 @@ pub struct CLIArgs {
     #[arg(value_parser = parse_duration_sec)]
     pub temporary_file_lifespan: Duration,
 
+    #[arg(long, env = "DATALITH_UPLOAD_SESSION_LIFESPAN")]
+    #[arg(default_value = "86400")]
+    #[arg(help = "Assign the lifespan (in seconds) for each of the resumable upload sessions")]
+    #[arg(long_help = "Assign the lifespan (in seconds) for each of the resumable upload \
+                       sessions. A session expires if no data is appended to it within its \
+                       lifespan. The lifespan ranges from 1 second to 10,000 hours")]
+    #[arg(value_parser = parse_duration_sec)]
+    pub upload_session_lifespan: Duration,
+
+    #[arg(long, env = "DATALITH_AUTH")]
+    #[arg(help = "Require an API key (`Authorization: Bearer <key>`) for each of the requests")]
+    pub auth: bool,
+
+    #[arg(long, env = "DATALITH_API_KEY", hide_env_values = true)]
+    #[arg(value_parser = NonEmptyStringValueParser::new())]
+    #[arg(help = "Assign a static API key which has all scopes in all namespaces")]
+    #[arg(long_help = "Assign a static API key which has all scopes in all namespaces. It is \
+                       accepted besides the API keys stored in the environment, which cannot be \
+                       managed while the service is running. It implies `--auth`")]
+    pub api_key: Option<String>,
+
+    #[arg(long, env = "DATALITH_ANONYMOUS_FETCH")]
+    #[arg(num_args = 0..=1, default_value = "true", default_missing_value = "true")]
+    #[arg(value_parser = BoolishValueParser::new())]
+    #[arg(help = "Allow fetching files in the default namespace without an API key when \
+                  `--auth` is enabled")]
+    pub anonymous_fetch: bool,
+
+    #[arg(long, env = "DATALITH_VERIFY_READ_RATE")]
+    #[arg(default_value = "16 MiB")]
+    #[arg(help = "Assign the maximum reading rate (in bytes per second) for verifying the \
+                  stored files in the background. `0` means unlimited")]
+    pub verify_read_rate: Byte,
+
+    #[arg(long, env = "DATALITH_QUARANTINE_CORRUPT_FILES")]
+    #[arg(help = "Quarantine the stored files which are found corrupt in the background \
+                  verification, so that they cannot be fetched")]
+    pub quarantine_corrupt_files: bool,
+
+    #[cfg(feature = "compression")]
+    #[arg(long, env = "DATALITH_COMPRESSION")]
+    #[arg(help = "Compress the uploaded text-based files (at least 512 bytes) with Zstandard. \
+                  Files are not compressed by default")]
+    pub compression: bool,
+
+    #[cfg(feature = "encryption")]
+    #[arg(long, env = "DATALITH_ENCRYPTION_KEY_FILE")]
+    #[arg(value_hint = clap::ValueHint::FilePath)]
+    #[arg(help = "Assign a keyfile for encrypting the stored files. The keyfile contains 32 raw \
+                  bytes or 64 hexadecimal digits")]
+    pub encryption_key_file: Option<PathBuf>,
+
+    #[cfg(feature = "encryption")]
+    #[arg(long, env = "DATALITH_ENCRYPTION_KEY", hide_env_values = true)]
+    #[arg(conflicts_with = "encryption_key_file")]
+    #[arg(help = "Assign a key (64 hexadecimal digits) for encrypting the stored files")]
+    pub encryption_key: Option<String>,
+
     #[cfg(feature = "image-convert")]
     #[arg(long, env = "DATALITH_MAX_IMAGE_RESOLUTION")]
     #[arg(default_value = "50000000")]
@@ -73,6 +170,93 @@ pub struct CLIArgs {
     pub max_image_resolution_multiplier: u8,
 }
 
+#[derive(Debug, Subcommand)]
+pub enum CLICommands {
+    #[command(about = "Manage the API keys")]
+    ApiKey {
+        #[command(subcommand)]
+        command: ApiKeyCommands,
+    },
+    #[command(about = "Back up the whole environment into a directory")]
+    #[command(long_about = "Back up the whole environment into a directory, which can be used as \
+                            an environment directly. The directory must not exist or must be \
+                            empty. The environment is opened read-only, so the service can keep \
+                            running")]
+    Backup {
+        #[arg(value_hint = clap::ValueHint::DirPath)]
+        #[arg(help = "The directory where the backup is created")]
+        path: PathBuf,
+    },
+    #[command(about = "Check the consistency between the metadata and the stored files")]
+    #[command(long_about = "Check the consistency between the metadata and the stored files, \
+                            including the files whose data is missing, the resources and images \
+                            pointing at nonexistent files and the reference counts which are too \
+                            small. The service should not be running")]
+    Fsck {
+        #[arg(long)]
+        #[arg(help = "Repair the problems. The broken resources and images are removed")]
+        repair: bool,
+    },
+    #[command(about = "Manage the quotas of namespaces")]
+    Quota {
+        #[command(subcommand)]
+        command: QuotaCommands,
+    },
+    #[cfg(feature = "encryption")]
+    #[command(about = "Encrypt the stored files which were put before the encryption was enabled")]
+    #[command(long_about = "Encrypt the stored files which were put before the encryption was \
+                            enabled. The service should not be running")]
+    Encrypt,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ApiKeyCommands {
+    #[command(about = "Create an API key and print it")]
+    Create {
+        #[arg(long)]
+        #[arg(help = "Assign a name to the API key")]
+        name:       String,
+        #[arg(long)]
+        #[arg(default_value = "read")]
+        #[arg(value_parser = parse_api_key_scopes)]
+        #[arg(help = "Assign the scopes of the API key, separated by commas (read, write, delete)")]
+        scopes:     DatalithApiKeyScopes,
+        #[arg(long = "namespace")]
+        #[arg(help = "Restrict the API key to a namespace. This can be repeated. The default \
+                      namespace is an empty string. If this is not set, the API key can access \
+                      all namespaces")]
+        namespaces: Vec<String>,
+    },
+    #[command(about = "List all API keys")]
+    List,
+    #[command(about = "Delete an API key")]
+    Delete {
+        #[arg(help = "The ID of the API key")]
+        id: Uuid,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum QuotaCommands {
+    #[command(about = "List the usage and the quota of every namespace")]
+    List,
+    #[command(about = "Set the quota of a namespace")]
+    Set {
+        #[arg(long)]
+        #[arg(default_value = "")]
+        #[arg(help = "The namespace. The default namespace is an empty string")]
+        namespace: String,
+        #[arg(long)]
+        #[arg(help = "Assign the maximum total size (in bytes) of the resources and images. If \
+                      this is not set, the size is unlimited")]
+        max_bytes: Option<Byte>,
+        #[arg(long)]
+        #[arg(help = "Assign the maximum number of the resources and images. If this is not \
+                      set, the number is unlimited")]
+        max_count: Option<u64>,
+    },
+}
+
 #[inline]
 fn parse_ip_addr(arg: &str) -> Result<IpAddr, AddrParseError> {
     IpAddr::from_str(arg)
@@ -83,6 +267,36 @@ fn parse_duration_sec(arg: &str) -> Result<Duration, ParseIntError> {
     Ok(Duration::from_secs(arg.parse()?))
 }
 
+fn parse_low_water_mark(arg: &str) -> Result<DatalithLowWaterMark, String> {
+    match arg.trim().strip_suffix('%') {
+        Some(percent) => match percent.trim().parse::<f64>() {
+            Ok(percent) if (0.0..=100.0).contains(&percent) => {
+                Ok(DatalithLowWaterMark::Percent(percent))
+            },
+            _ => Err(format!("invalid percentage {arg:?}")),
+        },
+        None => Byte::parse_str(arg, true)
+            .map(|e| DatalithLowWaterMark::Bytes(e.as_u64()))
+            .map_err(|error| error.to_string()),
+    }
+}
+
+fn parse_api_key_scopes(arg: &str) -> Result<DatalithApiKeyScopes, String> {
+    let mut scopes = DatalithApiKeyScopes::default();
+
+    for scope in arg.split(',') {
+        match scope.trim() {
+            "read" => scopes.read = true,
+            "write" => scopes.write = true,
+            "delete" => scopes.delete = true,
+            "all" => scopes = DatalithApiKeyScopes::ALL,
+            scope => return Err(format!("unknown scope {scope:?}")),
+        }
+    }
+
+    Ok(scopes)
+}
+
 pub fn get_args() -> CLIArgs {
     let args = CLIArgs::command();
 
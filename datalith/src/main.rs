@@ -5,18 +5,49 @@ mod cli;
 mod rocket_mounts;
 
 use cli::*;
-use datalith_core::{Datalith, DatalithManager};
+#[cfg(feature = "compression")]
+use datalith_core::DatalithCompressionPolicy;
+#[cfg(feature = "encryption")]
+use datalith_core::DatalithEncryptionKey;
+use datalith_core::{Datalith, DatalithManager, DatalithQuota, DatalithVerifyOptions};
 use rocket::{Ignite, Rocket};
 
 fn main() -> anyhow::Result<()> {
-    let args = get_args();
+    let mut args = get_args();
 
-    let rocket = rocket_mounts::create(args.address, args.listen_port, args.max_file_size.as_u64());
+    if let Some(command) = args.command.take() {
+        return rocket::execute(run_command(&args, command));
+    }
+
+    let rocket = rocket_mounts::create(
+        args.address,
+        args.listen_port,
+        args.max_file_size.as_u64(),
+        args.auth || args.api_key.is_some(),
+        args.anonymous_fetch,
+        args.api_key.clone(),
+    );
 
     rocket::execute(async {
-        let datalith = Datalith::new(args.environment).await?;
+        let datalith = create_datalith(&args, false).await?;
 
         datalith.set_temporary_file_lifespan(args.temporary_file_lifespan);
+        datalith.set_upload_session_lifespan(args.upload_session_lifespan);
+        datalith.set_global_quota(DatalithQuota {
+            max_bytes: args.quota_bytes.map(|e| e.as_u64()),
+            max_count: args.quota_count,
+        });
+        datalith.set_low_water_mark(args.low_water_mark);
+        datalith.set_background_verify_options(DatalithVerifyOptions {
+            max_read_rate: Some(args.verify_read_rate.as_u64()),
+            quarantine: args.quarantine_corrupt_files,
+            ..DatalithVerifyOptions::background()
+        });
+
+        #[cfg(feature = "compression")]
+        if args.compression {
+            datalith.set_compression_policy(DatalithCompressionPolicy::text());
+        }
 
         #[cfg(feature = "image-convert")]
         {
@@ -33,3 +64,205 @@ fn main() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+async fn create_datalith(args: &CLIArgs, read_only: bool) -> anyhow::Result<Datalith> {
+    let mut builder = Datalith::builder(args.environment.as_path()).read_only(read_only);
+
+    if let Some(file_directory_depth) = args.file_directory_depth {
+        builder = builder.file_directory_depth(file_directory_depth);
+    }
+
+    #[cfg(feature = "encryption")]
+    {
+        if let Some(encryption_key_file) = args.encryption_key_file.as_ref() {
+            builder = builder
+                .encryption_key(DatalithEncryptionKey::from_file(encryption_key_file).await?);
+        } else if let Some(encryption_key) = args.encryption_key.as_ref() {
+            builder = builder.encryption_key(DatalithEncryptionKey::from_hex(encryption_key)?);
+        }
+    }
+
+    Ok(builder.build().await?)
+}
+
+async fn run_command(args: &CLIArgs, command: CLICommands) -> anyhow::Result<()> {
+    // these commands can run while the service is running
+    let read_only = matches!(
+        command,
+        CLICommands::Backup { .. }
+            | CLICommands::ApiKey {
+                command: ApiKeyCommands::List
+            }
+            | CLICommands::Quota {
+                command: QuotaCommands::List
+            }
+    );
+
+    let datalith = create_datalith(args, read_only).await?;
+
+    let result = match command {
+        CLICommands::ApiKey {
+            command,
+        } => run_api_key_command(&datalith, command).await,
+        CLICommands::Backup {
+            path,
+        } => match datalith.backup_to(path.as_path()).await {
+            Ok(counter) => {
+                println!("{counter} file(s) have been backed up to {path:?}.");
+
+                Ok(())
+            },
+            Err(error) => Err(error.into()),
+        },
+        CLICommands::Fsck {
+            repair,
+        } => run_fsck_command(&datalith, repair).await,
+        CLICommands::Quota {
+            command,
+        } => run_quota_command(&datalith, command).await,
+        #[cfg(feature = "encryption")]
+        CLICommands::Encrypt => match datalith.encrypt_existing_files().await {
+            Ok(counter) => {
+                println!("{counter} file(s) have been encrypted.");
+
+                Ok(())
+            },
+            Err(error) => Err(error.into()),
+        },
+    };
+
+    datalith.close().await;
+
+    result
+}
+
+async fn run_fsck_command(datalith: &Datalith, repair: bool) -> anyhow::Result<()> {
+    let report = datalith.check_consistency(repair).await?;
+
+    for id in report.missing_file_data.iter() {
+        println!("The data of the file {id} is missing.");
+    }
+
+    for id in report.dangling_resources.iter() {
+        println!("The resource {id} points at a nonexistent file.");
+    }
+
+    for id in report.dangling_images.iter() {
+        println!("The image {id} points at a nonexistent file.");
+    }
+
+    for (id, count, expected_count) in report.count_mismatches.iter() {
+        println!(
+            "The reference count of the file {id} is {count}, less than its {expected_count} \
+             reference(s)."
+        );
+    }
+
+    for namespace in report.usage_mismatches.iter() {
+        println!("The usage of the namespace {namespace:?} is miscounted.");
+    }
+
+    if report.is_consistent() {
+        println!("No problem has been found.");
+    } else if report.repaired {
+        println!("The problems have been repaired.");
+    } else {
+        anyhow::bail!("some problems have been found, run with `--repair` to repair them");
+    }
+
+    Ok(())
+}
+
+async fn run_quota_command(datalith: &Datalith, command: QuotaCommands) -> anyhow::Result<()> {
+    match command {
+        QuotaCommands::List => {
+            for namespace in datalith.list_namespaces().await? {
+                let datalith = datalith.with_namespace(namespace.as_str()).unwrap();
+
+                let usage = datalith.get_usage().await?;
+                let quota = datalith.get_namespace_quota().await?;
+
+                let format_limit = |limit: Option<u64>| match limit {
+                    Some(limit) => limit.to_string(),
+                    None => String::from("unlimited"),
+                };
+
+                println!(
+                    "{namespace:?}\t{}/{} bytes\t{}/{} items",
+                    usage.bytes,
+                    format_limit(quota.max_bytes),
+                    usage.count,
+                    format_limit(quota.max_count)
+                );
+            }
+        },
+        QuotaCommands::Set {
+            namespace,
+            max_bytes,
+            max_count,
+        } => {
+            let Some(datalith) = datalith.with_namespace(namespace.as_str()) else {
+                anyhow::bail!("the namespace {namespace:?} is invalid");
+            };
+
+            datalith
+                .set_namespace_quota(DatalithQuota {
+                    max_bytes: max_bytes.map(|e| e.as_u64()),
+                    max_count,
+                })
+                .await?;
+        },
+    }
+
+    Ok(())
+}
+
+async fn run_api_key_command(datalith: &Datalith, command: ApiKeyCommands) -> anyhow::Result<()> {
+    match command {
+        ApiKeyCommands::Create {
+            name,
+            scopes,
+            namespaces,
+        } => {
+            let namespaces = if namespaces.is_empty() { None } else { Some(namespaces) };
+
+            let (api_key, key) = datalith.create_api_key(name, scopes, namespaces).await?;
+
+            println!("ID: {}", api_key.id());
+            println!("Scopes: {}", api_key.scopes());
+
+            if let Some(namespaces) = api_key.namespaces() {
+                println!("Namespaces: {}", format_namespaces(namespaces));
+            }
+
+            println!("Key: {key}");
+            eprintln!("The key cannot be shown again. Please store it safely.");
+        },
+        ApiKeyCommands::List => {
+            for api_key in datalith.list_api_keys().await? {
+                println!(
+                    "{}\t{}\t{}\t{}\t{}",
+                    api_key.id(),
+                    api_key.created_at().to_rfc3339(),
+                    api_key.scopes(),
+                    api_key.namespaces().map(format_namespaces).unwrap_or_else(|| "*".into()),
+                    api_key.name()
+                );
+            }
+        },
+        ApiKeyCommands::Delete {
+            id,
+        } => {
+            if !datalith.delete_api_key_by_id(id).await? {
+                anyhow::bail!("the API key {id} does not exist");
+            }
+        },
+    }
+
+    Ok(())
+}
+
+#[inline]
+fn format_namespaces(namespaces: &[String]) -> String {
+    namespaces.iter().map(|e| format!("{e:?}")).collect::<Vec<_>>().join(",")
+}
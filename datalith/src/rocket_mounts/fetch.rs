@@ -1,21 +1,48 @@
-use datalith_core::DatalithManager;
-use rocket::{Build, Rocket, State, http::Status, serde::uuid::Uuid};
+use datalith_core::parse_sha256_hex;
+use rocket::{Build, Rocket, http::Status, serde::uuid::Uuid};
 use rocket_cache_response::CacheResponse;
 use rocket_etag_if_none_match::EtagIfNoneMatch;
 
-use crate::rocket_mounts::{Boolean, rocket_utils::DatalithResponse};
+use crate::rocket_mounts::{
+    Boolean,
+    rocket_utils::{
+        AcceptEncoding, DatalithResponse, FetchScope, NamespacedDatalith, Preconditions,
+    },
+};
 
-#[get("/<id>?<download>")]
-async fn get(
+#[allow(clippy::too_many_arguments)]
+async fn fetch(
+    scope: FetchScope,
     etag_if_none_match: &EtagIfNoneMatch<'_>,
-    file_center: &State<DatalithManager>,
+    preconditions: Preconditions<'_>,
+    accept_encoding: AcceptEncoding<'_>,
+    file_center: NamespacedDatalith,
     id: Uuid,
     download: Option<Boolean>,
+    expires: Option<i64>,
+    sig: Option<&str>,
+    head: bool,
 ) -> Result<CacheResponse<DatalithResponse>, Status> {
     let download = download.map(|e| e.0).unwrap_or(false);
 
-    match DatalithResponse::from_resource_id(file_center.inner(), etag_if_none_match, id, download)
-        .await
+    if let FetchScope::Signed = scope {
+        match (expires, sig) {
+            (Some(expires), Some(sig))
+                if file_center.verify_resource_url(id, download, expires, sig) => {},
+            _ => return Err(Status::Forbidden),
+        }
+    }
+
+    match DatalithResponse::from_resource_id(
+        &file_center,
+        etag_if_none_match,
+        &preconditions,
+        &accept_encoding,
+        id,
+        download,
+        head,
+    )
+    .await
     {
         Ok(Some(response)) => {
             if response.is_temporary() {
@@ -33,7 +60,154 @@ async fn get(
     }
 }
 
+#[get("/<id>?<download>&<expires>&<sig>")]
+#[allow(clippy::too_many_arguments)]
+async fn get(
+    scope: FetchScope,
+    etag_if_none_match: &EtagIfNoneMatch<'_>,
+    preconditions: Preconditions<'_>,
+    accept_encoding: AcceptEncoding<'_>,
+    file_center: NamespacedDatalith,
+    id: Uuid,
+    download: Option<Boolean>,
+    expires: Option<i64>,
+    sig: Option<&str>,
+) -> Result<CacheResponse<DatalithResponse>, Status> {
+    fetch(
+        scope,
+        etag_if_none_match,
+        preconditions,
+        accept_encoding,
+        file_center,
+        id,
+        download,
+        expires,
+        sig,
+        false,
+    )
+    .await
+}
+
+/// Respond the headers of `get` without reading the data, and without consuming a temporary resource.
+#[head("/<id>?<download>&<expires>&<sig>")]
+#[allow(clippy::too_many_arguments)]
+async fn head(
+    scope: FetchScope,
+    etag_if_none_match: &EtagIfNoneMatch<'_>,
+    preconditions: Preconditions<'_>,
+    accept_encoding: AcceptEncoding<'_>,
+    file_center: NamespacedDatalith,
+    id: Uuid,
+    download: Option<Boolean>,
+    expires: Option<i64>,
+    sig: Option<&str>,
+) -> Result<CacheResponse<DatalithResponse>, Status> {
+    fetch(
+        scope,
+        etag_if_none_match,
+        preconditions,
+        accept_encoding,
+        file_center,
+        id,
+        download,
+        expires,
+        sig,
+        true,
+    )
+    .await
+}
+
+/// Find the resource whose data has the SHA-256 hash and respond it as `fetch` does. URLs by hashes cannot be signed.
+#[allow(clippy::too_many_arguments)]
+async fn fetch_by_hash(
+    scope: FetchScope,
+    etag_if_none_match: &EtagIfNoneMatch<'_>,
+    preconditions: Preconditions<'_>,
+    accept_encoding: AcceptEncoding<'_>,
+    file_center: NamespacedDatalith,
+    sha256: &str,
+    download: Option<Boolean>,
+    head: bool,
+) -> Result<CacheResponse<DatalithResponse>, Status> {
+    if let FetchScope::Signed = scope {
+        return Err(Status::Forbidden);
+    }
+
+    let hash = parse_sha256_hex(sha256).ok_or(Status::BadRequest)?;
+
+    let id = match file_center.get_resource_by_hash(&hash).await {
+        Ok(Some(resource)) => resource.id(),
+        Ok(None) => return Err(Status::NotFound),
+        Err(error) => {
+            rocket::error!("{error}");
+
+            return Err(Status::InternalServerError);
+        },
+    };
+
+    fetch(
+        FetchScope::Authorized,
+        etag_if_none_match,
+        preconditions,
+        accept_encoding,
+        file_center,
+        id,
+        download,
+        None,
+        None,
+        head,
+    )
+    .await
+}
+
+#[get("/<sha256>?<download>")]
+async fn get_by_hash(
+    scope: FetchScope,
+    etag_if_none_match: &EtagIfNoneMatch<'_>,
+    preconditions: Preconditions<'_>,
+    accept_encoding: AcceptEncoding<'_>,
+    file_center: NamespacedDatalith,
+    sha256: &str,
+    download: Option<Boolean>,
+) -> Result<CacheResponse<DatalithResponse>, Status> {
+    fetch_by_hash(
+        scope,
+        etag_if_none_match,
+        preconditions,
+        accept_encoding,
+        file_center,
+        sha256,
+        download,
+        false,
+    )
+    .await
+}
+
+/// Respond the headers of `get_by_hash` without reading the data. This can be used to check whether the data has been stored.
+#[head("/<sha256>?<download>")]
+async fn head_by_hash(
+    scope: FetchScope,
+    etag_if_none_match: &EtagIfNoneMatch<'_>,
+    preconditions: Preconditions<'_>,
+    accept_encoding: AcceptEncoding<'_>,
+    file_center: NamespacedDatalith,
+    sha256: &str,
+    download: Option<Boolean>,
+) -> Result<CacheResponse<DatalithResponse>, Status> {
+    fetch_by_hash(
+        scope,
+        etag_if_none_match,
+        preconditions,
+        accept_encoding,
+        file_center,
+        sha256,
+        download,
+        true,
+    )
+    .await
+}
+
 #[inline]
 pub fn mounts(rocket: Rocket<Build>) -> Rocket<Build> {
-    rocket.mount("/f", routes![get])
+    rocket.mount("/f", routes![get, head]).mount("/h", routes![get_by_hash, head_by_hash])
 }
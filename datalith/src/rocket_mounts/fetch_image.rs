@@ -1,32 +1,65 @@
-use datalith_core::DatalithManager;
-use rocket::{Build, Rocket, State, http::Status, serde::uuid::Uuid};
+use rocket::{Build, Rocket, http::Status, serde::uuid::Uuid};
 use rocket_cache_response::CacheResponse;
 use rocket_etag_if_none_match::EtagIfNoneMatch;
 
 use crate::rocket_mounts::{
     Boolean,
-    rocket_utils::{DatalithResponse, ResolutionType},
+    rocket_utils::{
+        AcceptEncoding, AcceptHeader, DatalithResponse, FetchScope, ImageFormat,
+        NamespacedDatalith, Preconditions, ResolutionType,
+    },
 };
 
-#[get("/<id>?<resolution>&<fallback>&<download>")]
-async fn get(
+#[allow(clippy::too_many_arguments)]
+async fn fetch(
+    scope: FetchScope,
     etag_if_none_match: &EtagIfNoneMatch<'_>,
-    file_center: &State<DatalithManager>,
+    preconditions: Preconditions<'_>,
+    accept_encoding: AcceptEncoding<'_>,
+    accept: AcceptHeader<'_>,
+    file_center: NamespacedDatalith,
     id: Uuid,
     resolution: Option<ResolutionType>,
+    format: Option<ImageFormat>,
     fallback: Option<Boolean>,
     download: Option<Boolean>,
+    expires: Option<i64>,
+    sig: Option<&str>,
+    head: bool,
 ) -> Result<CacheResponse<DatalithResponse>, Status> {
-    let fallback = fallback.map(|e| e.0).unwrap_or(false);
+    let fallback = fallback.map(|e| e.0);
     let download = download.map(|e| e.0).unwrap_or(false);
 
+    if let FetchScope::Signed = scope {
+        let resolution = resolution.map(|e| e.into()).unwrap_or_default();
+
+        match (expires, sig) {
+            (Some(expires), Some(sig))
+                if file_center.verify_image_url(
+                    id,
+                    resolution,
+                    format.map(|e| e.0),
+                    fallback.unwrap_or(false),
+                    download,
+                    expires,
+                    sig,
+                ) => {},
+            _ => return Err(Status::Forbidden),
+        }
+    }
+
     match DatalithResponse::from_image_id(
-        file_center.inner(),
+        &file_center,
         etag_if_none_match,
+        &preconditions,
+        &accept_encoding,
+        &accept,
         id,
         resolution,
+        format.map(|e| e.0),
         fallback,
         download,
+        head,
     )
     .await
     {
@@ -40,7 +73,80 @@ async fn get(
     }
 }
 
+#[get("/<id>?<resolution>&<format>&<fallback>&<download>&<expires>&<sig>")]
+#[allow(clippy::too_many_arguments)]
+async fn get(
+    scope: FetchScope,
+    etag_if_none_match: &EtagIfNoneMatch<'_>,
+    preconditions: Preconditions<'_>,
+    accept_encoding: AcceptEncoding<'_>,
+    accept: AcceptHeader<'_>,
+    file_center: NamespacedDatalith,
+    id: Uuid,
+    resolution: Option<ResolutionType>,
+    format: Option<ImageFormat>,
+    fallback: Option<Boolean>,
+    download: Option<Boolean>,
+    expires: Option<i64>,
+    sig: Option<&str>,
+) -> Result<CacheResponse<DatalithResponse>, Status> {
+    fetch(
+        scope,
+        etag_if_none_match,
+        preconditions,
+        accept_encoding,
+        accept,
+        file_center,
+        id,
+        resolution,
+        format,
+        fallback,
+        download,
+        expires,
+        sig,
+        false,
+    )
+    .await
+}
+
+/// Respond the headers of `get` without reading the data.
+#[head("/<id>?<resolution>&<format>&<fallback>&<download>&<expires>&<sig>")]
+#[allow(clippy::too_many_arguments)]
+async fn head(
+    scope: FetchScope,
+    etag_if_none_match: &EtagIfNoneMatch<'_>,
+    preconditions: Preconditions<'_>,
+    accept_encoding: AcceptEncoding<'_>,
+    accept: AcceptHeader<'_>,
+    file_center: NamespacedDatalith,
+    id: Uuid,
+    resolution: Option<ResolutionType>,
+    format: Option<ImageFormat>,
+    fallback: Option<Boolean>,
+    download: Option<Boolean>,
+    expires: Option<i64>,
+    sig: Option<&str>,
+) -> Result<CacheResponse<DatalithResponse>, Status> {
+    fetch(
+        scope,
+        etag_if_none_match,
+        preconditions,
+        accept_encoding,
+        accept,
+        file_center,
+        id,
+        resolution,
+        format,
+        fallback,
+        download,
+        expires,
+        sig,
+        true,
+    )
+    .await
+}
+
 #[inline]
 pub fn mounts(rocket: Rocket<Build>) -> Rocket<Build> {
-    rocket.mount("/i/f", routes![get])
+    rocket.mount("/i/f", routes![get, head])
 }
@@ -0,0 +1,281 @@
+use datalith_core::{
+    DatalithCursorOptions, DatalithResourceFilter, DatalithResourceOrderBy,
+    DatalithResourceSortKey, DatalithSearchHitKind, OrderMethod, PaginationOptions,
+};
+use rocket::{Build, Rocket, http::Status, response::content::RawJson, serde::uuid::Uuid};
+use serde_json::json;
+
+use super::{
+    Boolean,
+    operate::datalith_resource_to_json_value,
+    rocket_utils::{
+        MetadataJson, NamespacedDatalith, ReadScope, cursor_page_to_json_value,
+        pagination_to_json_value, parse_cursor, parse_cursor_order_by, parse_metadata_filter,
+        parse_name_query, parse_order_by, parse_time_query, signed_url_to_json_value,
+        validate_page, validate_signed_url_lifespan,
+    },
+};
+
+#[get(
+    "/?<page>&<per_page>&<order_by>&<cursor>&<count>&<meta>&<tag>&<file_type>&<created_since>&\
+     <created_before>&<file_name>&<file_name_glob>&<min_file_size>&<max_file_size>&<temporary>"
+)]
+#[allow(clippy::too_many_arguments)]
+async fn list(
+    _scope: ReadScope,
+    datalith: NamespacedDatalith,
+    page: Option<usize>,
+    per_page: Option<usize>,
+    order_by: Option<&str>,
+    cursor: Option<&str>,
+    count: Option<Boolean>,
+    meta: Vec<&str>,
+    tag: Vec<&str>,
+    file_type: Option<&str>,
+    created_since: Option<&str>,
+    created_before: Option<&str>,
+    file_name: Option<&str>,
+    file_name_glob: Option<&str>,
+    min_file_size: Option<u64>,
+    max_file_size: Option<u64>,
+    temporary: Option<Boolean>,
+) -> Result<RawJson<String>, Status> {
+    // a cursor listing is not divided into numbered pages
+    if cursor.is_some() && page.is_some() {
+        return Err(Status::BadRequest);
+    }
+
+    let (page, per_page) = validate_page(page, per_page)?;
+
+    let filter = DatalithResourceFilter {
+        metadata: parse_metadata_filter(meta, tag)?,
+        file_type: file_type.map(String::from),
+        created_since: parse_time_query(created_since)?,
+        created_before: parse_time_query(created_before)?,
+        file_name: parse_name_query(file_name, file_name_glob)?,
+        min_file_size,
+        max_file_size,
+        temporary: temporary.map(|e| e.0),
+    };
+
+    let (ids, mut value) = if let Some(cursor) = cursor {
+        let (sort_key, descending) = parse_cursor_order_by::<DatalithResourceSortKey>(order_by)?;
+
+        let options = DatalithCursorOptions {
+            sort_key,
+            descending,
+            limit: per_page,
+            cursor: parse_cursor(cursor)?,
+            count_total: count.map(|e| e.0).unwrap_or(false),
+        };
+
+        let page = match datalith.list_resource_ids_by_cursor(&filter, &options).await {
+            Ok(page) => page,
+            Err(error) => {
+                rocket::error!("{error}");
+
+                return Err(Status::InternalServerError);
+            },
+        };
+
+        let value = cursor_page_to_json_value(&page);
+
+        (page.ids, value)
+    } else {
+        let order_by = match order_by {
+            Some(order_by) => parse_resource_order_by(order_by)?,
+            None => DatalithResourceOrderBy::default(),
+        };
+
+        let pagination_options =
+            PaginationOptions::default().page(page).items_per_page(per_page).order_by(order_by);
+
+        let (ids, pagination) = match datalith.list_resource_ids(&filter, pagination_options).await
+        {
+            Ok(result) => result,
+            Err(error) => {
+                rocket::error!("{error}");
+
+                return Err(Status::InternalServerError);
+            },
+        };
+
+        let value = json!(
+            {
+                "pagination": pagination_to_json_value(&pagination),
+            }
+        );
+
+        (ids, value)
+    };
+
+    let mut items = Vec::with_capacity(ids.len());
+
+    for id in ids {
+        match datalith.peek_resource_by_id(id).await {
+            Ok(Some(resource)) => items.push(datalith_resource_to_json_value(resource)),
+            // deleted or expired after listing
+            Ok(None) => (),
+            Err(error) => {
+                rocket::error!("{error}");
+
+                return Err(Status::InternalServerError);
+            },
+        }
+    }
+
+    value["items"] = json!(items);
+
+    Ok(RawJson(serde_json::to_string(&value).unwrap()))
+}
+
+/// Search the resources and the images by their names, metadata values and tags. The best matches come first.
+#[get("/search?<q>&<page>&<per_page>")]
+async fn search(
+    _scope: ReadScope,
+    datalith: NamespacedDatalith,
+    q: &str,
+    page: Option<usize>,
+    per_page: Option<usize>,
+) -> Result<RawJson<String>, Status> {
+    let (page, per_page) = validate_page(page, per_page)?;
+
+    let pagination_options = PaginationOptions::default().page(page).items_per_page(per_page);
+
+    let (hits, pagination) = match datalith.search_resources(q, pagination_options).await {
+        Ok(result) => result,
+        Err(error) => {
+            rocket::error!("{error}");
+
+            return Err(Status::InternalServerError);
+        },
+    };
+
+    let mut items = Vec::with_capacity(hits.len());
+
+    for hit in hits {
+        let mut value = match hit.kind {
+            DatalithSearchHitKind::Resource => match datalith.peek_resource_by_id(hit.id).await {
+                Ok(Some(resource)) => datalith_resource_to_json_value(resource),
+                // deleted or expired after searching
+                Ok(None) => continue,
+                Err(error) => {
+                    rocket::error!("{error}");
+
+                    return Err(Status::InternalServerError);
+                },
+            },
+            #[cfg(feature = "image-convert")]
+            DatalithSearchHitKind::Image => match datalith.get_image_by_id(hit.id).await {
+                Ok(Some(image)) => super::operate_image::datalith_image_to_json_value(image),
+                // deleted after searching
+                Ok(None) => continue,
+                Err(error) => {
+                    rocket::error!("{error}");
+
+                    return Err(Status::InternalServerError);
+                },
+            },
+            // images cannot be served without image conversion
+            #[cfg(not(feature = "image-convert"))]
+            DatalithSearchHitKind::Image => continue,
+        };
+
+        value["kind"] = json!(match hit.kind {
+            DatalithSearchHitKind::Resource => "resource",
+            DatalithSearchHitKind::Image => "image",
+        });
+        value["snippet"] = json!(hit.snippet);
+
+        items.push(value);
+    }
+
+    let value = json!(
+        {
+            "items": items,
+            "pagination": pagination_to_json_value(&pagination),
+        }
+    );
+
+    Ok(RawJson(serde_json::to_string(&value).unwrap()))
+}
+
+#[get("/<id>")]
+async fn get(
+    _scope: ReadScope,
+    datalith: NamespacedDatalith,
+    id: Uuid,
+) -> Result<MetadataJson, Status> {
+    match datalith.peek_resource_by_id(id).await {
+        Ok(Some(resource)) => {
+            let metadata_version = resource.metadata_version();
+            let value = datalith_resource_to_json_value(resource);
+
+            Ok(MetadataJson::new(&value, metadata_version))
+        },
+        Ok(None) => Err(Status::NotFound),
+        Err(error) => {
+            rocket::error!("{error}");
+
+            Err(Status::InternalServerError)
+        },
+    }
+}
+
+/// Mint a signed URL for fetching a resource without an API key.
+#[post("/<id>/signed-url?<lifespan>&<download>")]
+async fn sign(
+    _scope: ReadScope,
+    datalith: NamespacedDatalith,
+    id: Uuid,
+    lifespan: Option<u64>,
+    download: Option<Boolean>,
+) -> Result<RawJson<String>, Status> {
+    let lifespan = validate_signed_url_lifespan(lifespan)?;
+    let download = download.map(|e| e.0).unwrap_or(false);
+
+    match datalith.check_resource_exist(id).await {
+        Ok(true) => {
+            let signed_url = datalith.sign_resource_url(id, lifespan, download);
+
+            let value =
+                signed_url_to_json_value(datalith.scoped_path(format_args!("/f/{id}")), signed_url);
+
+            Ok(RawJson(serde_json::to_string(&value).unwrap()))
+        },
+        Ok(false) => Err(Status::NotFound),
+        Err(error) => {
+            rocket::error!("{error}");
+
+            Err(Status::InternalServerError)
+        },
+    }
+}
+
+#[inline]
+pub fn mounts(rocket: Rocket<Build>) -> Rocket<Build> {
+    rocket.mount("/o", routes![list, search, get, sign])
+}
+
+fn parse_resource_order_by(order_by: &str) -> Result<DatalithResourceOrderBy, Status> {
+    let (keys, tie_breaker) = parse_order_by(order_by)?;
+
+    let mut order_by = DatalithResourceOrderBy {
+        id: tie_breaker,
+        created_at: OrderMethod::default(),
+        ..DatalithResourceOrderBy::default()
+    };
+
+    for (key, order_method) in keys {
+        match key {
+            "id" => order_by.id = order_method,
+            "created_at" => order_by.created_at = order_method,
+            "file_name" => order_by.file_name = order_method,
+            "file_size" => order_by.file_size = order_method,
+            "file_type" => order_by.file_type = order_method,
+            _ => return Err(Status::BadRequest),
+        }
+    }
+
+    Ok(order_by)
+}
@@ -0,0 +1,213 @@
+use datalith_core::{
+    DatalithCursorOptions, DatalithImageFilter, DatalithImageOrderBy, DatalithImageSortKey,
+    OrderMethod, PaginationOptions,
+};
+use rocket::{Build, Rocket, http::Status, response::content::RawJson, serde::uuid::Uuid};
+use serde_json::json;
+
+use super::{
+    Boolean,
+    operate_image::datalith_image_to_json_value,
+    rocket_utils::{
+        ImageFormat, MetadataJson, NamespacedDatalith, ReadScope, ResolutionType,
+        cursor_page_to_json_value, pagination_to_json_value, parse_cursor, parse_cursor_order_by,
+        parse_metadata_filter, parse_name_query, parse_order_by, parse_time_query,
+        signed_url_to_json_value, validate_page, validate_signed_url_lifespan,
+    },
+};
+
+#[get(
+    "/?<page>&<per_page>&<order_by>&<cursor>&<count>&<meta>&<tag>&<created_since>&\
+     <created_before>&<image_stem>&<image_stem_glob>&<has_alpha_channel>"
+)]
+#[allow(clippy::too_many_arguments)]
+async fn list(
+    _scope: ReadScope,
+    datalith: NamespacedDatalith,
+    page: Option<usize>,
+    per_page: Option<usize>,
+    order_by: Option<&str>,
+    cursor: Option<&str>,
+    count: Option<Boolean>,
+    meta: Vec<&str>,
+    tag: Vec<&str>,
+    created_since: Option<&str>,
+    created_before: Option<&str>,
+    image_stem: Option<&str>,
+    image_stem_glob: Option<&str>,
+    has_alpha_channel: Option<Boolean>,
+) -> Result<RawJson<String>, Status> {
+    // a cursor listing is not divided into numbered pages
+    if cursor.is_some() && page.is_some() {
+        return Err(Status::BadRequest);
+    }
+
+    let (page, per_page) = validate_page(page, per_page)?;
+
+    let filter = DatalithImageFilter {
+        metadata:          parse_metadata_filter(meta, tag)?,
+        created_since:     parse_time_query(created_since)?,
+        created_before:    parse_time_query(created_before)?,
+        image_stem:        parse_name_query(image_stem, image_stem_glob)?,
+        has_alpha_channel: has_alpha_channel.map(|e| e.0),
+    };
+
+    let (ids, mut value) = if let Some(cursor) = cursor {
+        let (sort_key, descending) = parse_cursor_order_by::<DatalithImageSortKey>(order_by)?;
+
+        let options = DatalithCursorOptions {
+            sort_key,
+            descending,
+            limit: per_page,
+            cursor: parse_cursor(cursor)?,
+            count_total: count.map(|e| e.0).unwrap_or(false),
+        };
+
+        let page = match datalith.list_image_ids_by_cursor(&filter, &options).await {
+            Ok(page) => page,
+            Err(error) => {
+                rocket::error!("{error}");
+
+                return Err(Status::InternalServerError);
+            },
+        };
+
+        let value = cursor_page_to_json_value(&page);
+
+        (page.ids, value)
+    } else {
+        let order_by = match order_by {
+            Some(order_by) => parse_image_order_by(order_by)?,
+            None => DatalithImageOrderBy::default(),
+        };
+
+        let pagination_options =
+            PaginationOptions::default().page(page).items_per_page(per_page).order_by(order_by);
+
+        let (ids, pagination) = match datalith.list_image_ids(&filter, pagination_options).await {
+            Ok(result) => result,
+            Err(error) => {
+                rocket::error!("{error}");
+
+                return Err(Status::InternalServerError);
+            },
+        };
+
+        let value = json!(
+            {
+                "pagination": pagination_to_json_value(&pagination),
+            }
+        );
+
+        (ids, value)
+    };
+
+    let mut items = Vec::with_capacity(ids.len());
+
+    for id in ids {
+        match datalith.get_image_by_id(id).await {
+            Ok(Some(image)) => items.push(datalith_image_to_json_value(image)),
+            // deleted after listing
+            Ok(None) => (),
+            Err(error) => {
+                rocket::error!("{error}");
+
+                return Err(Status::InternalServerError);
+            },
+        }
+    }
+
+    value["items"] = json!(items);
+
+    Ok(RawJson(serde_json::to_string(&value).unwrap()))
+}
+
+#[get("/<id>")]
+async fn get(
+    _scope: ReadScope,
+    datalith: NamespacedDatalith,
+    id: Uuid,
+) -> Result<MetadataJson, Status> {
+    match datalith.get_image_by_id(id).await {
+        Ok(Some(image)) => {
+            let metadata_version = image.metadata_version();
+            let value = datalith_image_to_json_value(image);
+
+            Ok(MetadataJson::new(&value, metadata_version))
+        },
+        Ok(None) => Err(Status::NotFound),
+        Err(error) => {
+            rocket::error!("{error}");
+
+            Err(Status::InternalServerError)
+        },
+    }
+}
+
+/// Mint a signed URL for fetching an image in a specific resolution and format without an API key.
+#[post("/<id>/signed-url?<resolution>&<format>&<fallback>&<lifespan>&<download>")]
+#[allow(clippy::too_many_arguments)]
+async fn sign(
+    _scope: ReadScope,
+    datalith: NamespacedDatalith,
+    id: Uuid,
+    resolution: Option<ResolutionType>,
+    format: Option<ImageFormat>,
+    fallback: Option<Boolean>,
+    lifespan: Option<u64>,
+    download: Option<Boolean>,
+) -> Result<RawJson<String>, Status> {
+    let lifespan = validate_signed_url_lifespan(lifespan)?;
+    let resolution = resolution.map(|e| e.into()).unwrap_or_default();
+    let format = format.map(|e| e.0);
+    let fallback = fallback.map(|e| e.0).unwrap_or(false);
+    let download = download.map(|e| e.0).unwrap_or(false);
+
+    match datalith.check_image_exist(id).await {
+        Ok(true) => {
+            let signed_url =
+                datalith.sign_image_url(id, resolution, format, fallback, lifespan, download);
+
+            let value = signed_url_to_json_value(
+                datalith.scoped_path(format_args!("/i/f/{id}")),
+                signed_url,
+            );
+
+            Ok(RawJson(serde_json::to_string(&value).unwrap()))
+        },
+        Ok(false) => Err(Status::NotFound),
+        Err(error) => {
+            rocket::error!("{error}");
+
+            Err(Status::InternalServerError)
+        },
+    }
+}
+
+#[inline]
+pub fn mounts(rocket: Rocket<Build>) -> Rocket<Build> {
+    rocket.mount("/i", routes![list, get, sign])
+}
+
+fn parse_image_order_by(order_by: &str) -> Result<DatalithImageOrderBy, Status> {
+    let (keys, tie_breaker) = parse_order_by(order_by)?;
+
+    let mut order_by = DatalithImageOrderBy {
+        id: tie_breaker,
+        created_at: OrderMethod::default(),
+        ..DatalithImageOrderBy::default()
+    };
+
+    for (key, order_method) in keys {
+        match key {
+            "id" => order_by.id = order_method,
+            "created_at" => order_by.created_at = order_method,
+            "image_width" => order_by.image_width = order_method,
+            "image_height" => order_by.image_height = order_method,
+            "has_alpha_channel" => order_by.has_alpha_channel = order_method,
+            _ => return Err(Status::BadRequest),
+        }
+    }
+
+    Ok(order_by)
+}
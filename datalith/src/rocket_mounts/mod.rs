@@ -1,31 +1,68 @@
 mod fetch;
 #[cfg(feature = "image-convert")]
 mod fetch_image;
+mod metadata;
+#[cfg(feature = "image-convert")]
+mod metadata_image;
 mod operate;
 #[cfg(feature = "image-convert")]
 mod operate_image;
+mod operate_upload;
 mod rocket_utils;
+mod status;
 
 use std::net::IpAddr;
 
-use rocket::{Build, Config, Request, Rocket, http::Status};
+use rocket::{
+    Build, Config, Request, Rocket,
+    http::{Header, Status},
+};
 use validators::prelude::*;
 
 #[derive(Debug)]
 struct ServerConfig {
-    pub(crate) max_file_size: u64,
+    pub(crate) max_file_size:   u64,
+    /// Whether API keys are required.
+    pub(crate) auth:            bool,
+    /// Whether fetching files is allowed without an API key when `auth` is enabled.
+    pub(crate) anonymous_fetch: bool,
+    /// A static API key which has all scopes in all namespaces.
+    pub(crate) api_key:         Option<String>,
 }
 
 #[derive(Debug, Clone, Copy, Validator)]
 #[validator(boolean)]
 struct Boolean(pub(crate) bool);
 
+#[derive(Responder)]
+#[response(status = 401)]
+struct Unauthorized {
+    body:             String,
+    www_authenticate: Header<'static>,
+}
+
+#[catch(401)]
+fn unauthorized_catcher(_req: &Request) -> Unauthorized {
+    Unauthorized {
+        body:             format!("{}", Status::Unauthorized),
+        www_authenticate: Header::new("www-authenticate", "Bearer"),
+    }
+}
+
 #[catch(default)]
 fn default_error_catcher(status: Status, _req: &Request) -> String {
     format!("{status}")
 }
 
-pub fn create(address: IpAddr, listen_port: u16, max_file_size: u64) -> Rocket<Build> {
+/// Create the HTTP service. Resources are under `/o`, images are under `/i`, and resumable uploads are under `/o/uploads`. Every path can be prefixed with `/n/<namespace>` to use a namespace other than the default one, e.g. `/n/shop/o/<id>`.
+pub fn create(
+    address: IpAddr,
+    listen_port: u16,
+    max_file_size: u64,
+    auth: bool,
+    anonymous_fetch: bool,
+    api_key: Option<String>,
+) -> Rocket<Build> {
     let figment = Config::figment()
         .merge(("ident", "Datalith"))
         .merge(("address", address))
@@ -34,8 +71,12 @@ pub fn create(address: IpAddr, listen_port: u16, max_file_size: u64) -> Rocket<B
     let rocket = rocket::custom(figment)
         .manage(ServerConfig {
             max_file_size,
+            auth,
+            anonymous_fetch,
+            api_key,
         })
-        .register("/", catchers![default_error_catcher]);
+        .attach(rocket_utils::namespace_fairing())
+        .register("/", catchers![unauthorized_catcher, default_error_catcher]);
 
     #[cfg(feature = "image-convert")]
     let rocket = fetch_image::mounts(rocket);
@@ -43,7 +84,16 @@ pub fn create(address: IpAddr, listen_port: u16, max_file_size: u64) -> Rocket<B
     #[cfg(feature = "image-convert")]
     let rocket = operate_image::mounts(rocket);
 
+    #[cfg(feature = "image-convert")]
+    let rocket = metadata_image::mounts(rocket);
+
     let rocket = fetch::mounts(rocket);
 
+    let rocket = metadata::mounts(rocket);
+
+    let rocket = operate_upload::mounts(rocket);
+
+    let rocket = status::mounts(rocket);
+
     operate::mounts(rocket)
 }
@@ -1,7 +1,7 @@
 use std::{io::ErrorKind, str::FromStr};
 
 use datalith_core::{
-    DatalithManager, DatalithResource, DatalithWriteError, FileTypeLevel, mime::Mime,
+    DatalithMetadata, DatalithResource, DatalithWriteError, FileTypeLevel, mime::Mime,
 };
 use rocket::{
     Build, Data, Rocket, State,
@@ -16,12 +16,19 @@ use serde_json::{Value, json};
 use validators::prelude::*;
 
 use super::{Boolean, ServerConfig};
-use crate::rocket_mounts::rocket_utils::FileLength;
+use crate::rocket_mounts::rocket_utils::{
+    DeleteScope, ExpectedHash, FileLength, MetadataHeaders, MetadataJson, NamespacedDatalith,
+    Preconditions, WriteScope, check_resource_metadata_preconditions, check_resource_preconditions,
+    insert_metadata_json_value, merge_metadata_fields, read_metadata_update,
+};
 
 #[post("/", format = "multipart/form-data", data = "<data>")]
 async fn upload(
+    _scope: WriteScope,
     server_config: &State<ServerConfig>,
-    datalith: &State<DatalithManager>,
+    datalith: NamespacedDatalith,
+    metadata: MetadataHeaders,
+    expected_hash: ExpectedHash,
     content_type: &ContentType,
     data: Data<'_>,
 ) -> Result<RawJson<String>, Status> {
@@ -32,6 +39,9 @@ async fn upload(
             MultipartFormDataField::text("file_name").size_limit(512),
             MultipartFormDataField::text("file_type").size_limit(100),
             MultipartFormDataField::text("temporary").size_limit(5),
+            MultipartFormDataField::text("metadata").size_limit(64 * 1024),
+            MultipartFormDataField::text("tags").size_limit(16 * 1024),
+            MultipartFormDataField::text("sha256").size_limit(64),
         ],
         ..MultipartFormDataOptions::default()
     };
@@ -78,18 +88,52 @@ async fn upload(
         false
     };
 
+    let expected_hash =
+        expected_hash.merge(multipart_form_data.texts.get("sha256").map(|v| v[0].text.as_str()))?;
+
+    let mut metadata = metadata.0;
+
+    merge_metadata_fields(
+        &mut metadata,
+        multipart_form_data.texts.get("metadata").map(|v| v[0].text.as_str()),
+        multipart_form_data.texts.get("tags").map(|v| v[0].text.as_str()),
+    )?;
+
     match if temporary {
         datalith
-            .put_resource_by_path_temporarily(file_field.path.as_path(), file_name, mime_type)
+            .put_resource_by_path_temporarily(
+                file_field.path.as_path(),
+                file_name,
+                mime_type,
+                expected_hash.as_ref(),
+            )
             .await
     } else {
-        datalith.put_resource_by_path(file_field.path.as_path(), file_name, mime_type).await
+        datalith
+            .put_resource_by_path(
+                file_field.path.as_path(),
+                file_name,
+                mime_type,
+                expected_hash.as_ref(),
+            )
+            .await
     } {
         Ok(resource) => {
-            let value = datalith_resource_to_json_value(resource);
+            let value = update_uploaded_resource_metadata(&datalith, resource, metadata).await?;
 
             Ok(RawJson(serde_json::to_string(&value).unwrap()))
         },
+        Err(DatalithWriteError::HashMismatch {
+            ..
+        }) => Err(Status::BadRequest),
+        Err(
+            DatalithWriteError::QuotaExceeded {
+                ..
+            }
+            | DatalithWriteError::InsufficientDiskSpace {
+                ..
+            },
+        ) => Err(Status::InsufficientStorage),
         Err(error) => {
             rocket::error!("{error}");
 
@@ -98,16 +142,21 @@ async fn upload(
     }
 }
 
-#[put("/?<file_name>&<file_type>&<temporary>", data = "<data>")]
+/// Upload a resource with the request body. If `sha256` is given and the data with the hash is already stored in the namespace, the resource is created from the stored data and the body is not read, so the client can send an empty body first. Otherwise, an empty body is responded with 404, and the body has to match the hash.
+#[put("/?<file_name>&<file_type>&<temporary>&<sha256>", data = "<data>")]
 #[allow(clippy::too_many_arguments)]
 async fn stream_upload(
+    _scope: WriteScope,
     server_config: &State<ServerConfig>,
-    datalith: &State<DatalithManager>,
+    datalith: NamespacedDatalith,
+    metadata: MetadataHeaders,
+    expected_hash: ExpectedHash,
     content_type: Option<&ContentType>,
     file_length: Option<&FileLength>,
     file_name: Option<&str>,
     file_type: Option<&str>,
     temporary: Option<Boolean>,
+    sha256: Option<&str>,
     data: Data<'_>,
 ) -> Result<RawJson<String>, Status> {
     let content_type = match file_type {
@@ -128,6 +177,39 @@ async fn stream_upload(
     let expected_reader_length = validate_content_length(server_config, file_length)?;
 
     let temporary = temporary.map(|e| e.0).unwrap_or(false);
+    let metadata = metadata.0;
+
+    let expected_hash = expected_hash.merge(sha256)?;
+
+    if sha256.is_some()
+        && let Some(hash) = expected_hash.as_ref()
+    {
+        match if temporary {
+            datalith.put_resource_by_hash_temporarily(hash, file_name, mime_type.clone()).await
+        } else {
+            datalith.put_resource_by_hash(hash, file_name, mime_type.clone()).await
+        } {
+            Ok(Some(resource)) => {
+                let value =
+                    update_uploaded_resource_metadata(&datalith, resource, metadata).await?;
+
+                return Ok(RawJson(serde_json::to_string(&value).unwrap()));
+            },
+            Ok(None) => {
+                if file_length.is_some_and(|file_length| file_length.to_u64() == 0) {
+                    return Err(Status::NotFound);
+                }
+            },
+            Err(DatalithWriteError::QuotaExceeded {
+                ..
+            }) => return Err(Status::InsufficientStorage),
+            Err(error) => {
+                rocket::error!("{error}");
+
+                return Err(Status::InternalServerError);
+            },
+        }
+    }
 
     // max_file_size plus 1 in order to distinguish the too large payload
     let stream = data.open((server_config.max_file_size + 1).into());
@@ -139,21 +221,39 @@ async fn stream_upload(
                 file_name,
                 mime_type,
                 Some(expected_reader_length),
+                expected_hash.as_ref(),
             )
             .await
     } else {
         datalith
-            .put_resource_by_reader(stream, file_name, mime_type, Some(expected_reader_length))
+            .put_resource_by_reader(
+                stream,
+                file_name,
+                mime_type,
+                Some(expected_reader_length),
+                expected_hash.as_ref(),
+            )
             .await
     } {
         Ok(resource) => {
-            let value = datalith_resource_to_json_value(resource);
+            let value = update_uploaded_resource_metadata(&datalith, resource, metadata).await?;
 
             Ok(RawJson(serde_json::to_string(&value).unwrap()))
         },
         Err(DatalithWriteError::FileLengthTooLarge {
             ..
         }) => Err(Status::PayloadTooLarge),
+        Err(DatalithWriteError::HashMismatch {
+            ..
+        }) => Err(Status::BadRequest),
+        Err(
+            DatalithWriteError::QuotaExceeded {
+                ..
+            }
+            | DatalithWriteError::InsufficientDiskSpace {
+                ..
+            },
+        ) => Err(Status::InsufficientStorage),
         Err(DatalithWriteError::IOError(error)) if error.kind() == ErrorKind::Other => {
             Err(Status::BadRequest)
         },
@@ -165,8 +265,62 @@ async fn stream_upload(
     }
 }
 
+/// Change the metadata and tags of a resource. `If-Match` is matched against the entity tag of the metadata.
+#[patch("/<id>", data = "<data>")]
+async fn update_metadata(
+    _scope: WriteScope,
+    preconditions: Preconditions<'_>,
+    datalith: NamespacedDatalith,
+    id: Uuid,
+    data: Data<'_>,
+) -> Result<MetadataJson, Status> {
+    let expected_metadata_version =
+        check_resource_metadata_preconditions(&datalith, &preconditions, id).await?;
+
+    let update = read_metadata_update(data).await?;
+
+    match datalith
+        .update_resource_metadata_with_version(id, &update, expected_metadata_version)
+        .await
+    {
+        Ok(Some(_)) => (),
+        Ok(None) => return Err(Status::NotFound),
+        Err(DatalithWriteError::MetadataVersionMismatch {
+            ..
+        }) => return Err(Status::PreconditionFailed),
+        Err(error) => {
+            rocket::error!("{error}");
+
+            return Err(Status::InternalServerError);
+        },
+    }
+
+    match datalith.peek_resource_by_id(id).await {
+        Ok(Some(resource)) => {
+            let metadata_version = resource.metadata_version();
+            let value = datalith_resource_to_json_value(resource);
+
+            Ok(MetadataJson::new(&value, metadata_version))
+        },
+        // deleted or expired after updating
+        Ok(None) => Err(Status::NotFound),
+        Err(error) => {
+            rocket::error!("{error}");
+
+            Err(Status::InternalServerError)
+        },
+    }
+}
+
 #[delete("/<id>")]
-async fn delete(datalith: &State<DatalithManager>, id: Uuid) -> Result<&'static str, Status> {
+async fn delete(
+    _scope: DeleteScope,
+    preconditions: Preconditions<'_>,
+    datalith: NamespacedDatalith,
+    id: Uuid,
+) -> Result<&'static str, Status> {
+    check_resource_preconditions(&datalith, &preconditions, id).await?;
+
     match datalith.delete_resource_by_id(id).await {
         Ok(true) => Ok("ok"),
         Ok(false) => Err(Status::NotFound),
@@ -180,7 +334,7 @@ async fn delete(datalith: &State<DatalithManager>, id: Uuid) -> Result<&'static
 
 #[inline]
 pub fn mounts(rocket: Rocket<Build>) -> Rocket<Build> {
-    rocket.mount("/o", routes![upload, stream_upload, delete])
+    rocket.mount("/o", routes![upload, stream_upload, update_metadata, delete])
 }
 
 #[inline]
@@ -203,9 +357,42 @@ pub fn validate_content_length(
     }
 }
 
+/// Attach the metadata sent along with an upload to the uploaded resource. The resource is deleted if that fails.
+async fn update_uploaded_resource_metadata(
+    datalith: &NamespacedDatalith,
+    resource: DatalithResource,
+    metadata: DatalithMetadata,
+) -> Result<Value, Status> {
+    let id = resource.id();
+    let mut value = datalith_resource_to_json_value(resource);
+
+    if metadata.is_empty() {
+        return Ok(value);
+    }
+
+    match datalith.update_resource_metadata_with_version(id, &metadata.into(), None).await {
+        Ok(Some((metadata, metadata_version))) => {
+            insert_metadata_json_value(&mut value, &metadata, metadata_version);
+
+            Ok(value)
+        },
+        // expired right after uploading
+        Ok(None) => Err(Status::NotFound),
+        Err(error) => {
+            rocket::error!("{error}");
+
+            if let Err(error) = datalith.delete_resource_by_id(id).await {
+                rocket::error!("{error}");
+            }
+
+            Err(Status::InternalServerError)
+        },
+    }
+}
+
 #[inline]
-fn datalith_resource_to_json_value(resource: DatalithResource) -> Value {
-    json!(
+pub fn datalith_resource_to_json_value(resource: DatalithResource) -> Value {
+    let mut value = json!(
         {
             "id": resource.id().to_string(),
             "created_at": resource.created_at().to_rfc3339(),
@@ -214,5 +401,9 @@ fn datalith_resource_to_json_value(resource: DatalithResource) -> Value {
             "file_name": resource.file_name(),
             "is_temporary": resource.file().is_temporary(),
         }
-    )
+    );
+
+    insert_metadata_json_value(&mut value, resource.metadata(), resource.metadata_version());
+
+    value
 }
@@ -1,5 +1,5 @@
 use datalith_core::{
-    CenterCrop, DatalithImage, DatalithImageWriteError, DatalithManager, DatalithWriteError, Uuid,
+    CenterCrop, DatalithImage, DatalithImageWriteError, DatalithMetadata, DatalithWriteError, Uuid,
 };
 use rocket::{
     Build, Data, Rocket, State,
@@ -13,12 +13,22 @@ use serde_json::{Value, json};
 use validators::prelude::*;
 
 use super::{Boolean, ServerConfig};
-use crate::rocket_mounts::{operate::validate_content_length, rocket_utils::FileLength};
+use crate::rocket_mounts::{
+    operate::validate_content_length,
+    rocket_utils::{
+        DeleteScope, FileLength, MetadataHeaders, MetadataJson, NamespacedDatalith, Preconditions,
+        WriteScope, check_image_metadata_preconditions, check_image_preconditions,
+        check_resource_preconditions, insert_metadata_json_value, merge_metadata_fields,
+        read_metadata_update,
+    },
+};
 
 #[post("/", format = "multipart/form-data", data = "<data>")]
 async fn upload(
+    _scope: WriteScope,
     server_config: &State<ServerConfig>,
-    datalith: &State<DatalithManager>,
+    datalith: NamespacedDatalith,
+    metadata: MetadataHeaders,
     content_type: &ContentType,
     data: Data<'_>,
 ) -> Result<RawJson<String>, Status> {
@@ -31,6 +41,8 @@ async fn upload(
             MultipartFormDataField::text("max_height").size_limit(10),
             MultipartFormDataField::text("center_crop").size_limit(30),
             MultipartFormDataField::text("save_original_file").size_limit(5),
+            MultipartFormDataField::text("metadata").size_limit(64 * 1024),
+            MultipartFormDataField::text("tags").size_limit(16 * 1024),
         ],
         ..MultipartFormDataOptions::default()
     };
@@ -85,6 +97,14 @@ async fn upload(
             true
         };
 
+    let mut metadata = metadata.0;
+
+    merge_metadata_fields(
+        &mut metadata,
+        multipart_form_data.texts.get("metadata").map(|v| v[0].text.as_str()),
+        multipart_form_data.texts.get("tags").map(|v| v[0].text.as_str()),
+    )?;
+
     match datalith
         .put_image_by_path(
             file_field.path.as_path(),
@@ -97,10 +117,18 @@ async fn upload(
         .await
     {
         Ok(image) => {
-            let value = datalith_image_to_json_value(image);
+            let value = update_uploaded_image_metadata(&datalith, image, metadata).await?;
 
             Ok(RawJson(serde_json::to_string(&value).unwrap()))
         },
+        Err(DatalithImageWriteError::DatalithWriteError(
+            DatalithWriteError::QuotaExceeded {
+                ..
+            }
+            | DatalithWriteError::InsufficientDiskSpace {
+                ..
+            },
+        )) => Err(Status::InsufficientStorage),
         Err(error) => {
             rocket::error!("{error}");
 
@@ -116,8 +144,10 @@ async fn upload(
 #[allow(clippy::too_many_arguments)]
 #[put("/?<file_name>&<max_width>&<max_height>&<center_crop>&<save_original_file>", data = "<data>")]
 async fn stream_upload(
+    _scope: WriteScope,
     server_config: &State<ServerConfig>,
-    datalith: &State<DatalithManager>,
+    datalith: NamespacedDatalith,
+    metadata: MetadataHeaders,
     file_length: Option<&FileLength>,
     file_name: Option<&str>,
     max_width: Option<u16>,
@@ -129,6 +159,7 @@ async fn stream_upload(
     let expected_reader_length = validate_content_length(server_config, file_length)?;
     let center_crop = parse_center_crop(center_crop)?;
     let save_original_file = save_original_file.map(|e| e.0).unwrap_or(true);
+    let metadata = metadata.0;
 
     // max_file_size plus 1 in order to distinguish the too large payload
     let stream = data.open((server_config.max_file_size + 1).into());
@@ -146,7 +177,7 @@ async fn stream_upload(
         .await
     {
         Ok(image) => {
-            let value = datalith_image_to_json_value(image);
+            let value = update_uploaded_image_metadata(&datalith, image, metadata).await?;
 
             Ok(RawJson(serde_json::to_string(&value).unwrap()))
         },
@@ -155,6 +186,14 @@ async fn stream_upload(
                 ..
             },
         )) => Err(Status::PayloadTooLarge),
+        Err(DatalithImageWriteError::DatalithWriteError(
+            DatalithWriteError::QuotaExceeded {
+                ..
+            }
+            | DatalithWriteError::InsufficientDiskSpace {
+                ..
+            },
+        )) => Err(Status::InsufficientStorage),
         Err(error) => {
             rocket::error!("{error}");
 
@@ -167,8 +206,60 @@ async fn stream_upload(
     }
 }
 
+/// Change the metadata and tags of an image. `If-Match` is matched against the entity tag of the metadata.
+#[patch("/<id>", data = "<data>")]
+async fn update_metadata(
+    _scope: WriteScope,
+    preconditions: Preconditions<'_>,
+    datalith: NamespacedDatalith,
+    id: Uuid,
+    data: Data<'_>,
+) -> Result<MetadataJson, Status> {
+    let expected_metadata_version =
+        check_image_metadata_preconditions(&datalith, &preconditions, id).await?;
+
+    let update = read_metadata_update(data).await?;
+
+    match datalith.update_image_metadata_with_version(id, &update, expected_metadata_version).await
+    {
+        Ok(Some(_)) => (),
+        Ok(None) => return Err(Status::NotFound),
+        Err(DatalithWriteError::MetadataVersionMismatch {
+            ..
+        }) => return Err(Status::PreconditionFailed),
+        Err(error) => {
+            rocket::error!("{error}");
+
+            return Err(Status::InternalServerError);
+        },
+    }
+
+    match datalith.get_image_by_id(id).await {
+        Ok(Some(image)) => {
+            let metadata_version = image.metadata_version();
+            let value = datalith_image_to_json_value(image);
+
+            Ok(MetadataJson::new(&value, metadata_version))
+        },
+        // deleted after updating
+        Ok(None) => Err(Status::NotFound),
+        Err(error) => {
+            rocket::error!("{error}");
+
+            Err(Status::InternalServerError)
+        },
+    }
+}
+
 #[delete("/<id>")]
-async fn delete(datalith: &State<DatalithManager>, id: Uuid) -> Result<&'static str, Status> {
+async fn delete(
+    _scope: DeleteScope,
+    preconditions: Preconditions<'_>,
+    datalith: NamespacedDatalith,
+    id: Uuid,
+) -> Result<&'static str, Status> {
+    check_image_preconditions(&datalith, &preconditions, id).await?;
+
     match datalith.delete_image_by_id(id).await {
         Ok(true) => Ok("ok"),
         Ok(false) => Err(Status::NotFound),
@@ -181,8 +272,12 @@ async fn delete(datalith: &State<DatalithManager>, id: Uuid) -> Result<&'static
 }
 
 #[delete("/<id>?convert-image&<max_width>&<max_height>&<center_crop>")]
+#[allow(clippy::too_many_arguments)]
 async fn convert_image(
-    datalith: &State<DatalithManager>,
+    _scope: WriteScope,
+    _delete_scope: DeleteScope,
+    preconditions: Preconditions<'_>,
+    datalith: NamespacedDatalith,
     id: Uuid,
     max_width: Option<u16>,
     max_height: Option<u16>,
@@ -190,6 +285,8 @@ async fn convert_image(
 ) -> Result<RawJson<String>, Status> {
     let center_crop = parse_center_crop(center_crop)?;
 
+    check_resource_preconditions(&datalith, &preconditions, id).await?;
+
     let resource = match datalith.get_resource_by_id(id).await {
         Ok(Some(resource)) => resource,
         Ok(None) => return Err(Status::NotFound),
@@ -206,6 +303,14 @@ async fn convert_image(
 
             Ok(RawJson(serde_json::to_string(&value).unwrap()))
         },
+        Err(DatalithImageWriteError::DatalithWriteError(
+            DatalithWriteError::QuotaExceeded {
+                ..
+            }
+            | DatalithWriteError::InsufficientDiskSpace {
+                ..
+            },
+        )) => Err(Status::InsufficientStorage),
         Err(error) => {
             rocket::error!("{error}");
 
@@ -220,12 +325,47 @@ async fn convert_image(
 
 #[inline]
 pub fn mounts(rocket: Rocket<Build>) -> Rocket<Build> {
-    rocket.mount("/i/o", routes![upload, stream_upload, delete]).mount("/o", routes![convert_image])
+    rocket
+        .mount("/i/o", routes![upload, stream_upload, update_metadata, delete])
+        .mount("/o", routes![convert_image])
+}
+
+/// Attach the metadata sent along with an upload to the uploaded image. The image is deleted if that fails.
+async fn update_uploaded_image_metadata(
+    datalith: &NamespacedDatalith,
+    image: DatalithImage,
+    metadata: DatalithMetadata,
+) -> Result<Value, Status> {
+    let id = image.id();
+    let mut value = datalith_image_to_json_value(image);
+
+    if metadata.is_empty() {
+        return Ok(value);
+    }
+
+    match datalith.update_image_metadata_with_version(id, &metadata.into(), None).await {
+        Ok(Some((metadata, metadata_version))) => {
+            insert_metadata_json_value(&mut value, &metadata, metadata_version);
+
+            Ok(value)
+        },
+        // deleted right after uploading
+        Ok(None) => Err(Status::NotFound),
+        Err(error) => {
+            rocket::error!("{error}");
+
+            if let Err(error) = datalith.delete_image_by_id(id).await {
+                rocket::error!("{error}");
+            }
+
+            Err(Status::InternalServerError)
+        },
+    }
 }
 
 #[inline]
-fn datalith_image_to_json_value(image: DatalithImage) -> Value {
-    json!(
+pub fn datalith_image_to_json_value(image: DatalithImage) -> Value {
+    let mut value = json!(
         {
             "id": image.id().to_string(),
             "created_at": image.created_at().to_rfc3339(),
@@ -233,7 +373,11 @@ fn datalith_image_to_json_value(image: DatalithImage) -> Value {
             "image_height": image.image_height(),
             "image_stem": image.image_stem(),
         }
-    )
+    );
+
+    insert_metadata_json_value(&mut value, image.metadata(), image.metadata_version());
+
+    value
 }
 
 #[inline]
@@ -0,0 +1,194 @@
+use std::{io::ErrorKind, str::FromStr};
+
+use datalith_core::{DatalithUploadSessionError, DatalithWriteError, FileTypeLevel, mime::Mime};
+use rocket::{Build, Data, Rocket, State, http::Status, serde::uuid::Uuid};
+
+use super::{
+    ServerConfig,
+    operate::datalith_resource_to_json_value,
+    rocket_utils::{
+        NamespacedDatalith, TUS_EXTENSION, TUS_VERSION, TusHeaders, TusResponse, WriteScope,
+    },
+};
+
+#[options("/")]
+fn options(server_config: &State<ServerConfig>) -> TusResponse {
+    TusResponse::new(Status::NoContent)
+        .header("tus-version", TUS_VERSION)
+        .header("tus-extension", TUS_EXTENSION)
+        .header("tus-max-size", server_config.max_file_size.to_string())
+}
+
+#[post("/")]
+async fn create(
+    _scope: WriteScope,
+    server_config: &State<ServerConfig>,
+    datalith: NamespacedDatalith,
+    tus_headers: TusHeaders<'_>,
+) -> Result<TusResponse, Status> {
+    let upload_length = tus_headers.upload_length()?.ok_or(Status::BadRequest)?;
+
+    if upload_length > server_config.max_file_size {
+        return Err(Status::PayloadTooLarge);
+    }
+
+    let upload_metadata = tus_headers.upload_metadata()?;
+
+    let file_name = upload_metadata.get("filename");
+
+    let file_type = match upload_metadata.get("filetype") {
+        Some(file_type) => match Mime::from_str(file_type) {
+            Ok(mime_type) => Some((mime_type, FileTypeLevel::Fallback)),
+            Err(_) => return Err(Status::BadRequest),
+        },
+        None => None,
+    };
+
+    match datalith.create_upload_session(Some(upload_length), file_name, file_type).await {
+        Ok(session) => Ok(TusResponse::new(Status::Created)
+            .header("location", datalith.scoped_path(uri!("/o/uploads", head(session.id()))))
+            .upload_expires(session.expired_at())),
+        Err(error) => {
+            rocket::error!("{error}");
+
+            Err(Status::InternalServerError)
+        },
+    }
+}
+
+#[head("/<id>")]
+async fn head(
+    _scope: WriteScope,
+    datalith: NamespacedDatalith,
+    _tus_headers: TusHeaders<'_>,
+    id: Uuid,
+) -> Result<TusResponse, Status> {
+    match datalith.get_upload_session_by_id(id).await {
+        Ok(Some(session)) => {
+            let mut response = TusResponse::new(Status::Ok)
+                .header("cache-control", "no-store")
+                .header("upload-offset", session.upload_offset().to_string())
+                .upload_expires(session.expired_at());
+
+            if let Some(upload_length) = session.upload_length() {
+                response = response.header("upload-length", upload_length.to_string());
+            }
+
+            Ok(response)
+        },
+        Ok(None) => Err(Status::NotFound),
+        Err(error) => {
+            rocket::error!("{error}");
+
+            Err(Status::InternalServerError)
+        },
+    }
+}
+
+/// Append data to an upload session. When all the data has been received, the session is finished and the resource is returned as JSON.
+#[patch("/<id>", data = "<data>")]
+async fn append(
+    _scope: WriteScope,
+    server_config: &State<ServerConfig>,
+    datalith: NamespacedDatalith,
+    tus_headers: TusHeaders<'_>,
+    id: Uuid,
+    data: Data<'_>,
+) -> Result<TusResponse, Status> {
+    if tus_headers.content_type() != Some("application/offset+octet-stream") {
+        return Err(Status::UnsupportedMediaType);
+    }
+
+    let offset = tus_headers.upload_offset()?.ok_or(Status::BadRequest)?;
+
+    let upload_length = match datalith.get_upload_session_by_id(id).await {
+        Ok(Some(session)) => session.upload_length().unwrap_or(server_config.max_file_size),
+        Ok(None) => return Err(Status::NotFound),
+        Err(error) => {
+            rocket::error!("{error}");
+
+            return Err(Status::InternalServerError);
+        },
+    };
+
+    // plus 1 in order to distinguish the too large payload
+    let stream = data.open((upload_length.saturating_sub(offset) + 1).into());
+
+    let upload_offset = datalith
+        .append_upload_session(id, offset, stream)
+        .await
+        .map_err(upload_session_error_to_status)?;
+
+    if upload_offset < upload_length {
+        let mut response =
+            TusResponse::new(Status::NoContent).header("upload-offset", upload_offset.to_string());
+
+        if let Ok(Some(session)) = datalith.get_upload_session_by_id(id).await {
+            response = response.upload_expires(session.expired_at());
+        }
+
+        return Ok(response);
+    }
+
+    let resource =
+        datalith.finish_upload_session(id).await.map_err(upload_session_error_to_status)?;
+
+    let value = datalith_resource_to_json_value(resource);
+
+    Ok(TusResponse::new(Status::Ok)
+        .header("upload-offset", upload_offset.to_string())
+        .json(serde_json::to_string(&value).unwrap()))
+}
+
+#[delete("/<id>")]
+async fn delete(
+    _scope: WriteScope,
+    datalith: NamespacedDatalith,
+    _tus_headers: TusHeaders<'_>,
+    id: Uuid,
+) -> Result<TusResponse, Status> {
+    match datalith.delete_upload_session_by_id(id).await {
+        Ok(true) => Ok(TusResponse::new(Status::NoContent)),
+        Ok(false) => Err(Status::NotFound),
+        Err(error) => Err(upload_session_error_to_status(error)),
+    }
+}
+
+#[inline]
+pub fn mounts(rocket: Rocket<Build>) -> Rocket<Build> {
+    rocket.mount("/o/uploads", routes![options, create, head, append, delete])
+}
+
+fn upload_session_error_to_status(error: DatalithUploadSessionError) -> Status {
+    match error {
+        DatalithUploadSessionError::NotFound => Status::NotFound,
+        DatalithUploadSessionError::Locked => Status::Locked,
+        DatalithUploadSessionError::OffsetMismatch {
+            ..
+        } => Status::Conflict,
+        DatalithUploadSessionError::LengthExceeded {
+            ..
+        } => Status::PayloadTooLarge,
+        DatalithUploadSessionError::DatalithWriteError(DatalithWriteError::IOError(error))
+            if error.kind() == ErrorKind::Other =>
+        {
+            Status::BadRequest
+        },
+        DatalithUploadSessionError::DatalithWriteError(DatalithWriteError::FileTypeInvalid {
+            ..
+        }) => Status::BadRequest,
+        DatalithUploadSessionError::DatalithWriteError(
+            DatalithWriteError::QuotaExceeded {
+                ..
+            }
+            | DatalithWriteError::InsufficientDiskSpace {
+                ..
+            },
+        ) => Status::InsufficientStorage,
+        error => {
+            rocket::error!("{error}");
+
+            Status::InternalServerError
+        },
+    }
+}
@@ -0,0 +1,16 @@
+use std::convert::Infallible;
+
+use rocket::{Request, outcome::Outcome, request, request::FromRequest};
+
+/// The `Accept` header of a request, which is used to negotiate the format of an image.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AcceptHeader<'r>(pub Option<&'r str>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AcceptHeader<'r> {
+    type Error = Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        Outcome::Success(Self(request.headers().get_one("accept")))
+    }
+}
@@ -0,0 +1,56 @@
+use std::convert::Infallible;
+
+use rocket::{Request, outcome::Outcome, request, request::FromRequest};
+
+/// The `Accept-Encoding` header of a request.
+///
+/// Requests with a `Range` header never accept any content coding, so that byte ranges always refer to the original file.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AcceptEncoding<'r> {
+    header: Option<&'r str>,
+}
+
+impl AcceptEncoding<'_> {
+    /// Check whether the client accepts a content coding (e.g. `zstd`).
+    pub fn accepts(&self, content_coding: &str) -> bool {
+        let Some(header) = self.header else {
+            return false;
+        };
+
+        header.split(',').any(|item| {
+            let mut parameters = item.split(';');
+
+            let coding = parameters.next().unwrap_or_default().trim();
+
+            if !coding.eq_ignore_ascii_case(content_coding) {
+                return false;
+            }
+
+            for parameter in parameters {
+                if let Some((name, value)) = parameter.split_once('=')
+                    && name.trim().eq_ignore_ascii_case("q")
+                {
+                    return value.trim().parse::<f32>().map(|q| q > 0.0).unwrap_or(false);
+                }
+            }
+
+            true
+        })
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AcceptEncoding<'r> {
+    type Error = Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let headers = request.headers();
+
+        let header =
+            if headers.contains("range") { None } else { headers.get_one("accept-encoding") };
+
+        Outcome::Success(Self {
+            header,
+        })
+    }
+}
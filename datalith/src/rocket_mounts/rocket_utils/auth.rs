@@ -0,0 +1,180 @@
+use datalith_core::{DatalithApiKey, DatalithApiKeyScopes, DatalithManager};
+use rocket::{Request, http::Status, outcome::Outcome, request, request::FromRequest};
+
+use super::request_namespace;
+use crate::rocket_mounts::ServerConfig;
+
+/// The API key of a request.
+#[derive(Debug)]
+pub enum RequestApiKey {
+    /// The static API key assigned by `--api-key`.
+    Static,
+    /// An API key stored in the environment.
+    Stored(DatalithApiKey),
+}
+
+impl RequestApiKey {
+    /// Retrieve the scopes. The static API key has all scopes.
+    #[inline]
+    pub fn scopes(&self) -> DatalithApiKeyScopes {
+        match self {
+            Self::Static => DatalithApiKeyScopes::ALL,
+            Self::Stored(api_key) => api_key.scopes(),
+        }
+    }
+
+    /// Check whether this key can access a namespace. The static API key can access all namespaces.
+    #[inline]
+    pub fn allows_namespace(&self, namespace: &str) -> bool {
+        match self {
+            Self::Static => true,
+            Self::Stored(api_key) => api_key.allows_namespace(namespace),
+        }
+    }
+}
+
+/// The API key of a request, which is verified at most once per request.
+struct CachedApiKey(Result<Option<RequestApiKey>, (Status, &'static str)>);
+
+/// Retrieve the API key in the `Authorization: Bearer <key>` header of a request. Return `None` if there is no such header.
+pub async fn request_api_key<'r>(
+    request: &'r Request<'_>,
+) -> Result<Option<&'r RequestApiKey>, (Status, &'static str)> {
+    let api_key = request
+        .local_cache_async(async {
+            let key = match request.headers().get_one("authorization") {
+                Some(authorization) => match authorization.split_once(' ') {
+                    Some((scheme, key)) if scheme.eq_ignore_ascii_case("bearer") => key.trim(),
+                    _ => {
+                        return CachedApiKey(Err((Status::Unauthorized, "invalid authorization")));
+                    },
+                },
+                None => return CachedApiKey(Ok(None)),
+            };
+
+            let server_config = request.rocket().state::<ServerConfig>().unwrap();
+
+            if let Some(static_key) = server_config.api_key.as_deref()
+                && constant_time_eq(key.as_bytes(), static_key.as_bytes())
+            {
+                return CachedApiKey(Ok(Some(RequestApiKey::Static)));
+            }
+
+            let datalith = request.rocket().state::<DatalithManager>().unwrap();
+
+            match datalith.verify_api_key(key).await {
+                Ok(Some(api_key)) => CachedApiKey(Ok(Some(RequestApiKey::Stored(api_key)))),
+                Ok(None) => CachedApiKey(Err((Status::Unauthorized, "invalid API key"))),
+                Err(error) => {
+                    rocket::error!("{error}");
+
+                    CachedApiKey(Err((Status::InternalServerError, "cannot verify the API key")))
+                },
+            }
+        })
+        .await;
+
+    match &api_key.0 {
+        Ok(api_key) => Ok(api_key.as_ref()),
+        Err(error) => Err(*error),
+    }
+}
+
+/// Compare two byte strings in a time which only depends on their lengths.
+#[inline]
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// Authorize a request by its `Authorization: Bearer <key>` header if the authentication is enabled. The key must be allowed to access the namespace of the request, and anonymous requests are only allowed in the default namespace.
+async fn authorize(
+    request: &Request<'_>,
+    required_scopes: DatalithApiKeyScopes,
+    allow_anonymous: bool,
+) -> request::Outcome<(), &'static str> {
+    let server_config = request.rocket().state::<ServerConfig>().unwrap();
+
+    if !server_config.auth {
+        return Outcome::Success(());
+    }
+
+    let namespace = request_namespace(request);
+
+    if allow_anonymous && server_config.anonymous_fetch && namespace.is_empty() {
+        return Outcome::Success(());
+    }
+
+    let api_key = match request_api_key(request).await {
+        Ok(Some(api_key)) => api_key,
+        Ok(None) => return Outcome::Error((Status::Unauthorized, "missing authorization")),
+        Err(error) => return Outcome::Error(error),
+    };
+
+    if !api_key.scopes().contains(required_scopes) {
+        Outcome::Error((Status::Forbidden, "insufficient scopes"))
+    } else if !api_key.allows_namespace(namespace) {
+        Outcome::Error((Status::Forbidden, "namespace not allowed"))
+    } else {
+        Outcome::Success(())
+    }
+}
+
+/// A request guard which requires the `read` scope.
+#[derive(Debug)]
+pub struct ReadScope;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ReadScope {
+    type Error = &'static str;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        authorize(request, DatalithApiKeyScopes::READ, false).await.map(|_| Self)
+    }
+}
+
+/// A request guard for fetching files. It requires the `read` scope, unless anonymous fetching is allowed or the URL is signed.
+#[derive(Debug)]
+pub enum FetchScope {
+    Authorized,
+    /// The request has a `sig` parameter. The route must verify the signature.
+    Signed,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for FetchScope {
+    type Error = &'static str;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        if request.query_value::<&str>("sig").is_some() {
+            return Outcome::Success(Self::Signed);
+        }
+
+        authorize(request, DatalithApiKeyScopes::READ, true).await.map(|_| Self::Authorized)
+    }
+}
+
+/// A request guard which requires the `write` scope.
+#[derive(Debug)]
+pub struct WriteScope;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for WriteScope {
+    type Error = &'static str;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        authorize(request, DatalithApiKeyScopes::WRITE, false).await.map(|_| Self)
+    }
+}
+
+/// A request guard which requires the `delete` scope.
+#[derive(Debug)]
+pub struct DeleteScope;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for DeleteScope {
+    type Error = &'static str;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        authorize(request, DatalithApiKeyScopes::DELETE, false).await.map(|_| Self)
+    }
+}
@@ -0,0 +1,380 @@
+use std::{
+    io,
+    io::SeekFrom,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use datalith_core::{
+    DatalithFileRangeReader, ReadableDatalithFile,
+    chrono::{DateTime, Local},
+    mime::Mime,
+};
+use tokio::io::{AsyncRead, AsyncSeek, ReadBuf};
+
+/// The maximum number of ranges accepted in a single `Range` header. Requests with more ranges are answered with the full content.
+const MAX_RANGES: usize = 32;
+
+/// The result of evaluating a `Range` header against a file.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ByteRanges {
+    /// One or more `(start, length)` pairs, sorted and without overlapping.
+    Satisfiable(Vec<(u64, u64)>),
+    /// None of the ranges overlaps the file.
+    Unsatisfiable,
+}
+
+/// Parse a `Range` header value. `None` means the header should be ignored and the full content should be sent.
+pub fn parse_byte_ranges(range: &str, file_size: u64) -> Option<ByteRanges> {
+    let range = range.trim();
+
+    let (unit, range_set) = range.split_once('=')?;
+
+    if !unit.trim().eq_ignore_ascii_case("bytes") {
+        return None;
+    }
+
+    let mut ranges: Vec<(u64, u64)> = Vec::new();
+    let mut count = 0usize;
+
+    for range_spec in range_set.split(',') {
+        let range_spec = range_spec.trim();
+
+        if range_spec.is_empty() {
+            continue;
+        }
+
+        count += 1;
+
+        if count > MAX_RANGES {
+            return None;
+        }
+
+        let (first, last) = range_spec.split_once('-')?;
+        let (first, last) = (first.trim(), last.trim());
+
+        if first.is_empty() {
+            // suffix range
+            let suffix_length: u64 = last.parse().ok()?;
+
+            if suffix_length == 0 || file_size == 0 {
+                continue;
+            }
+
+            let length = suffix_length.min(file_size);
+
+            ranges.push((file_size - length, length));
+        } else {
+            let first: u64 = first.parse().ok()?;
+
+            let last = if last.is_empty() {
+                None
+            } else {
+                let last: u64 = last.parse().ok()?;
+
+                if last < first {
+                    return None;
+                }
+
+                Some(last)
+            };
+
+            if first >= file_size {
+                continue;
+            }
+
+            let last = last.map(|last| last.min(file_size - 1)).unwrap_or(file_size - 1);
+
+            ranges.push((first, last - first + 1));
+        }
+    }
+
+    if count == 0 {
+        return None;
+    }
+
+    if ranges.is_empty() {
+        return Some(ByteRanges::Unsatisfiable);
+    }
+
+    // coalesce overlapping or adjacent ranges
+    ranges.sort_unstable_by_key(|(start, _)| *start);
+
+    let mut coalesced: Vec<(u64, u64)> = Vec::with_capacity(ranges.len());
+
+    for (start, length) in ranges {
+        if let Some((last_start, last_length)) = coalesced.last_mut() {
+            let last_end = *last_start + *last_length;
+
+            if start <= last_end {
+                *last_length = last_end.max(start + length) - *last_start;
+
+                continue;
+            }
+        }
+
+        coalesced.push((start, length));
+    }
+
+    Some(ByteRanges::Satisfiable(coalesced))
+}
+
+/// Check whether the `If-Range` precondition allows the `Range` header to be used.
+pub fn if_range_matches(if_range: Option<&str>, etag: &str, date: DateTime<Local>) -> bool {
+    let if_range = match if_range {
+        Some(if_range) => if_range.trim(),
+        None => return true,
+    };
+
+    if if_range.starts_with('"') || if_range.starts_with("W/") {
+        // only a strong entity tag can be used
+        !etag.starts_with("W/") && if_range == etag
+    } else {
+        match DateTime::parse_from_rfc2822(if_range) {
+            Ok(if_range_date) => if_range_date.timestamp() == date.timestamp(),
+            Err(_) => false,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Segment {
+    Bytes(Vec<u8>),
+    File { start: u64, length: u64 },
+}
+
+impl Segment {
+    #[inline]
+    fn len(&self) -> u64 {
+        match self {
+            Self::Bytes(bytes) => bytes.len() as u64,
+            Self::File {
+                length, ..
+            } => *length,
+        }
+    }
+}
+
+/// A `multipart/byteranges` body built from multiple ranges of a file.
+#[derive(Debug)]
+pub struct ByteRangesBody {
+    reader:   DatalithFileRangeReader,
+    segments: Vec<(u64, Segment)>,
+    length:   u64,
+    position: u64,
+    loaded:   Option<usize>,
+}
+
+impl ByteRangesBody {
+    pub fn new(
+        file: ReadableDatalithFile,
+        file_type: &Mime,
+        ranges: &[(u64, u64)],
+        boundary: &str,
+    ) -> Self {
+        let file_size = file.file_size();
+
+        let mut segments = Vec::with_capacity(ranges.len() * 2 + 1);
+        let mut length = 0u64;
+
+        let mut push_segment = |segment: Segment| {
+            let segment_length = segment.len();
+
+            segments.push((length, segment));
+
+            length += segment_length;
+        };
+
+        for (i, (start, range_length)) in ranges.iter().copied().enumerate() {
+            let header = format!(
+                "{}--{boundary}\r\ncontent-type: {file_type}\r\ncontent-range: bytes \
+                 {start}-{}/{file_size}\r\n\r\n",
+                if i == 0 { "" } else { "\r\n" },
+                start + range_length - 1,
+            );
+
+            push_segment(Segment::Bytes(header.into_bytes()));
+            push_segment(Segment::File {
+                start,
+                length: range_length,
+            });
+        }
+
+        push_segment(Segment::Bytes(format!("\r\n--{boundary}--\r\n").into_bytes()));
+
+        Self {
+            reader: file.into_range_reader(0, 0),
+            segments,
+            length,
+            position: 0,
+            loaded: None,
+        }
+    }
+
+    /// Retrieve the size of the whole body.
+    #[inline]
+    pub const fn size(&self) -> u64 {
+        self.length
+    }
+}
+
+impl AsyncRead for ByteRangesBody {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+
+        if this.position >= this.length || buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+
+        let index = this.segments.partition_point(|(offset, _)| *offset <= this.position) - 1;
+        let (offset, segment) = &this.segments[index];
+        let skip = this.position - offset;
+
+        match segment {
+            Segment::Bytes(bytes) => {
+                let bytes = &bytes[skip as usize..];
+                let c = bytes.len().min(buf.remaining());
+
+                buf.put_slice(&bytes[..c]);
+
+                this.position += c as u64;
+            },
+            Segment::File {
+                start,
+                length,
+            } => {
+                if this.loaded != Some(index) {
+                    this.reader.set_range(start + skip, length - skip);
+                    this.loaded = Some(index);
+                }
+
+                let filled = buf.filled().len();
+
+                ready!(Pin::new(&mut this.reader).poll_read(cx, buf))?;
+
+                this.position += (buf.filled().len() - filled) as u64;
+            },
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncSeek for ByteRangesBody {
+    fn start_seek(mut self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        let position = match position {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.length.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+
+        match position {
+            Some(position) => {
+                self.position = position;
+                self.loaded = None;
+
+                Ok(())
+            },
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+
+    #[inline]
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Poll::Ready(Ok(self.position))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datalith_core::chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let too_many_ranges =
+            (0..MAX_RANGES + 1).map(|i| format!("{}-{}", i * 10, i * 10)).collect::<Vec<_>>();
+
+        let cases: &[(&str, u64, Option<ByteRanges>)] = &[
+            ("bytes=0-499", 1000, Some(ByteRanges::Satisfiable(vec![(0, 500)]))),
+            ("Bytes = 0-0", 1000, Some(ByteRanges::Satisfiable(vec![(0, 1)]))),
+            // suffix ranges
+            ("bytes=-500", 1000, Some(ByteRanges::Satisfiable(vec![(500, 500)]))),
+            ("bytes=-2000", 1000, Some(ByteRanges::Satisfiable(vec![(0, 1000)]))),
+            // open ranges
+            ("bytes=500-", 1000, Some(ByteRanges::Satisfiable(vec![(500, 500)]))),
+            ("bytes=900-1999", 1000, Some(ByteRanges::Satisfiable(vec![(900, 100)]))),
+            // overlapping and adjacent ranges are merged
+            (
+                "bytes=0-99, 50-149, 200-299, 150-199",
+                1000,
+                Some(ByteRanges::Satisfiable(vec![(0, 300)])),
+            ),
+            ("bytes=500-, -100", 1000, Some(ByteRanges::Satisfiable(vec![(500, 500)]))),
+            ("bytes=20-29, 0-9", 1000, Some(ByteRanges::Satisfiable(vec![(0, 10), (20, 10)]))),
+            // unsatisfiable ranges are skipped
+            ("bytes=1000-, 0-0", 1000, Some(ByteRanges::Satisfiable(vec![(0, 1)]))),
+            ("bytes=1000-", 1000, Some(ByteRanges::Unsatisfiable)),
+            ("bytes=1000-1999, -0", 1000, Some(ByteRanges::Unsatisfiable)),
+            ("bytes=-5", 0, Some(ByteRanges::Unsatisfiable)),
+            // invalid headers are ignored
+            ("bytes=5-1", 1000, None),
+            ("bytes=0-1, 5-1", 1000, None),
+            ("bytes=a-b", 1000, None),
+            ("bytes=1", 1000, None),
+            ("bytes=", 1000, None),
+            ("items=0-1", 1000, None),
+        ];
+
+        for (range, file_size, expected) in cases {
+            assert_eq!(expected, &parse_byte_ranges(range, *file_size), "{range}");
+        }
+
+        let ranges = format!("bytes={}", too_many_ranges[..MAX_RANGES].join(","));
+
+        match parse_byte_ranges(&ranges, 1000) {
+            Some(ByteRanges::Satisfiable(ranges)) => assert_eq!(MAX_RANGES, ranges.len()),
+            result => panic!("{result:?}"),
+        }
+
+        // too many ranges
+        let ranges = format!("bytes={}", too_many_ranges.join(","));
+
+        assert_eq!(None, parse_byte_ranges(&ranges, 1000));
+    }
+
+    #[test]
+    fn if_range() {
+        let date = Local.timestamp_opt(1_700_000_000, 0).unwrap();
+        let other_date = Local.timestamp_opt(1_700_000_001, 0).unwrap();
+
+        let cases: &[(Option<&str>, &str, bool)] = &[
+            (None, "\"abc\"", true),
+            (None, "W/\"abc\"", true),
+            // strong comparison
+            (Some("\"abc\""), "\"abc\"", true),
+            (Some(" \"abc\" "), "\"abc\"", true),
+            (Some("\"xyz\""), "\"abc\"", false),
+            // weak entity tags never match
+            (Some("W/\"abc\""), "\"abc\"", false),
+            (Some("W/\"abc\""), "W/\"abc\"", false),
+            (Some("\"abc\""), "W/\"abc\"", false),
+            // dates
+            (Some(&date.to_rfc2822()), "\"abc\"", true),
+            (Some(&other_date.to_rfc2822()), "\"abc\"", false),
+            (Some("yesterday"), "\"abc\"", false),
+        ];
+
+        for (if_range, etag, expected) in cases {
+            assert_eq!(*expected, if_range_matches(*if_range, etag, date), "{if_range:?} {etag}");
+        }
+    }
+}
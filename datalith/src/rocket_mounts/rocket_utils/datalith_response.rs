@@ -1,17 +1,50 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, io, ops::Deref};
 
+use base64::{Engine, engine::general_purpose::STANDARD};
 use datalith_core::{
-    Datalith, DatalithFile, DatalithReadError, ReadableDatalithFile, Uuid,
+    Datalith, DatalithCompression, DatalithFile, DatalithReadError, ReadableDatalithFile, Uuid,
     chrono::{DateTime, Local},
     mime::Mime,
 };
 use rocket::{Request, Response, http::Status, response, response::Responder};
 use rocket_etag_if_none_match::{EtagIfNoneMatch, entity_tag::EntityTag};
 
+use super::{
+    AcceptEncoding, ByteRanges, ByteRangesBody, Preconditions, file_entity_tag, if_range_matches,
+    parse_byte_ranges, to_http_date,
+};
+
+/// The content coding of a response body which is served as it is stored.
+#[derive(Debug)]
+pub struct ResponseEncoding {
+    pub content_encoding: &'static str,
+    pub size:             u64,
+}
+
+/// The file to respond. The file is not opened for a `HEAD` request.
+#[derive(Debug)]
+pub enum ResponseFile {
+    Readable(ReadableDatalithFile),
+    Head(DatalithFile),
+}
+
+impl Deref for ResponseFile {
+    type Target = DatalithFile;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Self::Readable(file) => file,
+            Self::Head(file) => file,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct ResponseData {
     pub etag:          EntityTag<'static>,
-    pub file:          ReadableDatalithFile,
+    pub file:          ResponseFile,
+    pub encoding:      Option<ResponseEncoding>,
     pub download:      bool,
     pub uuid:          Uuid,
     pub date:          DateTime<Local>,
@@ -21,96 +54,316 @@ pub struct ResponseData {
     pub is_temporary:  bool,
 }
 
+#[derive(Debug)]
+pub(super) enum ResponseKind {
+    Data(Box<ResponseData>),
+    /// 304 Not Modified.
+    NotModified {
+        etag:          EntityTag<'static>,
+        last_modified: DateTime<Local>,
+    },
+    /// 412 Precondition Failed.
+    PreconditionFailed,
+    /// 406 Not Acceptable.
+    #[cfg(feature = "image-convert")]
+    NotAcceptable,
+}
+
 #[derive(Debug)]
 pub struct DatalithResponse {
-    pub(super) data: Option<ResponseData>,
+    pub(super) kind: ResponseKind,
 }
 
 impl DatalithResponse {
     #[inline]
     pub const fn is_temporary(&self) -> bool {
-        if let Some(data) = self.data.as_ref() { data.is_temporary } else { false }
+        if let ResponseKind::Data(data) = &self.kind { data.is_temporary } else { false }
     }
 }
 
 impl DatalithResponse {
+    /// The content coding in which a file is served as it is stored, if the file is compressed and the client accepts it.
+    #[inline]
+    pub(super) fn stored_content_encoding(
+        file: &DatalithFile,
+        accept_encoding: &AcceptEncoding<'_>,
+    ) -> Option<&'static str> {
+        file.compression()
+            .content_encoding()
+            .filter(|content_encoding| accept_encoding.accepts(content_encoding))
+    }
+
+    /// Evaluate the conditional request headers against a representation. Return a response without a body if the request should not be fulfilled.
+    pub(super) fn evaluate_preconditions(
+        etag_if_none_match: &EtagIfNoneMatch<'_>,
+        preconditions: &Preconditions<'_>,
+        etag: &EntityTag<'static>,
+        last_modified: DateTime<Local>,
+    ) -> Option<DatalithResponse> {
+        if !preconditions.check([etag], last_modified) {
+            return Some(Self {
+                kind: ResponseKind::PreconditionFailed
+            });
+        }
+
+        if etag_if_none_match.weak_eq(etag)
+            || preconditions.is_not_modified(etag_if_none_match.etag.is_some(), last_modified)
+        {
+            return Some(Self {
+                kind: ResponseKind::NotModified {
+                    etag: etag.clone(),
+                    last_modified,
+                },
+            });
+        }
+
+        None
+    }
+
+    /// Open a file for responding. If the file is compressed and the client accepts its content coding, the stored data is served directly. Requests with a `Range` header never accept a content coding (see [`AcceptEncoding`]), so the encoded data is never served for them. For a `HEAD` request, the file is only checked and not opened.
+    pub(super) async fn open_file(
+        file: DatalithFile,
+        accept_encoding: &AcceptEncoding<'_>,
+        head: bool,
+    ) -> io::Result<(ResponseFile, Option<ResponseEncoding>)> {
+        let encoding = match Self::stored_content_encoding(&file, accept_encoding) {
+            Some(content_encoding) => Some(ResponseEncoding {
+                content_encoding,
+                size: file.stored_size().await?,
+            }),
+            None => None,
+        };
+
+        let file = if head {
+            if file.is_quarantined() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "the file {} is quarantined because its stored data is corrupt",
+                        file.id()
+                    ),
+                ));
+            }
+
+            ResponseFile::Head(file)
+        } else if encoding.is_some() {
+            ResponseFile::Readable(file.into_raw_readable().await?)
+        } else {
+            ResponseFile::Readable(file.into_readable().await?)
+        };
+
+        Ok((file, encoding))
+    }
+
     pub async fn from_resource_id<'a>(
         datalith: &'a Datalith,
         etag_if_none_match: &EtagIfNoneMatch<'a>,
+        preconditions: &Preconditions<'_>,
+        accept_encoding: &AcceptEncoding<'_>,
         id: Uuid,
         download: bool,
+        head: bool,
     ) -> Result<Option<DatalithResponse>, DatalithReadError> {
-        let etag = EntityTag::with_string(true, format!("{:x}", id.as_u128())).unwrap();
+        // peek first, so that a temporary resource is not consumed by a request which is not fulfilled
+        let Some(resource) = datalith.peek_resource_by_id(id).await? else {
+            return Ok(None);
+        };
 
-        let is_etag_match = etag_if_none_match.weak_eq(&etag);
+        let etag = file_entity_tag(
+            resource.file(),
+            Self::stored_content_encoding(resource.file(), accept_encoding),
+        );
 
-        if is_etag_match {
-            Ok(Some(DatalithResponse {
-                data: None
-            }))
-        } else {
-            let resource = datalith.get_resource_by_id(id).await?;
-
-            match resource {
-                Some(resource) => {
-                    let uuid = resource.id();
-                    let date = resource.created_at();
-
-                    let file_name = resource.file_name().clone();
-                    let file_type = resource.file_type().clone();
-                    let is_temporary = resource.is_temporary();
-
-                    Ok(Some(Self {
-                        data: Some(ResponseData {
-                            etag,
-                            file: DatalithFile::from(resource).into_readable().await?,
-                            download,
-                            uuid,
-                            date,
-                            file_name,
-                            file_type,
-                            extra_headers: HashMap::new(),
-                            is_temporary,
-                        }),
-                    }))
-                },
-                None => Ok(None),
-            }
+        if let Some(response) = Self::evaluate_preconditions(
+            etag_if_none_match,
+            preconditions,
+            &etag,
+            resource.created_at(),
+        ) {
+            return Ok(Some(response));
         }
+
+        // a temporary resource is only consumed when its data is sent
+        let resource = if resource.is_temporary() && !head {
+            drop(resource);
+
+            match datalith.get_resource_by_id(id).await? {
+                Some(resource) => resource,
+                None => return Ok(None),
+            }
+        } else {
+            resource
+        };
+
+        let uuid = resource.id();
+        let date = resource.created_at();
+
+        let file_name = resource.file_name().clone();
+        let file_type = resource.file_type().clone();
+        let is_temporary = resource.is_temporary();
+
+        let (file, encoding) =
+            Self::open_file(DatalithFile::from(resource), accept_encoding, head).await?;
+
+        Ok(Some(Self {
+            kind: ResponseKind::Data(Box::new(ResponseData {
+                etag,
+                file,
+                encoding,
+                download,
+                uuid,
+                date,
+                file_name,
+                file_type,
+                extra_headers: HashMap::new(),
+                is_temporary,
+            })),
+        }))
     }
 }
 
 impl<'r, 'o: 'r> Responder<'r, 'o> for DatalithResponse {
-    fn respond_to(self, _: &'r Request<'_>) -> response::Result<'o> {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'o> {
         let mut response = Response::build();
 
-        if let Some(data) = self.data {
-            if !data.file.is_temporary() {
-                response.raw_header("etag", data.etag.to_string());
-            }
+        let data = match self.kind {
+            ResponseKind::Data(data) => *data,
+            ResponseKind::NotModified {
+                etag,
+                last_modified,
+            } => {
+                response.status(Status::NotModified);
+                response.raw_header("etag", etag.to_string());
+                response.raw_header("last-modified", to_http_date(last_modified));
 
-            {
-                let mut v = format!(
-                    "{}; filename*=UTF-8''",
-                    if data.download { "attachment" } else { "inline" }
-                );
+                return response.ok();
+            },
+            ResponseKind::PreconditionFailed => {
+                response.status(Status::PreconditionFailed);
 
-                url_escape::encode_component_to_string(data.file_name, &mut v);
+                return response.ok();
+            },
+            #[cfg(feature = "image-convert")]
+            ResponseKind::NotAcceptable => {
+                response.status(Status::NotAcceptable);
 
-                response.raw_header("content-disposition", v);
-            }
+                return response.ok();
+            },
+        };
+
+        let etag = data.etag.to_string();
+
+        if !data.file.is_temporary() {
+            response.raw_header("etag", etag.clone());
+        }
+
+        response.raw_header("last-modified", to_http_date(data.date));
+
+        {
+            let mut v = format!(
+                "{}; filename*=UTF-8''",
+                if data.download { "attachment" } else { "inline" }
+            );
+
+            url_escape::encode_component_to_string(data.file_name, &mut v);
+
+            response.raw_header("content-disposition", v);
+        }
+
+        response.raw_header("x-uuid", data.uuid.to_string());
+        response.raw_header("date", data.date.to_rfc2822());
+        // byte ranges always refer to the original file, so a client must not resume an encoded response with them
+        response
+            .raw_header("accept-ranges", if data.encoding.is_some() { "none" } else { "bytes" });
+
+        for (name, value) in data.extra_headers {
+            response.raw_header(name, value);
+        }
+
+        if data.file.compression() != DatalithCompression::None {
+            response.raw_header_adjoin("vary", "accept-encoding");
+        }
 
-            response.raw_header("x-uuid", data.uuid.to_string());
-            response.raw_header("date", data.date.to_rfc2822());
+        let file_size = data.file.file_size();
+
+        if let Some(encoding) = data.encoding {
             response.raw_header("content-type", data.file_type.to_string());
+            response.raw_header("content-encoding", encoding.content_encoding);
 
-            for (name, value) in data.extra_headers {
-                response.raw_header(name, value);
+            match data.file {
+                ResponseFile::Readable(file) => {
+                    response.sized_body(encoding.size.try_into().ok(), file);
+                },
+                ResponseFile::Head(_) => {
+                    response
+                        .sized_body(encoding.size.try_into().ok(), io::Cursor::new(&[] as &[u8]));
+                },
             }
 
-            response.sized_body(data.file.file_size().try_into().ok(), data.file);
-        } else {
-            response.status(Status::NotModified);
+            return response.ok();
+        }
+
+        // the digest of the stored data only describes the identity representation
+        response
+            .raw_header("repr-digest", format!("sha-256=:{}:", STANDARD.encode(data.file.hash())));
+
+        let file = match data.file {
+            ResponseFile::Readable(file) => file,
+            ResponseFile::Head(_) => {
+                // `Range` is ignored for `HEAD`
+                response.raw_header("content-type", data.file_type.to_string());
+
+                response.sized_body(file_size.try_into().ok(), io::Cursor::new(&[] as &[u8]));
+
+                return response.ok();
+            },
+        };
+
+        let byte_ranges = request.headers().get_one("range").and_then(|range| {
+            let if_range = request.headers().get_one("if-range");
+
+            if if_range_matches(if_range, &etag, data.date) {
+                parse_byte_ranges(range, file_size)
+            } else {
+                None
+            }
+        });
+
+        match byte_ranges {
+            None => {
+                response.raw_header("content-type", data.file_type.to_string());
+
+                response.sized_body(file_size.try_into().ok(), file);
+            },
+            Some(ByteRanges::Unsatisfiable) => {
+                response.status(Status::RangeNotSatisfiable);
+                response.raw_header("content-range", format!("bytes */{file_size}"));
+            },
+            Some(ByteRanges::Satisfiable(ranges)) => {
+                response.status(Status::PartialContent);
+
+                if let [(start, length)] = ranges[..] {
+                    response.raw_header("content-type", data.file_type.to_string());
+                    response.raw_header(
+                        "content-range",
+                        format!("bytes {start}-{}/{file_size}", start + length - 1),
+                    );
+
+                    response
+                        .sized_body(length.try_into().ok(), file.into_range_reader(start, length));
+                } else {
+                    let boundary = format!("{:x}", Uuid::new_v4().as_u128());
+
+                    response.raw_header(
+                        "content-type",
+                        format!("multipart/byteranges; boundary={boundary}"),
+                    );
+
+                    let body = ByteRangesBody::new(file, &data.file_type, &ranges, &boundary);
+
+                    response.sized_body(body.size().try_into().ok(), body);
+                }
+            },
         }
 
         response.ok()
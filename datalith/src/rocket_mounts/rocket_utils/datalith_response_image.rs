@@ -1,20 +1,36 @@
 use std::{collections::HashMap, fmt::Write, path::Path};
 
-use datalith_core::{Datalith, DatalithReadError, MIME_WEBP, Uuid, get_image_extension, mime};
+use datalith_core::{
+    Datalith, DatalithImageFormat, DatalithImageResolution, DatalithReadError, Uuid,
+    get_image_extension,
+};
 use rocket::{
     form,
     form::{FromFormField, ValueField},
 };
-use rocket_etag_if_none_match::{EtagIfNoneMatch, entity_tag::EntityTag};
+use rocket_etag_if_none_match::EtagIfNoneMatch;
 
-use super::{DatalithResponse, ResponseData};
+use super::{
+    AcceptEncoding, AcceptHeader, DatalithResponse, Preconditions, ResponseData, ResponseKind,
+    file_entity_tag,
+};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum ResolutionType {
     Original,
     Multiplier(u8),
 }
 
+impl From<ResolutionType> for DatalithImageResolution {
+    #[inline]
+    fn from(value: ResolutionType) -> Self {
+        match value {
+            ResolutionType::Original => Self::Original,
+            ResolutionType::Multiplier(multiplier) => Self::Multiplier(multiplier),
+        }
+    }
+}
+
 #[rocket::async_trait]
 impl<'r> FromFormField<'r> for ResolutionType {
     fn from_value(field: ValueField<'r>) -> form::Result<'r, Self> {
@@ -36,116 +52,166 @@ impl<'r> FromFormField<'r> for ResolutionType {
     }
 }
 
+/// The `format` parameter which requests a format of an image explicitly.
+#[derive(Debug, Clone, Copy)]
+pub struct ImageFormat(pub DatalithImageFormat);
+
+#[rocket::async_trait]
+impl<'r> FromFormField<'r> for ImageFormat {
+    fn from_value(field: ValueField<'r>) -> form::Result<'r, Self> {
+        if let Some(format) = DatalithImageFormat::from_name(field.value) {
+            return Ok(Self(format));
+        }
+
+        let mut errors = form::Errors::new();
+
+        errors.push(form::Error::validation("not webp, jpg, png or avif"));
+
+        Err(errors)
+    }
+}
+
 impl DatalithResponse {
+    /// Respond an image. The format is `format` if it is given, or the fallback format if `fallback` is `true`. Otherwise, the format is chosen by the `Accept` header. The original file is served for the original resolution if it exists and is in the requested format.
+    #[allow(clippy::too_many_arguments)]
     pub async fn from_image_id<'a>(
         datalith: &'a Datalith,
         etag_if_none_match: &EtagIfNoneMatch<'a>,
+        preconditions: &Preconditions<'_>,
+        accept_encoding: &AcceptEncoding<'_>,
+        accept: &AcceptHeader<'_>,
         id: Uuid,
         resolution_type: Option<ResolutionType>,
-        fallback: bool,
+        format: Option<DatalithImageFormat>,
+        fallback: Option<bool>,
         download: bool,
+        head: bool,
     ) -> Result<Option<DatalithResponse>, DatalithReadError> {
-        let etag = EntityTag::with_string(true, format!("{:x}", id.as_u128())).unwrap();
-
-        let is_etag_match = etag_if_none_match.weak_eq(&etag);
-
-        if is_etag_match {
-            Ok(Some(DatalithResponse {
-                data: None
-            }))
-        } else {
-            let resolution_type = resolution_type.unwrap_or(ResolutionType::Multiplier(1));
-
-            let image = datalith.get_image_by_id(id).await?;
-
-            match image {
-                Some(image) => {
-                    let uuid = image.id();
-                    let date = image.created_at();
-
-                    let mut file_name = image.image_stem().clone();
-                    let image_width = image.image_width();
-                    let image_height = image.image_height();
-                    let has_alpha_channel = image.has_alpha_channel();
+        let resolution_type = resolution_type.unwrap_or(ResolutionType::Multiplier(1));
+
+        let image = datalith.get_image_by_id(id).await?;
+
+        match image {
+            Some(image) => {
+                let uuid = image.id();
+                let date = image.created_at();
+
+                let mut file_name = image.image_stem().clone();
+                let image_width = image.image_width();
+                let image_height = image.image_height();
+
+                let mut extra_headers = HashMap::with_capacity(3);
+
+                let use_original_file = matches!(resolution_type, ResolutionType::Original)
+                    && image.original_file().is_some_and(|file| {
+                        format.is_none_or(|format| {
+                            DatalithImageFormat::from_mime(file.file_type()) == Some(format)
+                        })
+                    });
+
+                let thumbnail_format = if use_original_file {
+                    image.thumbnail_format()
+                } else {
+                    match (format, fallback) {
+                        (Some(format), _) => {
+                            if format != image.thumbnail_format()
+                                && format != image.fallback_thumbnail_format()
+                            {
+                                return Ok(Some(Self {
+                                    kind: ResponseKind::NotAcceptable
+                                }));
+                            }
 
-                    let mut extra_headers = HashMap::with_capacity(2);
+                            format
+                        },
+                        (None, Some(true)) => image.fallback_thumbnail_format(),
+                        (None, Some(false)) => image.thumbnail_format(),
+                        (None, None) => {
+                            extra_headers.insert("vary", "accept".to_string());
 
-                    let (file, multiplier) = match resolution_type {
-                        ResolutionType::Original => {
-                            if image.original_file().is_some() {
-                                (image.into_original_file().unwrap(), 0)
-                            } else {
-                                let v = if fallback {
-                                    image.into_fallback_thumbnails()
-                                } else {
-                                    image.into_thumbnails()
-                                };
-                                let multiplier = v.len();
-
-                                (v.into_iter().next_back().unwrap(), multiplier)
-                            }
+                            image.negotiate_thumbnail_format(accept.0)
                         },
-                        ResolutionType::Multiplier(multiplier) => {
-                            let multiplier =
-                                (multiplier as usize).clamp(1, image.thumbnails().len());
+                    }
+                };
+                let fallback = thumbnail_format != image.thumbnail_format();
+
+                let (file, multiplier) = match resolution_type {
+                    ResolutionType::Original => {
+                        if use_original_file {
+                            (image.into_original_file().unwrap(), 0)
+                        } else {
                             let v = if fallback {
                                 image.into_fallback_thumbnails()
                             } else {
                                 image.into_thumbnails()
                             };
+                            let multiplier = v.len();
 
-                            (v.into_iter().nth(multiplier - 1).unwrap(), multiplier)
-                        },
-                    };
-
-                    let file_type = if multiplier == 0 {
-                        if let Some(ext) = get_image_extension(file.file_type()).or_else(|| {
-                            Path::new(file.file_name()).extension().and_then(|e| e.to_str())
-                        }) {
-                            file_name.push('.');
-                            file_name.push_str(ext);
+                            (v.into_iter().next_back().unwrap(), multiplier)
                         }
-
-                        file.file_type().clone()
-                    } else {
-                        let (ext, file_type) = if fallback {
-                            if has_alpha_channel {
-                                ("png", mime::IMAGE_PNG)
-                            } else {
-                                ("jpg", mime::IMAGE_JPEG)
-                            }
+                    },
+                    ResolutionType::Multiplier(multiplier) => {
+                        let multiplier = (multiplier as usize).clamp(1, image.thumbnails().len());
+                        let v = if fallback {
+                            image.into_fallback_thumbnails()
                         } else {
-                            ("webp", MIME_WEBP.clone())
+                            image.into_thumbnails()
                         };
 
-                        file_name.write_fmt(format_args!("@{multiplier}x.{ext}")).unwrap();
-
-                        let multiplier_u16 = multiplier as u16;
-
-                        extra_headers
-                            .insert("x-image-width", (image_width * multiplier_u16).to_string());
-                        extra_headers
-                            .insert("x-image-height", (image_height * multiplier_u16).to_string());
-
-                        file_type
-                    };
-
-                    Ok(Some(Self {
-                        data: Some(ResponseData {
-                            etag,
-                            file: file.into_readable().await?,
-                            download,
-                            uuid,
-                            date,
-                            file_name,
-                            file_type,
-                            extra_headers,
-                            is_temporary: false,
-                        }),
-                    }))
-                },
-                None => Ok(None),
-            }
+                        (v.into_iter().nth(multiplier - 1).unwrap(), multiplier)
+                    },
+                };
+
+                let file_type = if multiplier == 0 {
+                    if let Some(ext) = get_image_extension(file.file_type()).or_else(|| {
+                        Path::new(file.file_name()).extension().and_then(|e| e.to_str())
+                    }) {
+                        file_name.push('.');
+                        file_name.push_str(ext);
+                    }
+
+                    file.file_type().clone()
+                } else {
+                    file_name
+                        .write_fmt(format_args!("@{multiplier}x.{}", thumbnail_format.extension()))
+                        .unwrap();
+
+                    let multiplier_u16 = multiplier as u16;
+
+                    extra_headers
+                        .insert("x-image-width", (image_width * multiplier_u16).to_string());
+                    extra_headers
+                        .insert("x-image-height", (image_height * multiplier_u16).to_string());
+
+                    thumbnail_format.mime_type()
+                };
+                let etag =
+                    file_entity_tag(&file, Self::stored_content_encoding(&file, accept_encoding));
+
+                if let Some(response) =
+                    Self::evaluate_preconditions(etag_if_none_match, preconditions, &etag, date)
+                {
+                    return Ok(Some(response));
+                }
+
+                let (file, encoding) = Self::open_file(file, accept_encoding, head).await?;
+
+                Ok(Some(Self {
+                    kind: ResponseKind::Data(Box::new(ResponseData {
+                        etag,
+                        file,
+                        encoding,
+                        download,
+                        uuid,
+                        date,
+                        file_name,
+                        file_type,
+                        extra_headers,
+                        is_temporary: false,
+                    })),
+                }))
+            },
+            None => Ok(None),
         }
     }
 }
@@ -0,0 +1,95 @@
+use base64::{Engine, engine::general_purpose::STANDARD};
+use datalith_core::parse_sha256_hex;
+use rocket::{
+    Request,
+    http::Status,
+    request::{FromRequest, Outcome},
+};
+
+/// The SHA-256 hash which the client expects the uploaded data to have. It is from the `Content-Digest`, `Repr-Digest` or `X-Sha256` header. Digests of other algorithms are ignored.
+#[derive(Debug, Default)]
+pub struct ExpectedHash(pub Option<[u8; 32]>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ExpectedHash {
+    type Error = &'static str;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let headers = request.headers();
+
+        let mut expected_hash = ExpectedHash::default();
+
+        for value in headers.get("content-digest").chain(headers.get("repr-digest")) {
+            let hash = match parse_sha256_digest(value) {
+                Ok(Some(hash)) => hash,
+                Ok(None) => continue,
+                Err(()) => return Outcome::Error((Status::BadRequest, "invalid digest")),
+            };
+
+            if expected_hash.merge_hash(hash).is_err() {
+                return Outcome::Error((Status::BadRequest, "conflicting digests"));
+            }
+        }
+
+        if let Some(value) = headers.get_one("x-sha256") {
+            let hash = match expected_hash.merge(Some(value)) {
+                Ok(hash) => hash,
+                Err(_) => return Outcome::Error((Status::BadRequest, "conflicting digests")),
+            };
+
+            expected_hash = ExpectedHash(hash);
+        }
+
+        Outcome::Success(expected_hash)
+    }
+}
+
+impl ExpectedHash {
+    /// Merge a hex SHA-256 hash given in another way, as in a form field. Return 400 if it is invalid or is different from the one in the headers.
+    pub fn merge(mut self, sha256: Option<&str>) -> Result<Option<[u8; 32]>, Status> {
+        if let Some(sha256) = sha256 {
+            let hash = parse_sha256_hex(sha256.trim()).ok_or(Status::BadRequest)?;
+
+            self.merge_hash(hash)?;
+        }
+
+        Ok(self.0)
+    }
+
+    #[inline]
+    fn merge_hash(&mut self, hash: [u8; 32]) -> Result<(), Status> {
+        match self.0 {
+            Some(expected_hash) if expected_hash != hash => Err(Status::BadRequest),
+            _ => {
+                self.0 = Some(hash);
+
+                Ok(())
+            },
+        }
+    }
+}
+
+/// Parse the `sha-256` member of a `Content-Digest` or `Repr-Digest` header, as in `sha-256=:<base64>:`.
+fn parse_sha256_digest(value: &str) -> Result<Option<[u8; 32]>, ()> {
+    for member in value.split(',') {
+        let Some((algorithm, digest)) = member.split_once('=') else {
+            continue;
+        };
+
+        if !algorithm.trim().eq_ignore_ascii_case("sha-256") {
+            continue;
+        }
+
+        // parameters are ignored
+        let digest = digest.split(';').next().unwrap().trim();
+
+        let digest = digest.strip_prefix(':').and_then(|e| e.strip_suffix(':')).ok_or(())?;
+
+        return match STANDARD.decode(digest) {
+            Ok(hash) => hash.try_into().map(Some).map_err(|_| ()),
+            Err(_) => Err(()),
+        };
+    }
+
+    Ok(None)
+}
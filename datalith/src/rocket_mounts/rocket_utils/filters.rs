@@ -0,0 +1,28 @@
+use datalith_core::{
+    DatalithNameMatch,
+    chrono::{DateTime, Utc},
+};
+use rocket::http::Status;
+
+/// Parse an RFC 3339 time query, as in `2024-01-31T00:00:00Z`.
+#[inline]
+pub fn parse_time_query(time: Option<&str>) -> Result<Option<DateTime<Utc>>, Status> {
+    time.map(|time| {
+        DateTime::parse_from_rfc3339(time).map(|e| e.to_utc()).map_err(|_| Status::BadRequest)
+    })
+    .transpose()
+}
+
+/// Parse a pair of name queries, one for a substring and one for a glob pattern. At most one of them can be used.
+#[inline]
+pub fn parse_name_query(
+    contains: Option<&str>,
+    glob: Option<&str>,
+) -> Result<Option<DatalithNameMatch>, Status> {
+    match (contains, glob) {
+        (Some(_), Some(_)) => Err(Status::BadRequest),
+        (Some(s), None) => Ok(Some(DatalithNameMatch::Contains(s.to_string()))),
+        (None, Some(pattern)) => Ok(Some(DatalithNameMatch::Glob(pattern.to_string()))),
+        (None, None) => Ok(None),
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::BTreeSet;
+
+use datalith_core::{DatalithMetadata, DatalithMetadataUpdate, DatalithWriteError};
+use rocket::{
+    Data, Request, data::ToByteUnit, http::Status, outcome::Outcome, request, request::FromRequest,
+};
+use serde_json::{Map, Value, json};
+
+/// The prefix of the headers which carry the metadata of an upload, as in `X-Datalith-Meta-Uploader-Id: 42`.
+const METADATA_HEADER_PREFIX: &str = "x-datalith-meta-";
+/// The header which carries the tags of an upload, separated by commas.
+const TAGS_HEADER_NAME: &str = "x-datalith-tags";
+/// The maximum size of a request body which changes metadata.
+const METADATA_BODY_LIMIT: u64 = 64 * 1024;
+
+/// A request guard of the metadata carried by the `X-Datalith-Meta-*` and `X-Datalith-Tags` headers. The keys are the lowercase header names without the prefix.
+#[derive(Debug, Clone, Default)]
+pub struct MetadataHeaders(pub DatalithMetadata);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for MetadataHeaders {
+    type Error = DatalithWriteError;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let mut metadata = DatalithMetadata::default();
+
+        for header in request.headers().iter() {
+            let name = header.name().as_str().to_ascii_lowercase();
+
+            if let Some(key) = name.strip_prefix(METADATA_HEADER_PREFIX) {
+                metadata.values.insert(key.to_string(), header.value().to_string());
+            } else if name == TAGS_HEADER_NAME {
+                metadata.tags.extend(parse_tags(header.value()));
+            }
+        }
+
+        match DatalithMetadataUpdate::from(metadata.clone()).validate() {
+            Ok(()) => Outcome::Success(Self(metadata)),
+            Err(error) => Outcome::Error((Status::BadRequest, error)),
+        }
+    }
+}
+
+/// Parse comma-separated tags.
+#[inline]
+pub fn parse_tags(tags: &str) -> BTreeSet<String> {
+    tags.split(',').map(|tag| tag.trim()).filter(|tag| !tag.is_empty()).map(String::from).collect()
+}
+
+/// Add the metadata carried by the `metadata` (a JSON object of string values, as in `{"uploader-id": "42"}`) and `tags` (separated by commas) fields of a multipart form, then check the result.
+pub fn merge_metadata_fields(
+    metadata: &mut DatalithMetadata,
+    values: Option<&str>,
+    tags: Option<&str>,
+) -> Result<(), Status> {
+    if let Some(values) = values {
+        let values: Map<String, Value> =
+            serde_json::from_str(values).map_err(|_| Status::BadRequest)?;
+
+        for (key, value) in values {
+            match value {
+                Value::String(value) => {
+                    metadata.values.insert(key, value);
+                },
+                _ => return Err(Status::BadRequest),
+            }
+        }
+    }
+
+    if let Some(tags) = tags {
+        metadata.tags.extend(parse_tags(tags));
+    }
+
+    DatalithMetadataUpdate::from(metadata.clone()).validate().map_err(|_| Status::BadRequest)
+}
+
+/// Read the body of a request which changes metadata, as in `{"metadata": {"category": "invoice", "draft": null}, "add_tags": ["paid"], "remove_tags": ["unpaid"]}`.
+pub async fn read_metadata_update(data: Data<'_>) -> Result<DatalithMetadataUpdate, Status> {
+    let body = data
+        .open(METADATA_BODY_LIMIT.bytes())
+        .into_string()
+        .await
+        .map_err(|_| Status::BadRequest)?;
+
+    if !body.is_complete() {
+        return Err(Status::PayloadTooLarge);
+    }
+
+    let body: Map<String, Value> =
+        serde_json::from_str(body.as_str()).map_err(|_| Status::BadRequest)?;
+
+    let mut update = DatalithMetadataUpdate::default();
+
+    for (field, value) in body {
+        match (field.as_str(), value) {
+            ("metadata", Value::Object(values)) => {
+                for (key, value) in values {
+                    let value = match value {
+                        Value::String(value) => Some(value),
+                        Value::Null => None,
+                        _ => return Err(Status::BadRequest),
+                    };
+
+                    update.values.insert(key, value);
+                }
+            },
+            ("add_tags", Value::Array(tags)) => {
+                update.add_tags = parse_tags_json(tags)?;
+            },
+            ("remove_tags", Value::Array(tags)) => {
+                update.remove_tags = parse_tags_json(tags)?;
+            },
+            _ => return Err(Status::BadRequest),
+        }
+    }
+
+    update.validate().map_err(|_| Status::BadRequest)?;
+
+    Ok(update)
+}
+
+#[inline]
+fn parse_tags_json(tags: Vec<Value>) -> Result<BTreeSet<String>, Status> {
+    tags.into_iter()
+        .map(|tag| match tag {
+            Value::String(tag) => Ok(tag),
+            _ => Err(Status::BadRequest),
+        })
+        .collect()
+}
+
+/// Parse the metadata conditions of a listing query, as in `?meta=category:invoice&tag=paid`.
+pub fn parse_metadata_filter(meta: Vec<&str>, tag: Vec<&str>) -> Result<DatalithMetadata, Status> {
+    let mut metadata = DatalithMetadata::default();
+
+    for meta in meta {
+        let (key, value) = meta.split_once(':').ok_or(Status::BadRequest)?;
+
+        metadata.values.insert(key.to_string(), value.to_string());
+    }
+
+    metadata.tags.extend(tag.into_iter().map(String::from));
+
+    Ok(metadata)
+}
+
+/// Set the `metadata`, `tags` and `metadata_version` fields of a JSON object.
+#[inline]
+pub fn insert_metadata_json_value(
+    value: &mut Value,
+    metadata: &DatalithMetadata,
+    metadata_version: u64,
+) {
+    value["metadata"] = json!(metadata.values);
+    value["tags"] = json!(metadata.tags);
+    value["metadata_version"] = json!(metadata_version);
+}
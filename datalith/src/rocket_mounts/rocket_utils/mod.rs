@@ -1,9 +1,35 @@
+#[cfg(feature = "image-convert")]
+mod accept;
+mod accept_encoding;
+mod auth;
+mod byte_ranges;
 mod content_length;
 mod datalith_response;
 #[cfg(feature = "image-convert")]
 mod datalith_response_image;
+mod expected_hash;
+mod filters;
+mod metadata;
+mod namespace;
+mod pagination;
+mod preconditions;
+mod signed_url;
+mod tus;
 
+#[cfg(feature = "image-convert")]
+pub use accept::*;
+pub use accept_encoding::*;
+pub use auth::*;
+pub use byte_ranges::*;
 pub use content_length::*;
 pub use datalith_response::*;
 #[cfg(feature = "image-convert")]
-pub use datalith_response_image::ResolutionType;
+pub use datalith_response_image::{ImageFormat, ResolutionType};
+pub use expected_hash::*;
+pub use filters::*;
+pub use metadata::*;
+pub use namespace::*;
+pub use pagination::*;
+pub use preconditions::*;
+pub use signed_url::*;
+pub use tus::*;
@@ -0,0 +1,123 @@
+use std::{fmt::Display, ops::Deref};
+
+use datalith_core::{Datalith, DatalithManager, is_valid_namespace};
+use rocket::{
+    Data, Request,
+    fairing::AdHoc,
+    http::{Status, uri::Origin},
+    outcome::Outcome,
+    request,
+    request::FromRequest,
+};
+
+use super::request_api_key;
+use crate::rocket_mounts::ServerConfig;
+
+/// The prefix of the paths which are scoped by a namespace, as in `/n/<namespace>/o/<id>`.
+///
+/// The namespace is put before the whole path instead of after `/o`, because `/o/<namespace>` could not be told apart from `/o/<id>` or `/o/uploads`, and the same prefix works for `/i`, `/f` and `/h` as well.
+const NAMESPACE_PATH_PREFIX: &str = "/n/";
+
+/// The namespace of a request, extracted from its path.
+#[derive(Debug, Default)]
+struct RequestNamespace(String);
+
+/// Strip the namespace from the path of a request, so that `/n/<namespace>/...` is routed as `/...` in that namespace. Invalid namespaces are left in the path, which is then not found.
+fn strip_namespace(request: &mut Request<'_>, _data: &Data<'_>) {
+    let uri = request.uri();
+
+    let Some(rest) = uri.path().as_str().strip_prefix(NAMESPACE_PATH_PREFIX) else {
+        return;
+    };
+
+    let (namespace, path) = rest.split_once('/').unwrap_or((rest, ""));
+
+    if namespace.is_empty() || !is_valid_namespace(namespace) {
+        return;
+    }
+
+    let uri = match uri.query() {
+        Some(query) => format!("/{path}?{query}"),
+        None => format!("/{path}"),
+    };
+
+    if let Ok(uri) = Origin::parse_owned(uri) {
+        let namespace = namespace.to_string();
+
+        request.local_cache(|| RequestNamespace(namespace));
+        request.set_uri(uri);
+    }
+}
+
+/// A fairing which routes the `/n/<namespace>/...` paths.
+#[inline]
+pub fn namespace_fairing() -> AdHoc {
+    AdHoc::on_request("Namespaces", |request, data| {
+        Box::pin(async move { strip_namespace(request, data) })
+    })
+}
+
+/// Retrieve the namespace of a request. The default namespace is an empty string.
+#[inline]
+pub fn request_namespace<'r>(request: &'r Request<'_>) -> &'r str {
+    // the namespace has been validated by the fairing
+    request.local_cache(RequestNamespace::default).0.as_str()
+}
+
+/// A request guard of the Datalith scoped by the namespace in the request path. If the authentication is enabled, the API key of the request must be allowed to access the namespace, and requests without API keys are only allowed in the default namespace or with signed URLs.
+#[derive(Debug, Clone)]
+pub struct NamespacedDatalith(Datalith);
+
+impl NamespacedDatalith {
+    /// Prefix an absolute path with the namespace, so that it refers to the same namespace.
+    #[inline]
+    pub fn scoped_path(&self, path: impl Display) -> String {
+        let namespace = self.0.get_namespace();
+
+        if namespace.is_empty() {
+            path.to_string()
+        } else {
+            format!("{NAMESPACE_PATH_PREFIX}{namespace}{path}")
+        }
+    }
+}
+
+impl Deref for NamespacedDatalith {
+    type Target = Datalith;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for NamespacedDatalith {
+    type Error = &'static str;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let rocket = request.rocket();
+
+        let namespace = request_namespace(request);
+
+        if rocket.state::<ServerConfig>().unwrap().auth {
+            match request_api_key(request).await {
+                Ok(Some(api_key)) => {
+                    if !api_key.allows_namespace(namespace) {
+                        return Outcome::Error((Status::Forbidden, "namespace not allowed"));
+                    }
+                },
+                Ok(None) => {
+                    if !namespace.is_empty() && request.query_value::<&str>("sig").is_none() {
+                        return Outcome::Error((Status::Unauthorized, "missing authorization"));
+                    }
+                },
+                Err(error) => return Outcome::Error(error),
+            }
+        }
+
+        let datalith = rocket.state::<DatalithManager>().unwrap();
+
+        Outcome::Success(Self(datalith.with_namespace(namespace).unwrap()))
+    }
+}
@@ -0,0 +1,120 @@
+use datalith_core::{DatalithCursor, DatalithCursorPage, DatalithSortKey, OrderMethod, Pagination};
+use rocket::http::Status;
+use serde_json::{Value, json};
+
+/// The number of items per page if it is not specified.
+pub const DEFAULT_ITEMS_PER_PAGE: usize = 20;
+/// The maximum number of items per page.
+pub const MAX_ITEMS_PER_PAGE: usize = 100;
+/// The maximum number of keys in an `order_by` query.
+const MAX_ORDER_BY_KEYS: usize = 8;
+
+/// Validate the `page` and `per_page` queries.
+#[inline]
+pub fn validate_page(
+    page: Option<usize>,
+    per_page: Option<usize>,
+) -> Result<(usize, usize), Status> {
+    let page = page.unwrap_or(1);
+    let per_page = per_page.unwrap_or(DEFAULT_ITEMS_PER_PAGE);
+
+    if page == 0 || per_page == 0 || per_page > MAX_ITEMS_PER_PAGE {
+        return Err(Status::BadRequest);
+    }
+
+    Ok((page, per_page))
+}
+
+/// Parse the `order_by` query, which is a comma-separated list of keys. A key prefixed with `-` is sorted in descending order.
+///
+/// The returned order methods are prioritized in the order of the keys. The order method for a tie-breaker which has the lowest priority is also returned.
+#[allow(clippy::type_complexity)]
+pub fn parse_order_by(order_by: &str) -> Result<(Vec<(&str, OrderMethod)>, OrderMethod), Status> {
+    let mut keys = Vec::new();
+
+    for key in order_by.split(',') {
+        let key = key.trim();
+
+        if key.is_empty() {
+            continue;
+        }
+
+        if keys.len() == MAX_ORDER_BY_KEYS {
+            return Err(Status::BadRequest);
+        }
+
+        let priority = 101 + keys.len() as i8;
+
+        let (key, order_method) = match key.strip_prefix('-') {
+            Some(key) => (key, -priority),
+            None => (key.strip_prefix('+').unwrap_or(key), priority),
+        };
+
+        if keys.iter().any(|(k, _)| *k == key) {
+            return Err(Status::BadRequest);
+        }
+
+        keys.push((key, OrderMethod::from(order_method)));
+    }
+
+    let tie_breaker = OrderMethod::from(101 + keys.len() as i8);
+
+    Ok((keys, tie_breaker))
+}
+
+/// Parse the `order_by` query of a cursor listing, which is a single key. A key prefixed with `-` is sorted in descending order. The default key in descending order is used if it is not specified.
+pub fn parse_cursor_order_by<K: DatalithSortKey + Default>(
+    order_by: Option<&str>,
+) -> Result<(K, bool), Status> {
+    let order_by = order_by.map(|e| e.trim()).unwrap_or_default();
+
+    if order_by.is_empty() {
+        return Ok((K::default(), true));
+    }
+
+    let (key, descending) = match order_by.strip_prefix('-') {
+        Some(key) => (key, true),
+        None => (order_by.strip_prefix('+').unwrap_or(order_by), false),
+    };
+
+    let key = K::from_name(key).ok_or(Status::BadRequest)?;
+
+    Ok((key, descending))
+}
+
+/// Parse the `cursor` query. An empty cursor starts from the beginning.
+#[inline]
+pub fn parse_cursor<K: DatalithSortKey>(cursor: &str) -> Result<Option<DatalithCursor<K>>, Status> {
+    if cursor.is_empty() {
+        return Ok(None);
+    }
+
+    DatalithCursor::decode(cursor).map(Some).ok_or(Status::BadRequest)
+}
+
+#[inline]
+pub fn cursor_page_to_json_value<K: DatalithSortKey>(page: &DatalithCursorPage<K>) -> Value {
+    let mut value = json!(
+        {
+            "next_cursor": page.next_cursor.as_ref().map(|e| e.to_string()),
+        }
+    );
+
+    if let Some(total_items) = page.total_items {
+        value["total_items"] = json!(total_items);
+    }
+
+    value
+}
+
+#[inline]
+pub fn pagination_to_json_value(pagination: &Pagination) -> Value {
+    json!(
+        {
+            "page": pagination.get_page(),
+            "per_page": pagination.get_items_per_page(),
+            "total_items": pagination.get_total_items(),
+            "total_pages": pagination.get_total_pages(),
+        }
+    )
+}
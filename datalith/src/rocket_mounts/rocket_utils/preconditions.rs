@@ -0,0 +1,300 @@
+use std::fmt::Write;
+
+use datalith_core::{
+    Datalith, DatalithFile, Uuid,
+    chrono::{DateTime, Local},
+};
+use rocket::{
+    Request,
+    http::{Header, Status},
+    request::{FromRequest, Outcome},
+    response::content::RawJson,
+};
+use rocket_etag_if_none_match::entity_tag::EntityTag;
+use serde_json::Value;
+
+/// The conditional request headers `If-Match`, `If-Unmodified-Since` and `If-Modified-Since`. `If-None-Match` is handled by `EtagIfNoneMatch`, and `If-Range` is handled along with byte ranges.
+#[derive(Debug, Default)]
+pub struct Preconditions<'r> {
+    if_match:            Option<&'r str>,
+    if_unmodified_since: Option<&'r str>,
+    if_modified_since:   Option<&'r str>,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Preconditions<'r> {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let headers = request.headers();
+
+        Outcome::Success(Self {
+            if_match:            headers.get_one("if-match"),
+            if_unmodified_since: headers.get_one("if-unmodified-since"),
+            if_modified_since:   headers.get_one("if-modified-since"),
+        })
+    }
+}
+
+impl Preconditions<'_> {
+    /// Check whether there is `If-Match` or `If-Unmodified-Since`.
+    #[inline]
+    pub const fn has_preconditions(&self) -> bool {
+        self.if_match.is_some() || self.if_unmodified_since.is_some()
+    }
+
+    /// Evaluate `If-Match`, or `If-Unmodified-Since` if there is no `If-Match`. `etags` are the strong entity tags of the current representations, any of which can be matched. Return `false` if the request should be responded with 412 Precondition Failed.
+    pub fn check<'a>(
+        &self,
+        etags: impl IntoIterator<Item = &'a EntityTag<'static>>,
+        last_modified: DateTime<Local>,
+    ) -> bool {
+        if let Some(if_match) = self.if_match {
+            let if_match = if_match.trim();
+
+            if if_match == "*" {
+                return true;
+            }
+
+            let etags = etags.into_iter().map(|etag| etag.to_string()).collect::<Vec<String>>();
+
+            // only strong entity tags can be matched
+            if_match
+                .split(',')
+                .map(str::trim)
+                .any(|tag| !tag.starts_with("W/") && etags.iter().any(|etag| etag == tag))
+        } else if let Some(date) = self.if_unmodified_since.and_then(parse_http_date) {
+            last_modified.timestamp() <= date.timestamp()
+        } else {
+            true
+        }
+    }
+
+    /// Parse the metadata entity tags in `If-Match`. Return `None` if there is no `If-Match` or it is `*`.
+    pub fn metadata_versions(&self) -> Option<Vec<u64>> {
+        let if_match = self.if_match?.trim();
+
+        if if_match == "*" {
+            return None;
+        }
+
+        Some(
+            if_match
+                .split(',')
+                .filter_map(|tag| tag.trim().strip_prefix("\"m")?.strip_suffix('"')?.parse().ok())
+                .collect(),
+        )
+    }
+
+    /// Check whether the representation has not been modified since `If-Modified-Since`. It is ignored if there is `If-None-Match`.
+    pub fn is_not_modified(&self, has_if_none_match: bool, last_modified: DateTime<Local>) -> bool {
+        if has_if_none_match {
+            return false;
+        }
+
+        match self.if_modified_since.and_then(parse_http_date) {
+            Some(date) => last_modified.timestamp() <= date.timestamp(),
+            None => false,
+        }
+    }
+}
+
+/// Create the strong entity tag of a file from its hash. The content coding, if any, is appended, because the encoded data is a different representation.
+pub fn file_entity_tag(file: &DatalithFile, content_encoding: Option<&str>) -> EntityTag<'static> {
+    let mut tag = String::with_capacity(80);
+
+    for b in file.hash() {
+        tag.write_fmt(format_args!("{b:02x}")).unwrap();
+    }
+
+    if let Some(content_encoding) = content_encoding {
+        tag.push('-');
+        tag.push_str(content_encoding);
+    }
+
+    EntityTag::with_string(false, tag).unwrap()
+}
+
+/// Create the strong entity tags of all the representations of a file, which are the data itself and the stored data if it is compressed.
+fn file_entity_tags(file: &DatalithFile) -> impl Iterator<Item = EntityTag<'static>> {
+    [
+        Some(file_entity_tag(file, None)),
+        file.compression()
+            .content_encoding()
+            .map(|content_encoding| file_entity_tag(file, Some(content_encoding))),
+    ]
+    .into_iter()
+    .flatten()
+}
+
+/// Create the strong entity tag of the metadata of a resource or an image from its version, as in `"m3"`. It cannot be confused with the entity tags of files, which are hexadecimal.
+#[inline]
+pub fn metadata_entity_tag(metadata_version: u64) -> EntityTag<'static> {
+    EntityTag::with_string(false, format!("m{metadata_version}")).unwrap()
+}
+
+/// A JSON response of a resource or an image along with the entity tag of its metadata, which can be used in `If-Match` when changing the metadata.
+#[derive(Responder)]
+pub struct MetadataJson {
+    body: RawJson<String>,
+    etag: Header<'static>,
+}
+
+impl MetadataJson {
+    #[inline]
+    pub fn new(value: &Value, metadata_version: u64) -> Self {
+        Self {
+            body: RawJson(serde_json::to_string(value).unwrap()),
+            etag: Header::new("etag", metadata_entity_tag(metadata_version).to_string()),
+        }
+    }
+}
+
+/// Choose the metadata version which a change is conditional on. `If-Match` is matched against the metadata entity tag, which changes whenever the metadata does, and `current_metadata_version` is only called if it has more than one tag. Without metadata entity tags, the other preconditions are evaluated by `check`. Return 412 if the preconditions fail.
+async fn choose_metadata_version<F: Future<Output = Result<Option<u64>, Status>>>(
+    preconditions: &Preconditions<'_>,
+    check: impl Future<Output = Result<(), Status>>,
+    current_metadata_version: impl FnOnce() -> F,
+) -> Result<Option<u64>, Status> {
+    match preconditions.metadata_versions() {
+        Some(metadata_versions) => match metadata_versions.as_slice() {
+            [] => Err(Status::PreconditionFailed),
+            [metadata_version] => Ok(Some(*metadata_version)),
+            _ => match current_metadata_version().await? {
+                Some(metadata_version) if metadata_versions.contains(&metadata_version) => {
+                    Ok(Some(metadata_version))
+                },
+                Some(_) => Err(Status::PreconditionFailed),
+                None => Err(Status::NotFound),
+            },
+        },
+        None => {
+            check.await?;
+
+            Ok(None)
+        },
+    }
+}
+
+/// Evaluate the preconditions before changing the metadata of a resource. Return the metadata version which the change must be conditional on, 404 if the resource does not exist, or 412 if the preconditions fail.
+pub async fn check_resource_metadata_preconditions(
+    datalith: &Datalith,
+    preconditions: &Preconditions<'_>,
+    id: Uuid,
+) -> Result<Option<u64>, Status> {
+    choose_metadata_version(
+        preconditions,
+        check_resource_preconditions(datalith, preconditions, id),
+        || async {
+            match datalith.peek_resource_by_id(id).await {
+                Ok(resource) => Ok(resource.map(|e| e.metadata_version())),
+                Err(error) => {
+                    rocket::error!("{error}");
+
+                    Err(Status::InternalServerError)
+                },
+            }
+        },
+    )
+    .await
+}
+
+/// Evaluate the preconditions before changing the metadata of an image. Return the metadata version which the change must be conditional on, 404 if the image does not exist, or 412 if the preconditions fail.
+#[cfg(feature = "image-convert")]
+pub async fn check_image_metadata_preconditions(
+    datalith: &Datalith,
+    preconditions: &Preconditions<'_>,
+    id: Uuid,
+) -> Result<Option<u64>, Status> {
+    choose_metadata_version(
+        preconditions,
+        check_image_preconditions(datalith, preconditions, id),
+        || async {
+            match datalith.get_image_by_id(id).await {
+                Ok(image) => Ok(image.map(|e| e.metadata_version())),
+                Err(error) => {
+                    rocket::error!("{error}");
+
+                    Err(Status::InternalServerError)
+                },
+            }
+        },
+    )
+    .await
+}
+
+/// Evaluate `If-Match` and `If-Unmodified-Since` before changing or deleting a resource. Return 404 if the resource does not exist, or 412 if the preconditions fail.
+pub async fn check_resource_preconditions(
+    datalith: &Datalith,
+    preconditions: &Preconditions<'_>,
+    id: Uuid,
+) -> Result<(), Status> {
+    if !preconditions.has_preconditions() {
+        return Ok(());
+    }
+
+    match datalith.peek_resource_by_id(id).await {
+        Ok(Some(resource)) => {
+            let etags = file_entity_tags(resource.file()).collect::<Vec<_>>();
+
+            if preconditions.check(&etags, resource.created_at()) {
+                Ok(())
+            } else {
+                Err(Status::PreconditionFailed)
+            }
+        },
+        Ok(None) => Err(Status::NotFound),
+        Err(error) => {
+            rocket::error!("{error}");
+
+            Err(Status::InternalServerError)
+        },
+    }
+}
+
+/// Evaluate `If-Match` and `If-Unmodified-Since` before changing or deleting an image. The entity tag of any of its files can be matched. Return 404 if the image does not exist, or 412 if the preconditions fail.
+#[cfg(feature = "image-convert")]
+pub async fn check_image_preconditions(
+    datalith: &Datalith,
+    preconditions: &Preconditions<'_>,
+    id: Uuid,
+) -> Result<(), Status> {
+    if !preconditions.has_preconditions() {
+        return Ok(());
+    }
+
+    match datalith.get_image_by_id(id).await {
+        Ok(Some(image)) => {
+            let etags = image
+                .thumbnails()
+                .iter()
+                .chain(image.fallback_thumbnails())
+                .chain(image.original_file())
+                .flat_map(file_entity_tags)
+                .collect::<Vec<_>>();
+
+            if preconditions.check(&etags, image.created_at()) {
+                Ok(())
+            } else {
+                Err(Status::PreconditionFailed)
+            }
+        },
+        Ok(None) => Err(Status::NotFound),
+        Err(error) => {
+            rocket::error!("{error}");
+
+            Err(Status::InternalServerError)
+        },
+    }
+}
+
+/// Format a time as an HTTP date, as in `Sun, 06 Nov 1994 08:49:37 GMT`.
+#[inline]
+pub fn to_http_date(date: DateTime<Local>) -> String {
+    date.to_utc().format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+#[inline]
+fn parse_http_date(s: &str) -> Option<DateTime<Local>> {
+    DateTime::parse_from_rfc2822(s.trim()).ok().map(|date| date.with_timezone(&Local))
+}
@@ -0,0 +1,32 @@
+use std::time::Duration;
+
+use datalith_core::DatalithSignedUrl;
+use rocket::http::Status;
+use serde_json::{Value, json};
+
+/// The default lifespan (in seconds) of a signed URL.
+pub const DEFAULT_SIGNED_URL_LIFESPAN: u64 = 60 * 60;
+/// The maximum lifespan (in seconds) of a signed URL.
+pub const MAX_SIGNED_URL_LIFESPAN: u64 = 7 * 24 * 60 * 60;
+
+/// Check the `lifespan` parameter (in seconds) of a request for minting a signed URL.
+#[inline]
+pub fn validate_signed_url_lifespan(lifespan: Option<u64>) -> Result<Duration, Status> {
+    let lifespan = lifespan.unwrap_or(DEFAULT_SIGNED_URL_LIFESPAN);
+
+    if lifespan == 0 || lifespan > MAX_SIGNED_URL_LIFESPAN {
+        return Err(Status::BadRequest);
+    }
+
+    Ok(Duration::from_secs(lifespan))
+}
+
+#[inline]
+pub fn signed_url_to_json_value(path: String, signed_url: DatalithSignedUrl) -> Value {
+    json!(
+        {
+            "url": format!("{path}?{}", signed_url.query_string()),
+            "expired_at": signed_url.expired_at().to_rfc3339(),
+        }
+    )
+}
@@ -0,0 +1,162 @@
+use std::{collections::HashMap, io::Cursor};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use datalith_core::chrono::{DateTime, Local};
+use rocket::{
+    Request, Response,
+    http::{HeaderMap, Status},
+    outcome::Outcome,
+    request,
+    request::FromRequest,
+    response,
+    response::Responder,
+};
+
+/// The version of the tus resumable upload protocol this service supports.
+pub const TUS_VERSION: &str = "1.0.0";
+/// The extensions of the tus resumable upload protocol this service supports.
+pub const TUS_EXTENSION: &str = "creation,termination,expiration";
+
+/// The headers of a tus request. The request must have a supported `Tus-Resumable` header.
+#[derive(Debug)]
+pub struct TusHeaders<'r> {
+    headers: &'r HeaderMap<'r>,
+}
+
+impl TusHeaders<'_> {
+    /// Retrieve the `Upload-Offset` header. `Err` means the header is invalid.
+    #[inline]
+    pub fn upload_offset(&self) -> Result<Option<u64>, Status> {
+        self.get_u64("upload-offset")
+    }
+
+    /// Retrieve the `Upload-Length` header. `Err` means the header is invalid.
+    #[inline]
+    pub fn upload_length(&self) -> Result<Option<u64>, Status> {
+        self.get_u64("upload-length")
+    }
+
+    /// Retrieve the `Content-Type` header.
+    #[inline]
+    pub fn content_type(&self) -> Option<&str> {
+        self.headers.get_one("content-type")
+    }
+
+    /// Parse the `Upload-Metadata` header. The values are decoded from Base64. `Err` means the header is invalid.
+    pub fn upload_metadata(&self) -> Result<HashMap<&str, String>, Status> {
+        let mut metadata = HashMap::new();
+
+        if let Some(upload_metadata) = self.headers.get_one("upload-metadata") {
+            for pair in upload_metadata.split(',') {
+                let pair = pair.trim();
+
+                if pair.is_empty() {
+                    continue;
+                }
+
+                let (key, value) = match pair.split_once(' ') {
+                    Some((key, value)) => {
+                        let value =
+                            STANDARD.decode(value.trim()).map_err(|_| Status::BadRequest)?;
+
+                        (key, String::from_utf8(value).map_err(|_| Status::BadRequest)?)
+                    },
+                    None => (pair, String::new()),
+                };
+
+                metadata.insert(key, value);
+            }
+        }
+
+        Ok(metadata)
+    }
+
+    fn get_u64(&self, name: &str) -> Result<Option<u64>, Status> {
+        match self.headers.get_one(name) {
+            Some(value) => match value.trim().parse::<u64>() {
+                Ok(value) => Ok(Some(value)),
+                Err(_) => Err(Status::BadRequest),
+            },
+            None => Ok(None),
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for TusHeaders<'r> {
+    type Error = &'static str;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let headers = request.headers();
+
+        match headers.get_one("tus-resumable") {
+            Some(TUS_VERSION) => Outcome::Success(Self {
+                headers,
+            }),
+            _ => Outcome::Error((Status::PreconditionFailed, "unsupported tus version")),
+        }
+    }
+}
+
+/// A response of the tus resumable upload protocol, which always has the `Tus-Resumable` header.
+#[derive(Debug)]
+pub struct TusResponse {
+    status:  Status,
+    headers: Vec<(&'static str, String)>,
+    body:    Option<(&'static str, String)>,
+}
+
+impl TusResponse {
+    #[inline]
+    pub const fn new(status: Status) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: None,
+        }
+    }
+
+    #[inline]
+    pub fn header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+
+        self
+    }
+
+    /// Add the `Upload-Expires` header.
+    #[inline]
+    pub fn upload_expires(self, expired_at: DateTime<Local>) -> Self {
+        self.header(
+            "upload-expires",
+            expired_at.to_utc().format("%a, %d %b %Y %H:%M:%S GMT").to_string(),
+        )
+    }
+
+    /// Set a JSON body.
+    #[inline]
+    pub fn json(mut self, json: String) -> Self {
+        self.body = Some(("application/json", json));
+
+        self
+    }
+}
+
+impl<'r, 'o: 'r> Responder<'r, 'o> for TusResponse {
+    fn respond_to(self, _: &'r Request<'_>) -> response::Result<'o> {
+        let mut response = Response::build();
+
+        response.status(self.status);
+        response.raw_header("tus-resumable", TUS_VERSION);
+
+        for (name, value) in self.headers {
+            response.raw_header(name, value);
+        }
+
+        if let Some((content_type, body)) = self.body {
+            response.raw_header("content-type", content_type);
+            response.sized_body(body.len(), Cursor::new(body));
+        }
+
+        response.ok()
+    }
+}
@@ -0,0 +1,83 @@
+use datalith_core::{Datalith, DatalithQuota, DatalithReadError, DatalithUsage};
+use rocket::{Build, Rocket, http::Status, response::content::RawJson};
+use serde_json::{Value, json};
+
+use super::rocket_utils::{NamespacedDatalith, ReadScope};
+
+/// Retrieve the usage and the quota of the namespace, and those of all namespaces as a whole.
+#[get("/usage")]
+async fn usage(_scope: ReadScope, datalith: NamespacedDatalith) -> Result<RawJson<String>, Status> {
+    match read_usage(&datalith).await {
+        Ok((usage, quota, global_usage)) => {
+            let value = json!(
+                {
+                    "namespace": datalith.get_namespace(),
+                    "usage": usage_to_json_value(usage, quota),
+                    "global_usage": usage_to_json_value(global_usage, datalith.get_global_quota()),
+                }
+            );
+
+            Ok(RawJson(serde_json::to_string(&value).unwrap()))
+        },
+        Err(error) => {
+            rocket::error!("{error}");
+
+            Err(Status::InternalServerError)
+        },
+    }
+}
+
+/// Retrieve the status of the service, including the free space of the disk.
+#[get("/status")]
+async fn status(
+    _scope: ReadScope,
+    datalith: NamespacedDatalith,
+) -> Result<RawJson<String>, Status> {
+    match datalith.get_disk_space() {
+        Ok(disk_space) => {
+            let value = json!(
+                {
+                    "disk": {
+                        "available_space": disk_space.available_space,
+                        "total_space": disk_space.total_space,
+                        "low_water_mark": disk_space.low_water_mark,
+                    },
+                }
+            );
+
+            Ok(RawJson(serde_json::to_string(&value).unwrap()))
+        },
+        Err(error) => {
+            rocket::error!("{error}");
+
+            Err(Status::InternalServerError)
+        },
+    }
+}
+
+#[inline]
+pub fn mounts(rocket: Rocket<Build>) -> Rocket<Build> {
+    rocket.mount("/", routes![usage, status])
+}
+
+async fn read_usage(
+    datalith: &Datalith,
+) -> Result<(DatalithUsage, DatalithQuota, DatalithUsage), DatalithReadError> {
+    Ok((
+        datalith.get_usage().await?,
+        datalith.get_namespace_quota().await?,
+        datalith.get_global_usage().await?,
+    ))
+}
+
+#[inline]
+fn usage_to_json_value(usage: DatalithUsage, quota: DatalithQuota) -> Value {
+    json!(
+        {
+            "bytes": usage.bytes,
+            "count": usage.count,
+            "max_bytes": quota.max_bytes,
+            "max_count": quota.max_count,
+        }
+    )
+}